@@ -14,7 +14,14 @@
  *     let airtable = Airtable::new_from_env();
  *
  *     // Get the current records from a table.
- *     let mut records: Vec<Record<SomeFormat>> = airtable.list_records("Table Name", "Grid view", vec!["the", "fields", "you", "want", "to", "return"]).await.unwrap();
+ *     let mut records: Vec<Record<SomeFormat>> = airtable
+ *         .list_records(
+ *             "Table Name",
+ *             "Grid view",
+ *             vec!["the", "fields", "you", "want", "to", "return"],
+ *         )
+ *         .await
+ *         .unwrap();
  *
  *     // Iterate over the records.
  *     for (i, record) in records.clone().iter().enumerate() {
@@ -51,6 +58,7 @@ pub struct Airtable {
     key: String,
     base_id: String,
     enterprise_account_id: String,
+    endpoint: String,
 
     client: Arc<Client>,
 }
@@ -78,6 +86,7 @@ impl Airtable {
                 key: key.to_string(),
                 base_id: base_id.to_string(),
                 enterprise_account_id: enterprise_account_id.to_string(),
+                endpoint: env::var("AIRTABLE_ENDPOINT").unwrap_or_else(|_| ENDPOINT.to_string()),
 
                 client: Arc::new(c),
             },
@@ -101,11 +110,22 @@ impl Airtable {
         &self.key
     }
 
+    /// Set the endpoint the client sends requests to. This defaults to the
+    /// production API, or the `AIRTABLE_ENDPOINT` environment variable if it is set,
+    /// and is useful for testing against a fake server.
+    pub fn with_endpoint<E>(mut self, endpoint: E) -> Self
+    where
+        E: ToString,
+    {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
     fn request<B>(&self, method: Method, path: String, body: B, query: Option<Vec<(&str, String)>>) -> Request
     where
         B: Serialize,
     {
-        let base = Url::parse(ENDPOINT).unwrap();
+        let base = Url::parse(&self.endpoint).unwrap();
         let url = base.join(&(self.base_id.to_string() + "/" + &path)).unwrap();
 
         let bt = format!("Bearer {}", self.key);
@@ -136,10 +156,21 @@ impl Airtable {
 
     /// List records in a table for a particular view.
     pub async fn list_records<T: DeserializeOwned>(&self, table: &str, view: &str, fields: Vec<&str>) -> Result<Vec<Record<T>>, APIError> {
+        self.list_records_matching(table, view, fields, "").await
+    }
+
+    /// List the records in a table for a particular view that match a formula,
+    /// for example `{Tracking number} = '1Z999'`. An empty formula matches every
+    /// record.
+    /// FROM: https://support.airtable.com/hc/en-us/articles/203255215-Formula-Field-Reference
+    pub async fn list_records_matching<T: DeserializeOwned>(&self, table: &str, view: &str, fields: Vec<&str>, formula: &str) -> Result<Vec<Record<T>>, APIError> {
         let mut params = vec![("pageSize", "100".to_string()), ("view", view.to_string())];
         for field in fields {
             params.push(("fields", field.to_string()));
         }
+        if !formula.is_empty() {
+            params.push(("filterByFormula", formula.to_string()));
+        }
 
         // Build the request.
         let mut request = self.request(Method::GET, table.to_string(), (), Some(params));
@@ -165,12 +196,11 @@ impl Airtable {
         // Paginate if we should.
         // TODO: make this more DRY
         while !offset.is_empty() {
-            request = self.request(
-                Method::GET,
-                table.to_string(),
-                (),
-                Some(vec![("pageSize", "100".to_string()), ("view", view.to_string()), ("offset", offset)]),
-            );
+            let mut params = vec![("pageSize", "100".to_string()), ("view", view.to_string()), ("offset", offset)];
+            if !formula.is_empty() {
+                params.push(("filterByFormula", formula.to_string()));
+            }
+            request = self.request(Method::GET, table.to_string(), (), Some(params));
 
             resp = self.client.execute(request).await.unwrap();
             match resp.status() {
@@ -0,0 +1 @@
+DROP TABLE zoom_recordings
@@ -0,0 +1,17 @@
+CREATE TABLE zoom_recordings (
+    id SERIAL PRIMARY KEY,
+    recording_id VARCHAR NOT NULL UNIQUE,
+    meeting_id BIGINT NOT NULL DEFAULT 0,
+    meeting_uuid VARCHAR NOT NULL,
+    topic VARCHAR NOT NULL,
+    host_id VARCHAR NOT NULL,
+    recording_start TIMESTAMPTZ NOT NULL,
+    recording_end TIMESTAMPTZ NOT NULL,
+    file_type VARCHAR NOT NULL,
+    file_size BIGINT NOT NULL DEFAULT 0,
+    file_name VARCHAR NOT NULL,
+    google_drive_link VARCHAR NOT NULL,
+    archived_time TIMESTAMPTZ NOT NULL,
+    deleted_from_zoom BOOLEAN NOT NULL DEFAULT 'f',
+    airtable_record_id VARCHAR NOT NULL DEFAULT ''
+)
@@ -0,0 +1,16 @@
+CREATE TABLE card_transactions (
+    id SERIAL PRIMARY KEY,
+    transaction_id VARCHAR NOT NULL UNIQUE,
+    card_last_four VARCHAR NOT NULL,
+    employee_email VARCHAR NOT NULL,
+    merchant_name VARCHAR NOT NULL,
+    category VARCHAR NOT NULL,
+    amount REAL NOT NULL DEFAULT 0,
+    currency VARCHAR NOT NULL,
+    time TIMESTAMPTZ NOT NULL,
+    memo VARCHAR NOT NULL,
+    receipt_matched BOOLEAN NOT NULL DEFAULT 'f',
+    receipt_link VARCHAR NOT NULL,
+    notes VARCHAR NOT NULL,
+    airtable_record_id VARCHAR NOT NULL DEFAULT ''
+)
@@ -0,0 +1,13 @@
+CREATE TABLE email_sends (
+    id SERIAL PRIMARY KEY,
+    send_id VARCHAR NOT NULL UNIQUE,
+    template_name VARCHAR NOT NULL,
+    template_version INTEGER NOT NULL DEFAULT 0,
+    recipient VARCHAR NOT NULL,
+    cc VARCHAR NOT NULL,
+    from_address VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    body VARCHAR NOT NULL,
+    sent_time TIMESTAMPTZ NOT NULL,
+    airtable_record_id VARCHAR NOT NULL DEFAULT ''
+)
@@ -0,0 +1,10 @@
+CREATE TABLE swag_items (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    size VARCHAR NOT NULL DEFAULT '',
+    description VARCHAR NOT NULL DEFAULT '',
+    image_link VARCHAR NOT NULL DEFAULT '',
+    current_stock INTEGER NOT NULL DEFAULT 0,
+    airtable_record_id VARCHAR NOT NULL DEFAULT '',
+    UNIQUE (name, size)
+)
@@ -0,0 +1 @@
+DROP TABLE geocoded_addresses
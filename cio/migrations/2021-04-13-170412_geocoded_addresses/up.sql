@@ -0,0 +1,7 @@
+CREATE TABLE geocoded_addresses (
+    id SERIAL PRIMARY KEY,
+    address VARCHAR NOT NULL UNIQUE,
+    latitude DOUBLE PRECISION NOT NULL DEFAULT 0,
+    longitude DOUBLE PRECISION NOT NULL DEFAULT 0,
+    airtable_record_id VARCHAR NOT NULL DEFAULT ''
+)
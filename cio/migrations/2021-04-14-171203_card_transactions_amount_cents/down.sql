@@ -0,0 +1,4 @@
+ALTER TABLE card_transactions ALTER COLUMN amount_cents DROP DEFAULT;
+ALTER TABLE card_transactions ALTER COLUMN amount_cents TYPE REAL USING amount_cents / 100.0;
+ALTER TABLE card_transactions ALTER COLUMN amount_cents SET DEFAULT 0;
+ALTER TABLE card_transactions RENAME COLUMN amount_cents TO amount;
@@ -0,0 +1,4 @@
+ALTER TABLE card_transactions RENAME COLUMN amount TO amount_cents;
+ALTER TABLE card_transactions ALTER COLUMN amount_cents DROP DEFAULT;
+ALTER TABLE card_transactions ALTER COLUMN amount_cents TYPE BIGINT USING ROUND(amount_cents * 100)::BIGINT;
+ALTER TABLE card_transactions ALTER COLUMN amount_cents SET DEFAULT 0;
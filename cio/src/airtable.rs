@@ -18,6 +18,8 @@ pub static AIRTABLE_JOURNAL_CLUB_MEETINGS_TABLE: &str = "Journal Club Meetings";
 pub static AIRTABLE_JOURNAL_CLUB_PAPERS_TABLE: &str = "Journal Club Papers";
 pub static AIRTABLE_GITHUB_REPOS_TABLE: &str = "GitHub Repos";
 pub static AIRTABLE_RECORDED_MEETINGS_TABLE: &str = "Recorded Meetings";
+pub static AIRTABLE_ZOOM_RECORDINGS_TABLE: &str = "Zoom Recordings";
+pub static AIRTABLE_EMAIL_SENDS_TABLE: &str = "Email Sends";
 
 pub static AIRTABLE_BASE_ID_RACK_ROADMAP: &str = "appvAEzcMvB2QNboC";
 pub static AIRTABLE_RFD_TABLE: &str = "RFDs";
@@ -34,8 +36,11 @@ pub static AIRTABLE_MEETING_SCHEDULE_TABLE: &str = "Meeting schedule";
 pub static AIRTABLE_BASE_ID_SHIPMENTS: &str = "appQD9Sitpo8baLZ4";
 pub static AIRTABLE_OUTBOUND_TABLE: &str = "Outbound";
 pub static AIRTABLE_INBOUND_TABLE: &str = "Inbound";
+pub static AIRTABLE_SWAG_INVENTORY_TABLE: &str = "Swag Inventory";
+pub static AIRTABLE_GEOCODED_ADDRESSES_TABLE: &str = "Geocoded Addresses";
 
 pub static AIRTABLE_BASE_ID_FINANCE: &str = "appduLHDVQ332gKyf";
 pub static AIRTABLE_SOFTWARE_VENDORS_TABLE: &str = "Software Vendors";
+pub static AIRTABLE_CARD_TRANSACTIONS_REVIEW_TABLE: &str = "Card Transactions Review";
 
 pub static AIRTABLE_GRID_VIEW: &str = "Grid view";
@@ -1,6 +1,7 @@
 #![allow(clippy::from_over_into)]
 use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::error::Error;
 use std::fs;
 use std::io::{copy, stderr, stdout, Write};
 use std::process::Command;
@@ -12,6 +13,7 @@ use chrono::offset::Utc;
 use chrono::{DateTime, Duration};
 use chrono_humanize::HumanTime;
 use google_drive::GoogleDrive;
+use handlebars::TemplateRenderError;
 use html2text::from_read;
 use hubcaps::comments::CommentOptions;
 use hubcaps::issues::{Issue, IssueListOptions, IssueOptions, State};
@@ -34,6 +36,7 @@ use crate::airtable::{AIRTABLE_APPLICATIONS_TABLE, AIRTABLE_BASE_ID_RECURITING_A
 use crate::configs::User;
 use crate::core::UpdateAirtableRecord;
 use crate::db::Database;
+use crate::emails::{EmailRecipient, EmailSend, EmailTemplate, EMAIL_TEMPLATE_APPLICATION_RECEIVED, EMAIL_TEMPLATE_REJECTION_NO_MATERIALS, EMAIL_TEMPLATE_REJECTION_TIMING};
 use crate::models::get_value;
 use crate::schema::{applicant_reviewers, applicants};
 use crate::slack::{get_hiring_channel_post_url, post_to_channel};
@@ -256,97 +259,42 @@ impl NewApplicant {
     }
 
     /// Send an email to the applicant that we recieved their application.
-    #[instrument]
+    #[instrument(skip(db))]
     #[inline]
-    pub async fn send_email_recieved_application_to_applicant(&self) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-
-        // Send the message.
-        sendgrid_client
-            .send_mail(
-                format!("Oxide Computer Company {} Application Received for {}", self.role, self.name),
-                format!(
-                    "Dear {},
-
-Thank you for submitting your application materials! We really appreciate all
-the time and thought everyone puts into their application. We will be in touch
-within the next couple weeks with more information.
-
-Sincerely,
-  The Oxide Team",
-                    self.name
-                ),
-                vec![self.email.to_string()],
-                vec![format!("careers@{}", DOMAIN)],
-                vec![],
-                format!("careers@{}", DOMAIN),
-            )
-            .await;
+    pub async fn send_email_recieved_application_to_applicant(&self, db: &Database) -> Result<Vec<EmailSend>, TemplateRenderError> {
+        self.send_email_template(db, &EMAIL_TEMPLATE_APPLICATION_RECEIVED).await
     }
 
     /// Send an email to the applicant that they did not provide materials.
-    #[instrument]
+    #[instrument(skip(db))]
     #[inline]
-    pub async fn send_email_rejection_did_not_provide_materials(&self) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-
-        // Send the message.
-        sendgrid_client
-            .send_mail(
-                format!("Thank you for your application, {}", self.name),
-                format!(
-                    "Dear {},
-
-Unfortunately, we cannot accept it at this time since you failed to provide the
-requested materials.
-
-All the best,
-The Oxide Team",
-                    self.name
-                ),
-                vec![self.email.to_string()],
-                vec![format!("careers@{}", DOMAIN)],
-                vec![],
-                format!("careers@{}", DOMAIN),
-            )
-            .await;
+    pub async fn send_email_rejection_did_not_provide_materials(&self, db: &Database) -> Result<Vec<EmailSend>, TemplateRenderError> {
+        self.send_email_template(db, &EMAIL_TEMPLATE_REJECTION_NO_MATERIALS).await
     }
 
     /// Send an email to the applicant about timing.
-    #[instrument]
+    #[instrument(skip(db))]
     #[inline]
-    pub async fn send_email_rejection_timing(&self) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
+    pub async fn send_email_rejection_timing(&self, db: &Database) -> Result<Vec<EmailSend>, TemplateRenderError> {
+        self.send_email_template(db, &EMAIL_TEMPLATE_REJECTION_TIMING).await
+    }
 
-        // Send the message.
-        sendgrid_client
-            .send_mail(
-                format!("Thank you for your application, {}", self.name),
-                format!(
-                    "Dear {},
-
-We are so humbled by your application to join Oxide Computer Company. At this
-stage of the company we are hyper-focused on certain areas of the stack and
-when we need specific domain space experience such as yours, please engage
-with us. Our roles will be updated as we need them.
-
-We are grateful you took the time to apply and put so much thought into the
-candidate materials, we loved reading them. We would absolutely love to work
-with you in the future and cannot wait for that stage of the company!
-
-All the best,
-The Oxide Team",
-                    self.name
-                ),
-                vec![self.email.to_string()],
+    /// Send one of the applicant email templates to the applicant, from and cc'ing careers@.
+    #[instrument(skip(db))]
+    #[inline]
+    async fn send_email_template(&self, db: &Database, template: &EmailTemplate) -> Result<Vec<EmailSend>, TemplateRenderError> {
+        template
+            .send(
+                db,
+                &json!({
+                    "name": self.name,
+                    "role": self.role,
+                }),
+                vec![EmailRecipient::new(&self.name, &self.email)],
                 vec![format!("careers@{}", DOMAIN)],
-                vec![],
-                format!("careers@{}", DOMAIN),
+                &format!("careers@{}", DOMAIN),
             )
-            .await;
+            .await
     }
 
     /// Send an email internally that we have a new application.
@@ -680,21 +628,25 @@ The Oxide Team",
     }
 
     /// Expand the applicants materials and do any automation that needs to be done.
-    #[instrument(skip(drive_client, sheets_client))]
+    #[instrument(skip(db, drive_client, sheets_client))]
     #[inline]
-    pub async fn expand(&mut self, drive_client: &GoogleDrive, sheets_client: &Sheets, sent_email_received_column_index: usize, sent_email_follow_up_index: usize, row_index: usize) {
+    pub async fn expand(&mut self, db: &Database, drive_client: &GoogleDrive, sheets_client: &Sheets, sent_email_received_column_index: usize, sent_email_follow_up_index: usize, row_index: usize) {
         // Check if we have sent them an email that we received their application.
         if !self.sent_email_received {
             // Send them an email.
-            self.send_email_recieved_application_to_applicant().await;
+            // If the template fails to render we leave the column alone so we try again.
+            match self.send_email_recieved_application_to_applicant(db).await {
+                Ok(_) => {
+                    // Mark the column as true not false.
+                    let mut colmn = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
+                    let rng = format!("{}{}", colmn.nth(sent_email_received_column_index).unwrap().to_string(), row_index);
 
-            // Mark the column as true not false.
-            let mut colmn = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
-            let rng = format!("{}{}", colmn.nth(sent_email_received_column_index).unwrap().to_string(), row_index);
-
-            sheets_client.update_values(&self.sheet_id, &rng, "TRUE".to_string()).await.unwrap();
+                    sheets_client.update_values(&self.sheet_id, &rng, "TRUE".to_string()).await.unwrap();
 
-            println!("[applicant] sent email to {} that we received their application", self.email);
+                    println!("[applicant] sent email to {} that we received their application", self.email);
+                }
+                Err(e) => println!("[applicant] rendering email to {} that we received their application failed: {}", self.email, e),
+            }
         }
 
         // Send an email follow up if we should.
@@ -706,23 +658,27 @@ The Oxide Team",
             let status = crate::applicant_status::Status::from_str(&self.status).unwrap_or_default();
             if status == crate::applicant_status::Status::Declined || status == crate::applicant_status::Status::Deferred {
                 // Check if we have sent the follow up email to them.unwrap_or_default().
-                if self.raw_status.contains("did not do materials") {
+                let sent = if self.raw_status.contains("did not do materials") {
                     // Send the email.
-                    self.send_email_rejection_did_not_provide_materials().await;
-
-                    println!("[applicant] sent email to {} tell them they did not do the materials", self.email);
+                    self.send_email_rejection_did_not_provide_materials(db).await
                 } else {
                     // Send the email.
-                    self.send_email_rejection_timing().await;
+                    self.send_email_rejection_timing(db).await
+                };
 
-                    println!("[applicant] sent email to {} tell them about timing", self.email);
-                }
+                match sent {
+                    Ok(_) => {
+                        println!("[applicant] sent follow up email to {} for status `{}`", self.email, self.raw_status);
 
-                // Update the cell in the google sheet so we know we sent the email.
-                // Mark the column as true not false.
-                sheets_client.update_values(&self.sheet_id, &rng, "TRUE".to_string()).await.unwrap();
+                        // Update the cell in the google sheet so we know we sent the email.
+                        // Mark the column as true not false.
+                        sheets_client.update_values(&self.sheet_id, &rng, "TRUE".to_string()).await.unwrap();
 
-                self.sent_email_follow_up = true;
+                        self.sent_email_follow_up = true;
+                    }
+                    // Leave the cell alone so we try again next time.
+                    Err(e) => println!("[applicant] rendering follow up email to {} failed: {}", self.email, e),
+                }
             } else if status != crate::applicant_status::Status::NeedsToBeTriaged {
                 // Just set that we have sent the email so that we don't do it again if we move to
                 // next steps then interviews etc.
@@ -1414,6 +1370,42 @@ cc @jessfraz @sdtuck @bcantrill",
 
         println!("[applicant]: created onboarding issue for {}", self.email);
     }
+
+    /// Update the status of the applicant in their row of the Google Sheet.
+    #[instrument(skip(sheets_client))]
+    #[inline]
+    pub async fn update_status_in_sheet(&self, sheets_client: &Sheets, status: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let sheet_values = sheets_client
+            .get_values(&self.sheet_id, "Form Responses 1!A1:S1000".to_string())
+            .await
+            .map_err(|e| format!("getting the values of Google sheet {} failed: {}", self.sheet_id, e))?;
+        let values = sheet_values.string_rows();
+        if values.is_empty() {
+            return Err(format!("unable to retrieve any data values from Google sheet {}", self.sheet_id).into());
+        }
+
+        // Parse the sheet columns.
+        let columns = ApplicantSheetColumns::parse(&values);
+
+        // Find the row for the applicant, the header row is skipped.
+        for (row_index, row) in values.iter().enumerate().skip(1) {
+            if row.len() <= columns.email || row[columns.email].trim().to_lowercase() != self.email.to_lowercase() {
+                continue;
+            }
+
+            let mut colmn = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
+            let rng = format!("Form Responses 1!{}{}", colmn.nth(columns.status).unwrap().to_string(), row_index + 1);
+            sheets_client
+                .update_values(&self.sheet_id, &rng, status.to_string())
+                .await
+                .map_err(|e| format!("updating {} in Google sheet {} failed: {}", rng, self.sheet_id, e))?;
+
+            println!("[applicant] updated status for {} to `{}` in Google sheet {}", self.email, status, self.sheet_id);
+            return Ok(());
+        }
+
+        Err(format!("no row for {} in Google sheet {}", self.email, self.sheet_id).into())
+    }
 }
 
 #[instrument]
@@ -1772,14 +1764,14 @@ pub fn get_role_from_sheet_id(sheet_id: &str) -> String {
 }
 
 /// Return a vector of all the raw applicants and add all the metadata.
-#[instrument]
+#[instrument(skip(db))]
 #[inline]
-pub async fn get_raw_applicants() -> Vec<NewApplicant> {
+pub async fn get_raw_applicants(db: &Database) -> Vec<NewApplicant> {
     // Get the GSuite token.
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
 
     // Initialize the GSuite sheets client.
     let drive_client = GoogleDrive::new(token.clone());
@@ -1790,7 +1782,7 @@ pub async fn get_raw_applicants() -> Vec<NewApplicant> {
     for (sheet_name, sheet_id) in get_sheets_map() {
         // Get the values in the sheet.
         let sheet_values = sheets_client.get_values(&sheet_id, "Form Responses 1!A1:S1000".to_string()).await.unwrap();
-        let values = sheet_values.values.unwrap();
+        let values = sheet_values.string_rows();
 
         if values.is_empty() {
             panic!("unable to retrieve any data values from Google sheet {} {}", sheet_id, sheet_name);
@@ -1814,7 +1806,7 @@ pub async fn get_raw_applicants() -> Vec<NewApplicant> {
             // Parse the applicant out of the row information.
             let mut applicant = NewApplicant::parse_from_row_with_columns(sheet_name, sheet_id, &columns, &row).await;
             applicant
-                .expand(&drive_client, &sheets_client, columns.sent_email_received, columns.sent_email_follow_up, row_index + 1)
+                .expand(db, &drive_client, &sheets_client, columns.sent_email_received, columns.sent_email_follow_up, row_index + 1)
                 .await;
 
             if !applicant.sent_email_received {
@@ -1836,7 +1828,7 @@ pub async fn get_raw_applicants() -> Vec<NewApplicant> {
 #[instrument(skip(db))]
 #[inline]
 pub async fn refresh_db_applicants(db: &Database) {
-    let applicants = get_raw_applicants().await;
+    let applicants = get_raw_applicants(db).await;
 
     let github = authenticate_github_jwt();
 
@@ -1856,6 +1848,56 @@ pub async fn refresh_db_applicants(db: &Database) {
     }
 }
 
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Move the applicants with the email from giving an offer to onboarding, once
+/// they have signed their offer letter, and create their onboarding issues.
+/// The status is changed in the Google Sheet as well as the database, since
+/// the database is synced from the sheet.
+/// Errors are returned, rather than logged, so the caller can have the event
+/// sent again.
+#[instrument(skip(db, github))]
+#[inline]
+pub async fn onboard_applicants_with_signed_offer(db: &Database, github: &Github, email: &str) -> Result<Vec<Applicant>, Box<dyn Error + Send + Sync>> {
+    // The email in the sheet might not be in the same case as the one they signed with.
+    let result = applicants::dsl::applicants
+        .filter(lower(applicants::dsl::email).eq(email.trim().to_lowercase()))
+        .load::<Applicant>(&db.conn())
+        .map_err(|e| format!("loading the applicants with email {} failed: {}", email, e))?;
+
+    let giving_offer = crate::applicant_status::Status::GivingOffer.to_string();
+    let onboarding = crate::applicant_status::Status::Onboarding.to_string();
+    let applicants: Vec<Applicant> = result.into_iter().filter(|a| a.status == giving_offer).collect();
+    if applicants.is_empty() {
+        return Ok(applicants);
+    }
+
+    // Initialize the GSuite sheets client.
+    let sheets_client = Sheets::new(get_gsuite_token("").await).map_err(|e| format!("creating the sheets client failed: {}", e))?;
+
+    // Get all the hiring issues on the configs repository.
+    let configs_issues = github
+        .repo(github_org(), "configs")
+        .issues()
+        .list(&IssueListOptions::builder().per_page(100).state(State::All).labels(vec!["hiring"]).build())
+        .await
+        .map_err(|e| format!("listing the hiring issues failed: {}", e))?;
+
+    let mut onboarded: Vec<Applicant> = Default::default();
+    for mut applicant in applicants {
+        applicant.update_status_in_sheet(&sheets_client, &onboarding).await?;
+
+        applicant.status = onboarding.to_string();
+        applicant.raw_status = onboarding.to_string();
+        let applicant = applicant.update(db).await;
+
+        applicant.create_github_onboarding_issue(github, &configs_issues).await;
+        onboarded.push(applicant);
+    }
+
+    Ok(onboarded)
+}
+
 /// The data type for a Google Sheet applicant form columns, we use this when
 /// parsing the Google Sheets for applicant forms where we leave our voting.
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -1888,11 +1930,11 @@ async fn get_reviewer_pool(sheet_id: &str) -> Vec<String> {
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
 
     // Get the values in the sheet.
     let sheet_values = sheets_client.get_values(&sheet_id, "Reviewer pool!A1:G1000".to_string()).await.unwrap();
-    let values = sheet_values.values.unwrap();
+    let values = sheet_values.string_rows();
 
     if values.is_empty() {
         panic!("unable to retrieve any data values from Google sheet for applicant forms {}", sheet_id);
@@ -1919,11 +1961,11 @@ pub async fn update_applications_with_scoring_forms(db: &Database) {
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
     for sheet_id in get_tracking_sheets() {
         // Get the values in the sheet.
         let sheet_values = sheets_client.get_values(&sheet_id, "Applicants to review!A1:G1000".to_string()).await.unwrap();
-        let values = sheet_values.values.unwrap();
+        let values = sheet_values.string_rows();
 
         if values.is_empty() {
             panic!("unable to retrieve any data values from Google sheet for applicant forms {}", sheet_id);
@@ -2001,11 +2043,11 @@ pub async fn update_applications_with_scoring_results(db: &Database) {
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
     for sheet_id in get_tracking_sheets() {
         // Get the values in the sheet.
         let sheet_values = sheets_client.get_values(&sheet_id, "Responses!A1:R1000".to_string()).await.unwrap();
-        let values = sheet_values.values.unwrap();
+        let values = sheet_values.string_rows();
 
         if values.is_empty() {
             panic!("unable to retrieve any data values from Google sheet for applicant form responses {}", sheet_id);
@@ -2193,12 +2235,12 @@ pub async fn update_applicant_reviewers(db: &Database) {
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
     let sheet_id = "1BOeZTdSNixkJsVHwf3Z0LMVlaXsc_0J8Fsy9BkCa7XM";
 
     // Get the values in the sheet.
     let sheet_values = sheets_client.get_values(&sheet_id, "Leaderboard!A1:R1000".to_string()).await.unwrap();
-    let values = sheet_values.values.unwrap();
+    let values = sheet_values.string_rows();
 
     if values.is_empty() {
         panic!("unable to retrieve any data values from Google sheet for reviewer leaderboard {}", sheet_id);
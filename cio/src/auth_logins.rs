@@ -1,15 +1,15 @@
 #![allow(clippy::from_over_into)]
 use std::collections::HashMap;
 use std::env;
-use std::{thread, time};
+use std::time::Duration;
 
 use async_trait::async_trait;
-use chrono::naive::NaiveDateTime;
 use chrono::offset::Utc;
 use chrono::DateTime;
 use chrono_humanize::HumanTime;
+use client_core::{Client, RateLimit};
 use macros::db;
-use reqwest::{Client, StatusCode};
+use reqwest::StatusCode;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
@@ -280,11 +280,37 @@ pub struct Token {
     pub token_type: String,
 }
 
+/// Return the base URL for the Auth0 API for a domain. This can be overridden
+/// with the `AUTH0_ENDPOINT` environment variable to test against a fake server.
+fn auth0_endpoint(domain: &str) -> String {
+    env::var("AUTH0_ENDPOINT").unwrap_or_else(|_| format!("https://{}.auth0.com", domain))
+}
+
 /// List users.
 #[instrument(skip(db))]
 #[inline]
 pub async fn get_auth_users(domain: String, db: &Database) -> Vec<NewAuthUser> {
-    let client = Client::new();
+    let mut auth_users: Vec<NewAuthUser> = Default::default();
+    for (auth_user, auth_user_logins) in get_auth_users_and_logins(&domain).await {
+        auth_users.push(auth_user);
+
+        // Update our database with all the auth_user_logins.
+        for auth_user_login in auth_user_logins {
+            auth_user_login.upsert(db).await;
+        }
+    }
+
+    auth_users
+}
+
+/// List users from Auth0, along with their logins.
+#[instrument]
+#[inline]
+async fn get_auth_users_and_logins(domain: &str) -> Vec<(NewAuthUser, Vec<NewAuthUserLogin>)> {
+    // We need to space out our requests so we don't get rate limited.
+    // https://auth0.com/docs/policies/rate-limit-policy
+    // https://auth0.com/docs/policies/rate-limit-policy/management-api-endpoint-rate-limits
+    let client = Client::new("auth0").unwrap().with_min_interval(Duration::from_millis(2000));
     // Get our token.
     let client_id = env::var("CIO_AUTH0_CLIENT_ID").unwrap();
     let client_secret = env::var("CIO_AUTH0_CLIENT_SECRET").unwrap();
@@ -295,22 +321,16 @@ pub async fn get_auth_users(domain: String, db: &Database) -> Vec<NewAuthUser> {
     map.insert("audience", format!("https://{}.auth0.com/api/v2/", domain));
     map.insert("grant_type", "client_credentials".to_string());
 
-    let resp = client.post(&format!("https://{}.auth0.com/oauth/token", domain)).json(&map).send().await.unwrap();
+    let resp = client.send(client.post(&format!("{}/oauth/token", auth0_endpoint(domain))).json(&map)).await.unwrap();
 
     let token: Token = resp.json().await.unwrap();
 
     let mut users: Vec<User> = Default::default();
 
-    let rate_limit_sleep = time::Duration::from_millis(2000);
-
     let mut i: i32 = 0;
     let mut has_records = true;
     while has_records {
-        let mut u = get_auth_users_page(&token.access_token, &domain, &i.to_string()).await;
-        // We need to sleep here for a half second so we don't get rate limited.
-        // https://auth0.com/docs/policies/rate-limit-policy
-        // https://auth0.com/docs/policies/rate-limit-policy/management-api-endpoint-rate-limits
-        thread::sleep(rate_limit_sleep);
+        let mut u = get_auth_users_page(&client, &token.access_token, domain, &i.to_string()).await;
 
         has_records = !u.is_empty();
         i += 1;
@@ -318,13 +338,13 @@ pub async fn get_auth_users(domain: String, db: &Database) -> Vec<NewAuthUser> {
         users.append(&mut u);
     }
 
-    let mut auth_users: Vec<NewAuthUser> = Default::default();
+    let mut auth_users: Vec<(NewAuthUser, Vec<NewAuthUserLogin>)> = Default::default();
     for user in users {
         // Convert the user to an AuthUser.
         let mut auth_user = user.to_auth_user();
 
         // Get the application they last accessed.
-        let auth_user_logins = get_auth_logs_for_user(&token.access_token, &domain, &user.user_id).await;
+        let mut auth_user_logins = get_auth_logs_for_user(&client, &token.access_token, domain, &user.user_id).await;
 
         // Get the first result.
         if !auth_user_logins.is_empty() {
@@ -332,33 +352,27 @@ pub async fn get_auth_users(domain: String, db: &Database) -> Vec<NewAuthUser> {
             auth_user.last_application_accessed = first_result.client_name.to_string();
         }
 
-        auth_users.push(auth_user);
-
-        // We need to sleep here for a half second so we don't get rate limited.
-        // https://auth0.com/docs/policies/rate-limit-policy
-        // https://auth0.com/docs/policies/rate-limit-policy/management-api-endpoint-rate-limits
-        thread::sleep(rate_limit_sleep);
-
-        // Update our database with all the auth_user_logins.
-        for mut auth_user_login in auth_user_logins {
+        for auth_user_login in auth_user_logins.iter_mut() {
             auth_user_login.email = user.email.to_string();
-            auth_user_login.upsert(db).await;
         }
+
+        auth_users.push((auth_user, auth_user_logins));
     }
 
     auth_users
 }
 
 // TODO: clean this all up to be an auth0 api library.
-#[instrument]
+#[instrument(skip(client))]
 #[inline]
-async fn get_auth_logs_for_user(token: &str, domain: &str, user_id: &str) -> Vec<NewAuthUserLogin> {
-    let client = Client::new();
+async fn get_auth_logs_for_user(client: &Client, token: &str, domain: &str, user_id: &str) -> Vec<NewAuthUserLogin> {
     let resp = client
-        .get(&format!("https://{}.auth0.com/api/v2/users/{}/logs", domain, user_id))
-        .bearer_auth(token)
-        .query(&[("sort", "date:-1"), ("per_page", "100")])
-        .send()
+        .send(
+            client
+                .get(&format!("{}/api/v2/users/{}/logs", auth0_endpoint(domain), user_id))
+                .bearer_auth(token)
+                .query(&[("sort", "date:-1"), ("per_page", "100")]),
+        )
         .await
         .unwrap();
 
@@ -366,21 +380,21 @@ async fn get_auth_logs_for_user(token: &str, domain: &str, user_id: &str) -> Vec
         StatusCode::OK => (),
         StatusCode::TOO_MANY_REQUESTS => {
             // Get the rate limit headers.
-            let headers = resp.headers();
-            let limit = headers.get("x-ratelimit-limit").unwrap().to_str().unwrap();
-            let remaining = headers.get("x-ratelimit-remaining").unwrap().to_str().unwrap();
-            let reset = headers.get("x-ratelimit-reset").unwrap().to_str().unwrap();
-            let reset_int = reset.parse::<i64>().unwrap();
+            let rate_limit = RateLimit::from_headers(resp.headers());
 
             // Convert the reset to a more sane number.
-            let ts = DateTime::from_utc(NaiveDateTime::from_timestamp(reset_int, 0), Utc);
-            let mut dur = ts - Utc::now();
+            let mut dur = rate_limit.reset.unwrap_or_else(Utc::now) - Utc::now();
             if dur.num_seconds() > 0 {
                 dur = -dur;
             }
             let time = HumanTime::from(dur);
 
-            println!("getting auth0 user logs failed because of rate limit: {}, remaining: {}, reset: {}", limit, remaining, time);
+            println!(
+                "getting auth0 user logs failed because of rate limit: {}, remaining: {}, reset: {}",
+                rate_limit.limit.unwrap_or_default(),
+                rate_limit.remaining.unwrap_or_default(),
+                time
+            );
 
             return vec![];
         }
@@ -394,15 +408,16 @@ async fn get_auth_logs_for_user(token: &str, domain: &str, user_id: &str) -> Vec
     resp.json::<Vec<NewAuthUserLogin>>().await.unwrap()
 }
 
-#[instrument]
+#[instrument(skip(client))]
 #[inline]
-async fn get_auth_users_page(token: &str, domain: &str, page: &str) -> Vec<User> {
-    let client = Client::new();
+async fn get_auth_users_page(client: &Client, token: &str, domain: &str, page: &str) -> Vec<User> {
     let resp = client
-        .get(&format!("https://{}.auth0.com/api/v2/users", domain))
-        .bearer_auth(token)
-        .query(&[("per_page", "20"), ("page", page), ("sort", "last_login:-1")])
-        .send()
+        .send(
+            client
+                .get(&format!("{}/api/v2/users", auth0_endpoint(domain)))
+                .bearer_auth(token)
+                .query(&[("per_page", "20"), ("page", page), ("sort", "last_login:-1")]),
+        )
         .await
         .unwrap();
 
@@ -432,9 +447,33 @@ pub async fn refresh_auth_users_and_logins(db: &Database) {
 
 #[cfg(test)]
 mod tests {
-    use crate::auth_logins::{refresh_auth_users_and_logins, AuthUserLogins, AuthUsers};
+    use testutils::FakeApis;
+
+    use crate::auth_logins::{get_auth_users_and_logins, refresh_auth_users_and_logins, AuthUserLogins, AuthUsers};
     use crate::db::Database;
 
+    #[tokio::test(threaded_scheduler)]
+    async fn test_auth_users_and_logins_against_fakes() {
+        let apis = FakeApis::start().await;
+        let _env = apis.env();
+
+        let auth_users = get_auth_users_and_logins("oxide").await;
+        assert_eq!(auth_users.len(), 1);
+
+        let (auth_user, auth_user_logins) = &auth_users[0];
+        assert_eq!(auth_user.user_id, "github|1234567");
+        assert_eq!(auth_user.email, "jane@example.com");
+        assert_eq!(auth_user.company, "Example Inc");
+        assert_eq!(auth_user.login_provider, "github");
+        assert_eq!(auth_user.logins_count, 12);
+        assert_eq!(auth_user.last_application_accessed, "Oxide Computer Company");
+
+        assert_eq!(auth_user_logins.len(), 1);
+        assert_eq!(auth_user_logins[0].email, "jane@example.com");
+        assert_eq!(auth_user_logins[0].typev, "s");
+        assert!(!auth_user_logins[0].is_mobile);
+    }
+
     #[ignore]
     #[tokio::test(threaded_scheduler)]
     async fn test_cron_auth_users_and_logins_refresh() {
@@ -0,0 +1,326 @@
+#![allow(clippy::from_over_into)]
+use async_trait::async_trait;
+use chrono::offset::Utc;
+use chrono::DateTime;
+use handlebars::{Handlebars, TemplateRenderError};
+use macros::db;
+use schemars::JsonSchema;
+use sendgrid_api::SendGrid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::airtable::{AIRTABLE_BASE_ID_MISC, AIRTABLE_EMAIL_SENDS_TABLE};
+use crate::core::UpdateAirtableRecord;
+use crate::db::Database;
+use crate::schema::email_sends;
+
+/// A named, versioned email template.
+///
+/// The `subject` and `body` are handlebars templates. They are rendered with the
+/// data passed when sending, plus a `recipient` object containing the `name` and
+/// `email` of the person the email is being sent to. The rendered body is then
+/// wrapped in our standard layout, ending with `closing` and signed by `signature`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailTemplate {
+    pub name: &'static str,
+    pub version: i32,
+    pub subject: &'static str,
+    pub body: &'static str,
+    pub closing: &'static str,
+    pub signature: &'static str,
+}
+
+/// A recipient of a templated email.
+#[derive(Debug, Default, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct EmailRecipient {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub email: String,
+}
+
+impl EmailRecipient {
+    /// Return a recipient for an email address.
+    pub fn new(name: &str, email: &str) -> Self {
+        EmailRecipient {
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+}
+
+impl EmailTemplate {
+    /// Render the subject and body of the template for a recipient.
+    /// This returns an error if the data is missing a field the template uses.
+    #[instrument(skip(data))]
+    #[inline]
+    pub fn render<T: Serialize>(&self, data: &T, recipient: &EmailRecipient) -> Result<(String, String), TemplateRenderError> {
+        // Initialize handlebars.
+        // These are plain text emails so we do not want anything to be escaped.
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(handlebars::no_escape);
+        handlebars.set_strict_mode(true);
+
+        // Add the recipient to the data so we can personalize the email.
+        let mut context = serde_json::to_value(data).unwrap();
+        if let Value::Object(ref mut map) = context {
+            map.insert("recipient".to_string(), serde_json::to_value(recipient).unwrap());
+        }
+
+        let subject = handlebars.render_template(self.subject, &context)?;
+        let content = handlebars.render_template(self.body, &context)?;
+
+        // Wrap the body in our layout.
+        let body = handlebars.render_template(
+            TEMPLATE_EMAIL_LAYOUT,
+            &json!({
+                "content": content.trim(),
+                "closing": self.closing,
+                "signature": self.signature,
+            }),
+        )?;
+
+        Ok((subject.trim().to_string(), body))
+    }
+
+    /// Render and send the template to each of the recipients, recording every
+    /// send in the database.
+    /// The emails are all rendered before any are sent, so if rendering fails
+    /// nobody gets an email.
+    #[instrument(skip(db, data))]
+    #[inline]
+    pub async fn send<T: Serialize>(&self, db: &Database, data: &T, to: Vec<EmailRecipient>, cc: Vec<String>, from: &str) -> Result<Vec<EmailSend>, TemplateRenderError> {
+        let mut rendered: Vec<(EmailRecipient, String, String)> = Default::default();
+        for recipient in to {
+            let (subject, body) = self.render(data, &recipient)?;
+            rendered.push((recipient, subject, body));
+        }
+
+        // Initialize the SendGrid client.
+        let sendgrid_client = SendGrid::new_from_env();
+
+        let mut sends: Vec<EmailSend> = Default::default();
+        for (recipient, subject, body) in rendered {
+            // Send the message.
+            sendgrid_client
+                .send_mail(subject.to_string(), body.to_string(), vec![recipient.email.to_string()], cc.clone(), vec![], from.to_string())
+                .await;
+
+            let sent_time = Utc::now();
+            let new_send = NewEmailSend {
+                send_id: format!("{}-{}-{}", self.name, recipient.email, sent_time.timestamp_nanos()),
+                template_name: self.name.to_string(),
+                template_version: self.version,
+                recipient: recipient.email.to_string(),
+                cc: cc.join(","),
+                from_address: from.to_string(),
+                subject,
+                body,
+                sent_time,
+            };
+
+            sends.push(new_send.upsert(db).await);
+        }
+
+        Ok(sends)
+    }
+}
+
+/// The data type for a log of an email we sent from a template.
+#[db {
+    new_struct_name = "EmailSend",
+    airtable_base_id = "AIRTABLE_BASE_ID_MISC",
+    airtable_table = "AIRTABLE_EMAIL_SENDS_TABLE",
+    match_on = {
+        "send_id" = "String",
+    },
+}]
+#[derive(Debug, Insertable, AsChangeset, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+#[table_name = "email_sends"]
+pub struct NewEmailSend {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub send_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub template_name: String,
+    #[serde(default)]
+    pub template_version: i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub recipient: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub cc: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from_address: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    pub sent_time: DateTime<Utc>,
+}
+
+/// Implement updating the Airtable record for an EmailSend.
+#[async_trait]
+impl UpdateAirtableRecord<EmailSend> for EmailSend {
+    async fn update_airtable_record(&mut self, _record: EmailSend) {}
+}
+
+/// The layout every templated email is wrapped in.
+pub static TEMPLATE_EMAIL_LAYOUT: &str = r#"{{content}}
+
+{{closing}}
+  {{signature}}
+
+--
+Oxide Computer Company
+https://oxide.computer
+"#;
+
+/// The email we send to the recipient of a shipment once it is in transit.
+pub static EMAIL_TEMPLATE_SHIPMENT_TO_RECIPIENT: EmailTemplate = EmailTemplate {
+    name: "shipment_to_recipient",
+    version: 1,
+    subject: "Your package from the Oxide Computer Company is on the way!",
+    body: r#"Below is the information for your package:
+
+**Contents:**
+{{contents}}
+
+**Address to:**
+{{name}}
+{{address}}
+
+**Tracking link:**
+{{oxide_tracking_link}}
+
+If you have any questions or concerns, please respond to this email!
+Have a splendid day!"#,
+    closing: "xoxo,",
+    signature: "The Oxide Shipping Bot",
+};
+
+/// The email we send internally once the label for a shipment is printed.
+pub static EMAIL_TEMPLATE_SHIPMENT_INTERNAL: EmailTemplate = EmailTemplate {
+    name: "shipment_internal",
+    version: 1,
+    subject: "Shipment to {{name}} is ready to be packaged",
+    body: r#"Below is the information the package:
+
+**Contents:**
+{{contents}}
+
+**Address to:**
+{{name}}
+{{address}}
+
+**Tracking link:**
+{{oxide_tracking_link}}
+
+The label should already be printed in the big conference room. Please take the
+label and affix it to the package with the specified contents. It can then be dropped off
+for {{carrier}}.
+
+As always, the Airtable with all the shipments lives at:
+https://airtable-shipments.corp.oxide.computer."#,
+    closing: "xoxo,",
+    signature: "The Oxide Shipping Bot",
+};
+
+/// The email we send to an applicant once we received their application.
+pub static EMAIL_TEMPLATE_APPLICATION_RECEIVED: EmailTemplate = EmailTemplate {
+    name: "application_received",
+    version: 1,
+    subject: "Oxide Computer Company {{role}} Application Received for {{name}}",
+    body: r#"Dear {{name}},
+
+Thank you for submitting your application materials! We really appreciate all
+the time and thought everyone puts into their application. We will be in touch
+within the next couple weeks with more information."#,
+    closing: "Sincerely,",
+    signature: "The Oxide Team",
+};
+
+/// The email we send to an applicant who did not provide the requested materials.
+pub static EMAIL_TEMPLATE_REJECTION_NO_MATERIALS: EmailTemplate = EmailTemplate {
+    name: "application_rejection_no_materials",
+    version: 1,
+    subject: "Thank you for your application, {{name}}",
+    body: r#"Dear {{name}},
+
+Unfortunately, we cannot accept it at this time since you failed to provide the
+requested materials."#,
+    closing: "All the best,",
+    signature: "The Oxide Team",
+};
+
+/// The email we send to an applicant we are not moving forward with right now.
+pub static EMAIL_TEMPLATE_REJECTION_TIMING: EmailTemplate = EmailTemplate {
+    name: "application_rejection_timing",
+    version: 1,
+    subject: "Thank you for your application, {{name}}",
+    body: r#"Dear {{name}},
+
+We are so humbled by your application to join Oxide Computer Company. At this
+stage of the company we are hyper-focused on certain areas of the stack and
+when we need specific domain space experience such as yours, please engage
+with us. Our roles will be updated as we need them.
+
+We are grateful you took the time to apply and put so much thought into the
+candidate materials, we loved reading them. We would absolutely love to work
+with you in the future and cannot wait for that stage of the company!"#,
+    closing: "All the best,",
+    signature: "The Oxide Team",
+};
+
+#[cfg(test)]
+mod tests {
+    use crate::emails::{
+        EmailRecipient, EMAIL_TEMPLATE_APPLICATION_RECEIVED, EMAIL_TEMPLATE_REJECTION_NO_MATERIALS, EMAIL_TEMPLATE_REJECTION_TIMING, EMAIL_TEMPLATE_SHIPMENT_INTERNAL,
+        EMAIL_TEMPLATE_SHIPMENT_TO_RECIPIENT,
+    };
+
+    #[test]
+    fn test_render_shipment_emails() {
+        let data = json!({
+            "name": "Jess Frazelle",
+            "contents": "1 x Oxide t-shirt",
+            "address": "1 Main St\nOakland, CA 94607 US",
+            "oxide_tracking_link": "https://track.oxide.computer/usps/123",
+            "carrier": "USPS",
+        });
+        let recipient = EmailRecipient::new("Jess Frazelle", "jess@example.com");
+
+        let (subject, body) = EMAIL_TEMPLATE_SHIPMENT_TO_RECIPIENT.render(&data, &recipient).unwrap();
+        assert_eq!(subject, "Your package from the Oxide Computer Company is on the way!");
+        assert!(body.contains("1 x Oxide t-shirt"));
+        assert!(body.contains("https://track.oxide.computer/usps/123"));
+        assert!(body.contains("xoxo,\n  The Oxide Shipping Bot"));
+
+        let (subject, body) = EMAIL_TEMPLATE_SHIPMENT_INTERNAL.render(&data, &recipient).unwrap();
+        assert_eq!(subject, "Shipment to Jess Frazelle is ready to be packaged");
+        assert!(body.contains("dropped off\nfor USPS."));
+
+        // Missing data is an error, not a panic or an empty field.
+        assert!(EMAIL_TEMPLATE_SHIPMENT_INTERNAL.render(&json!({ "name": "Jess Frazelle" }), &recipient).is_err());
+    }
+
+    #[test]
+    fn test_render_applicant_emails() {
+        let data = json!({
+            "name": "Jess Frazelle",
+            "role": "Engineering",
+        });
+        let recipient = EmailRecipient::new("Jess Frazelle", "jess@example.com");
+
+        let (subject, body) = EMAIL_TEMPLATE_APPLICATION_RECEIVED.render(&data, &recipient).unwrap();
+        assert_eq!(subject, "Oxide Computer Company Engineering Application Received for Jess Frazelle");
+        assert!(body.starts_with("Dear Jess Frazelle,"));
+        assert!(body.contains("Sincerely,\n  The Oxide Team"));
+
+        let (subject, body) = EMAIL_TEMPLATE_REJECTION_NO_MATERIALS.render(&data, &recipient).unwrap();
+        assert_eq!(subject, "Thank you for your application, Jess Frazelle");
+        assert!(body.contains("failed to provide the\nrequested materials."));
+        assert!(body.contains("All the best,\n  The Oxide Team"));
+
+        let (_, body) = EMAIL_TEMPLATE_REJECTION_TIMING.render(&data, &recipient).unwrap();
+        assert!(body.contains("cannot wait for that stage of the company!"));
+    }
+}
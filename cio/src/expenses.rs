@@ -0,0 +1,361 @@
+#![allow(clippy::from_over_into)]
+use std::env;
+
+use async_trait::async_trait;
+use chrono::offset::Utc;
+use chrono::DateTime;
+use google_drive::GoogleDrive;
+use macros::db;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::airtable::{AIRTABLE_BASE_ID_FINANCE, AIRTABLE_CARD_TRANSACTIONS_REVIEW_TABLE};
+use crate::core::UpdateAirtableRecord;
+use crate::db::Database;
+use crate::schema::card_transactions;
+use crate::utils::{get_gsuite_token, DOMAIN};
+
+/// The data type for a card transaction.
+/// Transactions without a matching receipt are mirrored to an Airtable table
+/// so they can be reviewed by a human.
+#[db {
+    new_struct_name = "CardTransaction",
+    airtable_base_id = "AIRTABLE_BASE_ID_FINANCE",
+    airtable_table = "AIRTABLE_CARD_TRANSACTIONS_REVIEW_TABLE",
+    match_on = {
+        "transaction_id" = "String",
+    },
+}]
+#[derive(Debug, Insertable, AsChangeset, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+#[table_name = "card_transactions"]
+pub struct NewCardTransaction {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transaction_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub card_last_four: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub employee_email: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub merchant_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub category: String,
+    /// The amount of the transaction in cents, so it can be compared exactly.
+    #[serde(default)]
+    pub amount_cents: i64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    pub time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub memo: String,
+    #[serde(default)]
+    pub receipt_matched: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub receipt_link: String,
+    /// This field is filled in by the Airtable and should not be edited by the
+    /// API updating.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+}
+
+/// Implement updating the Airtable record for a CardTransaction.
+#[async_trait]
+impl UpdateAirtableRecord<CardTransaction> for CardTransaction {
+    async fn update_airtable_record(&mut self, record: CardTransaction) {
+        if self.notes.is_empty() {
+            self.notes = record.notes;
+        }
+        if self.receipt_link.is_empty() {
+            self.receipt_link = record.receipt_link;
+        }
+    }
+}
+
+impl NewCardTransaction {
+    /// Ingest a card transaction into the database. If we do not already have a
+    /// receipt for the transaction, it will be mirrored to Airtable for review.
+    #[instrument(skip(db))]
+    #[inline]
+    pub async fn ingest(&self, db: &Database) -> CardTransaction {
+        let transaction = self.upsert_in_db(db);
+
+        if transaction.receipt_matched {
+            return transaction;
+        }
+
+        // Mirror the transaction to our review table.
+        transaction.update(db).await
+    }
+}
+
+impl CardTransaction {
+    /// Mark the transaction as having a receipt and remove it from the review table.
+    #[instrument(skip(db))]
+    #[inline]
+    pub async fn mark_receipt_matched(&mut self, db: &Database, receipt_link: &str) {
+        self.receipt_matched = true;
+        self.receipt_link = receipt_link.to_string();
+
+        // We no longer need a human to review this transaction.
+        self.delete_from_airtable().await;
+        self.airtable_record_id = Default::default();
+
+        *self = self.update_in_db(db);
+    }
+
+    /// Get all the transactions that we do not yet have receipts for.
+    #[instrument(skip(db))]
+    #[inline]
+    pub fn get_unmatched_from_db(db: &Database) -> Vec<CardTransaction> {
+        card_transactions::dsl::card_transactions
+            .filter(card_transactions::dsl::receipt_matched.eq(false))
+            .order_by(card_transactions::dsl::time.desc())
+            .load::<CardTransaction>(&db.conn())
+            .unwrap()
+    }
+}
+
+/// Return the email address receipts are forwarded to.
+#[instrument]
+#[inline]
+pub fn get_receipts_email() -> String {
+    env::var("RECEIPTS_EMAIL").unwrap_or_else(|_| format!("receipts@{}", DOMAIN))
+}
+
+/// A receipt sent to our receipts inbox and posted to us by the SendGrid
+/// inbound parse webhook.
+/// FROM: https://sendgrid.com/docs/for-developers/parsing-email/setting-up-the-inbound-parse-webhook/
+#[derive(Debug, Default, Clone)]
+pub struct InboundReceipt {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub attachments: Vec<ReceiptAttachment>,
+}
+
+/// An attachment on an inbound receipt.
+#[derive(Debug, Default, Clone)]
+pub struct ReceiptAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub contents: Vec<u8>,
+}
+
+impl InboundReceipt {
+    /// Returns if the email was sent to our receipts inbox.
+    #[instrument]
+    #[inline]
+    pub fn is_for_receipts_inbox(&self) -> bool {
+        self.to.to_lowercase().contains(&get_receipts_email().to_lowercase())
+    }
+
+    /// Return the email address of the sender, without the name.
+    /// The `from` field looks like `Jess Frazelle <jess@oxide.computer>`.
+    #[instrument]
+    #[inline]
+    pub fn sender_email(&self) -> String {
+        let from = self.from.trim();
+        match (from.rfind('<'), from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => from[start + 1..end].trim().to_lowercase(),
+            _ => from.to_lowercase(),
+        }
+    }
+
+    /// Parse all the dollar amounts mentioned in the subject and body of the
+    /// email, in cents. Amounts look like `$1,234.56`, `$ 12.00` or `$12`.
+    #[instrument]
+    #[inline]
+    pub fn amounts(&self) -> Vec<i64> {
+        let re = Regex::new(r"\$\s?([0-9][0-9,]*)(?:\.([0-9]{2}))?").unwrap();
+        let content = format!("{}\n{}", self.subject, self.text);
+
+        re.captures_iter(&content)
+            .filter_map(|c| {
+                let dollars = c[1].replace(',', "").parse::<i64>().ok()?;
+                let cents = c.get(2).map_or(Ok(0), |m| m.as_str().parse::<i64>()).ok()?;
+                dollars.checked_mul(100)?.checked_add(cents)
+            })
+            .collect()
+    }
+
+    /// Returns if the receipt is for the given transaction.
+    /// The receipt must mention the amount of the transaction and either be sent
+    /// by the cardholder or mention the merchant.
+    #[instrument]
+    #[inline]
+    pub fn matches(&self, transaction: &CardTransaction) -> bool {
+        if !self.amounts().contains(&transaction.amount_cents) {
+            return false;
+        }
+
+        let content = format!("{}\n{}", self.subject, self.text).to_lowercase();
+        self.sender_email() == transaction.employee_email.to_lowercase() || (!transaction.merchant_name.is_empty() && content.contains(&transaction.merchant_name.to_lowercase()))
+    }
+
+    /// Upload the attachments of the receipt to Google Drive and return the link
+    /// to the first one.
+    #[instrument(skip(self))]
+    #[inline]
+    pub async fn upload_to_drive(&self, transaction: &CardTransaction) -> String {
+        if self.attachments.is_empty() {
+            return Default::default();
+        }
+
+        // Initialize the Google Drive client.
+        let token = get_gsuite_token("").await;
+        let drive_client = GoogleDrive::new(token);
+
+        // Figure out where our directory is.
+        // It should be in the shared drive : "Automated Documents"/"receipts"
+        let shared_drive = drive_client.get_drive_by_name("Automated Documents").await.unwrap();
+        let drive_id = shared_drive.id.to_string();
+
+        // Get the directory by the name.
+        let drive_receipts_dir = drive_client.get_file_by_name(&drive_id, "receipts").await.unwrap();
+        let parent_id = drive_receipts_dir.get(0).unwrap().id.to_string();
+
+        let mut link = String::new();
+        for attachment in &self.attachments {
+            let file_name = format!("{}-{}-{}", transaction.time.format("%Y-%m-%d"), transaction.transaction_id, attachment.filename);
+
+            // Create or update the file in the google_drive.
+            drive_client
+                .create_or_upload_file(&drive_id, &parent_id, &file_name, &attachment.content_type, &attachment.contents)
+                .await
+                .unwrap();
+
+            if link.is_empty() {
+                // Get the file in drive.
+                let files = drive_client.get_file_by_name(&drive_id, &file_name).await.unwrap();
+                if let Some(f) = files.get(0) {
+                    link = format!("https://drive.google.com/open?id={}", f.id);
+                }
+            }
+        }
+
+        link
+    }
+}
+
+/// Match a receipt sent to our receipts inbox against the transactions we do not
+/// yet have receipts for. Returns the matched transaction, if any.
+#[instrument(skip(db, receipt))]
+#[inline]
+pub async fn handle_inbound_receipt(db: &Database, receipt: &InboundReceipt) -> Option<CardTransaction> {
+    if !receipt.is_for_receipts_inbox() {
+        println!("[receipts] email `{}` was not sent to {}, ignoring", receipt.subject, get_receipts_email());
+        return None;
+    }
+
+    for mut transaction in CardTransaction::get_unmatched_from_db(db) {
+        if !receipt.matches(&transaction) {
+            continue;
+        }
+
+        let link = receipt.upload_to_drive(&transaction).await;
+        transaction.mark_receipt_matched(db, &link).await;
+        println!("[receipts] matched receipt `{}` to transaction {}", receipt.subject, transaction.transaction_id);
+
+        return Some(transaction);
+    }
+
+    println!("[receipts] could not find a transaction for receipt `{}` from {}", receipt.subject, receipt.sender_email());
+    None
+}
+
+/// Sync the card transactions that still need receipts with our Airtable review table.
+#[instrument]
+#[inline]
+pub async fn refresh_card_transactions() {
+    let db = Database::new();
+
+    // Pull in any receipt links that were added by hand in Airtable.
+    let records = CardTransactions::get_from_airtable().await;
+    for (_, record) in records {
+        if let Some(mut transaction) = CardTransaction::get_from_db(&db, record.fields.transaction_id.to_string()) {
+            if !record.fields.receipt_link.is_empty() && !transaction.receipt_matched {
+                transaction.notes = record.fields.notes.to_string();
+                transaction.mark_receipt_matched(&db, &record.fields.receipt_link).await;
+            }
+        }
+    }
+
+    // Make sure everything that is unmatched is in the review table.
+    for transaction in CardTransaction::get_unmatched_from_db(&db) {
+        transaction.update(&db).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::expenses::{refresh_card_transactions, CardTransaction, InboundReceipt};
+
+    #[ignore]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_cron_card_transactions() {
+        refresh_card_transactions().await;
+    }
+
+    fn receipt(from: &str, subject: &str, text: &str) -> InboundReceipt {
+        InboundReceipt {
+            from: from.to_string(),
+            to: "receipts@oxide.computer".to_string(),
+            subject: subject.to_string(),
+            text: text.to_string(),
+            attachments: Default::default(),
+        }
+    }
+
+    fn transaction(employee_email: &str, merchant_name: &str, amount_cents: i64) -> CardTransaction {
+        serde_json::from_value(json!({
+            "transaction_id": "txn_1",
+            "employee_email": employee_email,
+            "merchant_name": merchant_name,
+            "amount_cents": amount_cents,
+            "currency": "USD",
+            "time": "2021-04-08T20:27:07Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_inbound_receipt_sender_email() {
+        assert_eq!(receipt("Jess Frazelle <Jess@Oxide.Computer>", "", "").sender_email(), "jess@oxide.computer");
+        assert_eq!(receipt("\"Frazelle, Jess\" <jess@oxide.computer> ", "", "").sender_email(), "jess@oxide.computer");
+        assert_eq!(receipt(" jess@oxide.computer ", "", "").sender_email(), "jess@oxide.computer");
+    }
+
+    #[test]
+    fn test_inbound_receipt_amounts() {
+        // Several amounts in the subject come before the ones in the body.
+        let r = receipt("", "Your order: $12.99 + $3.01 shipping", "Total: $16.00");
+        assert_eq!(r.amounts(), vec![1299, 301, 1600]);
+
+        // Thousands separators, a space after the sign and whole dollars.
+        let r = receipt("", "", "Subtotal $1,234.56, tax $ 98.76, tip $20 and $1,000");
+        assert_eq!(r.amounts(), vec![123456, 9876, 2000, 100000]);
+
+        // Numbers without a dollar sign are not amounts.
+        assert!(receipt("", "Order #1234", "12.34 USD").amounts().is_empty());
+    }
+
+    #[test]
+    fn test_inbound_receipt_matches() {
+        let t = transaction("jess@oxide.computer", "Amazon", 1999);
+
+        // Sent by the cardholder, or mentioning the merchant, with the exact amount.
+        assert!(receipt("Jess Frazelle <jess@oxide.computer>", "Fwd: your receipt", "$19.99").matches(&t));
+        assert!(receipt("orders@shop.example", "Your Amazon.com order", "Total: $19.99").matches(&t));
+
+        // A cent off is a different amount.
+        assert!(!receipt("jess@oxide.computer", "Fwd: your receipt", "$19.98").matches(&t));
+        assert!(!receipt("jess@oxide.computer", "Fwd: your receipt", "$20.00").matches(&t));
+
+        // The right amount from someone else about another merchant.
+        assert!(!receipt("orders@shop.example", "Your order", "Total: $19.99").matches(&t));
+    }
+}
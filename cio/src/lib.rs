@@ -10,6 +10,8 @@ pub mod certs;
 pub mod configs;
 pub mod core;
 pub mod db;
+pub mod emails;
+pub mod expenses;
 pub mod finance;
 pub mod gsuite;
 pub mod interviews;
@@ -22,9 +24,11 @@ pub mod schema;
 pub mod shipments;
 pub mod shorturls;
 pub mod slack;
+pub mod swag_store;
 pub mod tailscale;
 pub mod templates;
 pub mod utils;
+pub mod zoom_recordings;
 
 #[macro_use]
 extern crate diesel;
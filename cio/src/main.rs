@@ -17,6 +17,8 @@ use cio_api::db::Database;
 use cio_api::journal_clubs::{JournalClubMeeting, JournalClubMeetings};
 use cio_api::mailing_list::{MailingListSubscriber, MailingListSubscribers};
 use cio_api::models::{GithubRepo, GithubRepos, RFDs, RFD};
+use cio_api::shipments::get_shipments_geojson;
+use cio_api::utils::check_bearer_token;
 
 #[macro_use]
 extern crate serde_json;
@@ -80,8 +82,15 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     api.register(api_get_mailing_list_subscribers).unwrap();
     api.register(api_get_rfds).unwrap();
     api.register(api_get_schema).unwrap();
+    api.register(api_get_shipments_geojson).unwrap();
     api.register(api_get_users).unwrap();
 
+    // Without a token every request to the endpoints that need one is refused,
+    // so say why at startup instead of only returning 401s.
+    if env::var("CIO_API_TOKEN").unwrap_or_default().is_empty() {
+        println!("CIO_API_TOKEN is not set, requests to /shipments/geojson will be refused");
+    }
+
     // Print the OpenAPI Spec to stdout.
     let api_file = "openapi-cio.json";
     let mut tmp_file = env::temp_dir();
@@ -346,6 +355,46 @@ async fn api_get_rfds(rqctx: Arc<RequestContext>) -> Result<HttpResponseOk<Vec<R
     Ok(HttpResponseOk(RFDs::get_from_db(db).0))
 }
 
+/**
+ * Fetch a GeoJSON FeatureCollection of where our outbound shipments went.
+ * This includes the city and contents of shipments, so it needs a bearer token
+ * matching `CIO_API_TOKEN`. Only addresses the cron job already geocoded are
+ * included, we do not geocode on every request.
+ */
+#[endpoint {
+    method = GET,
+    path = "/shipments/geojson",
+}]
+#[instrument]
+#[inline]
+async fn api_get_shipments_geojson(rqctx: Arc<RequestContext>) -> Result<HttpResponseOk<serde_json::Value>, HttpError> {
+    authorize_request(&rqctx).await?;
+
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    match get_shipments_geojson(db, None).await {
+        Ok(geojson) => Ok(HttpResponseOk(geojson)),
+        Err(e) => Err(HttpError::for_internal_error(format!("getting the shipments from airtable failed: {}", e))),
+    }
+}
+
+/**
+ * Check that a request has a bearer token matching `CIO_API_TOKEN`.
+ */
+#[instrument]
+#[inline]
+async fn authorize_request(rqctx: &Arc<RequestContext>) -> Result<(), HttpError> {
+    let req = rqctx.request.lock().await;
+    let authorization = req.headers().get(hyper::header::AUTHORIZATION).and_then(|h| h.to_str().ok()).unwrap_or_default();
+
+    if !check_bearer_token(authorization, &env::var("CIO_API_TOKEN").unwrap_or_default()) {
+        return Err(HttpError::for_client_error(None, StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    Ok(())
+}
+
 /**
  * Fetch a list of employees.
  */
@@ -166,6 +166,25 @@ table! {
     }
 }
 
+table! {
+    card_transactions (id) {
+        id -> Int4,
+        transaction_id -> Varchar,
+        card_last_four -> Varchar,
+        employee_email -> Varchar,
+        merchant_name -> Varchar,
+        category -> Varchar,
+        amount_cents -> Int8,
+        currency -> Varchar,
+        time -> Timestamptz,
+        memo -> Varchar,
+        receipt_matched -> Bool,
+        receipt_link -> Varchar,
+        notes -> Varchar,
+        airtable_record_id -> Varchar,
+    }
+}
+
 table! {
     certificates (id) {
         id -> Int4,
@@ -193,6 +212,32 @@ table! {
     }
 }
 
+table! {
+    email_sends (id) {
+        id -> Int4,
+        send_id -> Varchar,
+        template_name -> Varchar,
+        template_version -> Int4,
+        recipient -> Varchar,
+        cc -> Varchar,
+        from_address -> Varchar,
+        subject -> Varchar,
+        body -> Varchar,
+        sent_time -> Timestamptz,
+        airtable_record_id -> Varchar,
+    }
+}
+
+table! {
+    geocoded_addresses (id) {
+        id -> Int4,
+        address -> Varchar,
+        latitude -> Float8,
+        longitude -> Float8,
+        airtable_record_id -> Varchar,
+    }
+}
+
 table! {
     github_repos (id) {
         id -> Int4,
@@ -446,6 +491,18 @@ table! {
     }
 }
 
+table! {
+    swag_items (id) {
+        id -> Int4,
+        name -> Varchar,
+        size -> Varchar,
+        description -> Varchar,
+        image_link -> Varchar,
+        current_stock -> Int4,
+        airtable_record_id -> Varchar,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -479,6 +536,26 @@ table! {
     }
 }
 
+table! {
+    zoom_recordings (id) {
+        id -> Int4,
+        recording_id -> Varchar,
+        meeting_id -> Int8,
+        meeting_uuid -> Varchar,
+        topic -> Varchar,
+        host_id -> Varchar,
+        recording_start -> Timestamptz,
+        recording_end -> Timestamptz,
+        file_type -> Varchar,
+        file_size -> Int8,
+        file_name -> Varchar,
+        google_drive_link -> Varchar,
+        archived_time -> Timestamptz,
+        deleted_from_zoom -> Bool,
+        airtable_record_id -> Varchar,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
     applicant_interviews,
     applicant_reviewers,
@@ -486,8 +563,11 @@ allow_tables_to_appear_in_same_query!(
     auth_user_logins,
     auth_users,
     buildings,
+    card_transactions,
     certificates,
     conference_rooms,
+    email_sends,
+    geocoded_addresses,
     github_repos,
     groups,
     inbound_shipments,
@@ -499,5 +579,7 @@ allow_tables_to_appear_in_same_query!(
     recorded_meetings,
     rfds,
     software_vendors,
+    swag_items,
     users,
+    zoom_recordings,
 );
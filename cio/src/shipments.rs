@@ -1,25 +1,30 @@
 #![allow(clippy::from_over_into)]
 use std::collections::HashMap;
 use std::env;
+use std::error::Error;
 
 use async_trait::async_trait;
 use chrono::naive::NaiveDate;
 use chrono::offset::Utc;
 use chrono::DateTime;
+use google_drive::GoogleDrive;
+use google_geocode::{Coordinates, Geocode};
 use macros::db;
 use reqwest::StatusCode;
 use schemars::JsonSchema;
-use sendgrid_api::SendGrid;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sheets::Sheets;
-use shippo::{Address, CustomsDeclaration, CustomsItem, NewShipment, NewTransaction, Parcel, Shippo};
+use shippo::{Address, Carrier, CustomsDeclaration, CustomsItem, DistanceUnit, MassUnit, NewShipment, NewTransaction, Parcel, Shippo, TrackingStatus, TrackingStatusCode, TransactionStatus, Webhook};
+use slack_chat_api::{FormattedMessage, MessageBlock, MessageBlockText, MessageBlockType, MessageType};
 use tracing::instrument;
 
-use crate::airtable::{AIRTABLE_BASE_ID_SHIPMENTS, AIRTABLE_INBOUND_TABLE, AIRTABLE_OUTBOUND_TABLE};
+use crate::airtable::{AIRTABLE_BASE_ID_SHIPMENTS, AIRTABLE_GEOCODED_ADDRESSES_TABLE, AIRTABLE_INBOUND_TABLE, AIRTABLE_OUTBOUND_TABLE};
 use crate::core::UpdateAirtableRecord;
 use crate::db::Database;
+use crate::emails::{EmailRecipient, EMAIL_TEMPLATE_SHIPMENT_INTERNAL, EMAIL_TEMPLATE_SHIPMENT_TO_RECIPIENT};
 use crate::models::get_value;
-use crate::schema::inbound_shipments;
+use crate::schema::{geocoded_addresses, inbound_shipments};
 use crate::utils::{get_gsuite_token, DOMAIN};
 
 /// The data type for an inbound shipment.
@@ -119,40 +124,47 @@ impl NewInboundShipment {
     }
 
     /// Get the details about the shipment from the tracking API.
+    /// If registering the tracking webhook fails, the shipment is left without
+    /// a tracking status, so `refresh_inbound_shipments` tries again.
     #[tracing::instrument]
     #[inline]
-    pub async fn expand(&mut self) {
+    pub async fn expand(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
         // Create the shippo client.
-        let shippo = Shippo::new_from_env();
-
-        let mut carrier = self.carrier.to_lowercase().to_string();
-        if carrier == "dhl" {
-            carrier = "dhl_express".to_string();
+        let shippo = Shippo::new_from_env().map_err(|e| format!("creating the shippo client failed: {}", e))?;
+
+        // Register a tracking webhook for this shipment, so we get updates
+        // pushed to us from now on, and fill in the details from the current
+        // tracking status.
+        let carrier = Carrier::from_friendly_name(&self.carrier);
+        let ts = shippo
+            .register_tracking_webhook_once(carrier.as_str(), &self.tracking_number)
+            .await
+            .map_err(|e| format!("registering the tracking webhook for {} failed: {}", self.tracking_number, e))?;
+        if !ts.tracking_number.is_empty() {
+            self.tracking_number = ts.tracking_number.to_string();
         }
+        self.update_from_tracking_status(ts);
+
+        Ok(())
+    }
 
-        // Get the tracking status for the shipment and fill in the details.
-        let ts = shippo.get_tracking_status(&carrier, &self.tracking_number).await.unwrap_or_default();
-        self.tracking_number = ts.tracking_number.to_string();
+    /// Update the shipment from its latest tracking status.
+    #[tracing::instrument]
+    #[inline]
+    pub fn update_from_tracking_status(&mut self, ts: TrackingStatus) {
         self.tracking_status = ts.tracking_status.status.to_string();
         self.tracking_link();
         self.eta = ts.eta;
 
         self.oxide_tracking_link = self.oxide_tracking_link();
 
-        /*
-        // Register a tracking webhook for this shipment.
-        let status = shippo_client.register_tracking_webhook(&carrier, &self.tracking_number).await.unwrap_or_else(|e| {
-            println!("registering the tracking webhook failed: {:?}", e);
-            Default::default()
-        });*/
-
         self.messages = ts.tracking_status.status_details;
 
         // Iterate over the tracking history and set the shipped_time.
         // Get the first date it was maked as in transit and use that as the shipped
         // time.
         for h in ts.tracking_history {
-            if h.status == *"TRANSIT" {
+            if h.status == TrackingStatusCode::Transit {
                 if let Some(shipped_time) = h.status_date {
                     let current_shipped_time = if let Some(s) = self.shipped_time { s } else { Utc::now() };
 
@@ -163,12 +175,104 @@ impl NewInboundShipment {
             }
         }
 
-        if ts.tracking_status.status == *"DELIVERED" {
+        if ts.tracking_status.status == TrackingStatusCode::Delivered {
             self.delivered_time = ts.tracking_status.status_date;
         }
     }
 }
 
+impl InboundShipment {
+    /// Get an inbound shipment by its tracking number, whatever the carrier.
+    #[tracing::instrument(skip(db))]
+    #[inline]
+    pub fn get_from_db_by_tracking_number(db: &Database, tracking_number: &str) -> Option<Self> {
+        inbound_shipments::dsl::inbound_shipments
+            .filter(inbound_shipments::dsl::tracking_number.eq(tracking_number.to_string()))
+            .first::<InboundShipment>(&db.conn())
+            .ok()
+    }
+
+    /// Get the inbound shipment with a tracking number from a carrier. Different
+    /// carriers can use the same tracking number. We write carriers the way
+    /// people do, so the carrier is matched by what carrier it is, see
+    /// `is_same_carrier`, among the shipments with the tracking number.
+    #[tracing::instrument(skip(db))]
+    #[inline]
+    pub fn get_from_db_by_carrier_and_tracking_number(db: &Database, carrier: &str, tracking_number: &str) -> Option<Self> {
+        inbound_shipments::dsl::inbound_shipments
+            .filter(inbound_shipments::dsl::tracking_number.eq(tracking_number.to_string()))
+            .load::<InboundShipment>(&db.conn())
+            .ok()?
+            .into_iter()
+            .find(|s| is_same_carrier(&s.carrier, carrier))
+    }
+
+    /// Convert the inbound shipment into JSON as Slack message.
+    #[instrument]
+    #[inline]
+    pub fn as_slack_msg(&self) -> Value {
+        let mut name = self.name.to_string();
+        if name.is_empty() {
+            name = "Inbound shipment".to_string();
+        }
+
+        let mut context = format!("status: *{}*", self.tracking_status);
+        if let Some(shipped_time) = self.shipped_time {
+            context += &format!(" | shipped: {}", shipped_time.format("%m/%d/%Y"));
+        }
+        if let Some(delivered_time) = self.delivered_time {
+            context += &format!(" | delivered: {}", delivered_time.format("%m/%d/%Y"));
+        } else if let Some(eta) = self.eta {
+            context += &format!(" | eta: {}", eta.format("%m/%d/%Y"));
+        }
+
+        let mut objects = vec![
+            MessageBlock {
+                block_type: MessageBlockType::Section,
+                text: Some(MessageBlockText {
+                    text_type: MessageType::Markdown,
+                    text: format!("*{}* <{}|{} {}>", name, self.oxide_tracking_link, self.carrier, self.tracking_number),
+                }),
+                elements: Default::default(),
+                accessory: Default::default(),
+                block_id: Default::default(),
+                fields: Default::default(),
+            },
+            MessageBlock {
+                block_type: MessageBlockType::Context,
+                elements: vec![MessageBlockText {
+                    text_type: MessageType::Markdown,
+                    text: context,
+                }],
+                text: Default::default(),
+                accessory: Default::default(),
+                block_id: Default::default(),
+                fields: Default::default(),
+            },
+        ];
+
+        if !self.messages.is_empty() {
+            objects.push(MessageBlock {
+                block_type: MessageBlockType::Context,
+                elements: vec![MessageBlockText {
+                    text_type: MessageType::Markdown,
+                    text: self.messages.to_string(),
+                }],
+                text: Default::default(),
+                accessory: Default::default(),
+                block_id: Default::default(),
+                fields: Default::default(),
+            });
+        }
+
+        json!(FormattedMessage {
+            channel: Default::default(),
+            attachments: Default::default(),
+            blocks: objects,
+        })
+    }
+}
+
 /// The data type for a internal shipment.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Shipment {
@@ -504,14 +608,20 @@ impl Shipment {
     }
 
     /// Create or get a shipment in shippo that matches this shipment.
-    #[tracing::instrument]
+    #[tracing::instrument(skip(db))]
     #[inline]
-    pub async fn create_or_get_shippo_shipment(&mut self) {
+    pub async fn create_or_get_shippo_shipment(&mut self, db: &Database) {
         // Update the formatted address.
         self.populate_formatted_address();
 
         // Create the shippo client.
-        let shippo_client = Shippo::new_from_env();
+        let shippo_client = match Shippo::new_from_env() {
+            Ok(shippo_client) => shippo_client,
+            Err(e) => {
+                println!("[shipments] creating the shippo client failed: {}", e);
+                return;
+            }
+        };
 
         // If we already have a shippo id, get the information for the label.
         if !self.shippo_id.is_empty() {
@@ -520,11 +630,11 @@ impl Shipment {
             // Set the additional fields.
             self.tracking_number = label.tracking_number;
             self.tracking_link = label.tracking_url_provider;
-            self.tracking_status = label.tracking_status;
+            self.tracking_status = label.tracking_status.to_string();
             self.label_link = label.label_url;
             self.eta = label.eta;
             self.shippo_id = label.object_id;
-            if label.status != "SUCCESS" {
+            if label.status != TransactionStatus::Success {
                 // Print the messages in the messages field.
                 // TODO: make the way it prints more pretty.
                 self.messages = format!("{:?}", label.messages);
@@ -532,53 +642,12 @@ impl Shipment {
             self.oxide_tracking_link = self.oxide_tracking_link();
 
             // Register a tracking webhook for this shipment.
-            let status = shippo_client.register_tracking_webhook(&self.carrier, &self.tracking_number).await.unwrap_or_else(|e| {
-                println!("registering the tracking webhook failed: {:?}", e);
+            let carrier = Carrier::from_friendly_name(&self.carrier);
+            let status = shippo_client.register_tracking_webhook_once(carrier.as_str(), &self.tracking_number).await.unwrap_or_else(|e| {
+                println!("[shipments] registering the tracking webhook failed: {}", e);
                 Default::default()
             });
-
-            if self.messages.is_empty() {
-                self.messages = status.tracking_status.status_details;
-            }
-
-            // Get the status of the shipment.
-            if status.tracking_status.status == *"TRANSIT" || status.tracking_status.status == "IN_TRANSIT" {
-                if self.status != *"Shipped" {
-                    // Send an email to the recipient with their tracking link.
-                    // Wait until it is in transit to do this.
-                    self.send_email_to_recipient().await;
-                    // We make sure it only does this one time.
-                    // Set the shipped date as this first date.
-                    self.shipped_time = status.tracking_status.status_date;
-                }
-
-                self.status = "Shipped".to_string();
-            }
-            if status.tracking_status.status == *"DELIVERED" {
-                self.status = "Delivered".to_string();
-                self.delivered_time = status.tracking_status.status_date;
-            }
-            if status.tracking_status.status == *"RETURNED" {
-                self.status = "Returned".to_string();
-            }
-            if status.tracking_status.status == *"FAILURE" {
-                self.status = "Failure".to_string();
-            }
-
-            // Iterate over the tracking history and set the shipped_time.
-            // Get the first date it was maked as in transit and use that as the shipped
-            // time.
-            for h in status.tracking_history {
-                if h.status == *"TRANSIT" {
-                    if let Some(shipped_time) = h.status_date {
-                        let current_shipped_time = if let Some(s) = self.shipped_time { s } else { Utc::now() };
-
-                        if shipped_time < current_shipped_time {
-                            self.shipped_time = Some(shipped_time);
-                        }
-                    }
-                }
-            }
+            self.update_from_tracking_status(status, db).await;
 
             // Return early.
             return;
@@ -596,11 +665,7 @@ impl Shipment {
             country: "US".to_string(),
             phone: office_phone.to_string(),
             email: format!("packages@{}", DOMAIN),
-            is_complete: Default::default(),
-            object_id: Default::default(),
-            test: Default::default(),
-            street2: Default::default(),
-            validation_results: Default::default(),
+            ..Default::default()
         };
 
         // If this is an international shipment, we need to define our customs
@@ -616,7 +681,7 @@ impl Shipment {
                 // TODO: this will break if more than 9, fix for the future.
                 ci.quantity = prefix.parse().unwrap();
                 ci.net_weight = "0.25".to_string();
-                ci.mass_unit = "lb".to_string();
+                ci.mass_unit = Some(MassUnit::Lb);
                 ci.value_amount = "100.00".to_string();
                 ci.value_currency = "USD".to_string();
                 ci.origin_country = "US".to_string();
@@ -660,28 +725,20 @@ impl Shipment {
                     country: self.country.to_string(),
                     phone: self.phone.to_string(),
                     email: self.email.to_string(),
-                    is_complete: Default::default(),
-                    object_id: Default::default(),
-                    test: Default::default(),
-                    company: Default::default(),
-                    validation_results: Default::default(),
+                    ..Default::default()
                 },
                 parcels: vec![Parcel {
                     metadata: "Default parcel for swag".to_string(),
                     length: "18.75".to_string(),
                     width: "14.5".to_string(),
                     height: "3".to_string(),
-                    distance_unit: "in".to_string(),
+                    distance_unit: Some(DistanceUnit::In),
                     weight: "1".to_string(),
-                    mass_unit: "lb".to_string(),
-                    object_id: Default::default(),
-                    object_owner: Default::default(),
-                    object_created: None,
-                    object_updated: None,
-                    object_state: Default::default(),
-                    test: Default::default(),
+                    mass_unit: Some(MassUnit::Lb),
+                    ..Default::default()
                 }],
                 customs_declaration: cd,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -696,8 +753,7 @@ impl Shipment {
                     .create_shipping_label_from_rate(NewTransaction {
                         rate: rate.object_id,
                         r#async: false,
-                        label_file_type: "".to_string(),
-                        metadata: "".to_string(),
+                        ..Default::default()
                     })
                     .await
                     .unwrap();
@@ -712,7 +768,7 @@ impl Shipment {
                 self.eta = label.eta;
                 self.shippo_id = label.object_id.to_string();
                 self.status = "Label created".to_string();
-                if label.status != "SUCCESS" {
+                if label.status != TransactionStatus::Success {
                     self.status = label.status.to_string();
                     // Print the messages in the messages field.
                     // TODO: make the way it prints more pretty.
@@ -724,17 +780,17 @@ impl Shipment {
                 self.create_or_update_in_airtable().await;
 
                 // Register a tracking webhook for this shipment.
-                shippo_client.register_tracking_webhook(&self.carrier, &self.tracking_number).await.unwrap_or_else(|e| {
-                    println!("registering the tracking webhook failed: {:?}", e);
-                    Default::default()
-                });
+                let carrier = Carrier::from_friendly_name(&self.carrier);
+                if let Err(e) = shippo_client.register_tracking_webhook_once(carrier.as_str(), &self.tracking_number).await {
+                    println!("[shipments] registering the tracking webhook failed: {}", e);
+                }
 
                 // Print the label.
                 self.print_label().await;
                 self.status = "Label printed".to_string();
 
                 // Send an email to us that we need to package the shipment.
-                self.send_email_internally().await;
+                self.send_email_internally(db).await;
 
                 break;
             }
@@ -744,6 +800,95 @@ impl Shipment {
         // However we should always find a rate.
     }
 
+    /// Update the shipment from its latest tracking status.
+    /// This sends the recipient their tracking link the first time the shipment
+    /// is in transit.
+    #[tracing::instrument(skip(db))]
+    #[inline]
+    pub async fn update_from_tracking_status(&mut self, status: TrackingStatus, db: &Database) {
+        if self.apply_tracking_status(status) {
+            // Send an email to the recipient with their tracking link.
+            // Wait until it is in transit to do this.
+            self.send_email_to_recipient(db).await;
+        }
+    }
+
+    /// Returns if the shipment has not left yet.
+    #[instrument]
+    #[inline]
+    fn is_before_shipped(&self) -> bool {
+        matches!(self.status.as_str(), "" | "Queued" | "Label created" | "Label printed")
+    }
+
+    /// Returns if the shipment is delivered, returned, or failed. Tracking
+    /// updates can arrive out of order, so these are never changed by one.
+    #[instrument]
+    #[inline]
+    fn is_done(&self) -> bool {
+        matches!(self.status.as_str(), "Delivered" | "Returned" | "Failure")
+    }
+
+    /// Update the shipment from a tracking status, without sending anything.
+    /// Returns true if the shipment was just shipped, so the recipient should
+    /// get their tracking link.
+    #[instrument]
+    #[inline]
+    fn apply_tracking_status(&mut self, status: TrackingStatus) -> bool {
+        if self.messages.is_empty() {
+            self.messages = status.tracking_status.status_details.to_string();
+        }
+        if status.eta.is_some() {
+            self.eta = status.eta;
+        }
+
+        // Iterate over the tracking history and set the shipped_time.
+        // Get the first date it was maked as in transit and use that as the shipped
+        // time.
+        for h in &status.tracking_history {
+            if h.status == TrackingStatusCode::Transit {
+                if let Some(shipped_time) = h.status_date {
+                    let current_shipped_time = if let Some(s) = self.shipped_time { s } else { Utc::now() };
+
+                    if shipped_time < current_shipped_time {
+                        self.shipped_time = Some(shipped_time);
+                    }
+                }
+            }
+        }
+
+        if self.is_done() {
+            return false;
+        }
+        if status.tracking_status.status != TrackingStatusCode::Unknown {
+            self.tracking_status = status.tracking_status.status.to_string();
+        }
+
+        // Get the status of the shipment.
+        // Only a shipment that has not left yet is promoted, so the recipient
+        // gets their tracking link once.
+        let in_transit = status.tracking_status.status == TrackingStatusCode::Transit || status.tracking_status.status.as_str() == "IN_TRANSIT";
+        let shipped = in_transit && self.is_before_shipped();
+        if shipped {
+            // Set the shipped date as this first date, unless the history had an earlier one.
+            if self.shipped_time.is_none() {
+                self.shipped_time = status.tracking_status.status_date;
+            }
+            self.status = "Shipped".to_string();
+        }
+        if status.tracking_status.status == TrackingStatusCode::Delivered {
+            self.status = "Delivered".to_string();
+            self.delivered_time = status.tracking_status.status_date;
+        }
+        if status.tracking_status.status == TrackingStatusCode::Returned {
+            self.status = "Returned".to_string();
+        }
+        if status.tracking_status.status == TrackingStatusCode::Failure {
+            self.status = "Failure".to_string();
+        }
+
+        shipped
+    }
+
     /// Send the label to our printer.
     #[tracing::instrument]
     #[inline]
@@ -832,6 +977,22 @@ impl Shipment {
         self.push_to_airtable().await;
     }
 
+    /// Get the record in our airtable workspace for the same form submission
+    /// as this shipment, if we already have one.
+    #[tracing::instrument]
+    #[inline]
+    pub async fn get_submission_from_airtable(&self) -> Result<Option<airtable_api::Record<Shipment>>, Box<dyn Error + Send + Sync>> {
+        // Initialize the Airtable client.
+        let airtable = airtable_api::Airtable::new(airtable_api::api_key_from_env(), AIRTABLE_BASE_ID_SHIPMENTS, "");
+
+        let records: Vec<airtable_api::Record<Shipment>> = airtable
+            .list_records_matching(AIRTABLE_OUTBOUND_TABLE, "Grid view", vec![], &submission_formula(&self.email, self.created_time))
+            .await
+            .map_err(|e| format!("listing the outbound shipments for {} failed: {}", self.email, e))?;
+
+        Ok(records.into_iter().next())
+    }
+
     /// Get the row in our airtable workspace.
     #[tracing::instrument]
     #[inline]
@@ -856,91 +1017,115 @@ impl Shipment {
         format!("{}\n{}, {} {} {}", street, self.city, self.state, self.zipcode, self.country)
     }
 
-    /// Send an email to the recipient with their tracking code and information.
+    /// Get the coordinates of the shipment's destination.
+    /// We first try the geocode cache Airtable keeps for the map block, then the
+    /// addresses we geocoded before. Only if `geocode` is passed do we geocode
+    /// new addresses ourselves, and save their coordinates in the database.
+    #[tracing::instrument(skip(db, geocode))]
+    #[inline]
+    pub async fn get_coordinates(&self, db: &Database, geocode: Option<&Geocode>) -> Option<Coordinates> {
+        if let Some(coordinates) = parse_geocode_cache(&self.geocode_cache) {
+            return Some(coordinates);
+        }
+
+        if self.street_1.is_empty() && self.zipcode.is_empty() {
+            return None;
+        }
+
+        let address = self.format_address().replace('\n', ", ");
+        if let Some(geocoded) = GeocodedAddress::get_from_db(db, address.to_string()) {
+            return Some(Coordinates {
+                lat: geocoded.latitude,
+                lng: geocoded.longitude,
+            });
+        }
+
+        match geocode?.get(&address).await {
+            Ok(reply) => {
+                let coordinates = reply.geometry.location;
+                NewGeocodedAddress {
+                    address,
+                    latitude: coordinates.lat,
+                    longitude: coordinates.lng,
+                }
+                .upsert_in_db(db);
+
+                Some(coordinates)
+            }
+            Err(e) => {
+                println!("[shipments] geocoding address for shipment to {} failed: {}", self.email, e);
+                None
+            }
+        }
+    }
+
+    /// Return the shipment as a GeoJSON feature, with the status of the shipment as properties.
+    /// We intentionally leave out the name and street address of the recipient.
     #[tracing::instrument]
     #[inline]
-    pub async fn send_email_to_recipient(&self) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-        // Send the message.
-        sendgrid_client
-            .send_mail(
-                "Your package from the Oxide Computer Company is on the way!".to_string(),
-                format!(
-                    "Below is the information for your package:
-
-**Contents:**
-{}
-
-**Address to:**
-{}
-{}
-
-**Tracking link:**
-{}
-
-If you have any questions or concerns, please respond to this email!
-Have a splendid day!
-
-xoxo,
-  The Oxide Shipping Bot",
-                    self.contents,
-                    self.name,
-                    self.format_address(),
-                    self.oxide_tracking_link
-                ),
-                vec![self.email.to_string()],
-                vec![],
-                vec![],
-                format!("packages@{}", DOMAIN),
-            )
-            .await;
+    pub fn as_geojson_feature(&self, coordinates: Coordinates) -> serde_json::Value {
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Point",
+                // GeoJSON positions are longitude first.
+                "coordinates": [coordinates.lng, coordinates.lat],
+            },
+            "properties": {
+                "status": self.status,
+                "carrier": self.carrier,
+                "contents": self.contents,
+                "city": self.city,
+                "state": self.state,
+                "country": self.country,
+                "created_time": self.created_time,
+                "shipped_time": self.shipped_time,
+                "delivered_time": self.delivered_time,
+            },
+        })
+    }
+
+    /// Send an email to the recipient with their tracking code and information.
+    #[tracing::instrument(skip(db))]
+    #[inline]
+    pub async fn send_email_to_recipient(&self, db: &Database) {
+        if let Err(e) = EMAIL_TEMPLATE_SHIPMENT_TO_RECIPIENT
+            .send(db, &self.email_data(), vec![EmailRecipient::new(&self.name, &self.email)], vec![], &format!("packages@{}", DOMAIN))
+            .await
+        {
+            println!("[shipments] rendering email to {} failed: {}", self.email, e);
+        }
     }
 
     /// Send an email internally that we need to package the shipment.
-    #[tracing::instrument]
+    #[tracing::instrument(skip(db))]
     #[inline]
-    pub async fn send_email_internally(&self) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-        // Send the message.
-        sendgrid_client
-            .send_mail(
-                format!("Shipment to {} is ready to be packaged", self.name),
-                format!(
-                    "Below is the information the package:
-
-**Contents:**
-{}
-
-**Address to:**
-{}
-{}
-
-**Tracking link:**
-{}
-
-The label should already be printed in the big conference room. Please take the
-label and affix it to the package with the specified contents. It can then be dropped off
-for {}.
-
-As always, the Airtable with all the shipments lives at:
-https://airtable-shipments.corp.oxide.computer.
-
-xoxo,
-  The Oxide Shipping Bot",
-                    self.contents,
-                    self.name,
-                    self.format_address(),
-                    self.oxide_tracking_link,
-                    self.carrier,
-                ),
-                vec![format!("packages@{}", DOMAIN)],
+    pub async fn send_email_internally(&self, db: &Database) {
+        if let Err(e) = EMAIL_TEMPLATE_SHIPMENT_INTERNAL
+            .send(
+                db,
+                &self.email_data(),
+                vec![EmailRecipient::new("", &format!("packages@{}", DOMAIN))],
                 vec![],
-                vec![],
-                format!("packages@{}", DOMAIN),
+                &format!("packages@{}", DOMAIN),
             )
-            .await;
+            .await
+        {
+            println!("[shipments] rendering internal email for shipment to {} failed: {}", self.email, e);
+        }
+    }
+
+    /// Return the data used to render the shipment email templates.
+    #[tracing::instrument]
+    #[inline]
+    fn email_data(&self) -> serde_json::Value {
+        json!({
+            "name": self.name,
+            "contents": self.contents,
+            "address": self.format_address(),
+            "oxide_tracking_link": self.oxide_tracking_link,
+            "carrier": self.carrier,
+        })
     }
 }
 
@@ -1090,14 +1275,14 @@ pub async fn get_google_sheets_shipments() -> Vec<Shipment> {
     let token = get_gsuite_token("").await;
 
     // Initialize the GSuite sheets client.
-    let sheets_client = Sheets::new(token.clone());
+    let sheets_client = Sheets::new(token.clone()).unwrap();
 
     // Iterate over the Google sheets and get the shipments.
     let mut shipments: Vec<Shipment> = Default::default();
     for sheet_id in get_shipments_spreadsheets() {
         // Get the values in the sheet.
         let sheet_values = sheets_client.get_values(&sheet_id, "Form Responses 1!A1:S1000".to_string()).await.unwrap();
-        let values = sheet_values.values.unwrap();
+        let values = sheet_values.string_rows();
 
         if values.is_empty() {
             panic!("unable to retrieve any data values from Google sheet {}", sheet_id);
@@ -1141,18 +1326,241 @@ pub fn get_shipments_spreadsheets() -> Vec<String> {
 #[instrument]
 #[inline]
 pub async fn refresh_airtable_shipments() {
+    let db = Database::new();
     let shipments = get_google_sheets_shipments().await;
 
     for mut shipment in shipments {
         shipment.create_or_update_in_airtable().await;
         // Create the shipment in shippo.
-        shipment.create_or_get_shippo_shipment().await;
+        shipment.create_or_get_shippo_shipment(&db).await;
         // Update airtable again.
         shipment.create_or_update_in_airtable().await;
     }
 }
 
+/// The data type for a destination address we geocoded, so we only ask Google
+/// for the coordinates of each address once.
+#[db {
+    new_struct_name = "GeocodedAddress",
+    airtable_base_id = "AIRTABLE_BASE_ID_SHIPMENTS",
+    airtable_table = "AIRTABLE_GEOCODED_ADDRESSES_TABLE",
+    match_on = {
+        "address" = "String",
+    },
+}]
+#[derive(Debug, Insertable, AsChangeset, Default, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+#[table_name = "geocoded_addresses"]
+pub struct NewGeocodedAddress {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub address: String,
+    #[serde(default)]
+    pub latitude: f64,
+    #[serde(default)]
+    pub longitude: f64,
+}
+
+/// Implement updating the Airtable record for a GeocodedAddress.
+#[async_trait]
+impl UpdateAirtableRecord<GeocodedAddress> for GeocodedAddress {
+    async fn update_airtable_record(&mut self, _record: GeocodedAddress) {}
+}
+
+/// Parse the coordinates out of the geocode cache Airtable's map block stores for
+/// a record. The cache looks like `🔵 <base64 encoded JSON>`.
+#[instrument]
+#[inline]
+fn parse_geocode_cache(cache: &str) -> Option<Coordinates> {
+    let encoded = cache.split_whitespace().last()?;
+    let decoded = base64::decode(encoded).ok()?;
+    let value: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
+
+    Some(Coordinates {
+        lat: value["o"]["lat"].as_f64()?,
+        lng: value["o"]["lng"].as_f64()?,
+    })
+}
+
+/// Return a GeoJSON FeatureCollection of where all our outbound shipments went.
+/// Without `geocode`, shipments to addresses we have not geocoded yet are left out.
+#[instrument(skip(db, geocode))]
+#[inline]
+pub async fn get_shipments_geojson(db: &Database, geocode: Option<&Geocode>) -> Result<serde_json::Value, airtable_api::APIError> {
+    // Initialize the Airtable client.
+    let airtable = airtable_api::Airtable::new(airtable_api::api_key_from_env(), AIRTABLE_BASE_ID_SHIPMENTS, "");
+    let records: Vec<airtable_api::Record<Shipment>> = airtable.list_records(AIRTABLE_OUTBOUND_TABLE, "Grid view", vec![]).await?;
+
+    let mut features: Vec<serde_json::Value> = Default::default();
+    for record in records {
+        if let Some(coordinates) = record.fields.get_coordinates(db, geocode).await {
+            features.push(record.fields.as_geojson_feature(coordinates));
+        }
+    }
+
+    Ok(json!({
+        "type": "FeatureCollection",
+        "features": features,
+    }))
+}
+
+/// Export the GeoJSON of our shipment destinations to Google Drive for the office
+/// dashboard map.
+#[instrument]
+#[inline]
+pub async fn refresh_shipments_geojson() {
+    let db = Database::new();
+
+    // Geocode any new addresses, so the API does not have to.
+    let geocode = Geocode::new_from_env();
+    let geojson = get_shipments_geojson(&db, Some(&geocode)).await.unwrap();
+
+    // Initialize the Google Drive client.
+    let token = get_gsuite_token("").await;
+    let drive_client = GoogleDrive::new(token);
+
+    // Figure out where our directory is.
+    // It should be in the shared drive : "Automated Documents"/"shipments"
+    let shared_drive = drive_client.get_drive_by_name("Automated Documents").await.unwrap();
+    let drive_id = shared_drive.id.to_string();
+
+    // Get the directory by the name.
+    let drive_shipments_dir = drive_client.get_file_by_name(&drive_id, "shipments").await.unwrap();
+    let parent_id = drive_shipments_dir.get(0).unwrap().id.to_string();
+
+    // Create or update the file in the google_drive.
+    drive_client
+        .create_or_upload_file(&drive_id, &parent_id, "shipments.geojson", "application/geo+json", geojson.to_string().as_bytes())
+        .await
+        .unwrap();
+}
+
+/// Returns if two carriers are the same, whether they are written the way
+/// people do, like "DHL", or as Shippo tokens, like "dhl_express".
+#[instrument]
+#[inline]
+pub fn is_same_carrier(a: &str, b: &str) -> bool {
+    Carrier::from_friendly_name(a) == Carrier::from_friendly_name(b)
+}
+
+/// Quote a string for an Airtable formula.
+#[instrument]
+#[inline]
+fn airtable_string(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Return the Airtable formula matching the records with a tracking number.
+#[instrument]
+#[inline]
+pub fn tracking_number_formula(tracking_number: &str) -> String {
+    format!("{{tracking_number}} = {}", airtable_string(tracking_number))
+}
+
+/// Return the Airtable formula matching the shipments requested by `email`
+/// with the form submitted at `created_time`.
+#[instrument]
+#[inline]
+pub fn submission_formula(email: &str, created_time: DateTime<Utc>) -> String {
+    format!(
+        "AND({{email}} = {}, IS_SAME({{created_time}}, {}, 'second'))",
+        airtable_string(email),
+        airtable_string(&created_time.to_rfc3339())
+    )
+}
+
+/// Update the inbound or outbound shipment matching a tracking status
+/// Shippo sent us. Returns false if we do not have a matching shipment.
+#[instrument(skip(db))]
+#[inline]
+pub async fn update_shipment_from_tracking_status(db: &Database, ts: TrackingStatus) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    // Check if it is one of our inbound shipments.
+    if let Some(shipment) = InboundShipment::get_from_db_by_carrier_and_tracking_number(db, &ts.carrier, &ts.tracking_number) {
+        let mut new_shipment: NewInboundShipment = shipment.into();
+        new_shipment.update_from_tracking_status(ts);
+        let shipment = new_shipment.upsert_in_db(db);
+        shipment.update(db).await;
+
+        return Ok(true);
+    }
+
+    // Check if it is one of our outbound shipments.
+    let airtable = airtable_api::Airtable::new(airtable_api::api_key_from_env(), AIRTABLE_BASE_ID_SHIPMENTS, "");
+    let records: Vec<airtable_api::Record<Shipment>> = airtable
+        .list_records_matching(AIRTABLE_OUTBOUND_TABLE, "Grid view", vec![], &tracking_number_formula(&ts.tracking_number))
+        .await
+        .map_err(|e| format!("listing the outbound shipments with tracking number {} failed: {}", ts.tracking_number, e))?;
+    if let Some(mut record) = records.into_iter().find(|r| is_same_carrier(&r.fields.carrier, &ts.carrier)) {
+        let mut shipment = record.fields.clone();
+        shipment.update_from_tracking_status(ts, db).await;
+        shipment.update_in_airtable(&mut record).await;
+
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Point the Shippo tracking webhook at `{base_url}/shippo/tracking`, with
+/// `token` in its URL, see `SHIPPO_WEBHOOK_TOKEN` in webhooky. The webhooks
+/// registered before for `/shippo/tracking` or `/shippo/tracking/update` on
+/// the same host without the token are refused now, so they are deleted once
+/// the new one exists. Running it again does nothing.
+#[instrument(skip(shippo, token))]
+#[inline]
+pub async fn update_shippo_tracking_webhook(shippo: &Shippo, base_url: &str, token: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let tracking = format!("{}/shippo/tracking", base_url.trim_end_matches('/'));
+    let url = format!("{}?token={}", tracking, token);
+    let legacy = [tracking.to_string(), format!("{}/update", tracking)];
+
+    let webhooks = shippo.list_webhooks().await.map_err(|e| format!("listing the shippo webhooks failed: {}", e))?;
+    let tracking_webhooks: Vec<&Webhook> = webhooks.iter().filter(|w| w.event == "track_updated").collect();
+
+    if !tracking_webhooks.iter().any(|w| w.url == url) {
+        println!("[shipments] registering the shippo tracking webhook for {}", tracking);
+        shippo
+            .create_webhook(Webhook {
+                url: url.to_string(),
+                event: "track_updated".to_string(),
+                active: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| format!("registering the shippo tracking webhook failed: {}", e))?;
+    }
+
+    // Don't log the URLs, they may have a token in them.
+    for w in tracking_webhooks.iter().filter(|w| w.url != url && legacy.iter().any(|l| w.url.split('?').next() == Some(l))) {
+        println!("[shipments] deleting the old shippo tracking webhook {}", w.object_id);
+        shippo
+            .delete_webhook(&w.object_id)
+            .await
+            .map_err(|e| format!("deleting the shippo tracking webhook {} failed: {}", w.object_id, e))?;
+    }
+
+    Ok(())
+}
+
+/// Update the Shippo tracking webhook for webhooky at `WEBHOOKY_URL`, see
+/// `update_shippo_tracking_webhook`.
+#[instrument]
+#[inline]
+pub async fn refresh_shippo_tracking_webhook() {
+    let base_url = env::var("WEBHOOKY_URL").unwrap_or_default();
+    let token = env::var("SHIPPO_WEBHOOK_TOKEN").unwrap_or_default();
+    if base_url.is_empty() || token.is_empty() {
+        println!("[shipments] WEBHOOKY_URL or SHIPPO_WEBHOOK_TOKEN is not set, not updating the shippo tracking webhook");
+        return;
+    }
+
+    let shippo = Shippo::new_from_env().unwrap();
+    if let Err(e) = update_shippo_tracking_webhook(&shippo, &base_url, &token).await {
+        println!("[shipments] {}", e);
+    }
+}
+
 // Sync the inbound shipments.
+// Shipments we already have are kept up to date by the tracking webhook
+// registered when they were added, so we only need to add the new ones and
+// try again for the ones we could not register a webhook for.
 #[instrument]
 #[inline]
 pub async fn refresh_inbound_shipments() {
@@ -1165,6 +1573,13 @@ pub async fn refresh_inbound_shipments() {
             continue;
         }
 
+        if let Some(existing) = InboundShipment::get_from_db(&db, record.fields.tracking_number.to_string(), record.fields.carrier.to_string()) {
+            if !existing.tracking_status.is_empty() {
+                // We already have it, the webhook takes care of updating it.
+                continue;
+            }
+        }
+
         let mut new_shipment = NewInboundShipment {
             carrier: record.fields.carrier,
             tracking_number: record.fields.tracking_number,
@@ -1178,7 +1593,9 @@ pub async fn refresh_inbound_shipments() {
             oxide_tracking_link: record.fields.oxide_tracking_link,
             tracking_link: record.fields.tracking_link,
         };
-        new_shipment.expand().await;
+        if let Err(e) = new_shipment.expand().await {
+            println!("[shipments] expanding inbound shipment {} failed: {}", new_shipment.tracking_number, e);
+        }
         let mut shipment = new_shipment.upsert_in_db(&db);
         if shipment.airtable_record_id.is_empty() {
             shipment.airtable_record_id = record.id;
@@ -1189,12 +1606,179 @@ pub async fn refresh_inbound_shipments() {
 
 #[cfg(test)]
 mod tests {
-    use crate::shipments::{refresh_airtable_shipments, refresh_inbound_shipments};
+    use chrono::offset::Utc;
+    use chrono::TimeZone;
+    use shippo::{Shippo, TrackingStatus, TrackingStatusCode};
+    use testutils::{fixture, FakeApis};
+    use wiremock::matchers::{body_partial_json, method, path, path_regex, query_param};
+    use wiremock::{Mock, ResponseTemplate};
+
+    use crate::shipments::{
+        is_same_carrier, refresh_airtable_shipments, refresh_inbound_shipments, refresh_shipments_geojson, refresh_shippo_tracking_webhook, submission_formula, tracking_number_formula,
+        update_shippo_tracking_webhook, NewInboundShipment, Shipment,
+    };
+
+    #[test]
+    fn test_tracking_status_matching() {
+        assert!(is_same_carrier("DHL", "dhl_express"));
+        assert!(is_same_carrier("USPS", "usps"));
+        assert!(is_same_carrier("FedEx", "fedex"));
+        assert!(!is_same_carrier("UPS", "usps"));
+
+        assert_eq!(tracking_number_formula("1Z999AA10123456784"), "{tracking_number} = '1Z999AA10123456784'");
+        assert_eq!(tracking_number_formula("1Z' OR '1"), "{tracking_number} = '1Z\\' OR \\'1'");
+    }
+
+    #[test]
+    fn test_submission_formula() {
+        let created_time = Utc.ymd(2021, 1, 22).and_hms(18, 41, 37);
+        assert_eq!(
+            submission_formula("jess@example.com", created_time),
+            "AND({email} = 'jess@example.com', IS_SAME({created_time}, '2021-01-22T18:41:37+00:00', 'second'))"
+        );
+        assert_eq!(
+            submission_formula("o'neil@example.com", created_time),
+            "AND({email} = 'o\\'neil@example.com', IS_SAME({created_time}, '2021-01-22T18:41:37+00:00', 'second'))"
+        );
+    }
+
+    fn tracking_status(status: &str) -> TrackingStatus {
+        let mut ts = fixture("shippo", "tracking_status");
+        ts["tracking_status"]["status"] = serde_json::json!(status);
+        serde_json::from_value(ts).unwrap()
+    }
+
+    #[test]
+    fn test_shipment_tracking_status_order() {
+        let shipment = |status: &str| -> Shipment { serde_json::from_value(serde_json::json!({"created_time": "2021-03-30T18:14:12Z", "status": status})).unwrap() };
+
+        // A shipment that has not left yet is shipped once, and the recipient
+        // is only sent their tracking link then.
+        let mut label_printed = shipment("Label printed");
+        assert!(label_printed.apply_tracking_status(tracking_status("TRANSIT")));
+        assert_eq!(label_printed.status, "Shipped");
+        assert_eq!(label_printed.shipped_time, Some(Utc.ymd(2021, 3, 31).and_hms(9, 12, 0)));
+        assert!(!label_printed.apply_tracking_status(tracking_status("TRANSIT")));
+
+        // A transit update that arrives after the delivery does not move the
+        // shipment back, or email the recipient.
+        let mut delivered = shipment("Label created");
+        assert!(!delivered.apply_tracking_status(tracking_status("DELIVERED")));
+        assert_eq!(delivered.status, "Delivered");
+        assert!(!delivered.apply_tracking_status(tracking_status("TRANSIT")));
+        assert_eq!(delivered.status, "Delivered");
+        assert_eq!(delivered.tracking_status, TrackingStatusCode::Delivered.to_string());
+
+        let mut returned = shipment("Returned");
+        assert!(!returned.apply_tracking_status(tracking_status("DELIVERED")));
+        assert_eq!(returned.status, "Returned");
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_inbound_shipment_expand_against_fakes() {
+        let apis = FakeApis::start().await;
+        let _env = apis.env();
+
+        let mut shipment = NewInboundShipment {
+            carrier: "USPS".to_string(),
+            tracking_number: "9205590164917312751089".to_string(),
+            ..Default::default()
+        };
+        shipment.expand().await.unwrap();
+
+        assert_eq!(shipment.tracking_status, TrackingStatusCode::Transit.to_string());
+        assert_eq!(shipment.messages, "Your shipment has been accepted.");
+        assert_eq!(shipment.tracking_link, "https://tools.usps.com/go/TrackConfirmAction_input?origTrackNum=9205590164917312751089");
+        assert_eq!(shipment.shipped_time, Some(Utc.ymd(2021, 3, 31).and_hms(9, 12, 0)));
+        assert!(shipment.eta.is_some());
+        assert!(shipment.delivered_time.is_none());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_shipment_submission_against_fakes() {
+        let apis = FakeApis::start().await;
+        let _env = apis.env();
+
+        let shipment: Shipment = serde_json::from_value(serde_json::json!({
+            "email": "jane@example.com",
+            "created_time": "2021-03-30T18:14:12Z",
+        }))
+        .unwrap();
+
+        // Only the records matching the formula are returned.
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/v0/[^/]+/[^/]+$"))
+            .and(query_param("filterByFormula", submission_formula(&shipment.email, shipment.created_time).as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "records": [{
+                    "id": "recShipment",
+                    "fields": {"email": "jane@example.com", "created_time": "2021-03-30T18:14:12Z", "shippo_id": "70ae8117ee1749e393f249d5b77c45e0"},
+                    "createdTime": "2021-03-30T18:14:12.000Z",
+                }],
+            })))
+            .with_priority(1)
+            .mount(&apis.airtable)
+            .await;
+
+        let record = shipment.get_submission_from_airtable().await.unwrap().unwrap();
+        assert_eq!(record.id, "recShipment");
+        assert_eq!(record.fields.shippo_id, "70ae8117ee1749e393f249d5b77c45e0");
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_shippo_tracking_webhook_against_fakes() {
+        let apis = FakeApis::start().await;
+        let _env = apis.env();
+
+        let webhook = |id: &str, url: &str, event: &str| serde_json::json!({"object_id": id, "url": url, "event": event, "active": true});
+        Mock::given(method("GET"))
+            .and(path("/webhooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "next": null,
+                "results": [
+                    webhook("legacy", "https://webhooky.example.com/shippo/tracking/update", "track_updated"),
+                    webhook("old-token", "https://webhooky.example.com/shippo/tracking?token=old", "track_updated"),
+                    webhook("other-host", "https://other.example.com/shippo/tracking/update", "track_updated"),
+                    webhook("transactions", "https://webhooky.example.com/shippo/tracking/update", "transaction_created"),
+                ],
+            })))
+            .mount(&apis.shippo)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/webhooks"))
+            .and(body_partial_json(
+                serde_json::json!({"url": "https://webhooky.example.com/shippo/tracking?token=secret", "event": "track_updated"}),
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(webhook("new", "https://webhooky.example.com/shippo/tracking?token=secret", "track_updated")))
+            .expect(1)
+            .mount(&apis.shippo)
+            .await;
+        // Only the tracking webhooks for webhooky without the new token are
+        // deleted, deleting any other one is not mocked and fails.
+        for id in &["legacy", "old-token"] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/webhooks/{}", id)))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&apis.shippo)
+                .await;
+        }
+
+        let shippo = Shippo::new_from_env().unwrap();
+        update_shippo_tracking_webhook(&shippo, "https://webhooky.example.com/", "secret").await.unwrap();
+    }
 
     #[ignore]
     #[tokio::test(threaded_scheduler)]
     async fn test_cron_shipments() {
+        refresh_shippo_tracking_webhook().await;
         refresh_inbound_shipments().await;
         refresh_airtable_shipments().await;
     }
+
+    #[ignore]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_cron_shipments_geojson() {
+        refresh_shipments_geojson().await;
+    }
 }
@@ -0,0 +1,571 @@
+#![allow(clippy::from_over_into)]
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+
+use async_trait::async_trait;
+use chrono::offset::Utc;
+use futures_util::FutureExt;
+use macros::db;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shippo::{Address, Shippo};
+use slack_chat_api::{FormattedMessage, MessageBlock, MessageBlockText, MessageBlockType, MessageType};
+use tracing::instrument;
+
+use crate::airtable::{AIRTABLE_BASE_ID_SHIPMENTS, AIRTABLE_SWAG_INVENTORY_TABLE};
+use crate::core::UpdateAirtableRecord;
+use crate::db::Database;
+use crate::schema::swag_items;
+use crate::shipments::Shipment;
+
+/// The data type for a swag item we keep in stock.
+/// The inventory is managed by a human in Airtable, stock is reserved here
+/// when an order comes in through the store.
+#[db {
+    new_struct_name = "SwagItem",
+    airtable_base_id = "AIRTABLE_BASE_ID_SHIPMENTS",
+    airtable_table = "AIRTABLE_SWAG_INVENTORY_TABLE",
+    match_on = {
+        "name" = "String",
+        "size" = "String",
+    },
+}]
+#[derive(Debug, Insertable, AsChangeset, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+#[table_name = "swag_items"]
+pub struct NewSwagItem {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub size: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub image_link: String,
+    #[serde(default)]
+    pub current_stock: i32,
+}
+
+/// Implement updating the Airtable record for a SwagItem.
+#[async_trait]
+impl UpdateAirtableRecord<SwagItem> for SwagItem {
+    async fn update_airtable_record(&mut self, record: SwagItem) {
+        if self.description.is_empty() {
+            self.description = record.description;
+        }
+        if self.image_link.is_empty() {
+            self.image_link = record.image_link;
+        }
+    }
+}
+
+impl SwagItem {
+    /// Get all the swag items we have in stock.
+    #[instrument(skip(db))]
+    #[inline]
+    pub fn get_in_stock_from_db(db: &Database) -> Vec<SwagItem> {
+        swag_items::dsl::swag_items
+            .filter(swag_items::dsl::current_stock.gt(0))
+            .order_by((swag_items::dsl::name.asc(), swag_items::dsl::size.asc()))
+            .load::<SwagItem>(&db.conn())
+            .unwrap()
+    }
+
+    /// Get all the swag items with a name containing the query, in stock or not.
+    #[instrument(skip(db))]
+    #[inline]
+    pub fn search_in_db(db: &Database, query: &str) -> Vec<SwagItem> {
+        swag_items::dsl::swag_items
+            .filter(swag_items::dsl::name.ilike(format!("%{}%", query.trim())))
+            .order_by((swag_items::dsl::name.asc(), swag_items::dsl::size.asc()))
+            .load::<SwagItem>(&db.conn())
+            .unwrap()
+    }
+}
+
+/// Convert the stock of swag items into JSON as Slack message.
+#[instrument]
+#[inline]
+pub fn swag_stock_as_slack_msg(items: &[SwagItem]) -> Value {
+    let mut objects: Vec<MessageBlock> = Default::default();
+    for item in items {
+        let mut name = item.name.to_string();
+        if !item.size.is_empty() {
+            name += &format!(", Size: {}", item.size);
+        }
+
+        objects.push(MessageBlock {
+            block_type: MessageBlockType::Section,
+            text: Some(MessageBlockText {
+                text_type: MessageType::Markdown,
+                text: format!("*{}* | in stock: *{}*", name, item.current_stock),
+            }),
+            elements: Default::default(),
+            accessory: Default::default(),
+            block_id: Default::default(),
+            fields: Default::default(),
+        });
+    }
+
+    json!(FormattedMessage {
+        channel: Default::default(),
+        attachments: Default::default(),
+        blocks: objects,
+    })
+}
+
+/// An order placed through the swag store.
+#[derive(Debug, Default, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct SwagOrder {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub email: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub phone: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub street_1: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub street_2: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub city: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub state: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub zipcode: String,
+    /// The two letter country code, this defaults to `US`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub country: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<SwagOrderItem>,
+}
+
+/// An item in an order placed through the swag store.
+#[derive(Debug, Default, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct SwagOrderItem {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub size: String,
+    pub quantity: i32,
+}
+
+/// The reasons we could not accept an order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SwagOrderError {
+    /// The order is missing information or has an invalid address.
+    Invalid(String),
+    /// We do not have enough of an item in stock.
+    OutOfStock(String),
+    /// Validating the address or buying the label failed on our side. Any
+    /// stock reserved for the order was released.
+    Failed(String),
+}
+
+impl fmt::Display for SwagOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwagOrderError::Invalid(m) => write!(f, "invalid order: {}", m),
+            SwagOrderError::OutOfStock(m) => write!(f, "out of stock: {}", m),
+            SwagOrderError::Failed(m) => write!(f, "placing the order failed: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for SwagOrderError {}
+
+impl SwagOrder {
+    /// Clean up the order the same way we clean up the rows from the Google Form.
+    #[instrument]
+    #[inline]
+    fn normalize(&mut self) {
+        self.name = self.name.trim().to_string();
+        self.email = self.email.trim().to_lowercase();
+        self.phone = self.phone.trim().to_string();
+        self.street_1 = self.street_1.trim().to_uppercase();
+        self.street_2 = self.street_2.trim().to_uppercase();
+        self.city = self.city.trim().to_uppercase();
+        self.state = self.state.trim().to_uppercase();
+        self.zipcode = self.zipcode.trim().to_uppercase();
+        self.country = self.country.trim().to_uppercase();
+        if self.country.is_empty() {
+            self.country = "US".to_string();
+        }
+    }
+
+    /// Validate that the order has everything we need to ship it, and that
+    /// Shippo can deliver to the address.
+    #[instrument]
+    #[inline]
+    pub async fn validate(&self) -> Result<(), SwagOrderError> {
+        self.validate_fields()?;
+
+        let shippo = Shippo::new_from_env().map_err(|e| SwagOrderError::Failed(format!("creating the shippo client failed: {}", e)))?;
+        self.validate_address(&shippo).await
+    }
+
+    /// Validate the address of the order with Shippo.
+    #[instrument(skip(shippo))]
+    #[inline]
+    async fn validate_address(&self, shippo: &Shippo) -> Result<(), SwagOrderError> {
+        let address = shippo
+            .create_address(
+                Address {
+                    name: self.name.to_string(),
+                    street1: self.street_1.to_string(),
+                    street2: self.street_2.to_string(),
+                    city: self.city.to_string(),
+                    state: self.state.to_string(),
+                    zip: self.zipcode.to_string(),
+                    country: self.country.to_string(),
+                    phone: self.phone.to_string(),
+                    email: self.email.to_string(),
+                    ..Default::default()
+                },
+                true,
+            )
+            .await
+            .map_err(|e| SwagOrderError::Failed(format!("validating the address failed: {}", e)))?;
+
+        if !address.is_valid() {
+            let messages: Vec<String> = address.validation_results.messages.iter().map(|m| m.text.to_string()).collect();
+            return Err(SwagOrderError::Invalid(format!("the address is not deliverable: {}", messages.join(", "))));
+        }
+
+        Ok(())
+    }
+
+    /// Validate that the order has every field we need, before asking Shippo
+    /// about the address.
+    #[instrument]
+    #[inline]
+    fn validate_fields(&self) -> Result<(), SwagOrderError> {
+        let required = vec![
+            ("name", &self.name),
+            ("email", &self.email),
+            ("street_1", &self.street_1),
+            ("city", &self.city),
+            ("zipcode", &self.zipcode),
+        ];
+        for (field, value) in required {
+            if value.is_empty() {
+                return Err(SwagOrderError::Invalid(format!("`{}` is required", field)));
+            }
+        }
+
+        if !self.email.contains('@') {
+            return Err(SwagOrderError::Invalid(format!("`{}` is not a valid email address", self.email)));
+        }
+
+        if self.country.len() != 2 {
+            return Err(SwagOrderError::Invalid(format!("`{}` is not a two letter country code", self.country)));
+        }
+
+        // Carriers require a state for shipments to these countries.
+        if (self.country == "US" || self.country == "CA" || self.country == "AU") && self.state.is_empty() {
+            return Err(SwagOrderError::Invalid(format!("`state` is required for shipments to {}", self.country)));
+        }
+
+        if self.country == "US" {
+            let re = Regex::new(r"^[0-9]{5}(-?[0-9]{4})?$").unwrap();
+            if !re.is_match(&self.zipcode) {
+                return Err(SwagOrderError::Invalid(format!("`{}` is not a valid US zipcode", self.zipcode)));
+            }
+        }
+
+        if self.items.is_empty() {
+            return Err(SwagOrderError::Invalid("the order has no items".to_string()));
+        }
+        for item in &self.items {
+            if item.quantity < 1 {
+                return Err(SwagOrderError::Invalid(format!("the quantity for `{}` must be at least 1", item.name)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the contents of the order, formatted the same as the shipments from the Google Form.
+    #[instrument]
+    #[inline]
+    pub fn contents(&self) -> String {
+        self.items
+            .iter()
+            .map(|item| {
+                if item.size.is_empty() {
+                    format!("{} x {}", item.quantity, item.name)
+                } else {
+                    format!("{} x {}, Size: {}", item.quantity, item.name, item.size)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Reserve the stock for every item in the order. If we do not have enough of
+    /// any one item, nothing is reserved.
+    #[instrument(skip(db))]
+    #[inline]
+    fn reserve_stock(&self, db: &Database) -> Result<Vec<SwagItem>, SwagOrderError> {
+        let conn = db.conn();
+
+        let mut error: Option<SwagOrderError> = None;
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let mut reserved: Vec<SwagItem> = Default::default();
+            for item in &self.items {
+                let matching = || {
+                    swag_items::dsl::swag_items
+                        .filter(swag_items::dsl::name.eq(item.name.to_string()))
+                        .filter(swag_items::dsl::size.eq(item.size.to_string()))
+                };
+
+                // Only decrement the stock if we have enough, so two orders for
+                // the last item cannot both succeed.
+                let updated = diesel::update(matching().filter(swag_items::dsl::current_stock.ge(item.quantity)))
+                    .set(swag_items::dsl::current_stock.eq(swag_items::dsl::current_stock - item.quantity))
+                    .get_result::<SwagItem>(&conn)
+                    .optional()?;
+
+                match updated {
+                    Some(s) => reserved.push(s),
+                    None => {
+                        error = Some(if matching().first::<SwagItem>(&conn).optional()?.is_some() {
+                            SwagOrderError::OutOfStock(format!("we do not have {} of `{}` in size `{}`", item.quantity, item.name, item.size))
+                        } else {
+                            SwagOrderError::Invalid(format!("`{}` in size `{}` is not in the store", item.name, item.size))
+                        });
+                        return Err(diesel::result::Error::RollbackTransaction);
+                    }
+                }
+            }
+
+            Ok(reserved)
+        });
+
+        match (result, error) {
+            (Ok(reserved), _) => Ok(reserved),
+            (Err(_), Some(e)) => Err(e),
+            (Err(e), None) => panic!("reserving stock for order from {} failed: {}", self.email, e),
+        }
+    }
+
+    /// Put the stock reserved for the order back, after we failed to queue it.
+    #[instrument(skip(db))]
+    #[inline]
+    fn release_stock(&self, db: &Database) -> Vec<SwagItem> {
+        let conn = db.conn();
+
+        let mut released: Vec<SwagItem> = Default::default();
+        for item in &self.items {
+            let result = diesel::update(
+                swag_items::dsl::swag_items
+                    .filter(swag_items::dsl::name.eq(item.name.to_string()))
+                    .filter(swag_items::dsl::size.eq(item.size.to_string())),
+            )
+            .set(swag_items::dsl::current_stock.eq(swag_items::dsl::current_stock + item.quantity))
+            .get_result::<SwagItem>(&conn);
+
+            match result {
+                Ok(s) => released.push(s),
+                Err(e) => println!("[swag_store] releasing {} of `{}` in size `{}` failed: {}", item.quantity, item.name, item.size, e),
+            }
+        }
+
+        released
+    }
+
+    /// Release the stock reserved for the order, and return `error` so the
+    /// caller can return it.
+    #[instrument(skip(db))]
+    #[inline]
+    async fn fail(&self, db: &Database, error: SwagOrderError) -> SwagOrderError {
+        for item in self.release_stock(db) {
+            item.update(db).await;
+        }
+
+        error
+    }
+
+    /// Return the shipment for the order.
+    #[instrument]
+    #[inline]
+    pub fn as_shipment(&self) -> Shipment {
+        Shipment {
+            created_time: Utc::now(),
+            name: self.name.to_string(),
+            email: self.email.to_string(),
+            phone: self.phone.to_string(),
+            street_1: self.street_1.to_string(),
+            street_2: self.street_2.to_string(),
+            city: self.city.to_string(),
+            state: self.state.to_string(),
+            zipcode: self.zipcode.to_string(),
+            country: self.country.to_string(),
+            address_formatted: String::new(),
+            contents: self.contents(),
+            carrier: Default::default(),
+            pickup_date: None,
+            delivered_time: None,
+            reprint_label: false,
+            schedule_pickup: false,
+            resend_email_to_recipient: false,
+            shipped_time: None,
+            shippo_id: Default::default(),
+            status: "Queued".to_string(),
+            tracking_link: Default::default(),
+            oxide_tracking_link: Default::default(),
+            tracking_number: Default::default(),
+            tracking_status: Default::default(),
+            cost: Default::default(),
+            label_link: Default::default(),
+            eta: None,
+            messages: Default::default(),
+            notes: Default::default(),
+            geocode_cache: Default::default(),
+        }
+    }
+
+    /// Validate the order, reserve the stock, and buy the label for the
+    /// shipment. The shipment is added to Airtable exactly like the orders from
+    /// the Google Form, from there it goes through the rest of the shipment
+    /// pipeline. If buying the label fails, the stock is released again.
+    #[instrument(skip(db))]
+    #[inline]
+    pub async fn submit(&mut self, db: &Database) -> Result<Shipment, SwagOrderError> {
+        self.normalize();
+        self.validate().await?;
+
+        let reserved = self.reserve_stock(db)?;
+
+        // Buy the label first, the shipment is saved in Airtable once it has
+        // one. Some failures while buying it panic, so catch those too.
+        let mut shipment = self.as_shipment();
+        let bought = AssertUnwindSafe(shipment.create_or_get_shippo_shipment(db)).catch_unwind().await;
+        if bought.is_err() || shipment.shippo_id.is_empty() {
+            return Err(self.fail(db, SwagOrderError::Failed(format!("buying the label for the order from {} failed", self.email))).await);
+        }
+
+        // Save the status the shipment has after printing the label.
+        shipment.create_or_update_in_airtable().await;
+
+        // Update the stock in Airtable.
+        for item in reserved {
+            item.update(db).await;
+        }
+
+        Ok(shipment)
+    }
+}
+
+/// Sync the swag inventory from Airtable, where it is managed, to our database.
+#[instrument]
+#[inline]
+pub async fn refresh_swag_inventory() {
+    let db = Database::new();
+
+    let records = SwagItems::get_from_airtable().await;
+    for (_, record) in records {
+        let new_item = NewSwagItem {
+            name: record.fields.name.to_string(),
+            size: record.fields.size.to_string(),
+            description: record.fields.description.to_string(),
+            image_link: record.fields.image_link.to_string(),
+            current_stock: record.fields.current_stock,
+        };
+
+        let mut item = new_item.upsert_in_db(&db);
+        if item.airtable_record_id.is_empty() {
+            item.airtable_record_id = record.id.to_string();
+            item.update_in_db(&db);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shippo::Shippo;
+    use testutils::{fixture, FakeApis};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    use crate::swag_store::{refresh_swag_inventory, SwagOrder, SwagOrderError, SwagOrderItem};
+
+    #[ignore]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_cron_swag_inventory() {
+        refresh_swag_inventory().await;
+    }
+
+    #[test]
+    fn test_swag_order_validate() {
+        let mut order = SwagOrder {
+            name: "Jess Frazelle".to_string(),
+            email: " Jess@Example.com ".to_string(),
+            street_1: "1 Main St".to_string(),
+            city: "Oakland".to_string(),
+            state: "ca".to_string(),
+            zipcode: "94607".to_string(),
+            items: vec![
+                SwagOrderItem {
+                    name: "Oxide Hoodie".to_string(),
+                    size: "L".to_string(),
+                    quantity: 1,
+                },
+                SwagOrderItem {
+                    name: "Oxide Sticker".to_string(),
+                    size: "".to_string(),
+                    quantity: 3,
+                },
+            ],
+            ..Default::default()
+        };
+        order.normalize();
+        assert_eq!(order.validate_fields(), Ok(()));
+        assert_eq!(order.email, "jess@example.com");
+        assert_eq!(order.country, "US");
+        assert_eq!(order.contents(), "1 x Oxide Hoodie, Size: L\n3 x Oxide Sticker");
+
+        order.zipcode = "ABC".to_string();
+        assert!(matches!(order.validate_fields(), Err(SwagOrderError::Invalid(_))));
+
+        order.zipcode = "94607".to_string();
+        order.items[0].quantity = 0;
+        assert!(matches!(order.validate_fields(), Err(SwagOrderError::Invalid(_))));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_swag_order_validate_address_against_fakes() {
+        let apis = FakeApis::start().await;
+        let _env = apis.env();
+        let shippo = Shippo::new_from_env().unwrap();
+
+        let order = SwagOrder {
+            name: "Jane Doe".to_string(),
+            email: "jane@example.com".to_string(),
+            street_1: "215 CLAYTON ST".to_string(),
+            city: "SAN FRANCISCO".to_string(),
+            state: "CA".to_string(),
+            zipcode: "94117".to_string(),
+            country: "US".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(order.validate_address(&shippo).await, Ok(()));
+
+        // Shippo can't deliver to this one.
+        let mut address = fixture("shippo", "address");
+        address["validation_results"] = serde_json::json!({
+            "is_valid": false,
+            "messages": [{"source": "USPS", "code": "Address Not Found", "type": "address_error", "text": "The address as submitted could not be found."}],
+        });
+        Mock::given(method("POST"))
+            .and(path("/addresses"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(address))
+            .with_priority(1)
+            .mount(&apis.shippo)
+            .await;
+        match order.validate_address(&shippo).await {
+            Err(SwagOrderError::Invalid(m)) => assert_eq!(m, "the address is not deliverable: The address as submitted could not be found."),
+            r => panic!("expected the address to be invalid, got {:?}", r),
+        }
+    }
+}
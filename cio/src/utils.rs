@@ -408,11 +408,58 @@ pub fn default_date() -> chrono::naive::NaiveDate {
     chrono::naive::NaiveDate::parse_from_str("1970-01-01", "%Y-%m-%d").unwrap()
 }
 
+/// Check the value of an `Authorization` header is exactly `Bearer <token>`.
+/// The token is compared in constant time, so it can't be guessed from how
+/// long a rejection takes.
+#[instrument(skip(authorization, token))]
+#[inline]
+pub fn check_bearer_token(authorization: &str, token: &str) -> bool {
+    match authorization.strip_prefix("Bearer ") {
+        Some(given) => check_secret(given, token),
+        None => false,
+    }
+}
+
+/// Check a secret given with a request, for example in its URL, is exactly
+/// `secret`. It is compared in constant time, like `check_bearer_token`.
+#[instrument(skip(given, secret))]
+#[inline]
+pub fn check_secret(given: &str, secret: &str) -> bool {
+    // If the secret is not set, nobody gets in.
+    if secret.is_empty() || given.len() != secret.len() {
+        return false;
+    }
+
+    given.bytes().zip(secret.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 #[cfg(test)]
 mod tests {
     use crate::db::Database;
     use crate::models::GithubRepos;
-    use crate::utils::{authenticate_github_jwt, refresh_db_github_repos};
+    use crate::utils::{authenticate_github_jwt, check_bearer_token, check_secret, refresh_db_github_repos};
+
+    #[test]
+    fn test_check_bearer_token() {
+        assert!(check_bearer_token("Bearer secret", "secret"));
+        assert!(!check_bearer_token("Bearer secret", ""));
+        assert!(!check_bearer_token("Bearer ", ""));
+        assert!(!check_bearer_token("Bearer other", "secret"));
+        assert!(!check_bearer_token("Bearer secret2", "secret"));
+        assert!(!check_bearer_token("secret", "secret"));
+        assert!(!check_bearer_token("bearer secret", "secret"));
+        assert!(!check_bearer_token("Bearer Bearer secret", "secret"));
+        assert!(!check_bearer_token("Basic secret", "secret"));
+    }
+
+    #[test]
+    fn test_check_secret() {
+        assert!(check_secret("secret", "secret"));
+        assert!(!check_secret("", ""));
+        assert!(!check_secret("secret", ""));
+        assert!(!check_secret("other1", "secret"));
+        assert!(!check_secret("secret2", "secret"));
+    }
 
     #[ignore]
     #[tokio::test(threaded_scheduler)]
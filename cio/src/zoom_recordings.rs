@@ -0,0 +1,374 @@
+#![allow(clippy::from_over_into)]
+use std::env;
+use std::error::Error;
+
+use async_trait::async_trait;
+use chrono::offset::Utc;
+use chrono::DateTime;
+use google_drive::GoogleDrive;
+use macros::db;
+use reqwest::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use zoom_api::{Meeting, Recording, Zoom};
+
+use crate::airtable::{AIRTABLE_BASE_ID_MISC, AIRTABLE_ZOOM_RECORDINGS_TABLE};
+use crate::core::UpdateAirtableRecord;
+use crate::db::Database;
+use crate::schema::zoom_recordings;
+use crate::utils::get_gsuite_token;
+
+/// The shared drive where we archive Zoom recordings.
+static ZOOM_RECORDINGS_SHARED_DRIVE: &str = "Automated Documents";
+
+/// The data type for a Zoom cloud recording that has been archived to Google Drive.
+#[db {
+    new_struct_name = "ZoomRecording",
+    airtable_base_id = "AIRTABLE_BASE_ID_MISC",
+    airtable_table = "AIRTABLE_ZOOM_RECORDINGS_TABLE",
+    match_on = {
+        "recording_id" = "String",
+    },
+}]
+#[derive(Debug, Insertable, AsChangeset, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+#[table_name = "zoom_recordings"]
+pub struct NewZoomRecording {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub recording_id: String,
+    #[serde(default)]
+    pub meeting_id: i64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub meeting_uuid: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub topic: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub host_id: String,
+    pub recording_start: DateTime<Utc>,
+    pub recording_end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub file_type: String,
+    #[serde(default)]
+    pub file_size: i64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub file_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub google_drive_link: String,
+    pub archived_time: DateTime<Utc>,
+    #[serde(default)]
+    pub deleted_from_zoom: bool,
+}
+
+/// Implement updating the Airtable record for a ZoomRecording.
+#[async_trait]
+impl UpdateAirtableRecord<ZoomRecording> for ZoomRecording {
+    async fn update_airtable_record(&mut self, _record: ZoomRecording) {}
+}
+
+impl NewZoomRecording {
+    /// Generate the name of the file in Google Drive for a recording.
+    /// This looks like `2021-04-06-weekly-product-huddle-81234567890-{recording id}-video.mp4`.
+    /// The recording ID keeps the files of the same type apart, for example the
+    /// speaker and gallery views, or two instances of a recurring meeting.
+    #[instrument]
+    #[inline]
+    pub fn generate_file_name(meeting: &Meeting, recording: &Recording) -> String {
+        let start = parse_zoom_time(&recording.recording_start);
+        let topic = meeting
+            .topic
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>()
+            .join("-");
+
+        format!(
+            "{}-{}-{}-{}{}",
+            start.format("%Y-%m-%d"),
+            topic,
+            meeting.id,
+            recording.id.as_deref().unwrap_or_default(),
+            recording.file_type.to_extension()
+        )
+    }
+}
+
+/// Parse a time returned from the Zoom API, defaulting to now if it is empty or invalid.
+#[instrument]
+#[inline]
+fn parse_zoom_time(s: &str) -> DateTime<Utc> {
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(_) => Utc::now(),
+    }
+}
+
+/// Start downloading a recording file from Zoom. The body of the response is
+/// not read here, so it can be streamed to Google Drive.
+#[instrument(skip(zoom))]
+#[inline]
+async fn download_recording(zoom: &Zoom, recording: &Recording) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+    let resp = reqwest::Client::new()
+        .get(&recording.download_url)
+        .bearer_auth(zoom.get_token())
+        .send()
+        .await
+        .map_err(|e| format!("downloading recording {} failed: {}", recording.download_url, e))?;
+    match resp.status() {
+        StatusCode::OK => Ok(resp),
+        s => Err(format!(
+            "downloading recording {} failed: status_code: {}, body: {}",
+            recording.download_url,
+            s,
+            resp.text().await.unwrap_or_default()
+        )
+        .into()),
+    }
+}
+
+/// Archive a recording file to Google Drive and save it in the database.
+#[instrument(skip(db, zoom, drive_client))]
+#[inline]
+async fn archive_recording(
+    db: &Database,
+    zoom: &Zoom,
+    drive_client: &GoogleDrive,
+    drive_id: &str,
+    parent_id: &str,
+    meeting: &Meeting,
+    recording: &Recording,
+) -> Result<ZoomRecording, Box<dyn Error + Send + Sync>> {
+    let recording_id = recording.id.clone().unwrap_or_default();
+    let file_name = NewZoomRecording::generate_file_name(meeting, recording);
+
+    let resp = download_recording(zoom, recording).await?;
+    let file_size = resp.content_length().or_else(|| recording.file_size.map(|s| s as u64)).unwrap_or_default();
+    if file_size == 0 {
+        return Err(format!("recording {} is empty", recording.download_url).into());
+    }
+
+    // Stream the file to google drive, recordings can be too big to hold in memory.
+    drive_client
+        .create_or_upload_file_stream(
+            drive_id,
+            parent_id,
+            &file_name,
+            &recording.file_type.get_mime_type(),
+            reqwest::Body::wrap_stream(resp.bytes_stream()),
+            file_size,
+        )
+        .await
+        .map_err(|e| format!("uploading {} to google drive failed: {}", file_name, e))?;
+
+    // Get the file in drive.
+    let files = drive_client
+        .get_file_by_name(drive_id, &file_name)
+        .await
+        .map_err(|e| format!("getting {} from google drive failed: {}", file_name, e))?;
+    let google_drive_link = match files.get(0) {
+        Some(f) => format!("https://drive.google.com/open?id={}", f.id),
+        None => return Err(format!("{} was not found in google drive after uploading it", file_name).into()),
+    };
+
+    let new_recording = NewZoomRecording {
+        recording_id,
+        meeting_id: meeting.id,
+        meeting_uuid: meeting.uuid.to_string(),
+        topic: meeting.topic.to_string(),
+        host_id: meeting.host_id.to_string(),
+        recording_start: parse_zoom_time(&recording.recording_start),
+        recording_end: parse_zoom_time(&recording.recording_end),
+        file_type: format!("{:?}", recording.file_type),
+        file_size: file_size as i64,
+        file_name,
+        google_drive_link,
+        archived_time: Utc::now(),
+        deleted_from_zoom: false,
+    };
+
+    // Upsert the recording in the database.
+    Ok(new_recording.upsert(db).await)
+}
+
+/// Returns if all the recordings of a meeting instance have finished processing,
+/// so they can be archived.
+#[instrument]
+#[inline]
+fn is_ready_to_archive(meeting: &Meeting) -> bool {
+    !meeting.recording_files.is_empty() && meeting.recording_files.iter().all(|r| r.status.as_deref() == Some("completed"))
+}
+
+/// Returns if every recording of a meeting instance is safe in Google Drive, so
+/// they can be deleted from Zoom.
+#[instrument]
+#[inline]
+fn is_archived(meeting: &Meeting, archived: &[ZoomRecording]) -> bool {
+    meeting.recording_files.iter().all(|r| {
+        let recording_id = r.id.as_deref().unwrap_or_default();
+        !recording_id.is_empty() && archived.iter().any(|a| a.recording_id == recording_id && !a.google_drive_link.is_empty())
+    })
+}
+
+/// Archive our completed Zoom cloud recordings to Google Drive and delete them
+/// from Zoom, so we stay under our cloud storage quota.
+#[instrument]
+#[inline]
+pub async fn refresh_zoom_recordings() {
+    let db = Database::new();
+    ZoomRecordings::get_from_db(&db).update_airtable().await;
+
+    let zoom = Zoom::new_from_env();
+
+    // Initialize the Google Drive client.
+    let token = get_gsuite_token("").await;
+    let drive_client = GoogleDrive::new(token);
+
+    // Figure out where our directory is.
+    // It should be in the shared drive : "Automated Documents"/"zoom_recordings"
+    let folder = env::var("ZOOM_RECORDINGS_DRIVE_FOLDER").unwrap_or_else(|_| "zoom_recordings".to_string());
+    let shared_drive = drive_client.get_drive_by_name(ZOOM_RECORDINGS_SHARED_DRIVE).await.unwrap();
+    let drive_id = shared_drive.id.to_string();
+
+    // Get the directory by the name.
+    let drive_recordings_dir = drive_client.get_file_by_name(&drive_id, &folder).await.unwrap();
+    let parent_id = drive_recordings_dir.get(0).unwrap().id.to_string();
+
+    // Each of these is a single instance of a meeting, recurring meetings
+    // have one for every instance that was recorded.
+    let meetings = zoom.list_recordings_as_admin().await.unwrap();
+    for meeting in meetings {
+        // Only archive meetings where all the recordings have finished processing.
+        if !is_ready_to_archive(&meeting) {
+            println!("[zoom] meeting {} instance {} has recordings that are not completed, skipping", meeting.id, meeting.uuid);
+            continue;
+        }
+
+        let mut db_recordings: Vec<ZoomRecording> = Default::default();
+        for recording in &meeting.recording_files {
+            let recording_id = recording.id.clone().unwrap_or_default();
+            if recording_id.is_empty() {
+                continue;
+            }
+
+            // Check if we already archived this recording.
+            if let Some(existing) = ZoomRecording::get_from_db(&db, recording_id.to_string()) {
+                if !existing.google_drive_link.is_empty() {
+                    db_recordings.push(existing);
+                    continue;
+                }
+            }
+
+            match archive_recording(&db, &zoom, &drive_client, &drive_id, &parent_id, &meeting, recording).await {
+                Ok(r) => db_recordings.push(r),
+                // We can't delete this meeting from Zoom since we failed to archive it.
+                Err(e) => println!("[zoom] archiving recording {} for meeting {} failed: {}", recording_id, meeting.id, e),
+            }
+        }
+
+        if !is_archived(&meeting, &db_recordings) {
+            println!(
+                "[zoom] not all recordings for meeting {} instance {} were archived, not deleting them from zoom",
+                meeting.id, meeting.uuid
+            );
+            continue;
+        }
+
+        // Everything for this instance is safe in Google Drive, we can delete its
+        // recordings from Zoom. We delete by the instance UUID, the meeting ID
+        // would also delete the recordings of other instances of the meeting.
+        if let Err(e) = zoom.delete_meeting_instance_recordings(&meeting.uuid).await {
+            println!("[zoom] deleting recordings for meeting {} instance {} failed: {}", meeting.id, meeting.uuid, e);
+            continue;
+        }
+        println!("[zoom] archived and deleted recordings for meeting {} instance {}", meeting.id, meeting.uuid);
+
+        for mut db_recording in db_recordings {
+            db_recording.deleted_from_zoom = true;
+            db_recording.update(&db).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use zoom_api::{FileType, Meeting, Recording};
+
+    use crate::zoom_recordings::{is_archived, is_ready_to_archive, refresh_zoom_recordings, NewZoomRecording, ZoomRecording};
+
+    fn recording(id: &str, status: &str) -> Recording {
+        let mut r = Recording::default();
+        r.id = Some(id.to_string());
+        r.status = Some(status.to_string());
+        r
+    }
+
+    fn archived(recording_id: &str, google_drive_link: &str) -> ZoomRecording {
+        serde_json::from_value(json!({
+            "recording_id": recording_id,
+            "google_drive_link": google_drive_link,
+            "recording_start": "2021-04-06T17:00:00Z",
+            "recording_end": "2021-04-06T18:00:00Z",
+            "archived_time": "2021-04-06T19:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_zoom_recording_is_ready_to_archive() {
+        let mut meeting = Meeting::default();
+        assert!(!is_ready_to_archive(&meeting));
+
+        meeting.recording_files = vec![recording("a1b2", "completed"), recording("c3d4", "processing")];
+        assert!(!is_ready_to_archive(&meeting));
+
+        meeting.recording_files[1].status = Some("completed".to_string());
+        assert!(is_ready_to_archive(&meeting));
+    }
+
+    #[test]
+    fn test_zoom_recording_is_archived() {
+        let mut meeting = Meeting::default();
+        meeting.recording_files = vec![recording("a1b2", "completed"), recording("c3d4", "completed")];
+
+        // Only delete from Zoom once every recording is in Google Drive.
+        assert!(!is_archived(&meeting, &[]));
+        assert!(!is_archived(&meeting, &[archived("a1b2", "https://drive.google.com/open?id=1")]));
+        assert!(!is_archived(&meeting, &[archived("a1b2", "https://drive.google.com/open?id=1"), archived("c3d4", "")]));
+        assert!(is_archived(
+            &meeting,
+            &[archived("a1b2", "https://drive.google.com/open?id=1"), archived("c3d4", "https://drive.google.com/open?id=2")]
+        ));
+
+        // A recording without an ID can never be archived.
+        meeting.recording_files.push(recording("", "completed"));
+        assert!(!is_archived(
+            &meeting,
+            &[archived("a1b2", "https://drive.google.com/open?id=1"), archived("c3d4", "https://drive.google.com/open?id=2")]
+        ));
+    }
+
+    #[test]
+    fn test_zoom_recording_file_names_are_unique() {
+        let mut meeting = Meeting::default();
+        meeting.id = 81234567890;
+        meeting.topic = "Weekly Product Huddle".to_string();
+
+        let mut speaker = Recording::default();
+        speaker.id = Some("a1b2".to_string());
+        speaker.recording_start = "2021-04-06T17:00:00Z".to_string();
+        speaker.file_type = FileType::MP4;
+        let mut gallery = speaker.clone();
+        gallery.id = Some("c3d4".to_string());
+
+        let speaker_name = NewZoomRecording::generate_file_name(&meeting, &speaker);
+        assert_eq!(speaker_name, "2021-04-06-weekly-product-huddle-81234567890-a1b2-video.mp4");
+        assert_ne!(speaker_name, NewZoomRecording::generate_file_name(&meeting, &gallery));
+    }
+
+    #[ignore]
+    #[tokio::test(threaded_scheduler)]
+    async fn test_cron_zoom_recordings() {
+        refresh_zoom_recordings().await;
+    }
+}
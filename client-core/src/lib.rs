@@ -0,0 +1,555 @@
+/*!
+ * Shared HTTP middleware for our API clients.
+ *
+ * This wraps a `reqwest::Client` so every API client gets the same retries with
+ * exponential backoff, client side rate limiting, tracing spans, and parsing of
+ * rate limit headers, instead of each crate implementing its own.
+ *
+ * Example:
+ *
+ * ```
+ * use client_core::Client;
+ * use reqwest::Method;
+ *
+ * async fn get_thing() {
+ *     // Initialize the client.
+ *     let client = Client::new("example").unwrap();
+ *
+ *     // Build and send the request, it will be retried if it fails.
+ *     let request = client
+ *         .request(Method::GET, "https://example.com/thing")
+ *         .build()
+ *         .unwrap();
+ *     let resp = client.execute(request).await.unwrap();
+ *
+ *     println!("{:?}", resp.status());
+ * }
+ * ```
+ */
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::offset::Utc;
+use chrono::{DateTime, NaiveDateTime};
+use rand::Rng;
+use reqwest::{header, IntoUrl, Method, Request, RequestBuilder, Response, StatusCode, Url};
+use tokio::sync::Mutex;
+use tracing::{event, Level};
+use tracing_futures::Instrument;
+
+/// How failed requests should be retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of times to retry a request, not including the first attempt.
+    pub max_retries: u32,
+    /// The delay before the first retry. This is doubled for every retry after.
+    pub base_delay: Duration,
+    /// The longest we will ever wait between retries.
+    pub max_delay: Duration,
+    /// Randomize the delay between half and all of the backoff, so clients that
+    /// were rate limited at the same time do not all retry at once.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy { max_retries: 0, ..Default::default() }
+    }
+
+    /// Return the delay before the given retry, starting at 0.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.base_delay.checked_mul(2u32.saturating_pow(retry)).unwrap_or(self.max_delay);
+        if delay > self.max_delay {
+            self.max_delay
+        } else {
+            delay
+        }
+    }
+
+    /// Return the delay before the given retry, starting at 0, with jitter
+    /// applied if it is enabled.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        if !self.jitter {
+            return backoff;
+        }
+
+        let half = backoff / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// The rate limit information returned by an API in the response headers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<DateTime<Utc>>,
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Parse the rate limit from the `X-RateLimit-*` and `Retry-After` headers.
+    pub fn from_headers(headers: &header::HeaderMap) -> Self {
+        let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_string());
+
+        RateLimit {
+            limit: get("x-ratelimit-limit").and_then(|v| v.parse().ok()),
+            remaining: get("x-ratelimit-remaining").and_then(|v| v.parse().ok()),
+            // The reset header is the time in seconds since the epoch.
+            reset: get("x-ratelimit-reset")
+                .and_then(|v| v.parse::<i64>().ok())
+                .map(|t| DateTime::from_utc(NaiveDateTime::from_timestamp(t, 0), Utc)),
+            // The retry after header can also be a date but we only handle the number of seconds.
+            retry_after: get("retry-after").and_then(|v| v.parse::<u64>().ok()).map(Duration::from_secs),
+        }
+    }
+
+    /// Return how long we should wait before making another request, if at all.
+    pub fn wait_time(&self) -> Option<Duration> {
+        if let Some(retry_after) = self.retry_after {
+            return Some(retry_after);
+        }
+
+        if self.remaining == Some(0) {
+            if let Some(reset) = self.reset {
+                return (reset - Utc::now()).to_std().ok();
+            }
+        }
+
+        None
+    }
+}
+
+/// Sends requests over the network. The default transport is a `reqwest::Client`,
+/// tests can use their own to return canned responses without a server.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> Pin<Box<dyn Future<Output = Result<Response, reqwest::Error>> + Send + '_>>;
+}
+
+impl Transport for reqwest::Client {
+    fn execute(&self, request: Request) -> Pin<Box<dyn Future<Output = Result<Response, reqwest::Error>> + Send + '_>> {
+        Box::pin(reqwest::Client::execute(self, request))
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn execute(&self, request: Request) -> Pin<Box<dyn Future<Output = Result<Response, reqwest::Error>> + Send + '_>> {
+        (**self).execute(request)
+    }
+}
+
+/// Hooks that are called around every attempt at sending a request, including
+/// retries, so callers can add their own logging and metrics.
+///
+/// The request headers include the API credentials, use `redact_headers` before
+/// logging them.
+pub trait Hook: Send + Sync {
+    /// Called right before a request is sent.
+    fn on_request(&self, _request: &Request) {}
+
+    /// Called when a request is done, with how long it took.
+    fn on_response(&self, _method: &Method, _url: &Url, _response: Result<&Response, &reqwest::Error>, _elapsed: Duration) {}
+}
+
+// So callers can keep a handle to a hook, for example to read the metrics it collected.
+impl<H: Hook + ?Sized> Hook for Arc<H> {
+    fn on_request(&self, request: &Request) {
+        (**self).on_request(request)
+    }
+
+    fn on_response(&self, method: &Method, url: &Url, response: Result<&Response, &reqwest::Error>, elapsed: Duration) {
+        (**self).on_response(method, url, response, elapsed)
+    }
+}
+
+/// Return a copy of the headers with the values of any headers that carry
+/// credentials replaced, so they can be logged.
+pub fn redact_headers(headers: &header::HeaderMap) -> header::HeaderMap {
+    let mut redacted = headers.clone();
+    for (name, value) in redacted.iter_mut() {
+        if name == header::AUTHORIZATION || name == header::PROXY_AUTHORIZATION || name == header::COOKIE || name.as_str().contains("api-key") {
+            *value = header::HeaderValue::from_static("[redacted]");
+        }
+    }
+    redacted
+}
+
+/// Counters for the requests sent by a client. They are shared by every clone
+/// of the client, so callers that share one client, and its connection pool,
+/// can see how much it is used.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Metrics {
+    /// The number of requests sent, counting every retry.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests waiting on a response right now.
+    pub fn in_flight(&self) -> u64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests that failed without a response, for example
+    /// because we could not connect or they timed out.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts a request as in flight until it is dropped, so requests that are
+/// canceled are not counted forever.
+struct InFlight<'a>(&'a Metrics);
+
+impl<'a> InFlight<'a> {
+    fn start(metrics: &'a Metrics) -> Self {
+        metrics.requests.fetch_add(1, Ordering::Relaxed);
+        metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(metrics)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Entrypoint for making requests with our shared middleware.
+#[derive(Clone)]
+pub struct Client {
+    name: String,
+    retry_policy: RetryPolicy,
+    min_interval: Option<Duration>,
+    last_request: Arc<Mutex<Option<Instant>>>,
+    hooks: Vec<Arc<dyn Hook>>,
+    transport: Arc<dyn Transport>,
+    metrics: Arc<Metrics>,
+
+    client: Arc<reqwest::Client>,
+}
+
+impl Client {
+    /// Create a new client. The name is used in the tracing spans for every request
+    /// so we can tell the API clients apart.
+    pub fn new<N>(name: N) -> Result<Self, reqwest::Error>
+    where
+        N: ToString,
+    {
+        Client::from_builder(name, reqwest::Client::builder())
+    }
+
+    /// Create a new client from a `reqwest::ClientBuilder`, for clients that need
+    /// to set timeouts, a proxy, or other options on the underlying client.
+    pub fn from_builder<N>(name: N, builder: reqwest::ClientBuilder) -> Result<Self, reqwest::Error>
+    where
+        N: ToString,
+    {
+        let client = Arc::new(builder.build()?);
+
+        Ok(Client {
+            name: name.to_string(),
+            retry_policy: Default::default(),
+            min_interval: None,
+            last_request: Arc::new(Mutex::new(None)),
+            hooks: Vec::new(),
+            transport: client.clone(),
+            metrics: Default::default(),
+
+            client,
+        })
+    }
+
+    /// Set the retry policy for the client.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limit the client to sending at most one request per `interval`.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = Some(interval);
+        self
+    }
+
+    /// Add a hook that is called around every request the client sends.
+    pub fn with_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Hook + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Send requests with a different transport, for example one that returns
+    /// canned responses in tests. Retries, rate limiting, and hooks still apply.
+    pub fn with_transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Return the name of the client.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the counters for the requests sent by this client and its clones.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Start building a request.
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// Start building a GET request.
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Start building a POST request.
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Build and execute a request.
+    pub async fn send(&self, rb: RequestBuilder) -> Result<Response, reqwest::Error> {
+        self.execute(rb.build()?).await
+    }
+
+    /// Execute a request. Requests that fail to connect or are rate limited are
+    /// retried with backoff. Idempotent requests are also retried on timeouts and
+    /// server errors.
+    pub async fn execute(&self, request: Request) -> Result<Response, reqwest::Error> {
+        let span = tracing::info_span!("http", client = %self.name, method = %request.method(), url = %request.url());
+
+        self.execute_with_retries(request).instrument(span).await
+    }
+
+    async fn execute_with_retries(&self, request: Request) -> Result<Response, reqwest::Error> {
+        let idempotent = is_idempotent(request.method());
+        let mut retry: u32 = 0;
+        loop {
+            // Requests with a streaming body cannot be cloned, so we can only send them once.
+            let attempt = match request.try_clone() {
+                Some(r) if retry < self.retry_policy.max_retries => r,
+                _ => {
+                    self.wait_for_rate_limit().await;
+                    return self.send_once(request).await;
+                }
+            };
+
+            self.wait_for_rate_limit().await;
+            let delay = match self.send_once(attempt).await {
+                Ok(resp) => {
+                    let status = resp.status();
+                    if !is_retryable(status, idempotent) {
+                        event!(Level::DEBUG, "{} {}", self.name, status);
+                        return Ok(resp);
+                    }
+
+                    let rate_limit = RateLimit::from_headers(resp.headers());
+                    event!(
+                        Level::WARN,
+                        "{} request failed with status {}, retry {} of {}",
+                        self.name,
+                        status,
+                        retry + 1,
+                        self.retry_policy.max_retries
+                    );
+                    // If the API told us how long to wait, listen to it.
+                    match rate_limit.wait_time() {
+                        Some(wait) => wait.min(self.retry_policy.max_delay),
+                        None => self.retry_policy.delay(retry),
+                    }
+                }
+                Err(e) => {
+                    // If we failed to connect the request was never sent, so it is always
+                    // safe to retry.
+                    if !(e.is_connect() || (idempotent && e.is_timeout())) {
+                        return Err(e);
+                    }
+
+                    event!(Level::WARN, "{} request failed: {}, retry {} of {}", self.name, e, retry + 1, self.retry_policy.max_retries);
+                    self.retry_policy.delay(retry)
+                }
+            };
+
+            tokio::time::delay_for(delay).await;
+            retry += 1;
+        }
+    }
+
+    /// Send a request once, counting it in the metrics.
+    async fn send_once(&self, request: Request) -> Result<Response, reqwest::Error> {
+        let _in_flight = InFlight::start(&self.metrics);
+        let result = self.send_with_hooks(request).await;
+        if result.is_err() {
+            self.metrics.errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    /// Send a request, calling the hooks around it.
+    async fn send_with_hooks(&self, request: Request) -> Result<Response, reqwest::Error> {
+        if self.hooks.is_empty() {
+            return self.transport.execute(request).await;
+        }
+
+        for hook in &self.hooks {
+            hook.on_request(&request);
+        }
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let start = Instant::now();
+        let result = self.transport.execute(request).await;
+        let elapsed = start.elapsed();
+        for hook in &self.hooks {
+            hook.on_response(&method, &url, result.as_ref(), elapsed);
+        }
+
+        result
+    }
+
+    /// Wait until we are allowed to send another request.
+    async fn wait_for_rate_limit(&self) {
+        let interval = match self.min_interval {
+            Some(i) => i,
+            None => return,
+        };
+
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last) = *last_request {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                tokio::time::delay_for(interval - elapsed).await;
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+/// Returns if a request that got this status should be retried.
+/// We only retry server errors for idempotent requests, so we never create something twice.
+fn is_retryable(status: StatusCode, idempotent: bool) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || (idempotent && status.is_server_error())
+}
+
+/// Returns if sending a request with this method more than once has the same effect as sending it once.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::offset::Utc;
+    use reqwest::{header, Method, StatusCode};
+
+    use crate::{is_idempotent, is_retryable, redact_headers, RateLimit, RetryPolicy};
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            jitter: false,
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(3));
+        assert_eq!(policy.backoff(100), Duration::from_secs(3));
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+
+        // With jitter the delay is between half and all of the backoff.
+        let policy = RetryPolicy { jitter: true, ..policy };
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1), "{:?}", delay);
+        }
+
+        assert_eq!(RetryPolicy::none().max_retries, 0);
+    }
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let mut headers = header::HeaderMap::new();
+        headers.insert("x-ratelimit-limit", "100".parse().unwrap());
+        headers.insert("x-ratelimit-remaining", " 0 ".parse().unwrap());
+        headers.insert("x-ratelimit-reset", (Utc::now().timestamp() + 60).to_string().parse().unwrap());
+
+        let rate_limit = RateLimit::from_headers(&headers);
+        assert_eq!(rate_limit.limit, Some(100));
+        assert_eq!(rate_limit.remaining, Some(0));
+        assert!(rate_limit.retry_after.is_none());
+        let wait = rate_limit.wait_time().unwrap();
+        assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60), "{:?}", wait);
+
+        // Retry-After wins over the reset time.
+        headers.insert(header::RETRY_AFTER, "5".parse().unwrap());
+        assert_eq!(RateLimit::from_headers(&headers).wait_time(), Some(Duration::from_secs(5)));
+
+        // Dates in Retry-After are not handled, and with requests left there is no wait.
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        headers.insert("x-ratelimit-remaining", "10".parse().unwrap());
+        assert_eq!(RateLimit::from_headers(&headers).wait_time(), None);
+        assert_eq!(RateLimit::from_headers(&header::HeaderMap::new()), RateLimit::default());
+    }
+
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "ShippoToken secret".parse().unwrap());
+        headers.insert(header::COOKIE, "session=secret".parse().unwrap());
+        headers.insert("x-goog-api-key", "secret".parse().unwrap());
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted[header::AUTHORIZATION], "[redacted]");
+        assert_eq!(redacted[header::COOKIE], "[redacted]");
+        assert_eq!(redacted["x-goog-api-key"], "[redacted]");
+        assert_eq!(redacted[header::CONTENT_TYPE], "application/json");
+        // The original headers are left alone.
+        assert_eq!(headers[header::AUTHORIZATION], "ShippoToken secret");
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS, false));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY, true));
+        assert!(!is_retryable(StatusCode::BAD_GATEWAY, false));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST, true));
+
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+}
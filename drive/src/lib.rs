@@ -49,7 +49,7 @@ use std::fmt;
 use std::time::Duration;
 
 use bytes::Bytes;
-use reqwest::{header, Client, Method, Request, StatusCode, Url};
+use reqwest::{header, Body, Client, Method, Request, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use yup_oauth2::AccessToken;
 
@@ -331,6 +331,61 @@ impl GoogleDrive {
     /// If the file already exists, it will update it.
     /// If the file does not exist, it will create it.
     pub async fn create_or_upload_file(&self, drive_id: &str, parent_id: &str, name: &str, mime_type: &str, contents: &[u8]) -> Result<(), APIError> {
+        let location = self.get_upload_location(drive_id, parent_id, name, mime_type).await?;
+
+        // Now upload the file to that location.
+        let request = self.request(Method::PUT, location, (), None, contents, mime_type);
+
+        let resp = self.client.execute(request).await.unwrap();
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => {
+                return Err(APIError {
+                    status_code: s,
+                    body: resp.text().await.unwrap(),
+                });
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Create or update a file in a drive, like `create_or_upload_file`, but
+    /// stream the contents from `contents` instead of holding them in memory.
+    /// The `content_length` must be the size of the contents in bytes.
+    pub async fn create_or_upload_file_stream(&self, drive_id: &str, parent_id: &str, name: &str, mime_type: &str, contents: Body, content_length: u64) -> Result<(), APIError> {
+        let location = self.get_upload_location(drive_id, parent_id, name, mime_type).await?;
+
+        // Now upload the file to that location.
+        let resp = self
+            .client
+            .put(&location)
+            .bearer_auth(self.token.as_str())
+            .header(header::CONTENT_TYPE, mime_type)
+            .header(header::CONTENT_LENGTH, content_length)
+            .body(contents)
+            .send()
+            .await
+            .unwrap();
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => {
+                return Err(APIError {
+                    status_code: s,
+                    body: resp.text().await.unwrap(),
+                });
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Start a resumable upload for a file in a drive and return the location
+    /// to upload its contents to. If the file already exists, the upload will
+    /// update it.
+    async fn get_upload_location(&self, drive_id: &str, parent_id: &str, name: &str, mime_type: &str) -> Result<String, APIError> {
         // Create the file.
         let mut f: File = Default::default();
         let mut method = Method::POST;
@@ -385,24 +440,7 @@ impl GoogleDrive {
         };
 
         // Get the "Location" header.
-        let location = resp.headers().get("Location").unwrap().to_str().unwrap();
-
-        // Now upload the file to that location.
-        let request = self.request(Method::PUT, location.to_string(), (), None, contents, mime_type);
-
-        let resp = self.client.execute(request).await.unwrap();
-        match resp.status() {
-            StatusCode::OK => (),
-            StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                });
-            }
-        };
-
-        Ok(())
+        Ok(resp.headers().get("Location").unwrap().to_str().unwrap().to_string())
     }
 
     /// Delete a file by its name.
@@ -9,28 +9,14 @@
  * use std::env;
  *
  * use sheets::Sheets;
- * use yup_oauth2::{read_service_account_key, ServiceAccountAuthenticator};
  *
  * async fn get_sheet_values() {
  *     // Get the GSuite credentials file.
  *     let gsuite_credential_file = env::var("GADMIN_CREDENTIAL_FILE").unwrap();
  *     let gsuite_subject = env::var("GADMIN_SUBJECT").unwrap();
- *     let gsuite_secret = read_service_account_key(gsuite_credential_file).await.expect("failed to read gsuite credential file");
- *     let auth = ServiceAccountAuthenticator::builder(gsuite_secret)
- *         .subject(gsuite_subject.to_string())
- *         .build()
- *         .await
- *         .expect("failed to create authenticator");
  *
- *     // Add the scopes to the secret and get the token.
- *     let token = auth.token(&["https://www.googleapis.com/auth/spreadsheets"]).await.expect("failed to get token");
- *
- *     if token.as_str().is_empty() {
- *         panic!("empty token is not valid");
- *     }
- *
- *     // Initialize the Google Sheets client.
- *     let sheets_client = Sheets::new(token);
+ *     // Initialize the Google Sheets client, acting as the subject.
+ *     let sheets_client = Sheets::from_service_account_file(gsuite_credential_file, &gsuite_subject).await.expect("failed to get token");
  *
  *     // Get the values in the sheet.
  *     let sheet_values = sheets_client.get_values("sheet_id", "Form Responses 1!A1:S1000".to_string()).await.unwrap();
@@ -47,62 +33,245 @@
  * }
  * ```
  */
+use std::collections::{HashMap, VecDeque};
+use std::env;
 use std::error;
 use std::fmt;
-use std::sync::Arc;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use reqwest::{header, Client, Method, Request, StatusCode, Url};
+use client_core::Client;
+pub use client_core::{redact_headers, Hook, Metrics, RetryPolicy};
+use futures_util::future::{BoxFuture, Future};
+use futures_util::stream::{self, Stream};
+use reqwest::{header, Method, Request, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use yup_oauth2::AccessToken;
+use yup_oauth2::{read_service_account_key, AccessToken, ServiceAccountAuthenticator, ServiceAccountKey};
 
 /// Endpoint for the Google Sheets API.
 const ENDPOINT: &str = "https://sheets.googleapis.com/v4/";
 
+/// Endpoint for the Google Drive API, which has the revisions of spreadsheets.
+const DRIVE_ENDPOINT: &str = "https://www.googleapis.com/drive/v3/";
+
+/// The OAuth scopes for reading and writing spreadsheets, and for reading
+/// their revision from the Drive API.
+const SCOPES: &[&str] = &["https://www.googleapis.com/auth/spreadsheets", "https://www.googleapis.com/auth/drive.metadata.readonly"];
+
+/// A source of access tokens, for clients that run longer than a token is good
+/// for. It is implemented for closures that return a future of the token, see
+/// `Sheets::from_token_source`.
+pub trait TokenSource: Send + Sync {
+    /// Return a token that is good for the next request.
+    fn token(&self) -> BoxFuture<'_, Result<String, SheetsError>>;
+}
+
+impl<F, Fut> TokenSource for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, SheetsError>> + Send + 'static,
+{
+    fn token(&self) -> BoxFuture<'_, Result<String, SheetsError>> {
+        Box::pin(self())
+    }
+}
+
+/// Where the client gets the token for a request from.
+enum Auth {
+    /// A token we were handed, it can't be refreshed.
+    Token(AccessToken),
+    /// A source that gets a new token when the old one expires.
+    Source(Box<dyn TokenSource>),
+}
+
 /// Entrypoint for interacting with the Google Sheets API.
 pub struct Sheets {
-    token: AccessToken,
+    auth: Auth,
+    endpoint: String,
+    drive_endpoint: String,
 
     client: Arc<Client>,
+    cache: Option<Arc<ValuesCache>>,
 }
 
 impl Sheets {
     /// Create a new Sheets client struct. It takes a type that can convert into
     /// an &str (`String` or `Vec<u8>` for example). As long as the function is
-    /// given a valid API Key and Secret your requests will work.
-    pub fn new(token: AccessToken) -> Self {
-        let client = Client::builder().build();
-        match client {
-            Ok(c) => Self { token, client: Arc::new(c) },
-            Err(e) => panic!("creating client failed: {:?}", e),
+    /// given a valid API Key and Secret your requests will work. This fails if
+    /// the underlying HTTP client can't be created.
+    pub fn new(token: AccessToken) -> Result<Self, SheetsError> {
+        Sheets::with_auth(Auth::Token(token))
+    }
+
+    /// Create a new Sheets client that asks `source` for the token before
+    /// every request, so it keeps working after a token expires.
+    pub fn from_token_source<S>(source: S) -> Result<Self, SheetsError>
+    where
+        S: TokenSource + 'static,
+    {
+        Sheets::with_auth(Auth::Source(Box::new(source)))
+    }
+
+    fn with_auth(auth: Auth) -> Result<Self, SheetsError> {
+        let client = Client::new("sheets")?;
+
+        Ok(Self {
+            auth,
+            endpoint: env::var("SHEETS_ENDPOINT").unwrap_or_else(|_| ENDPOINT.to_string()),
+            drive_endpoint: env::var("SHEETS_DRIVE_ENDPOINT").unwrap_or_else(|_| DRIVE_ENDPOINT.to_string()),
+            client: Arc::new(client),
+            cache: None,
+        })
+    }
+
+    /// Create a new Sheets client acting as `subject`, using domain-wide
+    /// delegation with the service account `key`. The authenticator keeps the
+    /// token cached and gets a new one when it expires, so the client can be
+    /// used for as long as needed. The token is for both the spreadsheets scope
+    /// and the Drive metadata scope `get_revision` needs, so the service account
+    /// must be allowed both.
+    pub async fn from_service_account(key: ServiceAccountKey, subject: &str) -> Result<Self, SheetsError> {
+        let auth = Arc::new(ServiceAccountAuthenticator::builder(key).subject(subject).build().await.map_err(SheetsError::Io)?);
+
+        // Get a token now, so a bad key or subject is reported here instead of
+        // on the first request.
+        auth.token(SCOPES).await.map_err(SheetsError::Auth)?;
+
+        Sheets::from_token_source(move || {
+            let auth = auth.clone();
+            async move { auth.token(SCOPES).await.map(|t| t.as_str().to_string()).map_err(SheetsError::Auth) }
+        })
+    }
+
+    /// Create a new Sheets client acting as `subject`, reading the service
+    /// account key from the JSON file at `path`, see `from_service_account`.
+    pub async fn from_service_account_file<P>(path: P, subject: &str) -> Result<Self, SheetsError>
+    where
+        P: AsRef<Path>,
+    {
+        let key = read_service_account_key(path).await.map_err(SheetsError::Io)?;
+
+        Sheets::from_service_account(key, subject).await
+    }
+
+    /// Get the token the next request would be sent with.
+    pub async fn get_token(&self) -> Result<String, SheetsError> {
+        match &self.auth {
+            Auth::Token(token) if token.is_expired() => Err(SheetsError::TokenExpired),
+            Auth::Token(token) => Ok(token.as_str().to_string()),
+            Auth::Source(source) => source.token().await,
         }
     }
 
-    /// Get the currently set authorization token.
-    pub fn get_token(&self) -> &AccessToken {
-        &self.token
+    /// Set the endpoint the client sends requests to. This defaults to the
+    /// production API, or the `SHEETS_ENDPOINT` environment variable if it is set,
+    /// and is useful for testing against a fake server.
+    pub fn with_endpoint<E>(mut self, endpoint: E) -> Self
+    where
+        E: ToString,
+    {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    /// Set the endpoint of the Google Drive API, used for `get_revision`. This
+    /// defaults to the production API, or the `SHEETS_DRIVE_ENDPOINT`
+    /// environment variable if it is set.
+    pub fn with_drive_endpoint<E>(mut self, endpoint: E) -> Self
+    where
+        E: ToString,
+    {
+        self.drive_endpoint = endpoint.to_string();
+        self
+    }
+
+    /// Set how requests are retried. By default, requests that hit a quota are
+    /// retried up to `max_retries` times with exponential backoff and jitter,
+    /// waiting as long as the `Retry-After` header asks. Server errors, like a
+    /// 503 when the API is busy, are only retried for reads and other requests
+    /// that are safe to send twice.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.client = Arc::new((*self.client).clone().with_retry_policy(retry_policy));
+        self
     }
 
-    fn request<B>(&self, method: Method, path: String, body: B, query: Option<Vec<(&str, String)>>) -> Request
+    /// Add a hook that is called around every request sent to Google Sheets,
+    /// including retries, for logging and metrics. The requests include our
+    /// token in the `Authorization` header, use `redact_headers` before logging
+    /// them.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use reqwest::{Method, Response, Url};
+    /// use sheets::Hook;
+    ///
+    /// /// Log how long every call took, so we can see which jobs use up the quota.
+    /// struct Latency {
+    ///     job: &'static str,
+    /// }
+    ///
+    /// impl Hook for Latency {
+    ///     fn on_response(&self, method: &Method, url: &Url, response: Result<&Response, &reqwest::Error>, elapsed: Duration) {
+    ///         let status = response.map(|r| r.status().as_u16()).unwrap_or_default();
+    ///         println!("{}: {} {} -> {} in {:?}", self.job, method, url.path(), status, elapsed);
+    ///     }
+    /// }
+    /// ```
+    pub fn with_hook<H>(mut self, hook: H) -> Self
     where
-        B: Serialize,
+        H: Hook + 'static,
     {
-        let base = Url::parse(ENDPOINT).unwrap();
-        let url = base.join(&path).unwrap();
+        self.client = Arc::new((*self.client).clone().with_hook(hook));
+        self
+    }
 
-        // Check if the token is expired and panic.
-        if self.token.is_expired() {
-            panic!("token is expired");
+    /// Keep the values read with `get_values`, `get_values_with_options` and the
+    /// reads built on them, like `stream_rows` and `export_csv`, in `cache`.
+    /// `batch_get_values` always reads from the API. The cache can be shared by
+    /// several clients, so jobs reading the same sheets within the TTL of the
+    /// cache only read them once. The values are kept by the revision of the
+    /// spreadsheet, see `get_revision`, which is checked before every cached
+    /// read, so changes made by anyone else are read again right away. The token
+    /// needs one of the Drive scopes for that. Writes through any client sharing
+    /// the cache clear the spreadsheet from it, and values read while a write was
+    /// in flight are not kept.
+    pub fn with_cache(mut self, cache: Arc<ValuesCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Clear a spreadsheet from the cache, if there is one.
+    fn invalidate_cache(&self, spreadsheet_id: &str) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(spreadsheet_id);
         }
+    }
+
+    /// Return the counters for the requests sent by this client.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.client.metrics()
+    }
 
-        let bt = format!("Bearer {}", self.token.as_str());
-        let bearer = header::HeaderValue::from_str(&bt).unwrap();
+    async fn request<B>(&self, method: Method, path: String, body: B, query: Option<Vec<(&str, String)>>) -> Result<Request, SheetsError>
+    where
+        B: Serialize,
+    {
+        let base = Url::parse(&self.endpoint)?;
+        let url = base.join(&path)?;
 
-        // Set the default headers.
-        let mut headers = header::HeaderMap::new();
-        headers.append(header::AUTHORIZATION, bearer);
-        headers.append(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+        let token = self.get_token().await?;
 
-        let mut rb = self.client.request(method.clone(), url).headers(headers);
+        // Set the default headers. An invalid token is reported when the request is built.
+        let mut rb = self
+            .client
+            .request(method.clone(), url)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json");
 
         match query {
             None => (),
@@ -117,114 +286,970 @@ impl Sheets {
         }
 
         // Build the request.
-        rb.build().unwrap()
+        Ok(rb.build()?)
     }
 
-    /// Get values.
-    pub async fn get_values(&self, sheet_id: &str, range: String) -> Result<ValueRange, APIError> {
+    /// Create a spreadsheet with a single empty sheet.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets/create
+    pub async fn create_spreadsheet(&self, title: &str) -> Result<Spreadsheet, SheetsError> {
+        let body = Spreadsheet {
+            properties: Some(SpreadsheetProperties { title: Some(title.to_string()) }),
+            ..Default::default()
+        };
+
         // Build the request.
-        let request = self.request(
-            Method::GET,
-            format!("spreadsheets/{}/values/{}", sheet_id.to_string(), range),
-            (),
-            Some(vec![
-                ("valueRenderOption", "FORMATTED_VALUE".to_string()),
-                ("dateTimeRenderOption", "FORMATTED_STRING".to_string()),
-                ("majorDimension", "ROWS".to_string()),
-            ]),
-        );
+        let request = self.request(Method::POST, "spreadsheets".to_string(), body, None).await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
 
-        let resp = self.client.execute(request).await.unwrap();
+    /// Get the properties of a spreadsheet, its sheets, and its named ranges,
+    /// without the values in the cells.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets/get
+    pub async fn get_spreadsheet(&self, spreadsheet_id: &str) -> Result<Spreadsheet, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(Method::GET, format!("spreadsheets/{}", spreadsheet_id), (), Some(vec![("includeGridData", "false".to_string())]))
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get the cells in a range with their values, formatting, notes, and
+    /// links, for when the values alone are not enough, like finding rows
+    /// that were struck through.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets/get
+    pub async fn get_grid_data(&self, spreadsheet_id: &str, range: &str) -> Result<GridData, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::GET,
+                format!("spreadsheets/{}", spreadsheet_id),
+                (),
+                Some(vec![("ranges", range.to_string()), ("includeGridData", "true".to_string())]),
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response, the range is the only data in it.
+        let spreadsheet: Spreadsheet = serde_json::from_str(&resp.text().await?)?;
+
+        Ok(spreadsheet.sheets.into_iter().flat_map(|s| s.data).next().unwrap_or_default())
+    }
+
+    /// Get the revision of a spreadsheet. It goes up with every change anyone
+    /// makes to the spreadsheet, including us. The revision comes from the
+    /// Google Drive API, so the token needs one of the Drive scopes, like
+    /// "https://www.googleapis.com/auth/drive.metadata.readonly". Clients from
+    /// `from_service_account` already ask for it.
+    /// FROM: https://developers.google.com/drive/api/v3/reference/files/get
+    pub async fn get_revision(&self, spreadsheet_id: &str) -> Result<i64, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::GET,
+                format!("{}files/{}", self.drive_endpoint, spreadsheet_id),
+                (),
+                Some(vec![("fields", "version".to_string())]),
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response, the version is an int64 sent as a string.
+        let file: DriveFile = serde_json::from_str(&resp.text().await?)?;
+        file.version.parse().map_err(|_| SheetsError::InvalidVersion(file.version))
+    }
+
+    /// Return `SheetsError::Conflict` if the spreadsheet changed since it was
+    /// at `revision`. Check this right before writing something computed from
+    /// values read at `revision`, so a job does not overwrite changes made by
+    /// another job running at the same time. There is still a short window
+    /// between the check and the write where changes are not seen.
+    pub async fn check_revision(&self, spreadsheet_id: &str, revision: i64) -> Result<(), SheetsError> {
+        let current = self.get_revision(spreadsheet_id).await?;
+        if current != revision {
+            return Err(SheetsError::Conflict { expected: revision, actual: current });
+        }
+
+        Ok(())
+    }
+
+    /// Update the values in a range, like `update_values_range`, unless the
+    /// spreadsheet changed since it was at `revision`, see `check_revision`.
+    pub async fn update_values_if_unchanged(&self, sheet_id: &str, range: &str, values: ValueRange, value_input_option: ValueInputOption, revision: i64) -> Result<UpdateValuesResponse, SheetsError> {
+        self.check_revision(sheet_id, revision).await?;
+
+        self.update_values_range(sheet_id, range, values, value_input_option).await
+    }
+
+    /// Apply changes to a spreadsheet, like adding sheets or formatting cells.
+    /// The changes are applied together, if any of them fails none are applied.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets/batchUpdate
+    pub async fn batch_update(&self, spreadsheet_id: &str, requests: BatchUpdateRequest) -> Result<BatchUpdateSpreadsheetResponse, SheetsError> {
+        // Build the request.
+        let request = self.request(Method::POST, format!("spreadsheets/{}:batchUpdate", spreadsheet_id), requests, None).await?;
+
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(spreadsheet_id);
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Add an empty sheet to the end of a spreadsheet, returning its properties.
+    pub async fn add_sheet(&self, spreadsheet_id: &str, title: &str) -> Result<SheetProperties, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().add_sheet(title)).await?;
+
+        Ok(resp.replies.into_iter().next().and_then(|r| r.add_sheet).map(|r| r.properties).unwrap_or_default())
+    }
+
+    /// Delete the sheet with the ID `sheet_id` from a spreadsheet.
+    pub async fn delete_sheet(&self, spreadsheet_id: &str, sheet_id: i64) -> Result<(), SheetsError> {
+        self.batch_update(spreadsheet_id, BatchUpdateRequest::new().delete_sheet(sheet_id)).await?;
+
+        Ok(())
+    }
+
+    /// Copy the sheet with the ID `sheet_id` to a new sheet called `title`, at
+    /// the end of the spreadsheet, returning the properties of the copy.
+    pub async fn duplicate_sheet(&self, spreadsheet_id: &str, sheet_id: i64, title: &str) -> Result<SheetProperties, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().duplicate_sheet(sheet_id, title)).await?;
+
+        Ok(resp.replies.into_iter().next().and_then(|r| r.duplicate_sheet).map(|r| r.properties).unwrap_or_default())
+    }
+
+    /// Copy the sheet with the ID `sheet_id` to the end of another spreadsheet,
+    /// returning the properties of the copy. The copy is called "Copy of" the
+    /// original title.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.sheets/copyTo
+    pub async fn copy_sheet_to(&self, spreadsheet_id: &str, sheet_id: i64, destination_spreadsheet_id: &str) -> Result<SheetProperties, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::POST,
+                format!("spreadsheets/{}/sheets/{}:copyTo", spreadsheet_id, sheet_id),
+                CopySheetToRequest {
+                    destination_spreadsheet_id: destination_spreadsheet_id.to_string(),
+                },
+                None,
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(destination_spreadsheet_id);
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Find and replace text, returning how much was changed.
+    pub async fn find_replace(&self, spreadsheet_id: &str, find_replace: FindReplace) -> Result<FindReplaceReply, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().find_replace(find_replace)).await?;
+
+        Ok(resp.replies.into_iter().next().and_then(|r| r.find_replace).unwrap_or_default())
+    }
+
+    /// Add a named range called `name` for `range`, returning it with the ID
+    /// picked by the API.
+    pub async fn add_named_range(&self, spreadsheet_id: &str, name: &str, range: GridRange) -> Result<NamedRange, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().add_named_range(name, range)).await?;
+
+        Ok(resp.replies.into_iter().next().and_then(|r| r.add_named_range).map(|r| r.named_range).unwrap_or_default())
+    }
+
+    /// List the named ranges of a spreadsheet.
+    pub async fn list_named_ranges(&self, spreadsheet_id: &str) -> Result<Vec<NamedRange>, SheetsError> {
+        Ok(self.get_spreadsheet(spreadsheet_id).await?.named_ranges)
+    }
+
+    /// Delete the named range with the ID `named_range_id`. Only the name is
+    /// deleted, the cells are left alone.
+    pub async fn delete_named_range(&self, spreadsheet_id: &str, named_range_id: &str) -> Result<(), SheetsError> {
+        self.batch_update(spreadsheet_id, BatchUpdateRequest::new().delete_named_range(named_range_id)).await?;
+
+        Ok(())
+    }
+
+    /// Get the values in the named range called `name`. Unlike A1 ranges, the
+    /// name keeps working when rows or columns are added around the range.
+    pub async fn get_values_by_named_range(&self, sheet_id: &str, name: &str) -> Result<ValueRange, SheetsError> {
+        self.get_values_with_options(sheet_id, name, &GetValuesOptions::default()).await
+    }
+
+    /// Protect a range from edits by anyone other than the `editors` of the
+    /// protection, returning it with the ID picked by the API.
+    pub async fn add_protected_range(&self, spreadsheet_id: &str, protected_range: ProtectedRange) -> Result<ProtectedRange, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().add_protected_range(protected_range)).await?;
+
+        Ok(resp.replies.into_iter().next().and_then(|r| r.add_protected_range).map(|r| r.protected_range).unwrap_or_default())
+    }
+
+    /// Remove the protection with the ID `protected_range_id`.
+    pub async fn delete_protected_range(&self, spreadsheet_id: &str, protected_range_id: i64) -> Result<(), SheetsError> {
+        self.batch_update(spreadsheet_id, BatchUpdateRequest::new().delete_protected_range(protected_range_id)).await?;
+
+        Ok(())
+    }
+
+    /// Get values.
+    pub async fn get_values(&self, sheet_id: &str, range: String) -> Result<ValueRange, SheetsError> {
+        self.get_values_with_options(sheet_id, &range, &GetValuesOptions::default()).await
+    }
+
+    /// Get values, choosing how they are rendered and whether they are
+    /// grouped by rows or columns.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/get
+    pub async fn get_values_with_options(&self, sheet_id: &str, range: &str, options: &GetValuesOptions) -> Result<ValueRange, SheetsError> {
+        let cached = match &self.cache {
+            Some(cache) => {
+                let generation = cache.generation();
+                let key = (sheet_id.to_string(), self.get_revision(sheet_id).await?, range.to_string(), *options);
+                if let Some(values) = cache.get(&key) {
+                    return Ok(values);
+                }
+
+                Some((cache, key, generation))
             }
+            None => None,
+        };
+
+        // Build the request.
+        let request = self.request(Method::GET, format!("spreadsheets/{}/values/{}", sheet_id, range), (), Some(options.query())).await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
         };
 
         // Try to deserialize the response.
-        Ok(resp.json().await.unwrap())
+        let values: ValueRange = serde_json::from_str(&resp.text().await?)?;
+        if let Some((cache, key, generation)) = cached {
+            cache.insert(key, values.clone(), generation);
+        }
+
+        Ok(values)
     }
 
     /// Get single cell value.
     /// The `cell_name` is something like `A1` and what is returned is a string representation of
     /// the cell's value.
-    pub async fn get_value(&self, sheet_id: &str, cell_name: String) -> Result<String, APIError> {
-        let value_range = self.get_values(sheet_id, cell_name).await.unwrap();
+    pub async fn get_value(&self, sheet_id: &str, cell_name: String) -> Result<String, SheetsError> {
+        let value_range = self.get_values(sheet_id, cell_name).await?;
         let values = value_range.values.unwrap_or_default();
-        if values.is_empty() {
-            return Ok(String::new());
+
+        Ok(values.first().and_then(|row| row.first()).map(|v| v.to_string()).unwrap_or_default())
+    }
+
+    /// Get the values in a range and deserialize each row into a `T`, using the
+    /// first row as the names of the fields, see `ValueRange::deserialize_rows`.
+    pub async fn read_as<T>(&self, sheet_id: &str, range: &str) -> Result<Vec<T>, SheetsError>
+    where
+        T: DeserializeOwned,
+    {
+        self.get_values(sheet_id, range.to_string()).await?.deserialize_rows()
+    }
+
+    /// Serialize each of `rows` into a row of the range, under the columns of
+    /// the header row, the first row of the range, see `ValueRange::serialize_rows`.
+    /// If the header row is empty, it is written with the fields of the first
+    /// row in alphabetical order. Values are written as is, so strings are
+    /// never parsed as formulas, and rows below the written ones are left alone.
+    pub async fn write_rows<T>(&self, sheet_id: &str, range: &str, rows: &[T]) -> Result<UpdateValuesResponse, SheetsError>
+    where
+        T: Serialize,
+    {
+        if rows.is_empty() {
+            return Ok(Default::default());
+        }
+
+        let range: A1Range = range.parse()?;
+        let header_row = range.start_row.unwrap_or_default();
+        let header_range = A1Range {
+            start_row: Some(header_row),
+            end_row: Some(header_row + 1),
+            ..range.clone()
+        };
+
+        // Start writing after the header row, unless we have to write it too.
+        let mut start_row = header_row + 1;
+        let mut values = Vec::new();
+        let mut headers: Vec<String> = self
+            .get_values(sheet_id, header_range.to_string())
+            .await?
+            .values
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_default()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        if headers.iter().all(|h| h.trim().is_empty()) {
+            headers = match rows.first().map(serde_json::to_value).transpose()? {
+                Some(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+                _ => Vec::new(),
+            };
+            values.push(headers.iter().map(|h| CellValue::from(h.as_str())).collect());
+            start_row = header_row;
         }
-        let nested_values = values.get(0).unwrap();
-        if nested_values.is_empty() {
-            return Ok(String::new());
+        let headers: Vec<&str> = headers.iter().map(|h| h.as_str()).collect();
+        values.extend(ValueRange::serialize_rows(&headers, rows)?);
+
+        let start_column = range.start_column.unwrap_or_default();
+        let write_range = A1Range {
+            sheet: range.sheet,
+            start_row: Some(start_row),
+            start_column: Some(start_column),
+            end_row: Some(start_row + values.len() as u32),
+            end_column: Some(start_column + headers.len() as u32),
         }
-        let value = nested_values.get(0).unwrap();
+        .to_string();
+
+        let values = ValueRange {
+            range: Some(write_range.clone()),
+            values: Some(values),
+            major_dimension: None,
+        };
+        self.update_values_range(sheet_id, &write_range, values, ValueInputOption::Raw).await
+    }
+
+    /// Stream the rows of the sheet called `sheet`, reading `chunk_size` rows
+    /// at a time. Chunks are only read as the stream is consumed, and the
+    /// stream ends at the first empty row or the end of the data, so sheets
+    /// that keep growing never have to be read with one huge range.
+    pub fn stream_rows<'a>(&'a self, sheet_id: &'a str, sheet: &'a str, chunk_size: u32) -> impl Stream<Item = Result<Vec<CellValue>, SheetsError>> + 'a {
+        let chunk_size = chunk_size.max(1);
+
+        stream::unfold((VecDeque::<Vec<CellValue>>::new(), Some(0)), move |(mut rows, mut next)| async move {
+            loop {
+                if let Some(row) = rows.pop_front() {
+                    if row.iter().all(CellValue::is_empty) {
+                        return None;
+                    }
+                    return Some((Ok(row), (rows, next)));
+                }
 
-        Ok(value.to_string())
+                // Read the next chunk, if the last one was full.
+                let start = next?;
+                let range = A1Range::rows(sheet, start, start + chunk_size).to_string();
+                match self.get_values_with_options(sheet_id, &range, &GetValuesOptions::default()).await {
+                    Ok(value_range) => {
+                        let values = value_range.values.unwrap_or_default();
+                        next = if values.len() < chunk_size as usize { None } else { Some(start + chunk_size) };
+                        rows.extend(values);
+                    }
+                    Err(e) => return Some((Err(e), (rows, None))),
+                }
+            }
+        })
     }
 
-    /// Update values.
-    pub async fn update_values(&self, sheet_id: &str, range: &str, value: String) -> Result<UpdateValuesResponse, APIError> {
+    /// Get the formatted values in a range as CSV, so they can be used by
+    /// tools that know nothing about Google Sheets, see `ValueRange::to_csv`.
+    pub async fn export_csv(&self, sheet_id: &str, range: &str) -> Result<String, SheetsError> {
+        self.get_values(sheet_id, range.to_string()).await?.to_csv()
+    }
+
+    /// Get the values in several ranges with one request. The value ranges in
+    /// the response are in the same order as the `ranges`.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/batchGet
+    pub async fn batch_get_values(&self, sheet_id: &str, ranges: &[&str], options: &GetValuesOptions) -> Result<BatchGetValuesResponse, SheetsError> {
+        let mut query: Vec<(&str, String)> = ranges.iter().map(|r| ("ranges", r.to_string())).collect();
+        query.extend(options.query());
+
         // Build the request.
-        let request = self.request(
-            Method::PUT,
-            format!("spreadsheets/{}/values/{}", sheet_id.to_string(), range.to_string()),
+        let request = self.request(Method::GET, format!("spreadsheets/{}/values:batchGet", sheet_id), (), Some(query)).await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Update values.
+    pub async fn update_values(&self, sheet_id: &str, range: &str, value: String) -> Result<UpdateValuesResponse, SheetsError> {
+        self.update_values_range(
+            sheet_id,
+            range,
             ValueRange {
                 range: Some(range.to_string()),
-                values: Some(vec![vec![value]]),
+                values: Some(vec![vec![value.into()]]),
                 major_dimension: None,
             },
-            Some(vec![
-                ("valueInputOption", "USER_ENTERED".to_string()),
-                ("responseValueRenderOption", "FORMATTED_VALUE".to_string()),
-                ("responseDateTimeRenderOption", "FORMATTED_STRING".to_string()),
-            ]),
-        );
+            ValueInputOption::UserEntered,
+        )
+        .await
+    }
+
+    /// Update the values in a range with as many rows and columns as the
+    /// range holds. The range of `values` must be empty or the same as `range`.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/update
+    pub async fn update_values_range(&self, sheet_id: &str, range: &str, values: ValueRange, value_input_option: ValueInputOption) -> Result<UpdateValuesResponse, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::PUT,
+                format!("spreadsheets/{}/values/{}", sheet_id, range),
+                values,
+                Some(vec![
+                    ("valueInputOption", value_input_option.as_str().to_string()),
+                    ("responseValueRenderOption", "FORMATTED_VALUE".to_string()),
+                    ("responseDateTimeRenderOption", "FORMATTED_STRING".to_string()),
+                ]),
+            )
+            .await?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(sheet_id);
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
         };
 
         // Try to deserialize the response.
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Append rows after the table of data found in `range`, returning where
+    /// they were written. With `InsertDataOption::InsertRows` new rows are
+    /// inserted for the values, so writers appending at the same time do not
+    /// overwrite each other.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/append
+    pub async fn append_values(
+        &self,
+        sheet_id: &str,
+        range: &str,
+        values: ValueRange,
+        value_input_option: ValueInputOption,
+        insert_data_option: InsertDataOption,
+    ) -> Result<AppendValuesResponse, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::POST,
+                format!("spreadsheets/{}/values/{}:append", sheet_id, range),
+                values,
+                Some(vec![
+                    ("valueInputOption", value_input_option.as_str().to_string()),
+                    ("insertDataOption", insert_data_option.as_str().to_string()),
+                ]),
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(sheet_id);
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Clear the values in a range. Only the values are cleared, formatting and
+    /// data validation are kept.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/clear
+    pub async fn clear_values(&self, sheet_id: &str, range: &str) -> Result<ClearValuesResponse, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(Method::POST, format!("spreadsheets/{}/values/{}:clear", sheet_id, range), ClearValuesRequest {}, None)
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(sheet_id);
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Update the values in several ranges with one request.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.values/batchUpdate
+    pub async fn batch_update_values(&self, sheet_id: &str, data: Vec<ValueRange>, value_input_option: ValueInputOption) -> Result<BatchUpdateValuesResponse, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::POST,
+                format!("spreadsheets/{}/values:batchUpdate", sheet_id),
+                BatchUpdateValuesRequest {
+                    value_input_option,
+                    data,
+                    response_value_render_option: "FORMATTED_VALUE".to_string(),
+                    response_date_time_render_option: "FORMATTED_STRING".to_string(),
+                },
+                None,
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        self.invalidate_cache(sheet_id);
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Attach developer metadata to a spreadsheet, sheet, or rows or columns,
+    /// returning it with the ID picked by the API.
+    pub async fn create_developer_metadata(&self, spreadsheet_id: &str, metadata: DeveloperMetadata) -> Result<DeveloperMetadata, SheetsError> {
+        let resp = self.batch_update(spreadsheet_id, BatchUpdateRequest::new().create_developer_metadata(metadata)).await?;
+
+        Ok(resp
+            .replies
+            .into_iter()
+            .next()
+            .and_then(|r| r.create_developer_metadata)
+            .map(|r| r.developer_metadata)
+            .unwrap_or_default())
+    }
+
+    /// Find the developer metadata matching any of the `filters`.
+    /// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets.developerMetadata/search
+    pub async fn search_developer_metadata(&self, spreadsheet_id: &str, filters: Vec<DataFilter>) -> Result<Vec<DeveloperMetadata>, SheetsError> {
+        // Build the request.
+        let request = self
+            .request(
+                Method::POST,
+                format!("spreadsheets/{}/developerMetadata:search", spreadsheet_id),
+                SearchDeveloperMetadataRequest { data_filters: filters },
+                None,
+            )
+            .await?;
+
+        let resp = self.client.execute(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(SheetsError::Api { status: s, body: resp.text().await? }),
+        };
+
+        // Try to deserialize the response.
+        let resp: SearchDeveloperMetadataResponse = serde_json::from_str(&resp.text().await?)?;
+
+        Ok(resp.matched_developer_metadata.into_iter().map(|m| m.developer_metadata).collect())
     }
 }
 
-/// Error type returned by our library.
-pub struct APIError {
-    pub status_code: StatusCode,
-    pub body: String,
+/// The key of cached values: the spreadsheet, its revision, the range, and how
+/// the values were read.
+type CacheKey = (String, i64, String, GetValuesOptions);
+
+/// An in-memory cache of the values read from spreadsheets, see
+/// `Sheets::with_cache`.
+pub struct ValuesCache {
+    ttl: Duration,
+    entries: Mutex<CacheEntries>,
 }
 
-impl fmt::Display for APIError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "APIError: status code -> {}, body -> {}", self.status_code.to_string(), self.body)
+#[derive(Default)]
+struct CacheEntries {
+    values: HashMap<CacheKey, (Instant, ValueRange)>,
+    /// Bumped every time something is cleared from the cache, so a read that
+    /// started before can tell its values might be stale.
+    generation: u64,
+}
+
+impl ValuesCache {
+    /// Create a cache that keeps values for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        ValuesCache {
+            ttl,
+            entries: Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    /// Clear every range of a spreadsheet from the cache, for example after
+    /// changing it some other way.
+    pub fn invalidate(&self, spreadsheet_id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.values.retain(|(id, _, _, _), _| id != spreadsheet_id);
+        entries.generation += 1;
+    }
+
+    /// Clear everything from the cache.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.values.clear();
+        entries.generation += 1;
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<ValueRange> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.values.get(key) {
+            Some((read_at, values)) if read_at.elapsed() < self.ttl => Some(values.clone()),
+            Some(_) => {
+                entries.values.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Return the generation to pass to `insert` for a read that is about to start.
+    fn generation(&self) -> u64 {
+        self.entries.lock().unwrap().generation
+    }
+
+    /// Keep values read since `generation`. If anything was cleared from the
+    /// cache in the meantime, a write may have raced the read, so the values
+    /// are not kept.
+    fn insert(&self, key: CacheKey, values: ValueRange, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation {
+            return;
+        }
+
+        // Drop anything that expired, so ranges that are never read again do not pile up.
+        let ttl = self.ttl;
+        entries.values.retain(|_, (read_at, _)| read_at.elapsed() < ttl);
+        entries.values.insert(key, (Instant::now(), values));
     }
 }
 
-impl fmt::Debug for APIError {
+/// Error type returned by our library.
+#[derive(Debug)]
+pub enum SheetsError {
+    /// Sending the request or reading the response failed.
+    Http(reqwest::Error),
+    /// The API returned an unexpected status code.
+    Api { status: StatusCode, body: String },
+    /// The response body could not be deserialized.
+    Serde(serde_json::Error),
+    /// The endpoint or path for the request is not a valid URL.
+    InvalidUrl(url::ParseError),
+    /// The access token given to `Sheets::new` has expired, a new client needs
+    /// to be created with a fresh one.
+    TokenExpired,
+    /// The range is not valid A1 notation.
+    InvalidRange(String),
+    /// Writing values as CSV failed.
+    Csv(csv::Error),
+    /// Getting a token for the service account failed.
+    Auth(yup_oauth2::Error),
+    /// Reading the service account key or setting up the authenticator failed.
+    Io(io::Error),
+    /// The spreadsheet changed since it was read.
+    Conflict { expected: i64, actual: i64 },
+    /// The Drive API returned a version that is not a number.
+    InvalidVersion(String),
+}
+
+impl fmt::Display for SheetsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "APIError: status code -> {}, body -> {}", self.status_code.to_string(), self.body)
+        match self {
+            SheetsError::Http(e) => write!(f, "SheetsError: http -> {}", e),
+            SheetsError::Api { status, body } => write!(f, "SheetsError: status code -> {}, body -> {}", status, body),
+            SheetsError::Serde(e) => write!(f, "SheetsError: deserializing response -> {}", e),
+            SheetsError::InvalidUrl(e) => write!(f, "SheetsError: invalid url -> {}", e),
+            SheetsError::TokenExpired => write!(f, "SheetsError: token is expired"),
+            SheetsError::InvalidRange(r) => write!(f, "SheetsError: invalid range -> {}", r),
+            SheetsError::Csv(e) => write!(f, "SheetsError: writing csv -> {}", e),
+            SheetsError::Auth(e) => write!(f, "SheetsError: getting token -> {}", e),
+            SheetsError::Io(e) => write!(f, "SheetsError: io -> {}", e),
+            SheetsError::Conflict { expected, actual } => write!(f, "SheetsError: spreadsheet changed -> expected revision {}, got {}", expected, actual),
+            SheetsError::InvalidVersion(v) => write!(f, "SheetsError: invalid version -> {}", v),
+        }
     }
 }
 
 // This is important for other errors to wrap this one.
-impl error::Error for APIError {
+impl error::Error for SheetsError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        // Generic error, underlying cause isn't tracked.
+        match self {
+            SheetsError::Http(e) => Some(e),
+            SheetsError::Serde(e) => Some(e),
+            SheetsError::InvalidUrl(e) => Some(e),
+            SheetsError::Csv(e) => Some(e),
+            SheetsError::Auth(e) => Some(e),
+            SheetsError::Io(e) => Some(e),
+            SheetsError::Api { .. } | SheetsError::TokenExpired | SheetsError::InvalidRange(_) | SheetsError::Conflict { .. } | SheetsError::InvalidVersion(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SheetsError {
+    fn from(e: reqwest::Error) -> Self {
+        SheetsError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for SheetsError {
+    fn from(e: serde_json::Error) -> Self {
+        SheetsError::Serde(e)
+    }
+}
+
+impl From<csv::Error> for SheetsError {
+    fn from(e: csv::Error) -> Self {
+        SheetsError::Csv(e)
+    }
+}
+
+impl From<url::ParseError> for SheetsError {
+    fn from(e: url::ParseError) -> Self {
+        SheetsError::InvalidUrl(e)
+    }
+}
+
+/// A range in A1 notation, like `'Form Responses 1'!A1:S1000`.
+///
+/// Rows and columns are zero-based like in a `GridRange`, the start is
+/// inclusive and the end exclusive, so `A1:S1000` starts at row 0 and column 0
+/// and ends before row 1000 and column 19. Missing indexes mean the range is
+/// unbounded on that side, like in `A:C`.
+///
+/// ```
+/// use sheets::A1Range;
+///
+/// let range: A1Range = "'Form Responses 1'!A2:S1000".parse().unwrap();
+/// assert_eq!(range.start_row, Some(1));
+/// assert_eq!(range.end_column, Some(19));
+/// assert_eq!(range.offset(500, 0).to_string(), "'Form Responses 1'!A502:S1500");
+/// ```
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct A1Range {
+    /// The title of the sheet. If unset the range is on the first sheet.
+    pub sheet: Option<String>,
+    pub start_row: Option<u32>,
+    pub start_column: Option<u32>,
+    pub end_row: Option<u32>,
+    pub end_column: Option<u32>,
+}
+
+impl A1Range {
+    /// The cells on `sheet` from `start_row` and `start_column` up to but not
+    /// including `end_row` and `end_column`.
+    pub fn new(sheet: &str, start_row: u32, start_column: u32, end_row: u32, end_column: u32) -> Self {
+        A1Range {
+            sheet: Some(sheet.to_string()),
+            start_row: Some(start_row),
+            start_column: Some(start_column),
+            end_row: Some(end_row),
+            end_column: Some(end_column),
+        }
+    }
+
+    /// Every cell on `sheet`.
+    pub fn sheet(sheet: &str) -> Self {
+        A1Range {
+            sheet: Some(sheet.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// The whole rows on `sheet` from `start` up to but not including `end`.
+    pub fn rows(sheet: &str, start: u32, end: u32) -> Self {
+        A1Range {
+            sheet: Some(sheet.to_string()),
+            start_row: Some(start),
+            end_row: Some(end),
+            ..Default::default()
+        }
+    }
+
+    /// Move the range down by `rows` and right by `columns`, or up and left if
+    /// they are negative. The range stops at the first row and column, keeping
+    /// its size.
+    pub fn offset(&self, rows: i64, columns: i64) -> Self {
+        let clamp = |start: Option<u32>, by: i64| start.map_or(by, |s| by.max(-i64::from(s)));
+        let (rows, columns) = (clamp(self.start_row, rows), clamp(self.start_column, columns));
+        let shift = |index: Option<u32>, by: i64| index.map(|i| (i64::from(i) + by).max(0) as u32);
+
+        A1Range {
+            sheet: self.sheet.clone(),
+            start_row: shift(self.start_row, rows),
+            start_column: shift(self.start_column, columns),
+            end_row: shift(self.end_row, rows),
+            end_column: shift(self.end_column, columns),
+        }
+    }
+}
+
+impl fmt::Display for A1Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bounded = self.start_row.is_some() || self.start_column.is_some() || self.end_row.is_some() || self.end_column.is_some();
+
+        if let Some(sheet) = &self.sheet {
+            // Titles with anything but letters, digits, and underscores have to be quoted.
+            if sheet.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                write!(f, "{}", sheet)?;
+            } else {
+                write!(f, "'{}'", sheet.replace('\'', "''"))?;
+            }
+            if !bounded {
+                return Ok(());
+            }
+            write!(f, "!")?;
+        }
+
+        // The start is inclusive, so we show row indexes one-based.
+        if let Some(column) = self.start_column {
+            write!(f, "{}", column_letters(column))?;
+        }
+        if let Some(row) = self.start_row {
+            write!(f, "{}", row + 1)?;
+        }
+
+        // Show a single cell without an end.
+        let single_cell = match (self.start_row, self.start_column, self.end_row, self.end_column) {
+            (Some(sr), Some(sc), Some(er), Some(ec)) => er == sr + 1 && ec == sc + 1,
+            _ => false,
+        };
+        if single_cell {
+            return Ok(());
+        }
+
+        // The end is exclusive, so the zero-based end row is the one-based last row.
+        write!(f, ":")?;
+        if let Some(column) = self.end_column {
+            write!(f, "{}", column_letters(column.saturating_sub(1)))?;
+        }
+        if let Some(row) = self.end_row {
+            write!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for A1Range {
+    type Err = SheetsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SheetsError::InvalidRange(s.to_string());
+
+        let (sheet, cells) = match s.rfind('!') {
+            Some(i) => {
+                let sheet = &s[..i];
+                let sheet = if sheet.len() >= 2 && sheet.starts_with('\'') && sheet.ends_with('\'') {
+                    sheet[1..sheet.len() - 1].replace("''", "'")
+                } else {
+                    sheet.to_string()
+                };
+                (Some(sheet), &s[i + 1..])
+            }
+            None => (None, s),
+        };
+
+        let (start, end) = match cells.find(':') {
+            Some(i) => (&cells[..i], Some(&cells[i + 1..])),
+            None => (cells, None),
+        };
+        let (start_column, start_row) = match (parse_cell(start), end, &sheet) {
+            // A single cell needs both a column and a row, anything else on
+            // its own, like "Costs", is the title of a sheet.
+            (Some((Some(c), Some(r))), _, _) => (Some(c), Some(r)),
+            (Some(cell), Some(_), _) => cell,
+            (_, None, None) => return Ok(A1Range::sheet(s)),
+            _ => return Err(invalid()),
+        };
+        let (end_column, end_row) = match end {
+            // A single cell ends where it starts.
+            None => (start_column, start_row),
+            Some(end) => parse_cell(end).ok_or_else(invalid)?,
+        };
+
+        Ok(A1Range {
+            sheet,
+            start_row,
+            start_column,
+            end_row: end_row.map(|r| r + 1),
+            end_column: end_column.map(|c| c + 1),
+        })
+    }
+}
+
+/// Parse a cell like `B12`, `B`, or `12` into its zero-based column and row.
+fn parse_cell(cell: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let split = cell.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(cell.len());
+    let (letters, digits) = cell.split_at(split);
+
+    let column = if letters.is_empty() { None } else { Some(column_index(letters)?) };
+    let row = if digits.is_empty() {
         None
+    } else {
+        // Rows are one-based in A1 notation.
+        Some(digits.parse::<u32>().ok()?.checked_sub(1)?)
+    };
+
+    if column.is_none() && row.is_none() {
+        return None;
     }
+    Some((column, row))
+}
+
+/// Return the letters of the zero-based column `index`, so 0 is "A", 25 is
+/// "Z", and 26 is "AA".
+pub fn column_letters(index: u32) -> String {
+    let mut letters = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push((b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+
+    letters.iter().rev().collect()
+}
+
+/// Return the zero-based index of the column with the `letters`, ignoring
+/// case, or `None` if they are not all letters.
+pub fn column_index(letters: &str) -> Option<u32> {
+    if letters.is_empty() {
+        return None;
+    }
+
+    let mut n: u32 = 0;
+    for c in letters.chars() {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+        let digit = u32::from(c.to_ascii_uppercase() as u8 - b'A') + 1;
+        n = n.checked_mul(26)?.checked_add(digit)?;
+    }
+
+    Some(n - 1)
 }
 
 /// A range of values.
@@ -244,9 +1269,11 @@ pub struct ValueRange {
     /// For output, empty trailing rows and columns will not be included.
     ///
     /// For input, supported value types are: bool, string, and double.
-    /// Null values will be skipped.
-    /// To set a cell to an empty value, set the string value to an empty string.
-    pub values: Option<Vec<Vec<String>>>,
+    /// Empty values clear the cell.
+    ///
+    /// With the default `FORMATTED_VALUE` render option every value is read as
+    /// a string, with `UNFORMATTED_VALUE` numbers and booleans keep their type.
+    pub values: Option<Vec<Vec<CellValue>>>,
     /// The major dimension of the values.
     ///
     /// For output, if the spreadsheet data is: `A1=1,B1=2,A2=3,B2=4`,
@@ -264,6 +1291,224 @@ pub struct ValueRange {
     pub major_dimension: Option<String>,
 }
 
+impl ValueRange {
+    /// Deserialize each row into a `T`, using the first row as the names of
+    /// the fields. Empty cells are left out, so they can be `Option`s or have a
+    /// `#[serde(default)]`, and empty rows are skipped.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Response {
+    ///     #[serde(rename = "Email Address")]
+    ///     email: String,
+    ///     #[serde(rename = "Hoodie size")]
+    ///     hoodie_size: Option<String>,
+    /// }
+    /// ```
+    pub fn deserialize_rows<T>(&self) -> Result<Vec<T>, SheetsError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut rows = self.values.iter().flatten();
+        let headers: Vec<String> = match rows.next() {
+            Some(h) => h.iter().map(|c| c.to_string().trim().to_string()).collect(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut result = Vec::new();
+        for row in rows {
+            let fields: serde_json::Map<String, serde_json::Value> = headers
+                .iter()
+                .zip(row)
+                .filter(|(header, cell)| !header.is_empty() && !cell.is_empty())
+                .map(|(header, cell)| Ok((header.to_string(), serde_json::to_value(cell)?)))
+                .collect::<Result<_, serde_json::Error>>()?;
+            if fields.is_empty() {
+                continue;
+            }
+
+            result.push(serde_json::from_value(serde_json::Value::Object(fields))?);
+        }
+
+        Ok(result)
+    }
+
+    /// Serialize each of `rows` into a row of cells, one per header, the
+    /// reverse of `deserialize_rows`. Fields without a header are left out and
+    /// headers without a field get an empty cell. Nested values, like lists,
+    /// are written as JSON.
+    pub fn serialize_rows<T>(headers: &[&str], rows: &[T]) -> Result<Vec<Vec<CellValue>>, SheetsError>
+    where
+        T: Serialize,
+    {
+        rows.iter()
+            .map(|row| {
+                let fields = match serde_json::to_value(row)? {
+                    serde_json::Value::Object(fields) => fields,
+                    v => return Err(SheetsError::Serde(serde::ser::Error::custom(format!("expected a struct or map, got {}", v)))),
+                };
+
+                Ok(headers
+                    .iter()
+                    .map(|header| match fields.get(header.trim()) {
+                        None | Some(serde_json::Value::Null) => CellValue::Empty,
+                        Some(serde_json::Value::Bool(b)) => CellValue::Bool(*b),
+                        Some(serde_json::Value::Number(n)) => n.as_f64().map(CellValue::Number).unwrap_or_else(|| CellValue::String(n.to_string())),
+                        Some(serde_json::Value::String(s)) => CellValue::from(s.as_str()),
+                        Some(v) => CellValue::String(v.to_string()),
+                    })
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Return the values as the text the spreadsheet shows for them, with
+    /// empty cells as empty strings. This is how values were read before
+    /// `CellValue`.
+    pub fn string_rows(&self) -> Vec<Vec<String>> {
+        self.values.as_deref().unwrap_or_default().iter().map(|row| row.iter().map(|c| c.to_string()).collect()).collect()
+    }
+
+    /// Write the values as CSV, one line per row. Rows are padded with empty
+    /// cells to the length of the longest row, since the API leaves out
+    /// trailing empty cells.
+    pub fn to_csv(&self) -> Result<String, SheetsError> {
+        let rows = self.values.as_deref().unwrap_or_default();
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for row in rows {
+            let mut record: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            record.resize(width, String::new());
+            writer.write_record(&record)?;
+        }
+
+        let bytes = writer.into_inner().map_err(|e| SheetsError::Csv(e.into_error().into()))?;
+        // Every field came from a string, so this is always valid UTF-8.
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// The value of a single cell.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum CellValue {
+    /// The cell is empty.
+    #[default]
+    Empty,
+    Bool(bool),
+    /// A number. Dates and times are numbers too, when they are read with the
+    /// `SERIAL_NUMBER` date time render option.
+    Number(f64),
+    String(String),
+}
+
+impl CellValue {
+    /// Return if the cell is empty.
+    pub fn is_empty(&self) -> bool {
+        *self == CellValue::Empty
+    }
+
+    /// Return the value if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            CellValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Return the value if it is a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            CellValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Return the value if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            CellValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CellValue {
+    /// Format the value the way the spreadsheet shows it without formatting,
+    /// empty cells are an empty string.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellValue::Empty => Ok(()),
+            CellValue::Bool(true) => write!(f, "TRUE"),
+            CellValue::Bool(false) => write!(f, "FALSE"),
+            CellValue::Number(n) => write!(f, "{}", n),
+            CellValue::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<String> for CellValue {
+    fn from(s: String) -> Self {
+        if s.is_empty() {
+            CellValue::Empty
+        } else {
+            CellValue::String(s)
+        }
+    }
+}
+
+impl From<&str> for CellValue {
+    fn from(s: &str) -> Self {
+        CellValue::from(s.to_string())
+    }
+}
+
+impl From<f64> for CellValue {
+    fn from(n: f64) -> Self {
+        CellValue::Number(n)
+    }
+}
+
+impl From<bool> for CellValue {
+    fn from(b: bool) -> Self {
+        CellValue::Bool(b)
+    }
+}
+
+impl Serialize for CellValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            // The API clears cells that are set to an empty string.
+            CellValue::Empty => serializer.serialize_str(""),
+            CellValue::Bool(b) => serializer.serialize_bool(*b),
+            CellValue::Number(n) => serializer.serialize_f64(*n),
+            CellValue::String(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CellValue {
+    /// Cells are read as strings, numbers, or booleans depending on the value
+    /// render option. Empty strings and nulls are both empty cells.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Null => Ok(CellValue::Empty),
+            serde_json::Value::Bool(b) => Ok(CellValue::Bool(b)),
+            serde_json::Value::Number(n) => n.as_f64().map(CellValue::Number).ok_or_else(|| serde::de::Error::custom(format!("number {} is out of range", n))),
+            serde_json::Value::String(s) => Ok(CellValue::from(s)),
+            v => Err(serde::de::Error::custom(format!("expected a cell value, got {}", v))),
+        }
+    }
+}
+
 /// The response returned from updating values.
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct UpdateValuesResponse {
@@ -288,3 +1533,2218 @@ pub struct UpdateValuesResponse {
     #[serde(rename = "updatedCells")]
     pub updated_cells: Option<i32>,
 }
+
+/// The response returned from getting the values in several ranges.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct BatchGetValuesResponse {
+    /// The ID of the spreadsheet the data was retrieved from.
+    #[serde(rename = "spreadsheetId")]
+    pub spreadsheet_id: Option<String>,
+    /// The requested values. The order of the value ranges is the same as the
+    /// order of the requested ranges.
+    #[serde(rename = "valueRanges", default)]
+    pub value_ranges: Vec<ValueRange>,
+}
+
+/// How values should be rendered in the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ValueRenderOption {
+    /// Values are calculated and formatted as displayed in the UI, so a
+    /// currency cell reads "$1.23".
+    FormattedValue,
+    /// Values are calculated but not formatted, so a currency cell reads 1.23.
+    UnformattedValue,
+    /// Values are not calculated, so a formula cell reads "=A1".
+    Formula,
+}
+
+impl ValueRenderOption {
+    fn as_str(self) -> &'static str {
+        match self {
+            ValueRenderOption::FormattedValue => "FORMATTED_VALUE",
+            ValueRenderOption::UnformattedValue => "UNFORMATTED_VALUE",
+            ValueRenderOption::Formula => "FORMULA",
+        }
+    }
+}
+
+/// How dates, times, and durations should be rendered in the output. This is
+/// ignored when the value render option is `FormattedValue`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DateTimeRenderOption {
+    /// Dates are returned as days since December 30th 1899, with the time as
+    /// the fractional part.
+    SerialNumber,
+    /// Dates are returned as strings in the cell's number format.
+    FormattedString,
+}
+
+impl DateTimeRenderOption {
+    fn as_str(self) -> &'static str {
+        match self {
+            DateTimeRenderOption::SerialNumber => "SERIAL_NUMBER",
+            DateTimeRenderOption::FormattedString => "FORMATTED_STRING",
+        }
+    }
+}
+
+/// The options for reading values. The default returns formatted values
+/// grouped by rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GetValuesOptions {
+    pub value_render_option: ValueRenderOption,
+    pub date_time_render_option: DateTimeRenderOption,
+    pub major_dimension: Dimension,
+}
+
+impl Default for GetValuesOptions {
+    fn default() -> Self {
+        GetValuesOptions {
+            value_render_option: ValueRenderOption::FormattedValue,
+            date_time_render_option: DateTimeRenderOption::FormattedString,
+            major_dimension: Dimension::Rows,
+        }
+    }
+}
+
+impl GetValuesOptions {
+    /// Set how values should be rendered.
+    pub fn value_render_option(mut self, option: ValueRenderOption) -> Self {
+        self.value_render_option = option;
+        self
+    }
+
+    /// Set how dates, times, and durations should be rendered.
+    pub fn date_time_render_option(mut self, option: DateTimeRenderOption) -> Self {
+        self.date_time_render_option = option;
+        self
+    }
+
+    /// Set whether the values are grouped by rows or columns.
+    pub fn major_dimension(mut self, dimension: Dimension) -> Self {
+        self.major_dimension = dimension;
+        self
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("valueRenderOption", self.value_render_option.as_str().to_string()),
+            ("dateTimeRenderOption", self.date_time_render_option.as_str().to_string()),
+            ("majorDimension", self.major_dimension.as_str().to_string()),
+        ]
+    }
+}
+
+/// How input data should be interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ValueInputOption {
+    /// The values will be stored as is.
+    Raw,
+    /// The values will be parsed as if the user typed them into the UI, so
+    /// "TRUE" becomes a boolean and "=A1" a formula.
+    UserEntered,
+}
+
+impl ValueInputOption {
+    fn as_str(self) -> &'static str {
+        match self {
+            ValueInputOption::Raw => "RAW",
+            ValueInputOption::UserEntered => "USER_ENTERED",
+        }
+    }
+}
+
+/// How appended values are written.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InsertDataOption {
+    /// The values overwrite whatever is in the rows after the table.
+    Overwrite,
+    /// Rows are inserted for the values.
+    InsertRows,
+}
+
+impl InsertDataOption {
+    fn as_str(self) -> &'static str {
+        match self {
+            InsertDataOption::Overwrite => "OVERWRITE",
+            InsertDataOption::InsertRows => "INSERT_ROWS",
+        }
+    }
+}
+
+/// The response returned from appending values.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct AppendValuesResponse {
+    /// The spreadsheet the values were appended to.
+    #[serde(rename = "spreadsheetId")]
+    pub spreadsheet_id: Option<String>,
+    /// The range (in A1 notation) of the table the values were appended
+    /// after, if a table was found.
+    #[serde(rename = "tableRange")]
+    pub table_range: Option<String>,
+    /// Where the values were written.
+    #[serde(default)]
+    pub updates: UpdateValuesResponse,
+}
+
+/// The request for updating the values in several ranges.
+#[derive(Clone, Debug, Serialize)]
+struct BatchUpdateValuesRequest {
+    #[serde(rename = "valueInputOption")]
+    value_input_option: ValueInputOption,
+    data: Vec<ValueRange>,
+    #[serde(rename = "responseValueRenderOption")]
+    response_value_render_option: String,
+    #[serde(rename = "responseDateTimeRenderOption")]
+    response_date_time_render_option: String,
+}
+
+/// The response returned from updating the values in several ranges.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct BatchUpdateValuesResponse {
+    /// The spreadsheet the updates were applied to.
+    #[serde(rename = "spreadsheetId")]
+    pub spreadsheet_id: Option<String>,
+    /// The total number of rows where at least one cell in the row was updated.
+    #[serde(rename = "totalUpdatedRows")]
+    pub total_updated_rows: Option<i32>,
+    /// The total number of columns where at least one cell in the column was
+    /// updated.
+    #[serde(rename = "totalUpdatedColumns")]
+    pub total_updated_columns: Option<i32>,
+    /// The total number of cells updated.
+    #[serde(rename = "totalUpdatedCells")]
+    pub total_updated_cells: Option<i32>,
+    /// The total number of sheets where at least one cell in the sheet was
+    /// updated.
+    #[serde(rename = "totalUpdatedSheets")]
+    pub total_updated_sheets: Option<i32>,
+    /// One response per range that was updated, in the order of the request.
+    #[serde(default)]
+    pub responses: Vec<UpdateValuesResponse>,
+}
+
+/// The fields we ask for of a file in Google Drive.
+#[derive(Clone, Debug, Deserialize)]
+struct DriveFile {
+    #[serde(default)]
+    version: String,
+}
+
+/// The request for copying a sheet to another spreadsheet.
+#[derive(Clone, Debug, Serialize)]
+struct CopySheetToRequest {
+    #[serde(rename = "destinationSpreadsheetId")]
+    destination_spreadsheet_id: String,
+}
+
+/// The request for clearing values, it has no fields.
+#[derive(Clone, Debug, Serialize)]
+struct ClearValuesRequest {}
+
+/// The response returned from clearing values.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ClearValuesResponse {
+    /// The spreadsheet the updates were applied to.
+    #[serde(rename = "spreadsheetId")]
+    pub spreadsheet_id: Option<String>,
+    /// The range (in A1 notation) that was cleared. If the requested range was
+    /// unbounded, this is the actual range that was cleared, bounded by the
+    /// sheet's limits.
+    #[serde(rename = "clearedRange")]
+    pub cleared_range: Option<String>,
+}
+
+/// A spreadsheet, made up of one or more sheets.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Spreadsheet {
+    /// The ID of the spreadsheet.
+    #[serde(rename = "spreadsheetId", skip_serializing_if = "Option::is_none")]
+    pub spreadsheet_id: Option<String>,
+    /// Overall properties of the spreadsheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<SpreadsheetProperties>,
+    /// The sheets that are part of the spreadsheet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sheets: Vec<Sheet>,
+    /// The url of the spreadsheet.
+    #[serde(rename = "spreadsheetUrl", skip_serializing_if = "Option::is_none")]
+    pub spreadsheet_url: Option<String>,
+    /// The named ranges defined in the spreadsheet.
+    #[serde(rename = "namedRanges", default, skip_serializing_if = "Vec::is_empty")]
+    pub named_ranges: Vec<NamedRange>,
+}
+
+impl Spreadsheet {
+    /// Return the titles of the sheets, in the order they are in the spreadsheet.
+    pub fn sheet_titles(&self) -> Vec<&str> {
+        self.sheets.iter().filter_map(|s| s.properties.title.as_deref()).collect()
+    }
+
+    /// Return the properties of the sheet called `title`, if there is one.
+    pub fn sheet_by_title(&self, title: &str) -> Option<&SheetProperties> {
+        self.sheets.iter().map(|s| &s.properties).find(|p| p.title.as_deref() == Some(title))
+    }
+}
+
+/// The properties of a spreadsheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct SpreadsheetProperties {
+    /// The title of the spreadsheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A sheet in a spreadsheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Sheet {
+    /// The properties of the sheet.
+    #[serde(default)]
+    pub properties: SheetProperties,
+    /// The protected ranges in the sheet.
+    #[serde(rename = "protectedRanges", default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_ranges: Vec<ProtectedRange>,
+    /// The cells in the sheet, only set when asking for grid data.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<GridData>,
+}
+
+/// The cells in a range of a sheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct GridData {
+    /// The first row of the range, zero-based.
+    #[serde(rename = "startRow", skip_serializing_if = "Option::is_none")]
+    pub start_row: Option<i32>,
+    /// The first column of the range, zero-based.
+    #[serde(rename = "startColumn", skip_serializing_if = "Option::is_none")]
+    pub start_column: Option<i32>,
+    /// The rows of the range, starting at `start_row`.
+    #[serde(rename = "rowData", default)]
+    pub row_data: Vec<RowData>,
+}
+
+/// The properties of a sheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct SheetProperties {
+    /// The ID of the sheet, unique within the spreadsheet. This is the `gid` in
+    /// the url of the sheet.
+    #[serde(rename = "sheetId", skip_serializing_if = "Option::is_none")]
+    pub sheet_id: Option<i64>,
+    /// The name of the sheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The index of the sheet within the spreadsheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<i32>,
+    /// True if the sheet is hidden in the UI, false if it's visible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    /// The size of the sheet and its frozen rows and columns.
+    #[serde(rename = "gridProperties", skip_serializing_if = "Option::is_none")]
+    pub grid_properties: Option<GridProperties>,
+}
+
+/// The size of a sheet and its frozen rows and columns.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct GridProperties {
+    /// The number of rows in the sheet.
+    #[serde(rename = "rowCount", skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<i32>,
+    /// The number of columns in the sheet.
+    #[serde(rename = "columnCount", skip_serializing_if = "Option::is_none")]
+    pub column_count: Option<i32>,
+    /// The number of rows that are frozen in the sheet.
+    #[serde(rename = "frozenRowCount", skip_serializing_if = "Option::is_none")]
+    pub frozen_row_count: Option<i32>,
+    /// The number of columns that are frozen in the sheet.
+    #[serde(rename = "frozenColumnCount", skip_serializing_if = "Option::is_none")]
+    pub frozen_column_count: Option<i32>,
+}
+
+/// A named range.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct NamedRange {
+    /// The ID of the named range.
+    #[serde(rename = "namedRangeId", skip_serializing_if = "Option::is_none")]
+    pub named_range_id: Option<String>,
+    /// The name of the named range.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The range this represents.
+    #[serde(default)]
+    pub range: GridRange,
+}
+
+/// A range that only some users may edit.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ProtectedRange {
+    /// The ID of the protected range.
+    #[serde(rename = "protectedRangeId", skip_serializing_if = "Option::is_none")]
+    pub protected_range_id: Option<i64>,
+    /// The range being protected. Only one of `range` and `named_range_id` may
+    /// be set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<GridRange>,
+    /// The ID of the named range being protected.
+    #[serde(rename = "namedRangeId", skip_serializing_if = "Option::is_none")]
+    pub named_range_id: Option<String>,
+    /// Why the range is protected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// If true, anyone may edit the range after confirming a warning, and
+    /// `editors` is ignored.
+    #[serde(rename = "warningOnly", skip_serializing_if = "Option::is_none")]
+    pub warning_only: Option<bool>,
+    /// Whether the user reading the protected range may edit it. This is read
+    /// only.
+    #[serde(rename = "requestingUserCanEdit", skip_serializing_if = "Option::is_none")]
+    pub requesting_user_can_edit: Option<bool>,
+    /// Who may edit the range. The owner of the spreadsheet and the user adding
+    /// the protection may always edit it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editors: Option<Editors>,
+}
+
+/// The users and groups allowed to edit a protected range.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Editors {
+    /// The email addresses of the users.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// The email addresses of the groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Whether anyone in the domain of the spreadsheet may edit.
+    #[serde(rename = "domainUsersCanEdit", skip_serializing_if = "Option::is_none")]
+    pub domain_users_can_edit: Option<bool>,
+}
+
+/// A range on a sheet. All indexes are zero-based, start indexes are inclusive
+/// and end indexes are exclusive. Missing indexes mean the range is unbounded
+/// on that side.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridRange {
+    /// The sheet this range is on.
+    #[serde(rename = "sheetId", skip_serializing_if = "Option::is_none")]
+    pub sheet_id: Option<i64>,
+    /// The start row of the range.
+    #[serde(rename = "startRowIndex", skip_serializing_if = "Option::is_none")]
+    pub start_row_index: Option<i32>,
+    /// The end row of the range.
+    #[serde(rename = "endRowIndex", skip_serializing_if = "Option::is_none")]
+    pub end_row_index: Option<i32>,
+    /// The start column of the range.
+    #[serde(rename = "startColumnIndex", skip_serializing_if = "Option::is_none")]
+    pub start_column_index: Option<i32>,
+    /// The end column of the range.
+    #[serde(rename = "endColumnIndex", skip_serializing_if = "Option::is_none")]
+    pub end_column_index: Option<i32>,
+}
+
+/// A single change to apply to a spreadsheet, see `BatchUpdateRequest`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpreadsheetRequest {
+    /// Add a sheet. The sheet ID and index are picked by the API if not set.
+    AddSheet { properties: SheetProperties },
+    /// Delete a sheet.
+    DeleteSheet {
+        #[serde(rename = "sheetId")]
+        sheet_id: i64,
+    },
+    /// Copy a sheet to the end of the spreadsheet.
+    DuplicateSheet {
+        #[serde(rename = "sourceSheetId")]
+        source_sheet_id: i64,
+        #[serde(rename = "newSheetName")]
+        new_sheet_name: String,
+    },
+    /// Set the properties of a sheet, like its title or frozen rows.
+    UpdateSheetProperties { properties: SheetProperties, fields: String },
+    /// Set the values or formatting of the cells in a range, row by row.
+    UpdateCells { range: GridRange, rows: Vec<RowData>, fields: String },
+    /// Set every cell in a range to the same value or formatting.
+    RepeatCell { range: GridRange, cell: Box<CellData>, fields: String },
+    /// Resize rows or columns to fit their contents.
+    AutoResizeDimensions { dimensions: DimensionRange },
+    /// Add a conditional formatting rule at `index`, where rules with lower
+    /// indexes are applied first.
+    AddConditionalFormatRule { rule: ConditionalFormatRule, index: i32 },
+    /// Add a named range. The ID is picked by the API if not set.
+    AddNamedRange {
+        #[serde(rename = "namedRange")]
+        named_range: NamedRange,
+    },
+    /// Delete a named range.
+    DeleteNamedRange {
+        #[serde(rename = "namedRangeId")]
+        named_range_id: String,
+    },
+    /// Protect a range. The ID is picked by the API if not set.
+    AddProtectedRange {
+        #[serde(rename = "protectedRange")]
+        protected_range: ProtectedRange,
+    },
+    /// Remove the protection of a range.
+    DeleteProtectedRange {
+        #[serde(rename = "protectedRangeId")]
+        protected_range_id: i64,
+    },
+    /// Attach developer metadata. The ID is picked by the API if not set.
+    CreateDeveloperMetadata {
+        #[serde(rename = "developerMetadata")]
+        developer_metadata: DeveloperMetadata,
+    },
+    /// Set the filter of a sheet, replacing any filter it already has.
+    SetBasicFilter { filter: BasicFilter },
+    /// Remove the filter of a sheet.
+    ClearBasicFilter {
+        #[serde(rename = "sheetId")]
+        sheet_id: i64,
+    },
+    /// Add a filter view. The ID is picked by the API if not set.
+    AddFilterView { filter: FilterView },
+    /// Set the data validation rule of every cell in a range, or clear it if
+    /// the rule is not set.
+    SetDataValidation {
+        range: GridRange,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rule: Option<DataValidationRule>,
+    },
+    /// Find and replace text.
+    FindReplace(FindReplace),
+}
+
+/// Builder for the changes to apply to a spreadsheet with `Sheets::batch_update`.
+///
+/// The `fields` of requests that update something are a field mask of what to
+/// set, for example "userEnteredFormat.textFormat.bold". Fields not in the mask
+/// are left alone, "*" sets every field.
+///
+/// ```
+/// use sheets::{BatchUpdateRequest, CellData, CellFormat, GridRange, TextFormat};
+///
+/// let header = GridRange {
+///     sheet_id: Some(0),
+///     start_row_index: Some(0),
+///     end_row_index: Some(1),
+///     ..Default::default()
+/// };
+/// let bold = CellData {
+///     user_entered_format: Some(CellFormat {
+///         text_format: Some(TextFormat { bold: Some(true), ..Default::default() }),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+///
+/// let requests = BatchUpdateRequest::new()
+///     .add_sheet("Shipping costs")
+///     .repeat_cell(header, bold, "userEnteredFormat.textFormat.bold")
+///     .freeze_rows(0, 1)
+///     .auto_resize_columns(0, 0, 10);
+/// ```
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BatchUpdateRequest {
+    requests: Vec<SpreadsheetRequest>,
+}
+
+impl BatchUpdateRequest {
+    /// Start building a set of changes.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a change.
+    pub fn request(mut self, request: SpreadsheetRequest) -> Self {
+        self.requests.push(request);
+        self
+    }
+
+    /// Add an empty sheet called `title` to the end of the spreadsheet.
+    pub fn add_sheet(self, title: &str) -> Self {
+        self.request(SpreadsheetRequest::AddSheet {
+            properties: SheetProperties {
+                title: Some(title.to_string()),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Delete the sheet with the ID `sheet_id`.
+    pub fn delete_sheet(self, sheet_id: i64) -> Self {
+        self.request(SpreadsheetRequest::DeleteSheet { sheet_id })
+    }
+
+    /// Copy the sheet with the ID `sheet_id` to a new sheet called `title`.
+    pub fn duplicate_sheet(self, sheet_id: i64, title: &str) -> Self {
+        self.request(SpreadsheetRequest::DuplicateSheet {
+            source_sheet_id: sheet_id,
+            new_sheet_name: title.to_string(),
+        })
+    }
+
+    /// Set the `fields` of the properties of a sheet. The sheet is picked by
+    /// the `sheet_id` of the properties.
+    pub fn update_sheet_properties(self, properties: SheetProperties, fields: &str) -> Self {
+        self.request(SpreadsheetRequest::UpdateSheetProperties {
+            properties,
+            fields: fields.to_string(),
+        })
+    }
+
+    /// Freeze the first `rows` rows of a sheet, so they stay in view when
+    /// scrolling, like a header row.
+    pub fn freeze_rows(self, sheet_id: i64, rows: i32) -> Self {
+        let properties = SheetProperties {
+            sheet_id: Some(sheet_id),
+            grid_properties: Some(GridProperties {
+                frozen_row_count: Some(rows),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        self.update_sheet_properties(properties, "gridProperties.frozenRowCount")
+    }
+
+    /// Set the `fields` of the cells in `range`, starting at its top left
+    /// corner, from `rows`.
+    pub fn update_cells(self, range: GridRange, rows: Vec<RowData>, fields: &str) -> Self {
+        self.request(SpreadsheetRequest::UpdateCells {
+            range,
+            rows,
+            fields: fields.to_string(),
+        })
+    }
+
+    /// Set the `fields` of every cell in `range` from `cell`.
+    pub fn repeat_cell(self, range: GridRange, cell: CellData, fields: &str) -> Self {
+        self.request(SpreadsheetRequest::RepeatCell {
+            range,
+            cell: Box::new(cell),
+            fields: fields.to_string(),
+        })
+    }
+
+    /// Resize the columns of a sheet, from `start` up to but not including
+    /// `end`, to fit their contents.
+    pub fn auto_resize_columns(self, sheet_id: i64, start: i32, end: i32) -> Self {
+        self.request(SpreadsheetRequest::AutoResizeDimensions {
+            dimensions: DimensionRange {
+                sheet_id,
+                dimension: Dimension::Columns,
+                start_index: Some(start),
+                end_index: Some(end),
+            },
+        })
+    }
+
+    /// Add a conditional formatting rule before the existing ones.
+    pub fn add_conditional_format_rule(self, rule: ConditionalFormatRule) -> Self {
+        self.request(SpreadsheetRequest::AddConditionalFormatRule { rule, index: 0 })
+    }
+
+    /// Add a named range called `name` for `range`.
+    pub fn add_named_range(self, name: &str, range: GridRange) -> Self {
+        self.request(SpreadsheetRequest::AddNamedRange {
+            named_range: NamedRange {
+                named_range_id: None,
+                name: Some(name.to_string()),
+                range,
+            },
+        })
+    }
+
+    /// Delete the named range with the ID `named_range_id`.
+    pub fn delete_named_range(self, named_range_id: &str) -> Self {
+        self.request(SpreadsheetRequest::DeleteNamedRange {
+            named_range_id: named_range_id.to_string(),
+        })
+    }
+
+    /// Protect a range.
+    pub fn add_protected_range(self, protected_range: ProtectedRange) -> Self {
+        self.request(SpreadsheetRequest::AddProtectedRange { protected_range })
+    }
+
+    /// Protect a whole sheet so only `editors` may change it, like a sheet
+    /// that is overwritten by a sync and should not be edited by hand.
+    pub fn protect_sheet(self, sheet_id: i64, description: &str, editors: Editors) -> Self {
+        self.add_protected_range(ProtectedRange {
+            range: Some(GridRange {
+                sheet_id: Some(sheet_id),
+                ..Default::default()
+            }),
+            description: Some(description.to_string()),
+            editors: Some(editors),
+            ..Default::default()
+        })
+    }
+
+    /// Remove the protection with the ID `protected_range_id`.
+    pub fn delete_protected_range(self, protected_range_id: i64) -> Self {
+        self.request(SpreadsheetRequest::DeleteProtectedRange { protected_range_id })
+    }
+
+    /// Attach developer metadata.
+    pub fn create_developer_metadata(self, developer_metadata: DeveloperMetadata) -> Self {
+        self.request(SpreadsheetRequest::CreateDeveloperMetadata { developer_metadata })
+    }
+
+    /// Set the filter of a sheet. The sheet is picked by the `sheet_id` of the
+    /// range of the filter.
+    pub fn set_basic_filter(self, filter: BasicFilter) -> Self {
+        self.request(SpreadsheetRequest::SetBasicFilter { filter })
+    }
+
+    /// Remove the filter of the sheet with the ID `sheet_id`.
+    pub fn clear_basic_filter(self, sheet_id: i64) -> Self {
+        self.request(SpreadsheetRequest::ClearBasicFilter { sheet_id })
+    }
+
+    /// Add a filter view, a named filter that people can switch to without
+    /// changing what everyone else sees.
+    pub fn add_filter_view(self, filter: FilterView) -> Self {
+        self.request(SpreadsheetRequest::AddFilterView { filter })
+    }
+
+    /// Only allow values that meet `rule` in the cells in `range`.
+    pub fn set_data_validation(self, range: GridRange, rule: DataValidationRule) -> Self {
+        self.request(SpreadsheetRequest::SetDataValidation { range, rule: Some(rule) })
+    }
+
+    /// Remove the data validation of the cells in `range`.
+    pub fn clear_data_validation(self, range: GridRange) -> Self {
+        self.request(SpreadsheetRequest::SetDataValidation { range, rule: None })
+    }
+
+    /// Add a pivot table with its top left corner at `row` and `column` of the
+    /// sheet with the ID `sheet_id`, replacing any pivot table already there.
+    pub fn add_pivot_table(self, sheet_id: i64, row: i32, column: i32, pivot_table: PivotTable) -> Self {
+        let anchor = GridRange {
+            sheet_id: Some(sheet_id),
+            start_row_index: Some(row),
+            end_row_index: Some(row + 1),
+            start_column_index: Some(column),
+            end_column_index: Some(column + 1),
+        };
+        let cell = CellData {
+            pivot_table: Some(pivot_table),
+            ..Default::default()
+        };
+        self.update_cells(anchor, vec![RowData { values: vec![cell] }], "pivotTable")
+    }
+
+    /// Find and replace text.
+    pub fn find_replace(self, find_replace: FindReplace) -> Self {
+        self.request(SpreadsheetRequest::FindReplace(find_replace))
+    }
+}
+
+/// What to find and what to replace it with. Only one of `range`, `sheet_id`,
+/// and `all_sheets` may be set.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FindReplace {
+    /// The text to find.
+    pub find: String,
+    /// The text to replace it with.
+    pub replacement: String,
+    /// If true, the search is case sensitive.
+    #[serde(rename = "matchCase", skip_serializing_if = "Option::is_none")]
+    pub match_case: Option<bool>,
+    /// If true, only cells whose whole value is `find` are replaced.
+    #[serde(rename = "matchEntireCell", skip_serializing_if = "Option::is_none")]
+    pub match_entire_cell: Option<bool>,
+    /// If true, `find` is a regular expression and `replacement` may refer to
+    /// its groups, like "$1".
+    #[serde(rename = "searchByRegex", skip_serializing_if = "Option::is_none")]
+    pub search_by_regex: Option<bool>,
+    /// If true, formulas are searched too, not only values.
+    #[serde(rename = "includeFormulas", skip_serializing_if = "Option::is_none")]
+    pub include_formulas: Option<bool>,
+    /// Only search this range.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<GridRange>,
+    /// Only search the sheet with this ID.
+    #[serde(rename = "sheetId", skip_serializing_if = "Option::is_none")]
+    pub sheet_id: Option<i64>,
+    /// Search every sheet.
+    #[serde(rename = "allSheets", skip_serializing_if = "Option::is_none")]
+    pub all_sheets: Option<bool>,
+}
+
+impl FindReplace {
+    /// Replace whole cells that are exactly `find` with `replacement` in the
+    /// sheet with the ID `sheet_id`, like normalizing "California" to "CA".
+    pub fn cells(sheet_id: i64, find: &str, replacement: &str) -> Self {
+        FindReplace {
+            find: find.to_string(),
+            replacement: replacement.to_string(),
+            match_entire_cell: Some(true),
+            sheet_id: Some(sheet_id),
+            ..Default::default()
+        }
+    }
+}
+
+/// Rows or columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Dimension {
+    Rows,
+    Columns,
+}
+
+impl Dimension {
+    fn as_str(self) -> &'static str {
+        match self {
+            Dimension::Rows => "ROWS",
+            Dimension::Columns => "COLUMNS",
+        }
+    }
+}
+
+/// A range of rows or columns in a sheet. Indexes are zero-based, the start is
+/// inclusive and the end exclusive.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DimensionRange {
+    /// The sheet the range is on.
+    #[serde(rename = "sheetId")]
+    pub sheet_id: i64,
+    /// Whether the range is rows or columns.
+    pub dimension: Dimension,
+    /// The first row or column, if unset the range starts at the beginning.
+    #[serde(rename = "startIndex", skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<i32>,
+    /// The row or column after the last one, if unset the range goes to the end.
+    #[serde(rename = "endIndex", skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<i32>,
+}
+
+/// The data for a row of cells.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct RowData {
+    /// The cells in the row, one per column.
+    #[serde(default)]
+    pub values: Vec<CellData>,
+}
+
+impl RowData {
+    /// Returns if every cell with a value in the row is struck through, which
+    /// is how rows are usually marked as cancelled by hand.
+    pub fn is_strikethrough(&self) -> bool {
+        let mut cells = self.values.iter().filter(|c| matches!(c.formatted_value.as_deref(), Some(v) if !v.is_empty())).peekable();
+
+        cells.peek().is_some() && cells.all(CellData::is_strikethrough)
+    }
+}
+
+/// The value and formatting of a cell.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct CellData {
+    /// The value of the cell, as if it was typed in.
+    #[serde(rename = "userEnteredValue", skip_serializing_if = "Option::is_none")]
+    pub user_entered_value: Option<ExtendedValue>,
+    /// The format of the cell.
+    #[serde(rename = "userEnteredFormat", skip_serializing_if = "Option::is_none")]
+    pub user_entered_format: Option<CellFormat>,
+    /// The value of the cell after formulas are calculated. This is read only.
+    #[serde(rename = "effectiveValue", skip_serializing_if = "Option::is_none")]
+    pub effective_value: Option<ExtendedValue>,
+    /// The value of the cell as it is shown. This is read only.
+    #[serde(rename = "formattedValue", skip_serializing_if = "Option::is_none")]
+    pub formatted_value: Option<String>,
+    /// The format of the cell after conditional formatting and themes are
+    /// applied. This is read only.
+    #[serde(rename = "effectiveFormat", skip_serializing_if = "Option::is_none")]
+    pub effective_format: Option<CellFormat>,
+    /// The note on the cell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// The link in the cell, if any. This is read only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyperlink: Option<String>,
+    /// A pivot table anchored at the cell, it fills the cells below and to
+    /// the right.
+    #[serde(rename = "pivotTable", skip_serializing_if = "Option::is_none")]
+    pub pivot_table: Option<PivotTable>,
+}
+
+impl CellData {
+    /// Returns if the text in the cell is struck through.
+    pub fn is_strikethrough(&self) -> bool {
+        self.effective_format
+            .as_ref()
+            .or(self.user_entered_format.as_ref())
+            .and_then(|f| f.text_format.as_ref())
+            .and_then(|t| t.strikethrough)
+            .unwrap_or_default()
+    }
+}
+
+/// The value of a cell. Only one of the fields should be set.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExtendedValue {
+    /// A number, dates and times are numbers formatted as dates.
+    #[serde(rename = "numberValue", skip_serializing_if = "Option::is_none")]
+    pub number_value: Option<f64>,
+    /// A string.
+    #[serde(rename = "stringValue", skip_serializing_if = "Option::is_none")]
+    pub string_value: Option<String>,
+    /// A boolean.
+    #[serde(rename = "boolValue", skip_serializing_if = "Option::is_none")]
+    pub bool_value: Option<bool>,
+    /// A formula, for example "=SUM(A1:A10)".
+    #[serde(rename = "formulaValue", skip_serializing_if = "Option::is_none")]
+    pub formula_value: Option<String>,
+}
+
+/// The format of a cell.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct CellFormat {
+    /// The background color of the cell.
+    #[serde(rename = "backgroundColor", skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<Color>,
+    /// How numbers and dates in the cell are formatted.
+    #[serde(rename = "numberFormat", skip_serializing_if = "Option::is_none")]
+    pub number_format: Option<NumberFormat>,
+    /// The format of the text in the cell.
+    #[serde(rename = "textFormat", skip_serializing_if = "Option::is_none")]
+    pub text_format: Option<TextFormat>,
+    /// The horizontal alignment of the value in the cell:
+    /// "LEFT" | "CENTER" | "RIGHT"
+    #[serde(rename = "horizontalAlignment", skip_serializing_if = "Option::is_none")]
+    pub horizontal_alignment: Option<String>,
+}
+
+/// A color, each component is between 0 and 1.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    #[serde(default)]
+    pub red: f32,
+    #[serde(default)]
+    pub green: f32,
+    #[serde(default)]
+    pub blue: f32,
+}
+
+impl Color {
+    /// A color from components between 0 and 255, like in a hex color code.
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        Color {
+            red: f32::from(red) / 255.0,
+            green: f32::from(green) / 255.0,
+            blue: f32::from(blue) / 255.0,
+        }
+    }
+}
+
+/// How numbers and dates are formatted.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct NumberFormat {
+    /// The type of the format:
+    /// "TEXT" | "NUMBER" | "PERCENT" | "CURRENCY" | "DATE" | "TIME" | "DATE_TIME" | "SCIENTIFIC"
+    #[serde(rename = "type")]
+    pub format_type: String,
+    /// The pattern, for example "$#,##0.00". If not set the default pattern
+    /// for the locale of the spreadsheet is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// The format of text in a cell.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct TextFormat {
+    /// The color of the text.
+    #[serde(rename = "foregroundColor", skip_serializing_if = "Option::is_none")]
+    pub foreground_color: Option<Color>,
+    /// The size of the font, in points.
+    #[serde(rename = "fontSize", skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<i32>,
+    /// If the text is bold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    /// If the text is italic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    /// If the text is struck through.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    /// If the text is underlined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underline: Option<bool>,
+}
+
+/// A pivot table, summarizing the data in `source`.
+///
+/// ```
+/// use sheets::{BatchUpdateRequest, DateTimeRuleType, GridRange, PivotGroup, PivotTable, PivotValue};
+///
+/// // Shipping costs per carrier and month, from a sheet with the carrier in
+/// // column A, the ship date in column B, and the cost in column C.
+/// let pivot = PivotTable {
+///     source: GridRange {
+///         sheet_id: Some(0),
+///         start_column_index: Some(0),
+///         end_column_index: Some(3),
+///         ..Default::default()
+///     },
+///     rows: vec![PivotGroup::new(0)],
+///     columns: vec![PivotGroup::new(1).group_by_date(DateTimeRuleType::YearMonth)],
+///     values: vec![PivotValue::sum(2, "Shipping cost")],
+/// };
+///
+/// let requests = BatchUpdateRequest::new().add_pivot_table(1, 0, 0, pivot);
+/// ```
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct PivotTable {
+    /// The data to summarize.
+    #[serde(default)]
+    pub source: GridRange,
+    /// The groups of the rows of the pivot table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<PivotGroup>,
+    /// The groups of the columns of the pivot table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<PivotGroup>,
+    /// The values to show for every row and column group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<PivotValue>,
+}
+
+/// Groups the rows or columns of a pivot table by the values in a column of
+/// the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PivotGroup {
+    /// The column of the source to group by, counting from the first column
+    /// of the source.
+    #[serde(rename = "sourceColumnOffset")]
+    pub source_column_offset: i32,
+    /// If true, show the totals of the group.
+    #[serde(rename = "showTotals", default)]
+    pub show_totals: bool,
+    /// The order of the groups.
+    #[serde(rename = "sortOrder", skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<SortOrder>,
+    /// How to group the values, if not each value on its own.
+    #[serde(rename = "groupRule", skip_serializing_if = "Option::is_none")]
+    pub group_rule: Option<PivotGroupRule>,
+}
+
+impl PivotGroup {
+    /// Group by each value of the column at `source_column_offset`, in
+    /// ascending order, with totals.
+    pub fn new(source_column_offset: i32) -> Self {
+        PivotGroup {
+            source_column_offset,
+            show_totals: true,
+            sort_order: Some(SortOrder::Ascending),
+            group_rule: None,
+        }
+    }
+
+    /// Group dates by a part of them, like the year and month.
+    pub fn group_by_date(mut self, rule_type: DateTimeRuleType) -> Self {
+        self.group_rule = Some(PivotGroupRule {
+            date_time_rule: Some(DateTimeRule { rule_type }),
+        });
+        self
+    }
+}
+
+/// How to group the values in a pivot group.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct PivotGroupRule {
+    /// Group dates and times by a part of them.
+    #[serde(rename = "dateTimeRule", skip_serializing_if = "Option::is_none")]
+    pub date_time_rule: Option<DateTimeRule>,
+}
+
+/// Groups dates and times by a part of them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DateTimeRule {
+    /// The part of the date or time to group by.
+    #[serde(rename = "type")]
+    pub rule_type: DateTimeRuleType,
+}
+
+/// The part of a date or time to group by.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DateTimeRuleType {
+    /// The year, like 2020.
+    Year,
+    /// The quarter, like Q1.
+    Quarter,
+    /// The month, like Jan, for every year together.
+    Month,
+    /// The year and quarter, like 2020 Q1.
+    YearQuarter,
+    /// The year and month, like 2020-Jan.
+    YearMonth,
+    /// The year, month, and day, like 2020-01-31.
+    YearMonthDay,
+    /// The day of the week, like Sunday.
+    DayOfWeek,
+}
+
+/// A value shown in a pivot table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PivotValue {
+    /// The column of the source to summarize, counting from the first column
+    /// of the source.
+    #[serde(rename = "sourceColumnOffset")]
+    pub source_column_offset: i32,
+    /// How to summarize the values.
+    #[serde(rename = "summarizeFunction")]
+    pub summarize_function: SummarizeFunction,
+    /// The name of the value in the pivot table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl PivotValue {
+    /// The sum of the column at `source_column_offset`, called `name`.
+    pub fn sum(source_column_offset: i32, name: &str) -> Self {
+        PivotValue {
+            source_column_offset,
+            summarize_function: SummarizeFunction::Sum,
+            name: Some(name.to_string()),
+        }
+    }
+}
+
+/// How to summarize the values in a pivot table.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SummarizeFunction {
+    Sum,
+    Counta,
+    Count,
+    Countunique,
+    Average,
+    Max,
+    Min,
+    Median,
+}
+
+/// A rule that formats the cells in some ranges when a condition is met.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ConditionalFormatRule {
+    /// The ranges the rule applies to.
+    #[serde(default)]
+    pub ranges: Vec<GridRange>,
+    /// The condition and the format to apply when it is met.
+    #[serde(rename = "booleanRule", skip_serializing_if = "Option::is_none")]
+    pub boolean_rule: Option<BooleanRule>,
+}
+
+impl ConditionalFormatRule {
+    /// A rule that sets the background of the cells in `ranges` that meet
+    /// `condition` to `color`, like late shipments in red.
+    pub fn highlight(ranges: Vec<GridRange>, condition: BooleanCondition, color: Color) -> Self {
+        ConditionalFormatRule {
+            ranges,
+            boolean_rule: Some(BooleanRule {
+                condition,
+                format: CellFormat {
+                    background_color: Some(color),
+                    ..Default::default()
+                },
+            }),
+        }
+    }
+}
+
+/// Format cells when a condition is met.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BooleanRule {
+    /// The condition, for example that the number in the cell is greater than 100.
+    pub condition: BooleanCondition,
+    /// The format to apply to cells that meet the condition. Only some of the
+    /// formatting can be set: bold, italic, strikethrough, foreground color and
+    /// background color.
+    pub format: CellFormat,
+}
+
+/// A condition on the value of a cell.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BooleanCondition {
+    /// The type of the condition.
+    #[serde(rename = "type")]
+    pub condition_type: ConditionType,
+    /// The values to compare against, how many depends on the type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<ConditionValue>,
+}
+
+impl BooleanCondition {
+    /// A condition of the type `condition_type` comparing against `values`,
+    /// each as if it was typed in.
+    pub fn new(condition_type: ConditionType, values: &[&str]) -> Self {
+        BooleanCondition {
+            condition_type,
+            values: values.iter().map(|v| ConditionValue { user_entered_value: v.to_string() }).collect(),
+        }
+    }
+
+    /// The number in the cell is greater than `value`.
+    pub fn number_greater(value: f64) -> Self {
+        BooleanCondition::new(ConditionType::NumberGreater, &[&value.to_string()])
+    }
+
+    /// The number in the cell is less than `value`.
+    pub fn number_less(value: f64) -> Self {
+        BooleanCondition::new(ConditionType::NumberLess, &[&value.to_string()])
+    }
+
+    /// The number in the cell is between `low` and `high`, inclusive.
+    pub fn number_between(low: f64, high: f64) -> Self {
+        BooleanCondition::new(ConditionType::NumberBetween, &[&low.to_string(), &high.to_string()])
+    }
+
+    /// The text in the cell contains `text`.
+    pub fn text_contains(text: &str) -> Self {
+        BooleanCondition::new(ConditionType::TextContains, &[text])
+    }
+
+    /// The text in the cell is exactly `text`.
+    pub fn text_eq(text: &str) -> Self {
+        BooleanCondition::new(ConditionType::TextEq, &[text])
+    }
+
+    /// The date in the cell is before `date`, which may be a formula like
+    /// "=TODAY()-7".
+    pub fn date_before(date: &str) -> Self {
+        BooleanCondition::new(ConditionType::DateBefore, &[date])
+    }
+
+    /// The cell is empty.
+    pub fn blank() -> Self {
+        BooleanCondition::new(ConditionType::Blank, &[])
+    }
+
+    /// The cell is not empty.
+    pub fn not_blank() -> Self {
+        BooleanCondition::new(ConditionType::NotBlank, &[])
+    }
+
+    /// The value of the cell is one of `values`.
+    pub fn one_of_list(values: &[&str]) -> Self {
+        BooleanCondition::new(ConditionType::OneOfList, values)
+    }
+
+    /// The `formula` is true. References in the formula are relative to the
+    /// top left cell of the range, so "=$E2<TODAY()" checks column E of each row.
+    pub fn custom_formula(formula: &str) -> Self {
+        BooleanCondition::new(ConditionType::CustomFormula, &[formula])
+    }
+}
+
+/// A rule for the values allowed in a cell.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DataValidationRule {
+    /// The condition values have to meet.
+    pub condition: BooleanCondition,
+    /// A message shown when the cell is selected.
+    #[serde(rename = "inputMessage", skip_serializing_if = "Option::is_none")]
+    pub input_message: Option<String>,
+    /// If true, values that do not meet the condition are rejected, otherwise
+    /// they are only marked with a warning.
+    #[serde(default)]
+    pub strict: bool,
+    /// If true, the cell shows a dropdown for lists and a checkbox for booleans.
+    #[serde(rename = "showCustomUi", default)]
+    pub show_custom_ui: bool,
+}
+
+impl DataValidationRule {
+    /// Reject values that do not meet `condition`.
+    pub fn strict(condition: BooleanCondition) -> Self {
+        DataValidationRule {
+            condition,
+            input_message: None,
+            strict: true,
+            show_custom_ui: false,
+        }
+    }
+
+    /// A dropdown of the `options`, rejecting anything else.
+    pub fn dropdown(options: &[&str]) -> Self {
+        DataValidationRule {
+            show_custom_ui: true,
+            ..DataValidationRule::strict(BooleanCondition::one_of_list(options))
+        }
+    }
+
+    /// A checkbox, which is TRUE when checked and FALSE otherwise.
+    pub fn checkbox() -> Self {
+        DataValidationRule {
+            show_custom_ui: true,
+            ..DataValidationRule::strict(BooleanCondition::new(ConditionType::Boolean, &[]))
+        }
+    }
+}
+
+/// The type of a condition.
+/// FROM: https://developers.google.com/sheets/api/reference/rest/v4/spreadsheets/other#conditiontype
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConditionType {
+    NumberGreater,
+    NumberGreaterThanEq,
+    NumberLess,
+    NumberLessThanEq,
+    NumberEq,
+    NumberNotEq,
+    NumberBetween,
+    NumberNotBetween,
+    TextContains,
+    TextNotContains,
+    TextStartsWith,
+    TextEndsWith,
+    TextEq,
+    TextIsEmail,
+    TextIsUrl,
+    DateEq,
+    DateBefore,
+    DateAfter,
+    DateOnOrBefore,
+    DateOnOrAfter,
+    DateBetween,
+    DateNotBetween,
+    DateIsValid,
+    OneOfRange,
+    OneOfList,
+    Blank,
+    NotBlank,
+    CustomFormula,
+    Boolean,
+}
+
+/// A value to compare against in a condition.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ConditionValue {
+    /// The value as if it was typed in, it may be a formula.
+    #[serde(rename = "userEnteredValue")]
+    pub user_entered_value: String,
+}
+
+/// The filter of a sheet, which hides rows and sorts them for everyone
+/// looking at the sheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct BasicFilter {
+    /// The range the filter covers.
+    #[serde(default)]
+    pub range: GridRange,
+    /// How to sort the rows, later sorts break ties in earlier ones.
+    #[serde(rename = "sortSpecs", default, skip_serializing_if = "Vec::is_empty")]
+    pub sort_specs: Vec<SortSpec>,
+    /// Which rows to show, per column.
+    #[serde(rename = "filterSpecs", default, skip_serializing_if = "Vec::is_empty")]
+    pub filter_specs: Vec<FilterSpec>,
+}
+
+impl BasicFilter {
+    /// A filter on `range` that shows every row, sorted by nothing.
+    pub fn new(range: GridRange) -> Self {
+        BasicFilter { range, ..Default::default() }
+    }
+
+    /// Sort the rows by the column at `column`.
+    pub fn sort_by(mut self, column: i32, order: SortOrder) -> Self {
+        self.sort_specs.push(SortSpec {
+            dimension_index: column,
+            sort_order: order,
+        });
+        self
+    }
+
+    /// Only show the rows where the cell in the column at `column` meets `condition`.
+    pub fn filter_by(mut self, column: i32, condition: BooleanCondition) -> Self {
+        self.filter_specs.push(FilterSpec {
+            column_index: column,
+            filter_criteria: FilterCriteria {
+                condition: Some(condition),
+                ..Default::default()
+            },
+        });
+        self
+    }
+}
+
+/// A named filter that people can switch to, without changing the sheet for
+/// everyone else.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FilterView {
+    /// The ID of the filter view.
+    #[serde(rename = "filterViewId", skip_serializing_if = "Option::is_none")]
+    pub filter_view_id: Option<i64>,
+    /// The name of the filter view.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The range the filter view covers.
+    #[serde(default)]
+    pub range: GridRange,
+    /// How to sort the rows, later sorts break ties in earlier ones.
+    #[serde(rename = "sortSpecs", default, skip_serializing_if = "Vec::is_empty")]
+    pub sort_specs: Vec<SortSpec>,
+    /// Which rows to show, per column.
+    #[serde(rename = "filterSpecs", default, skip_serializing_if = "Vec::is_empty")]
+    pub filter_specs: Vec<FilterSpec>,
+}
+
+impl FilterView {
+    /// A filter view called `title` on `range` that shows every row, sorted
+    /// by nothing.
+    pub fn new(title: &str, range: GridRange) -> Self {
+        FilterView {
+            title: Some(title.to_string()),
+            range,
+            ..Default::default()
+        }
+    }
+}
+
+/// How to sort by a column.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SortSpec {
+    /// The index of the column to sort by.
+    #[serde(rename = "dimensionIndex")]
+    pub dimension_index: i32,
+    /// The order to sort in.
+    #[serde(rename = "sortOrder")]
+    pub sort_order: SortOrder,
+}
+
+/// The order to sort in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Which rows to show based on the cells in one column.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FilterSpec {
+    /// The index of the column.
+    #[serde(rename = "columnIndex")]
+    pub column_index: i32,
+    /// Which cells in the column to show.
+    #[serde(rename = "filterCriteria")]
+    pub filter_criteria: FilterCriteria,
+}
+
+/// Which cells in a column to show.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FilterCriteria {
+    /// Hide the rows with any of these values.
+    #[serde(rename = "hiddenValues", default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_values: Vec<String>,
+    /// Only show the rows where the cell meets the condition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<BooleanCondition>,
+}
+
+/// The response returned from applying changes to a spreadsheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct BatchUpdateSpreadsheetResponse {
+    /// The spreadsheet the updates were applied to.
+    #[serde(rename = "spreadsheetId")]
+    pub spreadsheet_id: Option<String>,
+    /// One reply per request, in the order of the requests. Requests that have
+    /// nothing to reply with, like deleting a sheet, get an empty reply.
+    #[serde(default)]
+    pub replies: Vec<SpreadsheetReply>,
+}
+
+/// The reply to a single change to a spreadsheet. Only the field for the kind
+/// of the request is set.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct SpreadsheetReply {
+    /// The reply to adding a sheet.
+    #[serde(rename = "addSheet", skip_serializing_if = "Option::is_none")]
+    pub add_sheet: Option<SheetReply>,
+    /// The reply to duplicating a sheet.
+    #[serde(rename = "duplicateSheet", skip_serializing_if = "Option::is_none")]
+    pub duplicate_sheet: Option<SheetReply>,
+    /// The reply to adding a named range.
+    #[serde(rename = "addNamedRange", skip_serializing_if = "Option::is_none")]
+    pub add_named_range: Option<NamedRangeReply>,
+    /// The reply to protecting a range.
+    #[serde(rename = "addProtectedRange", skip_serializing_if = "Option::is_none")]
+    pub add_protected_range: Option<ProtectedRangeReply>,
+    /// The reply to attaching developer metadata.
+    #[serde(rename = "createDeveloperMetadata", skip_serializing_if = "Option::is_none")]
+    pub create_developer_metadata: Option<DeveloperMetadataReply>,
+    /// The reply to adding a filter view.
+    #[serde(rename = "addFilterView", skip_serializing_if = "Option::is_none")]
+    pub add_filter_view: Option<FilterViewReply>,
+    /// The reply to finding and replacing text.
+    #[serde(rename = "findReplace", skip_serializing_if = "Option::is_none")]
+    pub find_replace: Option<FindReplaceReply>,
+}
+
+/// The reply to finding and replacing text.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FindReplaceReply {
+    /// The number of cells with a value that changed.
+    #[serde(rename = "valuesChanged", default)]
+    pub values_changed: i32,
+    /// The number of cells with a formula that changed.
+    #[serde(rename = "formulasChanged", default)]
+    pub formulas_changed: i32,
+    /// The number of rows that changed.
+    #[serde(rename = "rowsChanged", default)]
+    pub rows_changed: i32,
+    /// The number of sheets that changed.
+    #[serde(rename = "sheetsChanged", default)]
+    pub sheets_changed: i32,
+    /// The number of replacements, a cell may have more than one.
+    #[serde(rename = "occurrencesChanged", default)]
+    pub occurrences_changed: i32,
+}
+
+/// The reply to adding a named range.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct NamedRangeReply {
+    /// The new named range.
+    #[serde(rename = "namedRange", default)]
+    pub named_range: NamedRange,
+}
+
+/// The reply to protecting a range.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct ProtectedRangeReply {
+    /// The new protected range.
+    #[serde(rename = "protectedRange", default)]
+    pub protected_range: ProtectedRange,
+}
+
+/// The reply to adding a filter view.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FilterViewReply {
+    /// The new filter view.
+    #[serde(default)]
+    pub filter: FilterView,
+}
+
+/// The reply to attaching developer metadata.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DeveloperMetadataReply {
+    /// The new developer metadata.
+    #[serde(rename = "developerMetadata", default)]
+    pub developer_metadata: DeveloperMetadata,
+}
+
+/// The reply to a request that made a sheet.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct SheetReply {
+    /// The properties of the new sheet.
+    #[serde(default)]
+    pub properties: SheetProperties,
+}
+
+/// A key and value attached to a location in a spreadsheet. Unlike cell
+/// values, metadata stays with its rows or columns when they move, so it can
+/// mark the rows a sync has already processed.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DeveloperMetadata {
+    /// The ID of the metadata, unique within the spreadsheet.
+    #[serde(rename = "metadataId", skip_serializing_if = "Option::is_none")]
+    pub metadata_id: Option<i32>,
+    /// The key of the metadata. Several pieces of metadata may share a key.
+    #[serde(rename = "metadataKey", skip_serializing_if = "Option::is_none")]
+    pub metadata_key: Option<String>,
+    /// The value of the metadata.
+    #[serde(rename = "metadataValue", skip_serializing_if = "Option::is_none")]
+    pub metadata_value: Option<String>,
+    /// Where the metadata is attached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<DeveloperMetadataLocation>,
+    /// Who can see the metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<DeveloperMetadataVisibility>,
+}
+
+impl DeveloperMetadata {
+    /// Metadata visible to anyone with access to the spreadsheet, attached to
+    /// the rows of a sheet from `start` up to but not including `end`.
+    pub fn rows(sheet_id: i64, start: i32, end: i32, key: &str, value: &str) -> Self {
+        DeveloperMetadata {
+            metadata_key: Some(key.to_string()),
+            metadata_value: Some(value.to_string()),
+            location: Some(DeveloperMetadataLocation {
+                dimension_range: Some(DimensionRange {
+                    sheet_id,
+                    dimension: Dimension::Rows,
+                    start_index: Some(start),
+                    end_index: Some(end),
+                }),
+                ..Default::default()
+            }),
+            visibility: Some(DeveloperMetadataVisibility::Document),
+            ..Default::default()
+        }
+    }
+}
+
+/// Where developer metadata is attached. Only one of `spreadsheet`,
+/// `sheet_id`, and `dimension_range` may be set.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DeveloperMetadataLocation {
+    /// The kind of location, this is read only.
+    #[serde(rename = "locationType", skip_serializing_if = "Option::is_none")]
+    pub location_type: Option<DeveloperMetadataLocationType>,
+    /// True if the metadata is attached to the whole spreadsheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spreadsheet: Option<bool>,
+    /// The ID of the sheet the metadata is attached to.
+    #[serde(rename = "sheetId", skip_serializing_if = "Option::is_none")]
+    pub sheet_id: Option<i64>,
+    /// The rows or columns the metadata is attached to.
+    #[serde(rename = "dimensionRange", skip_serializing_if = "Option::is_none")]
+    pub dimension_range: Option<DimensionRange>,
+}
+
+/// The kind of location developer metadata is attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeveloperMetadataLocationType {
+    Row,
+    Column,
+    Sheet,
+    Spreadsheet,
+}
+
+/// Who can see developer metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeveloperMetadataVisibility {
+    /// Anyone with access to the spreadsheet.
+    Document,
+    /// Only the project that created the metadata.
+    Project,
+}
+
+/// A filter picking data in a spreadsheet. Only one of the fields may be set.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DataFilter {
+    /// Pick the locations of the matching developer metadata.
+    #[serde(rename = "developerMetadataLookup", skip_serializing_if = "Option::is_none")]
+    pub developer_metadata_lookup: Option<DeveloperMetadataLookup>,
+    /// Pick a range in A1 notation.
+    #[serde(rename = "a1Range", skip_serializing_if = "Option::is_none")]
+    pub a1_range: Option<String>,
+    /// Pick a grid range.
+    #[serde(rename = "gridRange", skip_serializing_if = "Option::is_none")]
+    pub grid_range: Option<GridRange>,
+}
+
+impl DataFilter {
+    /// Pick the developer metadata with the key `key`.
+    pub fn metadata_key(key: &str) -> Self {
+        DataFilter {
+            developer_metadata_lookup: Some(DeveloperMetadataLookup {
+                metadata_key: Some(key.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// The developer metadata to match, every field that is set has to match.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DeveloperMetadataLookup {
+    /// The ID of the metadata.
+    #[serde(rename = "metadataId", skip_serializing_if = "Option::is_none")]
+    pub metadata_id: Option<i32>,
+    /// The key of the metadata.
+    #[serde(rename = "metadataKey", skip_serializing_if = "Option::is_none")]
+    pub metadata_key: Option<String>,
+    /// The value of the metadata.
+    #[serde(rename = "metadataValue", skip_serializing_if = "Option::is_none")]
+    pub metadata_value: Option<String>,
+    /// The kind of location the metadata is attached to.
+    #[serde(rename = "locationType", skip_serializing_if = "Option::is_none")]
+    pub location_type: Option<DeveloperMetadataLocationType>,
+    /// Who can see the metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<DeveloperMetadataVisibility>,
+}
+
+/// The request for searching developer metadata.
+#[derive(Clone, Debug, Serialize)]
+struct SearchDeveloperMetadataRequest {
+    #[serde(rename = "dataFilters")]
+    data_filters: Vec<DataFilter>,
+}
+
+/// The response returned from searching developer metadata.
+#[derive(Default, Clone, Debug, Deserialize)]
+struct SearchDeveloperMetadataResponse {
+    #[serde(rename = "matchedDeveloperMetadata", default)]
+    matched_developer_metadata: Vec<MatchedDeveloperMetadata>,
+}
+
+/// A piece of developer metadata matched by a search.
+#[derive(Default, Clone, Debug, Deserialize)]
+struct MatchedDeveloperMetadata {
+    #[serde(rename = "developerMetadata", default)]
+    developer_metadata: DeveloperMetadata,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize};
+    use testutils::fixture;
+
+    use crate::{
+        column_index, column_letters, A1Range, AppendValuesResponse, BasicFilter, BatchUpdateRequest, BatchUpdateSpreadsheetResponse, BooleanCondition, CellValue, Color, ConditionType,
+        ConditionalFormatRule, DataFilter, DataValidationRule, DateTimeRenderOption, DateTimeRuleType, DeveloperMetadata, DeveloperMetadataLocationType, Dimension, Editors, FilterCriteria,
+        FilterSpec, FilterView, FindReplace, GetValuesOptions, GridRange, InsertDataOption, PivotGroup, PivotTable, PivotValue, SheetsError, SortOrder, Spreadsheet, ValueRange, ValueRenderOption,
+        ValuesCache,
+    };
+
+    #[test]
+    fn test_string_rows() {
+        let value_range = ValueRange {
+            values: Some(vec![
+                vec![CellValue::from("Name"), CellValue::from("Shipped")],
+                vec![CellValue::from("Jess"), CellValue::Bool(true), CellValue::Number(3.5), CellValue::Empty],
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(value_range.string_rows(), vec![vec!["Name", "Shipped"], vec!["Jess", "TRUE", "3.5", ""]]);
+        assert!(ValueRange::default().string_rows().is_empty());
+    }
+
+    #[test]
+    fn test_values_cache_drops_reads_raced_by_writes() {
+        let cache = ValuesCache::new(Duration::from_secs(60));
+        let key = ("sheet".to_string(), 42, "A1:B2".to_string(), GetValuesOptions::default());
+
+        // A write lands while the read is in flight, so what it read may be stale.
+        let generation = cache.generation();
+        cache.invalidate("sheet");
+        cache.insert(key.clone(), ValueRange::default(), generation);
+        assert!(cache.get(&key).is_none());
+
+        // The next read starts after the write and is kept.
+        cache.insert(key.clone(), ValueRange::default(), cache.generation());
+        assert!(cache.get(&key).is_some());
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct SwagResponse {
+        #[serde(rename = "Email Address")]
+        email: String,
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Street address line 2")]
+        street_2: Option<String>,
+        #[serde(rename = "Cost", default)]
+        cost: Option<f64>,
+    }
+
+    #[test]
+    fn test_cell_values() {
+        // Formatted values are all strings, with empty cells as empty strings.
+        let formatted: ValueRange = serde_json::from_value(fixture("sheets", "values")).unwrap();
+        let row = &formatted.values.unwrap()[1];
+        assert_eq!(row[2], CellValue::String("Jane Doe".to_string()));
+        assert!(row[4].is_empty());
+        assert_eq!(row[7].as_f64(), None);
+
+        // Unformatted values keep their types.
+        let unformatted: ValueRange = serde_json::from_value(serde_json::json!({
+            "range": "Costs!A1:C2",
+            "majorDimension": "ROWS",
+            "values": [["Carrier", "Cost", "Sent"], ["USPS", 12.5, true]],
+        }))
+        .unwrap();
+        let row = &unformatted.values.unwrap()[1];
+        assert_eq!(row[0].as_str(), Some("USPS"));
+        assert_eq!(row[1].as_f64(), Some(12.5));
+        assert_eq!(row[2].as_bool(), Some(true));
+        assert_eq!(row[2].to_string(), "TRUE");
+
+        // Empty cells are written as empty strings, which clears them.
+        let write = ValueRange {
+            range: Some("Costs!A2:C2".to_string()),
+            values: Some(vec![vec!["UPS".into(), 30.1.into(), CellValue::Empty]]),
+            major_dimension: None,
+        };
+        assert_eq!(serde_json::to_value(&write).unwrap()["values"], serde_json::json!([["UPS", 30.1, ""]]));
+    }
+
+    #[test]
+    fn test_deserialize_rows() {
+        let values: ValueRange = serde_json::from_value(fixture("sheets", "values")).unwrap();
+        let responses: Vec<SwagResponse> = values.deserialize_rows().unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].email, "jane@example.com");
+        assert_eq!(responses[0].name, "Jane Doe");
+        assert!(responses[0].street_2.is_none());
+        assert!(responses[0].cost.is_none());
+
+        // Numbers read unformatted keep their type, and empty rows are skipped.
+        let values: ValueRange = serde_json::from_value(serde_json::json!({
+            "values": [["Email Address", "Name", "Cost"], [], ["john@example.com", "John Doe", 12.5]],
+        }))
+        .unwrap();
+        let responses: Vec<SwagResponse> = values.deserialize_rows().unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].cost, Some(12.5));
+
+        // Rows missing a required field fail.
+        let values: ValueRange = serde_json::from_value(serde_json::json!({
+            "values": [["Email Address", "Name"], ["", "No Email"]],
+        }))
+        .unwrap();
+        assert!(matches!(values.deserialize_rows::<SwagResponse>(), Err(SheetsError::Serde(_))));
+    }
+
+    #[test]
+    fn test_get_values_options() {
+        // The defaults match what get_values has always asked for.
+        let options = GetValuesOptions::default();
+        assert_eq!(options.value_render_option, ValueRenderOption::FormattedValue);
+        assert_eq!(options.date_time_render_option, DateTimeRenderOption::FormattedString);
+        assert_eq!(options.major_dimension, Dimension::Rows);
+
+        let options = GetValuesOptions::default()
+            .value_render_option(ValueRenderOption::UnformattedValue)
+            .date_time_render_option(DateTimeRenderOption::SerialNumber)
+            .major_dimension(Dimension::Columns);
+        assert_eq!(options.value_render_option, ValueRenderOption::UnformattedValue);
+        assert_eq!(options.date_time_render_option, DateTimeRenderOption::SerialNumber);
+        assert_eq!(options.major_dimension, Dimension::Columns);
+
+        // Unformatted dates come back as serial numbers.
+        let columns: ValueRange = serde_json::from_value(serde_json::json!({
+            "range": "Costs!A1:B3",
+            "majorDimension": "COLUMNS",
+            "values": [["Carrier", "USPS", "UPS"], ["Shipped", 44197.5, 44198]],
+        }))
+        .unwrap();
+        let values = columns.values.unwrap();
+        assert_eq!(values[1][1].as_f64(), Some(44197.5));
+        assert_eq!(values[1][2].as_f64(), Some(44198.0));
+    }
+
+    #[test]
+    fn test_named_ranges() {
+        let range = GridRange {
+            sheet_id: Some(0),
+            start_row_index: Some(0),
+            start_column_index: Some(0),
+            end_column_index: Some(19),
+            ..Default::default()
+        };
+        let requests = BatchUpdateRequest::new().add_named_range("SwagResponses", range.clone()).delete_named_range("old");
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [
+                {"addNamedRange": {"namedRange": {"name": "SwagResponses", "range": {"sheetId": 0, "startRowIndex": 0, "startColumnIndex": 0, "endColumnIndex": 19}}}},
+                {"deleteNamedRange": {"namedRangeId": "old"}},
+            ]})
+        );
+
+        let resp: BatchUpdateSpreadsheetResponse = serde_json::from_value(serde_json::json!({
+            "spreadsheetId": "abc",
+            "replies": [{"addNamedRange": {"namedRange": {"namedRangeId": "nr1", "name": "SwagResponses", "range": {"sheetId": 0, "startRowIndex": 0, "startColumnIndex": 0, "endColumnIndex": 19}}}}],
+        }))
+        .unwrap();
+        let named_range = resp.replies[0].add_named_range.as_ref().unwrap().named_range.clone();
+        assert_eq!(named_range.named_range_id.as_deref(), Some("nr1"));
+        assert_eq!(named_range.range, range);
+    }
+
+    #[test]
+    fn test_protected_ranges() {
+        let editors = Editors {
+            users: vec!["sync@example.com".to_string()],
+            ..Default::default()
+        };
+        let requests = BatchUpdateRequest::new().protect_sheet(7, "Generated by the inventory sync", editors).delete_protected_range(3);
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [
+                {"addProtectedRange": {"protectedRange": {
+                    "range": {"sheetId": 7},
+                    "description": "Generated by the inventory sync",
+                    "editors": {"users": ["sync@example.com"]},
+                }}},
+                {"deleteProtectedRange": {"protectedRangeId": 3}},
+            ]})
+        );
+
+        let resp: BatchUpdateSpreadsheetResponse = serde_json::from_value(serde_json::json!({
+            "replies": [{"addProtectedRange": {"protectedRange": {"protectedRangeId": 42, "range": {"sheetId": 7}, "requestingUserCanEdit": true}}}],
+        }))
+        .unwrap();
+        let protected_range = resp.replies[0].add_protected_range.as_ref().unwrap().protected_range.clone();
+        assert_eq!(protected_range.protected_range_id, Some(42));
+        assert_eq!(protected_range.requesting_user_can_edit, Some(true));
+    }
+
+    #[test]
+    fn test_developer_metadata() {
+        let requests = BatchUpdateRequest::new().create_developer_metadata(DeveloperMetadata::rows(0, 1, 51, "shipment-sync", "2020-11-02"));
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [{"createDeveloperMetadata": {"developerMetadata": {
+                "metadataKey": "shipment-sync",
+                "metadataValue": "2020-11-02",
+                "location": {"dimensionRange": {"sheetId": 0, "dimension": "ROWS", "startIndex": 1, "endIndex": 51}},
+                "visibility": "DOCUMENT",
+            }}}]})
+        );
+
+        assert_eq!(
+            serde_json::to_value(DataFilter::metadata_key("shipment-sync")).unwrap(),
+            serde_json::json!({"developerMetadataLookup": {"metadataKey": "shipment-sync"}})
+        );
+
+        let resp: BatchUpdateSpreadsheetResponse = serde_json::from_value(serde_json::json!({
+            "replies": [{"createDeveloperMetadata": {"developerMetadata": {
+                "metadataId": 1234,
+                "metadataKey": "shipment-sync",
+                "metadataValue": "2020-11-02",
+                "location": {"locationType": "ROW", "dimensionRange": {"sheetId": 0, "dimension": "ROWS", "startIndex": 1, "endIndex": 51}},
+                "visibility": "DOCUMENT",
+            }}}],
+        }))
+        .unwrap();
+        let metadata = resp.replies[0].create_developer_metadata.as_ref().unwrap().developer_metadata.clone();
+        assert_eq!(metadata.metadata_id, Some(1234));
+        assert_eq!(metadata.location.unwrap().location_type, Some(DeveloperMetadataLocationType::Row));
+    }
+
+    #[test]
+    fn test_a1_range() {
+        assert_eq!(column_letters(0), "A");
+        assert_eq!(column_letters(18), "S");
+        assert_eq!(column_letters(26), "AA");
+        assert_eq!(column_letters(701), "ZZ");
+        assert_eq!(column_letters(702), "AAA");
+        assert_eq!(column_index("a"), Some(0));
+        assert_eq!(column_index("AAA"), Some(702));
+        assert_eq!(column_index("A1"), None);
+
+        let range = A1Range::new("Form Responses 1", 0, 0, 1000, 19);
+        assert_eq!(range.to_string(), "'Form Responses 1'!A1:S1000");
+        assert_eq!(range.to_string().parse::<A1Range>().unwrap(), range);
+
+        // Ranges can be unbounded on any side.
+        for s in &["Sheet1!A:C", "Sheet1!A5:A", "1:500", "'Bob''s sheet'!B2", "Costs"] {
+            assert_eq!(s.parse::<A1Range>().unwrap().to_string(), *s);
+        }
+        assert_eq!("Costs".parse::<A1Range>().unwrap(), A1Range::sheet("Costs"));
+        let range: A1Range = "'Bob''s sheet'!B2".parse().unwrap();
+        assert_eq!(range.sheet.as_deref(), Some("Bob's sheet"));
+        assert_eq!((range.start_row, range.start_column, range.end_row, range.end_column), (Some(1), Some(1), Some(2), Some(2)));
+        assert_eq!(A1Range::rows("Sheet1", 500, 1000).to_string(), "Sheet1!501:1000");
+
+        assert_eq!(range.offset(-5, 3).to_string(), "'Bob''s sheet'!E1");
+
+        assert!(matches!("Sheet1!A0".parse::<A1Range>(), Err(SheetsError::InvalidRange(_))));
+        assert!(matches!("Sheet1!".parse::<A1Range>(), Err(SheetsError::InvalidRange(_))));
+        assert!(matches!("A1:B2:C3".parse::<A1Range>(), Err(SheetsError::InvalidRange(_))));
+    }
+
+    #[test]
+    fn test_filters() {
+        let range = GridRange {
+            sheet_id: Some(3),
+            ..Default::default()
+        };
+        let late = BooleanCondition::date_before("=TODAY()-7");
+        let filter = BasicFilter::new(range.clone()).sort_by(4, SortOrder::Descending).filter_by(4, late);
+        let mut view = FilterView::new("Unsent", range);
+        view.filter_specs.push(FilterSpec {
+            column_index: 9,
+            filter_criteria: FilterCriteria {
+                hidden_values: vec!["TRUE".to_string()],
+                ..Default::default()
+            },
+        });
+
+        let requests = BatchUpdateRequest::new().set_basic_filter(filter).add_filter_view(view).clear_basic_filter(5);
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [
+                {"setBasicFilter": {"filter": {
+                    "range": {"sheetId": 3},
+                    "sortSpecs": [{"dimensionIndex": 4, "sortOrder": "DESCENDING"}],
+                    "filterSpecs": [{"columnIndex": 4, "filterCriteria": {"condition": {"type": "DATE_BEFORE", "values": [{"userEnteredValue": "=TODAY()-7"}]}}}],
+                }}},
+                {"addFilterView": {"filter": {
+                    "title": "Unsent",
+                    "range": {"sheetId": 3},
+                    "filterSpecs": [{"columnIndex": 9, "filterCriteria": {"hiddenValues": ["TRUE"]}}],
+                }}},
+                {"clearBasicFilter": {"sheetId": 5}},
+            ]})
+        );
+
+        let resp: BatchUpdateSpreadsheetResponse = serde_json::from_value(serde_json::json!({
+            "replies": [{}, {"addFilterView": {"filter": {"filterViewId": 99, "title": "Unsent", "range": {"sheetId": 3}}}}, {}],
+        }))
+        .unwrap();
+        assert_eq!(resp.replies[1].add_filter_view.as_ref().unwrap().filter.filter_view_id, Some(99));
+    }
+
+    #[test]
+    fn test_conditional_format_rules() {
+        let costs = GridRange {
+            sheet_id: Some(0),
+            start_column_index: Some(4),
+            end_column_index: Some(5),
+            ..Default::default()
+        };
+        let rule = ConditionalFormatRule::highlight(vec![costs], BooleanCondition::number_greater(100.0), Color::from_rgb(255, 0, 0));
+        assert_eq!(
+            serde_json::to_value(BatchUpdateRequest::new().add_conditional_format_rule(rule)).unwrap(),
+            serde_json::json!({"requests": [{"addConditionalFormatRule": {"index": 0, "rule": {
+                "ranges": [{"sheetId": 0, "startColumnIndex": 4, "endColumnIndex": 5}],
+                "booleanRule": {
+                    "condition": {"type": "NUMBER_GREATER", "values": [{"userEnteredValue": "100"}]},
+                    "format": {"backgroundColor": {"red": 1.0, "green": 0.0, "blue": 0.0}},
+                },
+            }}}]})
+        );
+
+        let between = BooleanCondition::number_between(1.5, 10.0);
+        assert_eq!(between.condition_type, ConditionType::NumberBetween);
+        assert_eq!(between.values.iter().map(|v| v.user_entered_value.as_str()).collect::<Vec<_>>(), vec!["1.5", "10"]);
+
+        let condition: BooleanCondition = serde_json::from_value(serde_json::json!({"type": "CUSTOM_FORMULA", "values": [{"userEnteredValue": "=$E2<TODAY()"}]})).unwrap();
+        assert_eq!(condition.condition_type, ConditionType::CustomFormula);
+        assert_eq!(serde_json::to_value(BooleanCondition::not_blank()).unwrap(), serde_json::json!({"type": "NOT_BLANK"}));
+    }
+
+    #[test]
+    fn test_data_validation() {
+        let column = |index| GridRange {
+            sheet_id: Some(0),
+            start_row_index: Some(1),
+            start_column_index: Some(index),
+            end_column_index: Some(index + 1),
+            ..Default::default()
+        };
+        let mut size = DataValidationRule::dropdown(&["S", "M", "L"]);
+        size.input_message = Some("Pick a size".to_string());
+
+        let requests = BatchUpdateRequest::new()
+            .set_data_validation(column(5), size)
+            .set_data_validation(column(9), DataValidationRule::checkbox())
+            .clear_data_validation(column(10));
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [
+                {"setDataValidation": {
+                    "range": {"sheetId": 0, "startRowIndex": 1, "startColumnIndex": 5, "endColumnIndex": 6},
+                    "rule": {
+                        "condition": {"type": "ONE_OF_LIST", "values": [{"userEnteredValue": "S"}, {"userEnteredValue": "M"}, {"userEnteredValue": "L"}]},
+                        "inputMessage": "Pick a size",
+                        "strict": true,
+                        "showCustomUi": true,
+                    },
+                }},
+                {"setDataValidation": {
+                    "range": {"sheetId": 0, "startRowIndex": 1, "startColumnIndex": 9, "endColumnIndex": 10},
+                    "rule": {"condition": {"type": "BOOLEAN"}, "strict": true, "showCustomUi": true},
+                }},
+                {"setDataValidation": {"range": {"sheetId": 0, "startRowIndex": 1, "startColumnIndex": 10, "endColumnIndex": 11}}},
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_append_values_response() {
+        let resp: AppendValuesResponse = serde_json::from_value(serde_json::json!({
+            "spreadsheetId": "abc",
+            "tableRange": "Shipments!A1:H41",
+            "updates": {
+                "spreadsheetId": "abc",
+                "updatedRange": "Shipments!A42:H43",
+                "updatedRows": 2,
+                "updatedColumns": 8,
+                "updatedCells": 16,
+            },
+        }))
+        .unwrap();
+        assert_eq!(resp.table_range.as_deref(), Some("Shipments!A1:H41"));
+        assert_eq!(resp.updates.updated_range.as_deref(), Some("Shipments!A42:H43"));
+        assert_eq!(resp.updates.updated_rows, Some(2));
+        assert_eq!(serde_json::to_value(InsertDataOption::InsertRows).unwrap(), "INSERT_ROWS");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let values: ValueRange = serde_json::from_value(serde_json::json!({
+            "values": [["Carrier", "Cost", "Notes"], ["USPS", 12.5, "fragile, \"glass\""], ["UPS"], [], ["DHL", true]],
+        }))
+        .unwrap();
+        assert_eq!(values.to_csv().unwrap(), "Carrier,Cost,Notes\nUSPS,12.5,\"fragile, \"\"glass\"\"\"\nUPS,,\n,,\nDHL,TRUE,\n");
+
+        assert_eq!(ValueRange::default().to_csv().unwrap(), "");
+    }
+
+    #[test]
+    fn test_grid_data() {
+        let spreadsheet: Spreadsheet = serde_json::from_value(serde_json::json!({
+            "spreadsheetId": "abc",
+            "sheets": [{
+                "properties": {"sheetId": 0, "title": "Form Responses 1"},
+                "data": [{
+                    "startRow": 1,
+                    "rowData": [
+                        {"values": [
+                            {"formattedValue": "jane@example.com", "effectiveFormat": {"textFormat": {"strikethrough": true}}},
+                            {"formattedValue": "L", "effectiveFormat": {"textFormat": {"strikethrough": true}}, "note": "cancelled by email"},
+                            {},
+                        ]},
+                        {"values": [
+                            {"formattedValue": "john@example.com", "effectiveFormat": {"textFormat": {"strikethrough": true}}, "hyperlink": "mailto:john@example.com"},
+                            {"formattedValue": "M", "effectiveFormat": {"textFormat": {"strikethrough": false}}},
+                        ]},
+                        {},
+                    ],
+                }],
+            }],
+        }))
+        .unwrap();
+        let data = &spreadsheet.sheets[0].data[0];
+        assert_eq!(data.start_row, Some(1));
+        assert!(data.row_data[0].is_strikethrough());
+        assert_eq!(data.row_data[0].values[1].note.as_deref(), Some("cancelled by email"));
+        assert!(!data.row_data[1].is_strikethrough());
+        assert_eq!(data.row_data[1].values[0].hyperlink.as_deref(), Some("mailto:john@example.com"));
+        assert!(!data.row_data[2].is_strikethrough());
+    }
+
+    #[test]
+    fn test_find_replace() {
+        let mut zip = FindReplace::cells(0, r"^(\d{4})$", "0$1");
+        zip.match_entire_cell = None;
+        zip.search_by_regex = Some(true);
+        let requests = BatchUpdateRequest::new().find_replace(FindReplace::cells(0, "California", "CA")).find_replace(zip);
+        assert_eq!(
+            serde_json::to_value(&requests).unwrap(),
+            serde_json::json!({"requests": [
+                {"findReplace": {"find": "California", "replacement": "CA", "matchEntireCell": true, "sheetId": 0}},
+                {"findReplace": {"find": r"^(\d{4})$", "replacement": "0$1", "searchByRegex": true, "sheetId": 0}},
+            ]})
+        );
+
+        let resp: BatchUpdateSpreadsheetResponse = serde_json::from_value(serde_json::json!({
+            "replies": [{"findReplace": {"valuesChanged": 3, "rowsChanged": 3, "sheetsChanged": 1, "occurrencesChanged": 3}}, {"findReplace": {}}],
+        }))
+        .unwrap();
+        let reply = resp.replies[0].find_replace.as_ref().unwrap();
+        assert_eq!((reply.values_changed, reply.formulas_changed, reply.occurrences_changed), (3, 0, 3));
+        assert_eq!(resp.replies[1].find_replace.as_ref().unwrap().values_changed, 0);
+    }
+
+    #[test]
+    fn test_pivot_table() {
+        let pivot = PivotTable {
+            source: GridRange {
+                sheet_id: Some(0),
+                start_column_index: Some(0),
+                end_column_index: Some(3),
+                ..Default::default()
+            },
+            rows: vec![PivotGroup::new(0)],
+            columns: vec![PivotGroup::new(1).group_by_date(DateTimeRuleType::YearMonth)],
+            values: vec![PivotValue::sum(2, "Shipping cost")],
+        };
+        assert_eq!(
+            serde_json::to_value(BatchUpdateRequest::new().add_pivot_table(1, 0, 0, pivot)).unwrap(),
+            serde_json::json!({"requests": [{"updateCells": {
+                "range": {"sheetId": 1, "startRowIndex": 0, "endRowIndex": 1, "startColumnIndex": 0, "endColumnIndex": 1},
+                "rows": [{"values": [{"pivotTable": {
+                    "source": {"sheetId": 0, "startColumnIndex": 0, "endColumnIndex": 3},
+                    "rows": [{"sourceColumnOffset": 0, "showTotals": true, "sortOrder": "ASCENDING"}],
+                    "columns": [{"sourceColumnOffset": 1, "showTotals": true, "sortOrder": "ASCENDING", "groupRule": {"dateTimeRule": {"type": "YEAR_MONTH"}}}],
+                    "values": [{"sourceColumnOffset": 2, "summarizeFunction": "SUM", "name": "Shipping cost"}],
+                }}]}],
+                "fields": "pivotTable",
+            }}]})
+        );
+    }
+
+    #[derive(Debug, Serialize)]
+    struct InventoryRow {
+        #[serde(rename = "Item")]
+        item: String,
+        #[serde(rename = "Size")]
+        size: Option<String>,
+        #[serde(rename = "Current stock")]
+        stock: i32,
+        #[serde(rename = "Sizes")]
+        sizes: Vec<String>,
+        #[serde(rename = "Not in the sheet")]
+        internal: bool,
+    }
+
+    #[test]
+    fn test_serialize_rows() {
+        let rows = vec![
+            InventoryRow {
+                item: "Hoodie".to_string(),
+                size: Some("L".to_string()),
+                stock: 12,
+                sizes: vec!["M".to_string(), "L".to_string()],
+                internal: true,
+            },
+            InventoryRow {
+                item: "Sticker".to_string(),
+                size: None,
+                stock: 300,
+                sizes: vec![],
+                internal: false,
+            },
+        ];
+        let headers = ["Item", "Size", " Current stock ", "Sizes", "Notes"];
+        let values = ValueRange::serialize_rows(&headers, &rows).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                vec!["Hoodie".into(), "L".into(), 12.0.into(), "[\"M\",\"L\"]".into(), CellValue::Empty],
+                vec!["Sticker".into(), CellValue::Empty, 300.0.into(), "[]".into(), CellValue::Empty],
+            ]
+        );
+
+        // Serializing and deserializing gets the same rows back.
+        let mut table = vec![headers.iter().map(|h| CellValue::from(*h)).collect::<Vec<_>>()];
+        table.extend(values);
+        let range = ValueRange {
+            values: Some(table),
+            ..Default::default()
+        };
+        let read: Vec<serde_json::Value> = range.deserialize_rows().unwrap();
+        assert_eq!(read[1], serde_json::json!({"Item": "Sticker", "Current stock": 300.0, "Sizes": "[]"}));
+
+        assert!(matches!(ValueRange::serialize_rows(&headers, &[1, 2]), Err(SheetsError::Serde(_))));
+    }
+
+    #[test]
+    fn test_conflict_error() {
+        let err = SheetsError::Conflict { expected: 41, actual: 43 };
+        assert_eq!(err.to_string(), "SheetsError: spreadsheet changed -> expected revision 41, got 43");
+        assert!(std::error::Error::source(&err).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+/*!
+ * A blocking client for the Shippo API, for tools that are not async.
+ *
+ * Every method mirrors the method of the same name on the async `shippo::Shippo`
+ * and blocks until it is done. The client runs its own runtime, so it must not
+ * be used from inside an async runtime.
+ *
+ * Example:
+ *
+ * ```
+ * use shippo::blocking::Shippo;
+ *
+ * fn get_shipments() {
+ *     // Initialize the blocking Shippo client.
+ *     let shippo = Shippo::new_from_env().unwrap();
+ *
+ *     // List the shipments from the last 90 days.
+ *     let shipments = shippo
+ *         .list_shipments(&shippo::ShipmentListParams::created_in_last_days(90))
+ *         .unwrap();
+ *
+ *     for shipment in shipments {
+ *         println!("{:?}", shipment);
+ *     }
+ * }
+ * ```
+ */
+use std::future::Future;
+use std::sync::Mutex;
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::*;
+
+/// Entrypoint for interacting with the Shippo API without async.
+pub struct Shippo {
+    inner: crate::Shippo,
+
+    runtime: Mutex<Runtime>,
+}
+
+/// Generate blocking methods that wait for the async method of the same name.
+macro_rules! blocking {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret, ShippoError> {
+                self.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl Shippo {
+    /// Create a new blocking Shippo client struct, see `shippo::Shippo::new`.
+    pub fn new<K>(token: K) -> Result<Self, ShippoError>
+    where
+        K: ToString,
+    {
+        Ok(Shippo::from_async(crate::Shippo::new(token)?))
+    }
+
+    /// Create a new blocking Shippo client struct from environment variables,
+    /// see `shippo::Shippo::new_from_env`.
+    pub fn new_from_env() -> Result<Self, ShippoError> {
+        Ok(Shippo::from_async(crate::Shippo::new_from_env()?))
+    }
+
+    /// Create a new blocking Shippo client struct with the test token from the
+    /// environment, see `shippo::Shippo::new_test_from_env`.
+    pub fn new_test_from_env() -> Result<Self, ShippoError> {
+        Ok(Shippo::from_async(crate::Shippo::new_test_from_env()?))
+    }
+
+    /// Return a client that uses a different token, see `shippo::Shippo::with_token`.
+    pub fn with_token<K>(&self, token: K) -> Self
+    where
+        K: ToString,
+    {
+        Shippo::from_async(self.inner.with_token(token))
+    }
+
+    /// Return if the client uses a test token.
+    pub fn is_test(&self) -> bool {
+        self.inner.is_test()
+    }
+
+    /// Return the counters for the requests sent by the client, see
+    /// `shippo::Shippo::metrics`.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.inner.metrics()
+    }
+
+    /// Set the most a label bought from a rate may cost in live mode, see
+    /// `shippo::Shippo::with_max_label_cost`.
+    pub fn with_max_label_cost(mut self, max: Money) -> Self {
+        self.inner = self.inner.with_max_label_cost(max);
+        self
+    }
+
+    /// Wrap an async client, for example one made with `shippo::Shippo::builder`.
+    pub fn from_async(inner: crate::Shippo) -> Self {
+        // The basic scheduler runs everything on the calling thread, so this
+        // only fails if we cannot set up the IO driver.
+        let runtime = Builder::new().basic_scheduler().enable_all().build().expect("creating runtime for blocking client failed");
+
+        Shippo { inner, runtime: Mutex::new(runtime) }
+    }
+
+    fn block_on<F: Future>(&self, f: F) -> F::Output {
+        // If another call panicked, the runtime is still usable.
+        let mut runtime = self.runtime.lock().unwrap_or_else(|e| e.into_inner());
+        runtime.block_on(f)
+    }
+
+    /// Get the tracking status of many shipments, see
+    /// `shippo::Shippo::get_tracking_statuses`.
+    pub fn get_tracking_statuses(&self, shipments: &[(&str, &str)]) -> Vec<Result<TrackingStatus, ShippoError>> {
+        self.block_on(self.inner.get_tracking_statuses(shipments))
+    }
+
+    blocking! {
+        fn list_shipments(&self, params: &ShipmentListParams) -> Vec<Shipment>;
+        fn list_shipments_paginated(&self, params: &ShipmentListParams, max: Option<usize>) -> Vec<Shipment>;
+        fn find_shipment_by_metadata(&self, metadata: &str) -> Option<Shipment>;
+        fn create_shipment(&self, ns: NewShipment) -> Shipment;
+        fn get_shipment(&self, id: &str) -> Shipment;
+        fn get_rates_for_shipment(&self, shipment_id: &str, currency: &str) -> Vec<Rate>;
+        fn create_address(&self, address: Address, validate: bool) -> Address;
+        fn get_address(&self, id: &str) -> Address;
+        fn list_addresses(&self) -> Vec<Address>;
+        fn validate_address(&self, id: &str) -> Address;
+        fn list_carrier_accounts(&self) -> Vec<CarrierAccount>;
+        fn get_carrier_account(&self, id: &str) -> CarrierAccount;
+        fn create_carrier_account(&self, ca: CarrierAccount) -> CarrierAccount;
+        fn update_carrier_account(&self, ca: CarrierAccount) -> CarrierAccount;
+        fn create_pickup(&self, np: NewPickup) -> Pickup;
+        fn get_pickup(&self, id: &str) -> Pickup;
+        fn list_pickups(&self) -> Vec<Pickup>;
+        fn cancel_pickup(&self, id: &str) -> Pickup;
+        fn create_customs_item(&self, c: CustomsItem) -> CustomsItem;
+        fn get_customs_item(&self, id: &str) -> CustomsItem;
+        fn list_customs_items(&self) -> Vec<CustomsItem>;
+        fn create_customs_declaration(&self, c: CustomsDeclaration) -> CustomsDeclaration;
+        fn get_customs_declaration(&self, id: &str) -> CustomsDeclaration;
+        fn list_customs_declarations(&self) -> Vec<CustomsDeclaration>;
+        fn get_rate(&self, id: &str) -> Rate;
+        fn create_shipping_label_from_rate(&self, nt: NewTransaction) -> Transaction;
+        fn create_shipping_label_from_shipment(&self, nit: NewInstantTransaction) -> Transaction;
+        fn get_shipping_label(&self, id: &str) -> Transaction;
+        fn download_label(&self, transaction: &Transaction, format: LabelFileType) -> Bytes;
+        fn list_shipping_labels(&self, params: &TransactionListParams) -> Vec<Transaction>;
+        fn list_shipping_labels_paginated(&self, page_size: u32, max: Option<usize>) -> Vec<Transaction>;
+        fn create_refund(&self, transaction_id: &str) -> Refund;
+        fn get_refund(&self, id: &str) -> Refund;
+        fn list_refunds(&self) -> Vec<Refund>;
+        fn create_manifest(&self, nm: NewManifest) -> Manifest;
+        fn get_manifest(&self, id: &str) -> Manifest;
+        fn list_manifests(&self) -> Vec<Manifest>;
+        fn create_batch(&self, nb: NewBatch) -> Batch;
+        fn get_batch(&self, id: &str) -> Batch;
+        fn add_shipments_to_batch(&self, id: &str, shipments: Vec<NewBatchShipment>) -> Batch;
+        fn remove_shipments_from_batch(&self, id: &str, batch_shipment_ids: Vec<String>) -> Batch;
+        fn purchase_batch(&self, id: &str) -> Batch;
+        fn create_order(&self, no: NewOrder) -> Order;
+        fn get_order(&self, id: &str) -> Order;
+        fn list_orders(&self) -> Vec<Order>;
+        fn get_live_rates(&self, nlr: NewLiveRates) -> Vec<LiveRate>;
+        fn create_webhook(&self, w: Webhook) -> Webhook;
+        fn get_webhook(&self, id: &str) -> Webhook;
+        fn list_webhooks(&self) -> Vec<Webhook>;
+        fn delete_webhook(&self, id: &str) -> ();
+        fn register_tracking_webhook(&self, carrier: &str, tracking_number: &str) -> TrackingStatus;
+        fn register_tracking_webhook_once(&self, carrier: &str, tracking_number: &str) -> TrackingStatus;
+        fn get_tracking_status(&self, carrier: &str, tracking_number: &str) -> TrackingStatus;
+    }
+}
@@ -7,14 +7,17 @@
  *
  * ```
  * use serde::{Deserialize, Serialize};
- * use shippo::Shippo;
+ * use shippo::{ShipmentListParams, Shippo};
  *
  * async fn get_shipments() {
  *     // Initialize the Shippo client.
- *     let shippo = Shippo::new_from_env();
+ *     let shippo = Shippo::new_from_env().unwrap();
  *
- *     // List the shipments.
- *     let shipments = shippo.list_shipments().await.unwrap();
+ *     // List the shipments from the last 90 days.
+ *     let shipments = shippo
+ *         .list_shipments(&ShipmentListParams::created_in_last_days(90))
+ *         .await
+ *         .unwrap();
  *
  *     // Iterate over the shipments.
  *     for shipment in shipments {
@@ -24,74 +27,423 @@
  * ```
  */
 #![allow(clippy::field_reassign_with_default)]
-use std::collections::HashMap;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::error;
 use std::fmt;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+pub use bytes::Bytes;
 use chrono::offset::Utc;
-use chrono::DateTime;
-use reqwest::{header, Client, Method, Request, StatusCode, Url};
+use chrono::{DateTime, SecondsFormat};
+pub use client_core::{redact_headers, Client, Hook, Metrics, RetryPolicy, Transport};
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use futures_util::{future, pin_mut};
+pub use reqwest::Proxy;
+use reqwest::{header, Method, Request, RequestBuilder, StatusCode, Url};
+pub use rust_decimal::Decimal;
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 /// Endpoint for the Shippo API.
 const ENDPOINT: &str = "https://api.goshippo.com/";
 
+/// The number of results we request per page when listing.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// The largest date range, in days, Shippo allows when listing shipments.
+pub const MAX_SHIPMENT_LIST_DAYS: i64 = 90;
+
+/// How long we wait for a request, unless the client is built with another timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The most requests we send at once when looking up many objects.
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// The header that pins the version of the API a request is handled with.
+const API_VERSION: &str = "Shippo-API-Version";
+
+/// The version of the API the types in this crate were last checked against.
+/// Pass it to `ShippoBuilder::api_version` so changes to the API do not take
+/// effect until we have tested them.
+pub const TESTED_API_VERSION: &str = "2018-02-08";
+
+/// The `User-Agent` we send, unless the client is built with another one.
+const USER_AGENT: &str = concat!("shippo-rust/", env!("CARGO_PKG_VERSION"));
+
+/// The header Shippo uses to make sure the same object is not created twice.
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Read an API token from an environment variable.
+fn token_from_env(var: &'static str) -> Result<String, ShippoError> {
+    match env::var(var) {
+        Ok(token) if !token.is_empty() => Ok(token),
+        _ => Err(ShippoError::MissingEnv(var)),
+    }
+}
+
+/// Refuse a label whose price is over `max`, or one we can't read the price of.
+fn check_label_cost(rate: &str, price: Option<Money>, max: &Money) -> Result<(), ShippoError> {
+    match price {
+        Some(p) if p <= *max => Ok(()),
+        price => Err(ShippoError::OverMaxCost {
+            rate: rate.to_string(),
+            price,
+            max: max.clone(),
+        }),
+    }
+}
+
+/// Check the cost of the rate for a carrier account and service level, and
+/// return the rate. If the shipment has no such rate, we don't know what the
+/// label costs, so it is refused with the service level as the rate.
+fn check_rate_cost<'a>(rates: &'a [Rate], carrier_account: &str, servicelevel_token: &str, max: &Money) -> Result<&'a Rate, ShippoError> {
+    match rates.iter().find(|r| r.carrier_account == carrier_account && r.servicelevel.token.as_str() == servicelevel_token) {
+        Some(r) => check_label_cost(&r.object_id, r.price(), max).map(|_| r),
+        None => Err(ShippoError::OverMaxCost {
+            rate: servicelevel_token.to_string(),
+            price: None,
+            max: max.clone(),
+        }),
+    }
+}
+
 /// Entrypoint for interacting with the Shippo API.
 pub struct Shippo {
     token: String,
+    endpoint: String,
+    api_version: Option<String>,
+    max_label_cost: Option<Money>,
+    deadline: Option<Instant>,
 
     client: Arc<Client>,
 }
 
+/// Builder for a Shippo client, for when the defaults are not what you want.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use shippo::Shippo;
+///
+/// let shippo = Shippo::builder("token")
+///     .timeout(Duration::from_secs(30))
+///     .user_agent("cio")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct ShippoBuilder {
+    token: String,
+    endpoint: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    user_agent: Option<String>,
+    api_version: Option<String>,
+    retry_policy: Option<RetryPolicy>,
+    client: Option<Client>,
+}
+
+impl ShippoBuilder {
+    /// Set the base URL the client sends requests to. This defaults to the
+    /// production API, or the `SHIPPO_ENDPOINT` environment variable if it is set.
+    pub fn endpoint<E>(mut self, endpoint: E) -> Self
+    where
+        E: ToString,
+    {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Set the timeout for each request, from when it is sent until the
+    /// response body has been read. This defaults to 60 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for connecting to the API.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send all requests through a proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request. This defaults to
+    /// "shippo-rust/" and the version of this crate.
+    pub fn user_agent<U>(mut self, user_agent: U) -> Self
+    where
+        U: ToString,
+    {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Pin the version of the API requests are handled with, for example to
+    /// `TESTED_API_VERSION`. By default the version set for the account is used.
+    /// FROM: https://goshippo.com/docs/api_versions
+    pub fn api_version<V>(mut self, api_version: V) -> Self
+    where
+        V: ToString,
+    {
+        self.api_version = Some(api_version.to_string());
+        self
+    }
+
+    /// Set how requests are retried, see `Shippo::with_retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Send requests with an existing client, so it shares the connection pool
+    /// with every other API client that uses it, for example:
+    /// `Shippo::builder(token).client(Client::new("cio")?)`.
+    /// The timeout, connect timeout, proxy, and user agent set on this builder
+    /// are ignored, since they are part of the pool.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the Shippo client.
+    pub fn build(self) -> Result<Shippo, ShippoError> {
+        let endpoint = match self.endpoint {
+            Some(e) => e,
+            None => env::var("SHIPPO_ENDPOINT").unwrap_or_else(|_| ENDPOINT.to_string()),
+        };
+        // Make sure the endpoint is valid now, rather than on the first request.
+        Url::parse(&endpoint)?;
+
+        let mut client = match self.client {
+            Some(c) => c,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                builder = builder.timeout(self.timeout.unwrap_or(DEFAULT_TIMEOUT));
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(proxy) = self.proxy {
+                    builder = builder.proxy(proxy);
+                }
+                builder = builder.user_agent(self.user_agent.unwrap_or_else(|| USER_AGENT.to_string()));
+
+                Client::from_builder("shippo", builder)?
+            }
+        };
+        if let Some(retry_policy) = self.retry_policy {
+            client = client.with_retry_policy(retry_policy);
+        }
+
+        Ok(Shippo {
+            token: self.token,
+            endpoint,
+            api_version: self.api_version,
+            max_label_cost: None,
+            deadline: None,
+
+            client: Arc::new(client),
+        })
+    }
+}
+
 impl Shippo {
     /// Create a new Shippo client struct. It takes a type that can convert into
     /// an &str (`String` or `Vec<u8>` for example). As long as the function is
     /// given a valid API Token your requests will work.
-    pub fn new<K>(token: K) -> Self
+    pub fn new<K>(token: K) -> Result<Self, ShippoError>
     where
         K: ToString,
     {
-        let client = Client::builder().build();
-        match client {
-            Ok(c) => Self {
-                token: token.to_string(),
+        Shippo::builder(token).build()
+    }
 
-                client: Arc::new(c),
-            },
-            Err(e) => panic!("creating client failed: {:?}", e),
+    /// Start building a Shippo client, to set timeouts, a proxy, or a different
+    /// endpoint.
+    pub fn builder<K>(token: K) -> ShippoBuilder
+    where
+        K: ToString,
+    {
+        ShippoBuilder {
+            token: token.to_string(),
+            endpoint: None,
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            user_agent: None,
+            api_version: None,
+            retry_policy: None,
+            client: None,
         }
     }
 
-    /// Create a new Shippo client struct from environment variables. It
-    /// takes a type that can convert into
-    /// an &str (`String` or `Vec<u8>` for example). As long as the function is
-    /// given a valid API Token and your requests will work.
-    pub fn new_from_env() -> Self {
-        let token = env::var("SHIPPO_API_TOKEN").unwrap();
+    /// Create a new Shippo client struct from the `SHIPPO_API_TOKEN` environment
+    /// variable. Returns an error if it is not set.
+    pub fn new_from_env() -> Result<Self, ShippoError> {
+        Shippo::new(token_from_env("SHIPPO_API_TOKEN")?)
+    }
+
+    /// Create a new Shippo client struct that uses the test token from the
+    /// `SHIPPO_TEST_API_TOKEN` environment variable. Returns an error if it is
+    /// not set. Labels bought in test mode are not charged for.
+    pub fn new_test_from_env() -> Result<Self, ShippoError> {
+        Shippo::new(token_from_env("SHIPPO_TEST_API_TOKEN")?)
+    }
+
+    /// Return a client that uses a different token, for example to make calls in
+    /// test mode with a client set up for live mode. It shares the connections,
+    /// endpoint, and retry policy of this client.
+    pub fn with_token<K>(&self, token: K) -> Self
+    where
+        K: ToString,
+    {
+        Shippo {
+            token: token.to_string(),
+            endpoint: self.endpoint.clone(),
+            api_version: self.api_version.clone(),
+            max_label_cost: self.max_label_cost.clone(),
+            deadline: self.deadline,
+            client: self.client.clone(),
+        }
+    }
+
+    /// Return the counters for the requests sent through this client's connection
+    /// pool, including by any other API clients it is shared with.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.client.metrics()
+    }
+
+    /// Return if the client uses a test token, so nothing it buys is charged for.
+    pub fn is_test(&self) -> bool {
+        self.token.starts_with("shippo_test_")
+    }
+
+    /// Fail any call that has not had a response by `deadline`, including the
+    /// time spent retrying, with `ShippoError::DeadlineExceeded`. This lets a job bound how
+    /// long it runs, even if Shippo hangs. Calls can also be canceled by dropping
+    /// them, no state is left behind.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Send a request, giving up if we pass the deadline.
+    async fn send(&self, request: Request) -> Result<reqwest::Response, ShippoError> {
+        let deadline = match self.deadline {
+            Some(d) => d,
+            None => return Ok(self.client.execute(request).await?),
+        };
+
+        let remaining = deadline.checked_duration_since(Instant::now()).ok_or(ShippoError::DeadlineExceeded)?;
+        match tokio::time::timeout(remaining, self.client.execute(request)).await {
+            Ok(resp) => Ok(resp?),
+            Err(_) => Err(ShippoError::DeadlineExceeded),
+        }
+    }
+
+    /// Refuse to buy labels that cost more than `max` in live mode, unless the
+    /// transaction sets `allow_over_max_cost`. Rates in a different currency
+    /// than `max` are refused too. Labels bought with
+    /// `create_shipping_label_from_shipment` are bought from the rate for the
+    /// same carrier account and service level instead, once its price is checked,
+    /// and `purchase_batch` checks every shipment in the batch before buying any
+    /// of them.
+    pub fn with_max_label_cost(mut self, max: Money) -> Self {
+        self.max_label_cost = Some(max);
+        self
+    }
+
+    /// Set the endpoint the client sends requests to. This defaults to the
+    /// production API, or the `SHIPPO_ENDPOINT` environment variable if it is set,
+    /// and is useful for testing against a fake server.
+    pub fn with_endpoint<E>(mut self, endpoint: E) -> Self
+    where
+        E: ToString,
+    {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    /// Set how requests are retried. By default, rate limited requests are retried
+    /// with exponential backoff and jitter, waiting as long as the `Retry-After`
+    /// header asks. Server errors are only retried for requests that are safe to
+    /// send twice, so we never buy a label twice.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.client = Arc::new((*self.client).clone().with_retry_policy(retry_policy));
+        self
+    }
+
+    /// Add a hook that is called around every request sent to Shippo, including
+    /// retries, for logging and metrics. The requests include our token in the
+    /// `Authorization` header, use `redact_headers` before logging them.
+    pub fn with_hook<H>(mut self, hook: H) -> Self
+    where
+        H: Hook + 'static,
+    {
+        self.client = Arc::new((*self.client).clone().with_hook(hook));
+        self
+    }
+
+    /// Send requests with a different transport, so tests can return canned
+    /// responses without a server or a real token.
+    pub fn with_transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.client = Arc::new((*self.client).clone().with_transport(transport));
+        self
+    }
 
-        Shippo::new(token)
+    fn request<B>(&self, method: Method, path: &str, body: B, query: Option<Vec<(&str, String)>>) -> Result<Request, ShippoError>
+    where
+        B: Serialize,
+    {
+        Ok(self.request_builder(method, path, body, query)?.build()?)
     }
 
-    fn request<B>(&self, method: Method, path: &str, body: B, query: Option<Vec<(&str, String)>>) -> Request
+    /// Build a request that creates something, sending the idempotency key if it
+    /// is set so Shippo does not create it twice if we retry.
+    fn idempotent_request<B>(&self, path: &str, body: B, idempotency_key: &str) -> Result<Request, ShippoError>
     where
         B: Serialize,
     {
-        let base = Url::parse(ENDPOINT).unwrap();
-        let url = base.join(path).unwrap();
+        let mut rb = self.request_builder(Method::POST, path, body, None)?;
+        if !idempotency_key.is_empty() {
+            // An invalid key is reported when the request is built.
+            rb = rb.header(IDEMPOTENCY_KEY, idempotency_key);
+        }
 
-        let bt = format!("ShippoToken {}", self.token);
-        let bearer = header::HeaderValue::from_str(&bt).unwrap();
+        Ok(rb.build()?)
+    }
 
-        // Set the default headers.
-        let mut headers = header::HeaderMap::new();
-        headers.append(header::AUTHORIZATION, bearer);
-        headers.append(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    fn request_builder<B>(&self, method: Method, path: &str, body: B, query: Option<Vec<(&str, String)>>) -> Result<RequestBuilder, ShippoError>
+    where
+        B: Serialize,
+    {
+        let base = Url::parse(&self.endpoint)?;
+        let url = base.join(path)?;
 
-        let mut rb = self.client.request(method.clone(), url).headers(headers);
+        // Set the default headers. An invalid token is reported when the request is built.
+        let mut rb = self
+            .client
+            .request(method.clone(), url)
+            .header(header::AUTHORIZATION, format!("ShippoToken {}", self.token))
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(api_version) = &self.api_version {
+            rb = rb.header(API_VERSION, api_version);
+        }
 
         match query {
             None => (),
@@ -105,260 +457,1157 @@ impl Shippo {
             rb = rb.json(&body);
         }
 
-        // Build the request.
-        rb.build().unwrap()
+        Ok(rb)
     }
 
-    /// List shipments.
+    /// List all the shipments matching the parameters, following the `next`
+    /// cursor until there are no more pages.
     /// FROM: https://goshippo.com/docs/reference#shipments-list
     /// A maximum date range of 90 days is permitted. Provided dates should be ISO 8601 UTC dates.
-    pub async fn list_shipments(&self) -> Result<Vec<Shipment>, APIError> {
-        // Build the request.
-        // TODO: paginate.
-        let request = self.request(Method::GET, "shipments", (), None);
+    pub async fn list_shipments(&self, params: &ShipmentListParams) -> Result<Vec<Shipment>, ShippoError> {
+        self.list_shipments_paginated(params, None).await
+    }
+
+    /// List the shipments matching the parameters, stopping once we have `max`
+    /// shipments, if it is set. The page size is set by `params.results`.
+    /// FROM: https://goshippo.com/docs/reference#shipments-list
+    pub async fn list_shipments_paginated(&self, params: &ShipmentListParams, max: Option<usize>) -> Result<Vec<Shipment>, ShippoError> {
+        self.list_all("shipments", params.to_query(), max).await
+    }
+
+    /// Stream all the shipments matching the parameters. Pages are only fetched
+    /// as the stream is consumed, so large result sets are never all in memory.
+    /// FROM: https://goshippo.com/docs/reference#shipments-list
+    pub fn stream_shipments(&self, params: &ShipmentListParams) -> impl Stream<Item = Result<Shipment, ShippoError>> + '_ {
+        self.stream_all("shipments", params.to_query())
+    }
+
+    /// Find the first shipment whose `metadata` is exactly `metadata`, paging
+    /// through the shipments until one is found. Store a token of your own in
+    /// the metadata when creating a shipment and this can recover it later,
+    /// for example after a crash, instead of creating it again.
+    /// FROM: https://goshippo.com/docs/reference#shipments-list
+    pub async fn find_shipment_by_metadata(&self, metadata: &str) -> Result<Option<Shipment>, ShippoError> {
+        let shipments = self.stream_shipments(&ShipmentListParams::default()).try_filter(|s| future::ready(s.metadata == metadata));
+        pin_mut!(shipments);
+        shipments.try_next().await
+    }
+
+    /// List every object at `path`, following the `next` cursor until there
+    /// are no more pages or we have `max` objects, if it is set.
+    async fn list_all<T>(&self, path: &str, query: Vec<(&str, String)>, max: Option<usize>) -> Result<Vec<T>, ShippoError>
+    where
+        T: DeserializeOwned,
+    {
+        let stream = self.stream_all(path, query);
+        match max {
+            Some(m) => stream.take(m).try_collect().await,
+            None => stream.try_collect().await,
+        }
+    }
+
+    /// Stream every object at `path`, fetching the page at the `next` cursor
+    /// once we have run out of objects from the last one.
+    fn stream_all<'a, T>(&'a self, path: &str, query: Vec<(&str, String)>) -> impl Stream<Item = Result<T, ShippoError>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+    {
+        // Build the request for the first page.
+        let first = self.request(Method::GET, path, (), Some(query));
+
+        stream::unfold((VecDeque::new(), Some(first)), move |(mut objects, mut next)| async move {
+            loop {
+                if let Some(object) = objects.pop_front() {
+                    return Some((Ok(object), (objects, next)));
+                }
+
+                // Once we are out of pages, or hit an error, the stream is done.
+                let request = match next.take()? {
+                    Ok(r) => r,
+                    Err(e) => return Some((Err(e), (objects, None))),
+                };
+                let r: ListResponse<T> = match self.get_page(request).await {
+                    Ok(r) => r,
+                    Err(e) => return Some((Err(e), (objects, None))),
+                };
+                objects.extend(r.results);
+
+                // The next cursor is the full URL for the next page, with the query
+                // parameters already set.
+                if !r.next.is_empty() {
+                    next = Some(self.request(Method::GET, &r.next, (), None));
+                }
+            }
+        })
+    }
 
-        let resp = self.client.execute(request).await.unwrap();
+    async fn get_page<T>(&self, request: Request) -> Result<ListResponse<T>, ShippoError>
+    where
+        T: DeserializeOwned,
+    {
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        let r: APIResponse = resp.json().await.unwrap();
-
-        Ok(r.shipments)
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
     /// Create a shipment.
     /// FROM: https://goshippo.com/docs/reference#shipments-create
-    pub async fn create_shipment(&self, ns: NewShipment) -> Result<Shipment, APIError> {
+    pub async fn create_shipment(&self, ns: NewShipment) -> Result<Shipment, ShippoError> {
         // Build the request.
-        let request = self.request(Method::POST, "shipments", ns, None);
+        let key = ns.idempotency_key.clone();
+        let request = self.idempotent_request("shipments", ns, &key)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
     /// Get a shipment.
     /// FROM: https://goshippo.com/docs/reference#shipments-retrieve
-    pub async fn get_shipment(&self, id: &str) -> Result<Shipment, APIError> {
+    pub async fn get_shipment(&self, id: &str) -> Result<Shipment, ShippoError> {
         // Build the request.
-        let request = self.request(Method::GET, &format!("shipments/{}", id), (), None);
+        let request = self.request(Method::GET, &format!("shipments/{}", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Create a pickup.
-    /// FROM: https://goshippo.com/docs/reference#pickups-create
-    pub async fn create_pickup(&self, np: NewPickup) -> Result<Pickup, APIError> {
+    /// List the rates for an existing shipment, with `amount_local` in the
+    /// given currency, for example the recipient's.
+    /// FROM: https://goshippo.com/docs/reference#rates-list
+    pub async fn get_rates_for_shipment(&self, shipment_id: &str, currency: &str) -> Result<Vec<Rate>, ShippoError> {
+        self.list_all(&format!("shipments/{}/rates/{}", shipment_id, currency), vec![("results", DEFAULT_PAGE_SIZE.to_string())], None)
+            .await
+    }
+
+    /// Create an address. If `validate` is true, Shippo will also validate the
+    /// address and set the `validation_results`.
+    /// FROM: https://goshippo.com/docs/reference#addresses-create
+    pub async fn create_address(&self, address: Address, validate: bool) -> Result<Address, ShippoError> {
         // Build the request.
-        let request = self.request(Method::POST, "pickups", np, None);
+        let request = self.request(Method::POST, "addresses", NewAddress { address, validate }, None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Create a customs item.
-    /// FROM: https://goshippo.com/docs/reference#customs-items-create
-    pub async fn create_customs_item(&self, c: CustomsItem) -> Result<CustomsItem, APIError> {
+    /// Get an address.
+    /// FROM: https://goshippo.com/docs/reference#addresses-retrieve
+    pub async fn get_address(&self, id: &str) -> Result<Address, ShippoError> {
         // Build the request.
-        let request = self.request(Method::POST, "customs/items/", c, None);
+        let request = self.request(Method::GET, &format!("addresses/{}", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
-            StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Create a shipping label based on a rate.
-    /// FROM: https://goshippo.com/docs/reference#transactions-create
-    pub async fn create_shipping_label_from_rate(&self, nt: NewTransaction) -> Result<Transaction, APIError> {
+    /// List all addresses.
+    /// FROM: https://goshippo.com/docs/reference#addresses-list
+    pub async fn list_addresses(&self) -> Result<Vec<Address>, ShippoError> {
+        self.list_all("addresses", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Validate an existing address. The result of the validation is in the
+    /// `validation_results` of the returned address.
+    /// FROM: https://goshippo.com/docs/reference#addresses-validate
+    pub async fn validate_address(&self, id: &str) -> Result<Address, ShippoError> {
         // Build the request.
-        let request = self.request(Method::POST, "transactions", nt, None);
+        let request = self.request(Method::GET, &format!("addresses/{}/validate", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
-            StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Get a shipping label.
-    /// FROM: https://goshippo.com/docs/reference#transactions-retrieve
-    pub async fn get_shipping_label(&self, id: &str) -> Result<Transaction, APIError> {
+    /// List all carrier accounts.
+    /// FROM: https://goshippo.com/docs/reference#carrier-accounts-list
+    pub async fn list_carrier_accounts(&self) -> Result<Vec<CarrierAccount>, ShippoError> {
+        self.list_all("carrier_accounts", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Get a carrier account.
+    /// FROM: https://goshippo.com/docs/reference#carrier-accounts-retrieve
+    pub async fn get_carrier_account(&self, id: &str) -> Result<CarrierAccount, ShippoError> {
         // Build the request.
-        let request = self.request(Method::GET, &format!("transactions/{}", id), (), None);
+        let request = self.request(Method::GET, &format!("carrier_accounts/{}", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        let status = &resp.status();
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
 
-        match &resp.json::<Transaction>().await {
-            Ok(v) => return Ok(v.clone()),
-            Err(e) => {
-                return Err(APIError {
-                    status_code: *status,
-                    // TODO: somehow get the body
-                    body: format!("{}", e),
-                });
-            }
-        }
+    /// Create a carrier account.
+    /// FROM: https://goshippo.com/docs/reference#carrier-accounts-create
+    pub async fn create_carrier_account(&self, ca: CarrierAccount) -> Result<CarrierAccount, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "carrier_accounts", ca, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// List shiping labels.
-    /// FROM: https://goshippo.com/docs/reference#transactions-list
-    pub async fn list_shipping_labels(&self) -> Result<Vec<Transaction>, APIError> {
+    /// Update a carrier account. This is how accounts are activated or
+    /// deactivated and moved in and out of test mode.
+    /// FROM: https://goshippo.com/docs/reference#carrier-accounts-update
+    pub async fn update_carrier_account(&self, ca: CarrierAccount) -> Result<CarrierAccount, ShippoError> {
         // Build the request.
-        // TODO: paginate.
-        let request = self.request(Method::GET, "transactions", (), None);
+        let request = self.request(Method::PUT, &format!("carrier_accounts/{}", ca.object_id), &ca, None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        let r: TransactionsAPIResponse = resp.json().await.unwrap();
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Create a pickup.
+    /// FROM: https://goshippo.com/docs/reference#pickups-create
+    pub async fn create_pickup(&self, np: NewPickup) -> Result<Pickup, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "pickups", np, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
 
-        Ok(r.transactions)
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Register a tracking webhook.
-    /// You can register your webhook(s) for a Shipment (and request the current status at the same time)
-    /// by POSTing to the tracking endpoint. This way Shippo will send HTTP notifications to your
-    /// track_updated webhook(s) whenever the status changes.
-    /// FROM: https://goshippo.com/docs/reference#tracks-create
-    pub async fn register_tracking_webhook(&self, carrier: &str, tracking_number: &str) -> Result<TrackingStatus, APIError> {
-        let mut body: HashMap<&str, &str> = HashMap::new();
-        body.insert("tracking_number", tracking_number);
-        body.insert("carrier", carrier);
+    /// Get a pickup.
+    /// FROM: https://goshippo.com/docs/reference#pickups-retrieve
+    pub async fn get_pickup(&self, id: &str) -> Result<Pickup, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("pickups/{}", id), (), None)?;
 
-        // Build the request
-        let request = self.request(Method::POST, "tracks", body, None);
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// List all pickups.
+    /// FROM: https://goshippo.com/docs/reference#pickups-list
+    pub async fn list_pickups(&self) -> Result<Vec<Pickup>, ShippoError> {
+        self.list_all("pickups", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Cancel a pickup. Not every carrier allows cancelling pickups through Shippo,
+    /// and it must be done before the pickup's `cancel_by_time`. If the carrier does
+    /// not allow it, this returns an error and you will need to contact the carrier
+    /// directly with the pickup's `confirmation_code`.
+    /// FROM: https://goshippo.com/docs/reference#pickups-cancel
+    pub async fn cancel_pickup(&self, id: &str) -> Result<Pickup, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, &format!("pickups/{}/cancel", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
+            StatusCode::ACCEPTED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Create a customs item.
+    /// FROM: https://goshippo.com/docs/reference#customs-items-create
+    pub async fn create_customs_item(&self, c: CustomsItem) -> Result<CustomsItem, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "customs/items/", c, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
             StatusCode::CREATED => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap_or_default())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
 
-    /// Request the tracking status of a shipment by sending a GET request.
-    /// FROM: https://goshippo.com/docs/reference#tracks-retrieve
-    pub async fn get_tracking_status(&self, carrier: &str, tracking_number: &str) -> Result<TrackingStatus, APIError> {
-        // Build the request
-        let request = self.request(Method::GET, &format!("tracks/{}/{}", carrier, tracking_number), (), None);
+    /// Get a customs item.
+    /// FROM: https://goshippo.com/docs/reference#customs-items-retrieve
+    pub async fn get_customs_item(&self, id: &str) -> Result<CustomsItem, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("customs/items/{}", id), (), None)?;
 
-        let resp = self.client.execute(request).await.unwrap();
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
-            s => {
-                return Err(APIError {
-                    status_code: s,
-                    body: resp.text().await.unwrap(),
-                })
-            }
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
         };
 
-        Ok(resp.json().await.unwrap_or_default())
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
-}
 
-/// Error type returned by our library.
-pub struct APIError {
-    pub status_code: StatusCode,
-    pub body: String,
-}
+    /// List all customs items.
+    /// FROM: https://goshippo.com/docs/reference#customs-items-list
+    pub async fn list_customs_items(&self) -> Result<Vec<CustomsItem>, ShippoError> {
+        self.list_all("customs/items", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
 
-impl fmt::Display for APIError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "APIError: status code -> {}, body -> {}", self.status_code.to_string(), self.body)
+    /// Create a customs declaration.
+    /// The `items` must be the object ids of customs items that were already created.
+    /// FROM: https://goshippo.com/docs/reference#customs-declarations-create
+    pub async fn create_customs_declaration(&self, c: CustomsDeclaration) -> Result<CustomsDeclaration, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "customs/declarations/", c, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
-}
 
-impl fmt::Debug for APIError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "APIError: status code -> {}, body -> {}", self.status_code.to_string(), self.body)
+    /// Get a customs declaration.
+    /// FROM: https://goshippo.com/docs/reference#customs-declarations-retrieve
+    pub async fn get_customs_declaration(&self, id: &str) -> Result<CustomsDeclaration, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("customs/declarations/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
     }
-}
 
-// This is important for other errors to wrap this one.
-impl error::Error for APIError {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        // Generic error, underlying cause isn't tracked.
-        None
+    /// List all customs declarations.
+    /// FROM: https://goshippo.com/docs/reference#customs-declarations-list
+    pub async fn list_customs_declarations(&self) -> Result<Vec<CustomsDeclaration>, ShippoError> {
+        self.list_all("customs/declarations", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Get a rate.
+    /// FROM: https://goshippo.com/docs/reference#rates-retrieve
+    pub async fn get_rate(&self, id: &str) -> Result<Rate, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("rates/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Create a shipping label based on a rate.
+    /// FROM: https://goshippo.com/docs/reference#transactions-create
+    pub async fn create_shipping_label_from_rate(&self, nt: NewTransaction) -> Result<Transaction, ShippoError> {
+        if let Some(max) = &self.max_label_cost {
+            if !self.is_test() && !nt.allow_over_max_cost {
+                let rate = self.get_rate(&nt.rate).await?;
+                check_label_cost(&nt.rate, rate.price(), max)?;
+            }
+        }
+
+        // Build the request.
+        let key = nt.idempotency_key.clone();
+        let request = self.idempotent_request("transactions", nt, &key)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Create a shipping label in one call, from a shipment and the service
+    /// level to buy, without creating the shipment and picking a rate first.
+    /// If the client has a maximum label cost, see `with_max_label_cost`, the
+    /// shipment is created and the label is bought from its rate instead, since
+    /// the instant call does not tell us the price up front. The shipment then
+    /// uses the idempotency key of the transaction with `-shipment` appended,
+    /// unless it has its own.
+    /// FROM: https://goshippo.com/docs/reference#transactions-create-instant
+    pub async fn create_shipping_label_from_shipment(&self, nit: NewInstantTransaction) -> Result<Transaction, ShippoError> {
+        if let Some(max) = &self.max_label_cost {
+            if !self.is_test() && !nit.allow_over_max_cost {
+                // Buy the label for the shipment we rated, so a retry with the
+                // same key does not leave another shipment behind.
+                let mut ns = nit.shipment;
+                if ns.idempotency_key.is_empty() && !nit.idempotency_key.is_empty() {
+                    ns.idempotency_key = format!("{}-shipment", nit.idempotency_key);
+                }
+                let shipment = self.create_shipment(ns).await?;
+                let rate = check_rate_cost(&shipment.rates, &nit.carrier_account, nit.servicelevel_token.as_str(), max)?;
+
+                return self
+                    .create_shipping_label_from_rate(NewTransaction {
+                        rate: rate.object_id.to_string(),
+                        metadata: nit.metadata,
+                        label_file_type: nit.label_file_type,
+                        r#async: nit.r#async,
+                        // We just checked the price.
+                        allow_over_max_cost: true,
+                        idempotency_key: nit.idempotency_key,
+                        ..Default::default()
+                    })
+                    .await;
+            }
+        }
+
+        // Build the request.
+        let key = nit.idempotency_key.clone();
+        let request = self.idempotent_request("transactions", nit, &key)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get a shipping label.
+    /// FROM: https://goshippo.com/docs/reference#transactions-retrieve
+    pub async fn get_shipping_label(&self, id: &str) -> Result<Transaction, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("transactions/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Download the label of a transaction, so it can be sent to a printer as is.
+    /// Shippo only makes a label in the format it was bought in, set
+    /// `label_file_type` on the transaction to get a ZPLII label for a thermal
+    /// printer, for example. Returns `ShippoError::LabelUnavailable` if the
+    /// transaction has no label yet or it is in another format.
+    /// Our token is only sent if the label is served by the API itself.
+    pub async fn download_label(&self, transaction: &Transaction, format: LabelFileType) -> Result<Bytes, ShippoError> {
+        let unavailable = || ShippoError::LabelUnavailable {
+            transaction: transaction.object_id.to_string(),
+            format: format.clone(),
+        };
+        if transaction.label_url.is_empty() {
+            return Err(unavailable());
+        }
+        // If the format is not set the label is in the default format for the
+        // account, which we have no way to check.
+        if matches!(&transaction.label_file_type, Some(f) if *f != format) {
+            return Err(unavailable());
+        }
+
+        let url = Url::parse(&transaction.label_url)?;
+        let mut rb = self.client.request(Method::GET, url.clone());
+        if url.origin() == Url::parse(&self.endpoint)?.origin() {
+            rb = rb.header(header::AUTHORIZATION, format!("ShippoToken {}", self.token));
+        }
+
+        let resp = self.send(rb.build()?).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(resp.bytes().await?)
+    }
+
+    /// List all the shiping labels matching the parameters, following the `next`
+    /// cursor until there are no more pages.
+    /// FROM: https://goshippo.com/docs/reference#transactions-list
+    pub async fn list_shipping_labels(&self, params: &TransactionListParams) -> Result<Vec<Transaction>, ShippoError> {
+        self.list_all("transactions", params.to_query(), None).await
+    }
+
+    /// List the shipping labels, `page_size` at a time, stopping once we have
+    /// `max` labels, if it is set.
+    /// FROM: https://goshippo.com/docs/reference#transactions-list
+    pub async fn list_shipping_labels_paginated(&self, page_size: u32, max: Option<usize>) -> Result<Vec<Transaction>, ShippoError> {
+        self.list_all("transactions", vec![("results", page_size.to_string())], max).await
+    }
+
+    /// Stream all the shipping labels. Pages are only fetched as the stream is consumed.
+    /// FROM: https://goshippo.com/docs/reference#transactions-list
+    pub fn stream_transactions(&self) -> impl Stream<Item = Result<Transaction, ShippoError>> + '_ {
+        self.stream_all("transactions", vec![("results", DEFAULT_PAGE_SIZE.to_string())])
+    }
+
+    /// Request a refund for a shipping label, this voids the label.
+    /// FROM: https://goshippo.com/docs/reference#refunds-create
+    pub async fn create_refund(&self, transaction_id: &str) -> Result<Refund, ShippoError> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("transaction", transaction_id);
+
+        // Build the request.
+        let request = self.request(Method::POST, "refunds", body, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get a refund.
+    /// FROM: https://goshippo.com/docs/reference#refunds-retrieve
+    pub async fn get_refund(&self, id: &str) -> Result<Refund, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("refunds/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// List all refunds.
+    /// FROM: https://goshippo.com/docs/reference#refunds-list
+    pub async fn list_refunds(&self) -> Result<Vec<Refund>, ShippoError> {
+        self.list_all("refunds", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Create a manifest, also known as a scan form, for all the shipping labels
+    /// for a carrier account on a shipment date.
+    /// FROM: https://goshippo.com/docs/reference#manifests-create
+    pub async fn create_manifest(&self, nm: NewManifest) -> Result<Manifest, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "manifests", nm, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get a manifest.
+    /// FROM: https://goshippo.com/docs/reference#manifests-retrieve
+    pub async fn get_manifest(&self, id: &str) -> Result<Manifest, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("manifests/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// List all manifests.
+    /// FROM: https://goshippo.com/docs/reference#manifests-list
+    pub async fn list_manifests(&self) -> Result<Vec<Manifest>, ShippoError> {
+        self.list_all("manifests", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Create a batch of shipments. Shippo validates the shipments in the
+    /// background, poll the batch with `get_batch` until it is `VALID`.
+    /// FROM: https://goshippo.com/docs/reference#batches-create
+    pub async fn create_batch(&self, nb: NewBatch) -> Result<Batch, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "batches", nb, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get a batch. This is how you poll for the status of a batch.
+    /// FROM: https://goshippo.com/docs/reference#batches-retrieve
+    pub async fn get_batch(&self, id: &str) -> Result<Batch, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("batches/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Add shipments to a batch.
+    /// FROM: https://goshippo.com/docs/reference#batches-add-shipments
+    pub async fn add_shipments_to_batch(&self, id: &str, shipments: Vec<NewBatchShipment>) -> Result<Batch, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, &format!("batches/{}/add_shipments", id), shipments, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Remove shipments from a batch, by the object ids of the batch shipments.
+    /// FROM: https://goshippo.com/docs/reference#batches-remove-shipments
+    pub async fn remove_shipments_from_batch(&self, id: &str, batch_shipment_ids: Vec<String>) -> Result<Batch, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, &format!("batches/{}/remove_shipments", id), batch_shipment_ids, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Purchase the labels for all the shipments in a batch. The batch must be
+    /// `VALID`. The labels are purchased in the background, poll the batch with
+    /// `get_batch` until it is `PURCHASED`.
+    ///
+    /// If the client has a maximum label cost, nothing is bought when any
+    /// shipment in the batch costs more than it. A batch like that can only be
+    /// bought with a client without a maximum.
+    /// FROM: https://goshippo.com/docs/reference#batches-purchase
+    pub async fn purchase_batch(&self, id: &str) -> Result<Batch, ShippoError> {
+        if let Some(max) = &self.max_label_cost {
+            if !self.is_test() {
+                self.check_batch_cost(id, max).await?;
+            }
+        }
+
+        // Build the request.
+        let request = self.request(Method::POST, &format!("batches/{}/purchase", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::ACCEPTED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Check every shipment in a batch that does not have a label yet against
+    /// the maximum label cost, following the pages of batch shipments.
+    async fn check_batch_cost(&self, id: &str, max: &Money) -> Result<(), ShippoError> {
+        let mut batch = self.get_batch(id).await?;
+        loop {
+            for bs in batch.batch_shipments.results.iter().filter(|bs| bs.transaction.is_empty()) {
+                let carrier_account = if bs.carrier_account.is_empty() { &batch.default_carrier_account } else { &bs.carrier_account };
+                let servicelevel_token = bs.servicelevel_token.as_ref().filter(|t| !t.as_str().is_empty()).unwrap_or(&batch.default_servicelevel_token);
+
+                let shipment = self.get_shipment(&bs.shipment).await?;
+                check_rate_cost(&shipment.rates, carrier_account, servicelevel_token.as_str(), max)?;
+            }
+
+            if batch.batch_shipments.next.is_empty() {
+                return Ok(());
+            }
+
+            // The next cursor is the full URL for the next page of batch shipments.
+            let request = self.request(Method::GET, &batch.batch_shipments.next, (), None)?;
+            let resp = self.send(request).await?;
+            match resp.status() {
+                StatusCode::OK => (),
+                s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+            };
+            batch = serde_json::from_str(&resp.text().await?)?;
+        }
+    }
+
+    /// Create an order.
+    /// FROM: https://goshippo.com/docs/reference#orders-create
+    pub async fn create_order(&self, no: NewOrder) -> Result<Order, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "orders", no, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get an order.
+    /// FROM: https://goshippo.com/docs/reference#orders-retrieve
+    pub async fn get_order(&self, id: &str) -> Result<Order, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("orders/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// List all orders.
+    /// FROM: https://goshippo.com/docs/reference#orders-list
+    pub async fn list_orders(&self) -> Result<Vec<Order>, ShippoError> {
+        self.list_all("orders", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Get live rates for shipping line items between two addresses, without
+    /// creating a shipment. This is useful to quote the cost of shipping before
+    /// committing to it.
+    /// FROM: https://goshippo.com/docs/reference#live-rates-create
+    pub async fn get_live_rates(&self, nlr: NewLiveRates) -> Result<Vec<LiveRate>, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "live-rates", nlr, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        let r: ListResponse<LiveRate> = serde_json::from_str(&resp.text().await?)?;
+
+        Ok(r.results)
+    }
+
+    /// Create a webhook, Shippo will POST events of the webhook's type to its url.
+    /// FROM: https://goshippo.com/docs/reference#webhooks-create
+    pub async fn create_webhook(&self, w: Webhook) -> Result<Webhook, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::POST, "webhooks", w, None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get a webhook.
+    /// FROM: https://goshippo.com/docs/reference#webhooks-retrieve
+    pub async fn get_webhook(&self, id: &str) -> Result<Webhook, ShippoError> {
+        // Build the request.
+        let request = self.request(Method::GET, &format!("webhooks/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// List all webhooks.
+    /// FROM: https://goshippo.com/docs/reference#webhooks-list
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>, ShippoError> {
+        self.list_all("webhooks", vec![("results", DEFAULT_PAGE_SIZE.to_string())], None).await
+    }
+
+    /// Delete a webhook.
+    /// FROM: https://goshippo.com/docs/reference#webhooks-delete
+    pub async fn delete_webhook(&self, id: &str) -> Result<(), ShippoError> {
+        // Build the request.
+        let request = self.request(Method::DELETE, &format!("webhooks/{}", id), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::NO_CONTENT => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(())
+    }
+
+    /// Register a tracking webhook.
+    /// You can register your webhook(s) for a Shipment (and request the current status at the same time)
+    /// by POSTing to the tracking endpoint. This way Shippo will send HTTP notifications to your
+    /// track_updated webhook(s) whenever the status changes.
+    /// FROM: https://goshippo.com/docs/reference#tracks-create
+    pub async fn register_tracking_webhook(&self, carrier: &str, tracking_number: &str) -> Result<TrackingStatus, ShippoError> {
+        self.create_track(carrier, tracking_number, "").await
+    }
+
+    /// Register a tracking webhook, unless it was already registered. The request
+    /// is sent with an idempotency key made from the carrier and tracking number,
+    /// so if it is sent again, for example when a job is retried, Shippo returns
+    /// the existing registration instead of making another one.
+    /// FROM: https://goshippo.com/docs/reference#tracks-create
+    pub async fn register_tracking_webhook_once(&self, carrier: &str, tracking_number: &str) -> Result<TrackingStatus, ShippoError> {
+        self.create_track(carrier, tracking_number, &format!("track-{}-{}", carrier, tracking_number)).await
+    }
+
+    async fn create_track(&self, carrier: &str, tracking_number: &str, idempotency_key: &str) -> Result<TrackingStatus, ShippoError> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("tracking_number", tracking_number);
+        body.insert("carrier", carrier);
+
+        // Build the request
+        let request = self.idempotent_request("tracks", body, idempotency_key)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Request the tracking status of a shipment by sending a GET request.
+    /// FROM: https://goshippo.com/docs/reference#tracks-retrieve
+    pub async fn get_tracking_status(&self, carrier: &str, tracking_number: &str) -> Result<TrackingStatus, ShippoError> {
+        // Build the request
+        let request = self.request(Method::GET, &format!("tracks/{}/{}", carrier, tracking_number), (), None)?;
+
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => return Err(ShippoError::Api { status: s, body: resp.text().await? }),
+        };
+
+        Ok(serde_json::from_str(&resp.text().await?)?)
+    }
+
+    /// Get the tracking status of many shipments, given as `(carrier, tracking_number)`,
+    /// sending a few requests at a time. The results are in the same order as the
+    /// shipments, so one failed lookup does not fail the others.
+    pub async fn get_tracking_statuses(&self, shipments: &[(&str, &str)]) -> Vec<Result<TrackingStatus, ShippoError>> {
+        stream::iter(shipments)
+            .map(|(carrier, tracking_number)| self.get_tracking_status(carrier, tracking_number))
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await
+    }
+}
+
+/// Error type returned by our library.
+#[derive(Debug)]
+pub enum ShippoError {
+    /// Sending the request or reading the response failed.
+    Http(reqwest::Error),
+    /// The API returned an unexpected status code.
+    Api { status: StatusCode, body: String },
+    /// The response body could not be deserialized.
+    Serde(serde_json::Error),
+    /// The endpoint or path for the request is not a valid URL.
+    InvalidUrl(url::ParseError),
+    /// The environment variable the client is configured from is not set.
+    MissingEnv(&'static str),
+    /// The label costs more than the client is allowed to spend in live mode,
+    /// see `Shippo::with_max_label_cost`. The price is `None` if the rate did not
+    /// have one we could read.
+    OverMaxCost { rate: String, price: Option<Money>, max: Money },
+    /// The call was not done by the deadline, see `Shippo::with_deadline`.
+    DeadlineExceeded,
+    /// The transaction has no label in the format asked for, either because
+    /// it has no label yet or because it was bought in another format.
+    LabelUnavailable { transaction: String, format: LabelFileType },
+}
+
+impl fmt::Display for ShippoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShippoError::Http(e) => write!(f, "ShippoError: http -> {}", e),
+            ShippoError::Api { status, body } => write!(f, "ShippoError: status code -> {}, body -> {}", status, body),
+            ShippoError::Serde(e) => write!(f, "ShippoError: deserializing response -> {}", e),
+            ShippoError::InvalidUrl(e) => write!(f, "ShippoError: invalid url -> {}", e),
+            ShippoError::MissingEnv(var) => write!(f, "ShippoError: environment variable {} is not set", var),
+            ShippoError::OverMaxCost { rate, price: Some(price), max } => write!(f, "ShippoError: rate {} costs {}, more than the maximum of {}", rate, price, max),
+            ShippoError::OverMaxCost { rate, price: None, max } => write!(f, "ShippoError: rate {} has no price to check against the maximum of {}", rate, max),
+            ShippoError::DeadlineExceeded => write!(f, "ShippoError: deadline exceeded"),
+            ShippoError::LabelUnavailable { transaction, format } => write!(f, "ShippoError: transaction {} has no {} label", transaction, format),
+        }
+    }
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for ShippoError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ShippoError::Http(e) => Some(e),
+            ShippoError::Api { .. } => None,
+            ShippoError::Serde(e) => Some(e),
+            ShippoError::InvalidUrl(e) => Some(e),
+            ShippoError::MissingEnv(_) | ShippoError::OverMaxCost { .. } | ShippoError::DeadlineExceeded | ShippoError::LabelUnavailable { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ShippoError {
+    fn from(e: reqwest::Error) -> Self {
+        ShippoError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for ShippoError {
+    fn from(e: serde_json::Error) -> Self {
+        ShippoError::Serde(e)
+    }
+}
+
+impl From<url::ParseError> for ShippoError {
+    fn from(e: url::ParseError) -> Self {
+        ShippoError::InvalidUrl(e)
+    }
+}
+
+impl ShippoError {
+    /// Return the details of an error returned by the API, if Shippo sent them
+    /// as JSON, for example which fields of an address are invalid.
+    pub fn details(&self) -> Option<ApiErrorDetails> {
+        match self {
+            ShippoError::Api { body, .. } => ApiErrorDetails::parse(body),
+            _ => None,
+        }
+    }
+}
+
+/// The details of an error returned by the API.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ApiErrorDetails {
+    /// A message about the request as a whole, e.g. "Not found.".
+    pub detail: String,
+    /// The fields that were invalid.
+    pub fields: Vec<FieldError>,
+}
+
+/// The errors for one field of a request.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldError {
+    /// The path to the field, e.g. "address_to.zip" or "parcels.0.weight".
+    /// Empty for errors that are not about one field.
+    pub field: String,
+    pub messages: Vec<String>,
+}
+
+impl ApiErrorDetails {
+    /// Parse an error body, either `{"detail": "..."}` or an object of fields to
+    /// their errors, which can be nested like the request. Returns `None` if
+    /// the body is not JSON in either shape.
+    pub fn parse(body: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(body).ok()?;
+        let object = value.as_object()?;
+
+        let mut details = ApiErrorDetails::default();
+        for (key, value) in object {
+            match (key.as_str(), value) {
+                ("detail", serde_json::Value::String(detail)) => details.detail = detail.to_string(),
+                // Errors that are not about one field.
+                ("__all__", v) | ("non_field_errors", v) => collect_field_errors("", v, &mut details.fields),
+                (k, v) => collect_field_errors(k, v, &mut details.fields),
+            }
+        }
+
+        if details.detail.is_empty() && details.fields.is_empty() {
+            return None;
+        }
+
+        Some(details)
+    }
+
+    /// Return every message, prefixed with the field it is for, so they can be
+    /// shown to people, e.g. "address_to.zip: Invalid zip code for country".
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        if !self.detail.is_empty() {
+            messages.push(self.detail.to_string());
+        }
+        for f in &self.fields {
+            for m in &f.messages {
+                if f.field.is_empty() {
+                    messages.push(m.to_string());
+                } else {
+                    messages.push(format!("{}: {}", f.field, m));
+                }
+            }
+        }
+        messages
+    }
+}
+
+impl fmt::Display for ApiErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.messages().join("; "))
+    }
+}
+
+/// Walk the errors for a field, adding the messages for it and any fields
+/// nested in it.
+fn collect_field_errors(field: &str, value: &serde_json::Value, errors: &mut Vec<FieldError>) {
+    let join = |key: &str| if field.is_empty() { key.to_string() } else { format!("{}.{}", field, key) };
+
+    match value {
+        serde_json::Value::String(m) => errors.push(FieldError {
+            field: field.to_string(),
+            messages: vec![m.to_string()],
+        }),
+        serde_json::Value::Array(items) => {
+            let messages: Vec<String> = items.iter().filter_map(|i| i.as_str().map(|m| m.to_string())).collect();
+            if !messages.is_empty() {
+                errors.push(FieldError { field: field.to_string(), messages });
+            }
+            // Lists of objects, like parcels, have the errors for each one.
+            for (i, item) in items.iter().enumerate() {
+                if item.is_object() {
+                    collect_field_errors(&join(&i.to_string()), item, errors);
+                }
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for (key, value) in fields {
+                collect_field_errors(&join(key), value, errors);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Error returned when building an object with missing or invalid fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildError {
+    /// The field that is missing or invalid.
+    pub field: &'static str,
+    /// Why the field is invalid.
+    pub reason: String,
+}
+
+impl BuildError {
+    fn new(field: &'static str, reason: &str) -> Self {
+        BuildError { field, reason: reason.to_string() }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BuildError: field -> {}, reason -> {}", self.field, self.reason)
+    }
+}
+
+impl error::Error for BuildError {}
+
+/// An amount of money in a currency. The API sends amounts as strings, use
+/// this to do math on them without floating point rounding errors.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: Decimal,
+    /// The ISO 4217 currency code, e.g. "USD" or "EUR".
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        Money {
+            amount,
+            currency: currency.trim().to_uppercase(),
+        }
+    }
+
+    /// Parse an amount as it is sent by the API, for example "5.50". Returns
+    /// `None` if the amount is empty or not a number.
+    pub fn parse(amount: &str, currency: &str) -> Option<Self> {
+        amount.trim().parse().ok().map(|a| Money::new(a, currency))
+    }
+
+    /// Returns if both amounts are in the same currency, so they can be compared.
+    pub fn same_currency(&self, other: &Money) -> bool {
+        self.currency == other.currency
+    }
+
+    /// Add two amounts, returning `None` if they are in different currencies.
+    pub fn checked_add(&self, other: &Money) -> Option<Money> {
+        if !self.same_currency(other) {
+            return None;
+        }
+
+        Some(Money::new(self.amount.checked_add(other.amount)?, &self.currency))
+    }
+}
+
+impl PartialOrd for Money {
+    /// Amounts in different currencies can not be compared.
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        if !self.same_currency(other) {
+            return None;
+        }
+
+        self.amount.partial_cmp(&other.amount)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amount.round_dp(2), self.currency)
     }
 }
 
@@ -386,6 +1635,114 @@ pub struct TransactionsAPIResponse {
     pub transactions: Vec<Transaction>,
 }
 
+/// The data type for a page of objects returned by a list endpoint.
+#[derive(Clone, Debug, Deserialize)]
+struct ListResponse<T> {
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize")]
+    next: String,
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+}
+
+/// The query parameters for listing shipments.
+/// FROM: https://goshippo.com/docs/reference#shipments-list
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShipmentListParams {
+    /// Only return shipments created after this time.
+    pub object_created_gt: Option<DateTime<Utc>>,
+    /// Only return shipments created at or after this time.
+    pub object_created_gte: Option<DateTime<Utc>>,
+    /// Only return shipments created before this time.
+    pub object_created_lt: Option<DateTime<Utc>>,
+    /// Only return shipments created at or before this time.
+    pub object_created_lte: Option<DateTime<Utc>>,
+    /// The number of shipments to return per page.
+    pub results: Option<u32>,
+}
+
+impl ShipmentListParams {
+    /// Return the parameters for the shipments created in the last `days` days.
+    /// Shippo only permits a date range of up to 90 days, so `days` is clamped
+    /// to `MAX_SHIPMENT_LIST_DAYS`.
+    pub fn created_in_last_days(days: i64) -> Self {
+        let days = days.clamp(0, MAX_SHIPMENT_LIST_DAYS);
+        ShipmentListParams {
+            object_created_gte: Some(Utc::now() - chrono::Duration::days(days)),
+            ..Default::default()
+        }
+    }
+
+    fn to_query(&self) -> Vec<(&str, String)> {
+        let dates = vec![
+            ("object_created_gt", self.object_created_gt),
+            ("object_created_gte", self.object_created_gte),
+            ("object_created_lt", self.object_created_lt),
+            ("object_created_lte", self.object_created_lte),
+        ];
+
+        let mut query: Vec<(&str, String)> = dates.into_iter().filter_map(|(k, v)| v.map(|d| (k, d.to_rfc3339_opts(SecondsFormat::Secs, true)))).collect();
+        query.push(("results", self.results.unwrap_or(DEFAULT_PAGE_SIZE).to_string()));
+
+        query
+    }
+}
+
+/// The query parameters for listing shipping labels.
+/// FROM: https://goshippo.com/docs/reference#transactions-list
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransactionListParams {
+    /// Only return labels with this status, e.g. `TransactionStatus::Success`.
+    pub object_status: Option<TransactionStatus>,
+    /// Only return labels with this tracking status.
+    pub tracking_status: Option<TrackingStatusCode>,
+    /// Only return labels created after this time.
+    pub object_created_gt: Option<DateTime<Utc>>,
+    /// Only return labels created at or after this time.
+    pub object_created_gte: Option<DateTime<Utc>>,
+    /// Only return labels created before this time.
+    pub object_created_lt: Option<DateTime<Utc>>,
+    /// Only return labels created at or before this time.
+    pub object_created_lte: Option<DateTime<Utc>>,
+    /// The number of labels to return per page.
+    pub results: Option<u32>,
+}
+
+impl TransactionListParams {
+    /// Return the parameters for the labels created in the last `days` days.
+    pub fn created_in_last_days(days: i64) -> Self {
+        TransactionListParams {
+            object_created_gte: Some(Utc::now() - chrono::Duration::days(days)),
+            ..Default::default()
+        }
+    }
+
+    /// Only return labels with this status.
+    pub fn with_status(mut self, status: TransactionStatus) -> Self {
+        self.object_status = Some(status);
+        self
+    }
+
+    fn to_query(&self) -> Vec<(&str, String)> {
+        let dates = vec![
+            ("object_created_gt", self.object_created_gt),
+            ("object_created_gte", self.object_created_gte),
+            ("object_created_lt", self.object_created_lt),
+            ("object_created_lte", self.object_created_lte),
+        ];
+
+        let mut query: Vec<(&str, String)> = dates.into_iter().filter_map(|(k, v)| v.map(|d| (k, d.to_rfc3339_opts(SecondsFormat::Secs, true)))).collect();
+        if let Some(status) = &self.object_status {
+            query.push(("object_status", status.to_string()));
+        }
+        if let Some(status) = &self.tracking_status {
+            query.push(("tracking_status", status.to_string()));
+        }
+        query.push(("results", self.results.unwrap_or(DEFAULT_PAGE_SIZE).to_string()));
+
+        query
+    }
+}
+
 /// The data type for a Shipment.
 /// FROM: https://goshippo.com/docs/reference#shipments
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -395,9 +1752,8 @@ pub struct Shipment {
     /// shipments have been processed successfully, meaning that rate
     /// generation has concluded. "Error" does not occur currently and is
     /// reserved for future use.
-    /// "WAITING" | "QUEUED" | "SUCCESS" | "ERROR"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub status: String,
+    #[serde(default)]
+    pub status: ShipmentStatus,
     /// Date and time of Shipment creation.
     pub object_created: DateTime<Utc>,
     /// Date and time of last Shipment update.
@@ -442,6 +1798,77 @@ pub struct Shipment {
     /// Indicates whether the object has been created in test mode.
     #[serde(default)]
     pub test: bool,
+    /// Fields returned by Shippo that this crate does not know about yet.
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Shipment {
+    /// Return the cheapest of the shipment's rates, see `RateSelection::cheapest`.
+    pub fn cheapest(&self) -> Option<&Rate> {
+        self.rates.cheapest()
+    }
+
+    /// Return the fastest of the shipment's rates, see `RateSelection::fastest`.
+    pub fn fastest(&self) -> Option<&Rate> {
+        self.rates.fastest()
+    }
+
+    /// Return the best value of the shipment's rates, see `RateSelection::best_value`.
+    pub fn best_value(&self) -> Option<&Rate> {
+        self.rates.best_value()
+    }
+}
+
+/// Helpers for picking a rate out of a list of rates, for example the `rates`
+/// of a shipment.
+pub trait RateSelection {
+    /// Return the rate with the lowest `amount`. Rates without a price are skipped.
+    fn cheapest(&self) -> Option<&Rate>;
+
+    /// Return the rate with the fewest `estimated_days`, or the cheapest of
+    /// those if there is a tie. Rates without an estimate are skipped.
+    fn fastest(&self) -> Option<&Rate>;
+
+    /// Return the rate Shippo marked as the best value, falling back to the
+    /// cheapest rate if none is marked.
+    fn best_value(&self) -> Option<&Rate>;
+
+    /// Return the rates from any of the given providers, e.g. `&["USPS", "UPS"]`,
+    /// ignoring case.
+    fn filter_by_provider(&self, providers: &[&str]) -> Vec<Rate>;
+
+    /// Return the rates for any of the given service levels.
+    fn filter_by_service_level(&self, service_levels: &[ServiceLevelToken]) -> Vec<Rate>;
+}
+
+impl RateSelection for [Rate] {
+    fn cheapest(&self) -> Option<&Rate> {
+        self.iter().filter_map(|r| r.amount_as_decimal().map(|a| (a, r))).min_by(|a, b| a.0.cmp(&b.0)).map(|(_, r)| r)
+    }
+
+    fn fastest(&self) -> Option<&Rate> {
+        self.iter()
+            .filter_map(|r| r.estimated_days.map(|d| (d, r.amount_as_decimal(), r)))
+            // Rates without a price sort last among those with the same estimate.
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.is_none().cmp(&b.1.is_none())).then_with(|| a.1.cmp(&b.1)))
+            .map(|(_, _, r)| r)
+    }
+
+    fn best_value(&self) -> Option<&Rate> {
+        // The attributes are only set by Shippo, so we can not tell the best value
+        // ourselves.
+        self.iter().find(|r| r.attributes.iter().any(|a| a == "BESTVALUE")).or_else(|| self.cheapest())
+    }
+
+    fn filter_by_provider(&self, providers: &[&str]) -> Vec<Rate> {
+        self.iter().filter(|r| providers.iter().any(|p| p.eq_ignore_ascii_case(&r.provider))).cloned().collect()
+    }
+
+    fn filter_by_service_level(&self, service_levels: &[ServiceLevelToken]) -> Vec<Rate> {
+        self.iter().filter(|r| service_levels.contains(&r.servicelevel.token)).cloned().collect()
+    }
 }
 
 /// The data type for an address.
@@ -508,9 +1935,33 @@ pub struct Address {
     /// messages(array).
     #[serde(default)]
     pub validation_results: ValidationResults,
+    /// Fields returned by Shippo that this crate does not know about yet.
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// The data type for creating an address.
+#[derive(Clone, Debug, Serialize)]
+struct NewAddress {
+    #[serde(flatten)]
+    address: Address,
+    /// Validate the address when it is created.
+    #[serde(skip_serializing_if = "is_false")]
+    validate: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
 }
 
 impl Address {
+    /// Returns if the address was validated and found to be deliverable.
+    pub fn is_valid(&self) -> bool {
+        self.validation_results.is_valid
+    }
+
+    /// Return the address formatted on multiple lines.
     pub fn formatted(&self) -> String {
         let street = format!("{}\n{}", self.street1, self.street2);
         let mut zip = self.zip.to_string();
@@ -523,6 +1974,92 @@ impl Address {
             .trim()
             .to_string()
     }
+
+    /// Start building an address.
+    pub fn builder() -> AddressBuilder {
+        AddressBuilder { address: Default::default() }
+    }
+}
+
+/// Builder for an address, returned by `Address::builder`.
+#[derive(Clone, Debug)]
+pub struct AddressBuilder {
+    address: Address,
+}
+
+impl AddressBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.address.name = name.to_string();
+        self
+    }
+
+    pub fn company(mut self, company: &str) -> Self {
+        self.address.company = company.to_string();
+        self
+    }
+
+    pub fn street1(mut self, street1: &str) -> Self {
+        self.address.street1 = street1.to_string();
+        self
+    }
+
+    pub fn street2(mut self, street2: &str) -> Self {
+        self.address.street2 = street2.to_string();
+        self
+    }
+
+    pub fn city(mut self, city: &str) -> Self {
+        self.address.city = city.to_string();
+        self
+    }
+
+    pub fn state(mut self, state: &str) -> Self {
+        self.address.state = state.to_string();
+        self
+    }
+
+    pub fn zip(mut self, zip: &str) -> Self {
+        self.address.zip = zip.to_string();
+        self
+    }
+
+    /// Set the country, as an ISO 3166-1 alpha-2 code, for example "US".
+    pub fn country(mut self, country: &str) -> Self {
+        self.address.country = country.trim().to_uppercase();
+        self
+    }
+
+    pub fn phone(mut self, phone: &str) -> Self {
+        self.address.phone = phone.to_string();
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.address.email = email.to_string();
+        self
+    }
+
+    /// Build the address, making sure the fields Shippo needs to buy a label are set.
+    pub fn build(self) -> Result<Address, BuildError> {
+        let a = &self.address;
+        if a.name.trim().is_empty() && a.company.trim().is_empty() {
+            return Err(BuildError::new("name", "either a name or a company is required"));
+        }
+        if a.street1.trim().is_empty() {
+            return Err(BuildError::new("street1", "is required"));
+        }
+        if a.city.trim().is_empty() {
+            return Err(BuildError::new("city", "is required"));
+        }
+        if a.country.len() != 2 || !a.country.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(BuildError::new("country", &format!("`{}` is not an ISO 3166-1 alpha-2 country code", a.country)));
+        }
+        if a.country == "US" && (a.state.trim().is_empty() || a.zip.trim().is_empty()) {
+            return Err(BuildError::new("state", "a state and zip are required for addresses in the US"));
+        }
+
+        Ok(self.address)
+    }
 }
 
 /// The data type for a parcel.
@@ -531,9 +2068,8 @@ impl Address {
 pub struct Parcel {
     /// A Parcel will only be valid when all required values have been sent and
     /// validated successfully.
-    /// "VALID"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub object_state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_state: Option<ObjectState>,
     /// Date and time of Parcel creation.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub object_created: Option<DateTime<Utc>>,
@@ -550,6 +2086,11 @@ pub struct Parcel {
     /// Username of the user who created the Parcel object.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub object_owner: String,
+    /// A carrier's standard packaging, for example a USPS flat rate box. If it is
+    /// set, the dimensions of the template are used instead of the length,
+    /// width, and height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<ParcelTemplate>,
     /// Length of the Parcel. Up to six digits in front and four digits after
     /// the decimal separator are accepted.
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -563,17 +2104,15 @@ pub struct Parcel {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub height: String,
     /// The unit used for length, width and height.
-    /// "cm" | "in" | "ft" | "mm" | "m" | "yd"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub distance_unit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_unit: Option<DistanceUnit>,
     /// Weight of the parcel. Up to six digits in front and four digits after
     /// the decimal separator are accepted.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub weight: String,
     /// The unit used for weight.
-    /// "g" | "oz" | "lb" | "kg"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub mass_unit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass_unit: Option<MassUnit>,
     /// A string of up to 100 characters that can be filled with any additional
     /// information you want to attach to the object.
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -583,6 +2122,78 @@ pub struct Parcel {
     pub test: bool,
 }
 
+impl Parcel {
+    /// Start building a parcel.
+    pub fn builder() -> ParcelBuilder {
+        ParcelBuilder { parcel: Default::default() }
+    }
+}
+
+/// Builder for a parcel, returned by `Parcel::builder`.
+#[derive(Clone, Debug)]
+pub struct ParcelBuilder {
+    parcel: Parcel,
+}
+
+impl ParcelBuilder {
+    /// Set the length, width, and height of the parcel, and the unit they are in.
+    pub fn dimensions(mut self, length: f64, width: f64, height: f64, distance_unit: DistanceUnit) -> Self {
+        self.parcel.length = length.to_string();
+        self.parcel.width = width.to_string();
+        self.parcel.height = height.to_string();
+        self.parcel.distance_unit = Some(distance_unit);
+        self
+    }
+
+    /// Set the weight of the parcel, and the unit it is in.
+    pub fn weight(mut self, weight: f64, mass_unit: MassUnit) -> Self {
+        self.parcel.weight = weight.to_string();
+        self.parcel.mass_unit = Some(mass_unit);
+        self
+    }
+
+    /// Use a carrier's standard packaging, so the dimensions do not need to be set.
+    pub fn template(mut self, template: ParcelTemplate) -> Self {
+        self.parcel.template = Some(template);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: &str) -> Self {
+        self.parcel.metadata = metadata.to_string();
+        self
+    }
+
+    /// Build the parcel, making sure the weight and either the dimensions or a
+    /// template are set, in units Shippo understands.
+    pub fn build(self) -> Result<Parcel, BuildError> {
+        let p = &self.parcel;
+        let mut numbers = vec![("weight", &p.weight)];
+        if p.template.is_none() {
+            numbers.extend_from_slice(&[("length", &p.length), ("width", &p.width), ("height", &p.height)]);
+        }
+        for (field, value) in numbers {
+            match value.parse::<f64>() {
+                Ok(v) if v > 0.0 => (),
+                _ => return Err(BuildError::new(field, "must be set to a number greater than zero")),
+            }
+        }
+        match (&p.template, &p.distance_unit) {
+            (Some(ParcelTemplate::Other(t)), _) => return Err(BuildError::new("template", &format!("`{}` is not a template Shippo knows", t))),
+            (Some(_), _) => (),
+            (None, None) => return Err(BuildError::new("distance_unit", "must be set")),
+            (None, Some(DistanceUnit::Other(u))) => return Err(BuildError::new("distance_unit", &format!("`{}` is not a unit Shippo knows", u))),
+            (None, Some(_)) => (),
+        }
+        match &p.mass_unit {
+            None => return Err(BuildError::new("mass_unit", "must be set")),
+            Some(MassUnit::Other(u)) => return Err(BuildError::new("mass_unit", &format!("`{}` is not a unit Shippo knows", u))),
+            Some(_) => (),
+        }
+
+        Ok(self.parcel)
+    }
+}
+
 /// The data type for a rate.
 /// A rate is an available service of a shipping provider for a given shipment,
 /// typically including the price and transit time.
@@ -646,9 +2257,52 @@ pub struct Rate {
     /// Object ID of the carrier account that has been used to retrieve the rate.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub carrier_account: String,
+    /// The duties and taxes the recipient is expected to pay when an
+    /// international shipment clears customs, if the carrier gives an estimate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duties_and_taxes: Option<DutiesAndTaxes>,
     /// Indicates whether the object has been created in test mode.
     #[serde(default)]
     pub test: bool,
+    /// Fields returned by Shippo that this crate does not know about yet.
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Rate {
+    /// Return the customs charges the recipient should expect to pay, if the
+    /// carrier gave an estimate.
+    pub fn estimated_customs_charges(&self) -> Option<Money> {
+        self.duties_and_taxes.as_ref()?.total()
+    }
+
+    /// Return the landed cost of the rate: the price of the rate plus the
+    /// estimated customs charges. Returns `None` if there is no estimate or it
+    /// is in a different currency than the price.
+    pub fn landed_cost(&self) -> Option<Money> {
+        self.price()?.checked_add(&self.estimated_customs_charges()?)
+    }
+
+    /// Return the price of the rate in the sender's currency.
+    pub fn amount_as_decimal(&self) -> Option<Decimal> {
+        self.amount.parse().ok()
+    }
+
+    /// Return the price of the rate in the recipient's currency.
+    pub fn amount_local_as_decimal(&self) -> Option<Decimal> {
+        self.amount_local.parse().ok()
+    }
+
+    /// Return the price of the rate, with its currency, in the sender's currency.
+    pub fn price(&self) -> Option<Money> {
+        Money::parse(&self.amount, &self.currency)
+    }
+
+    /// Return the price of the rate, with its currency, in the recipient's currency.
+    pub fn price_local(&self) -> Option<Money> {
+        Money::parse(&self.amount_local, &self.currency_local)
+    }
 }
 
 /// The service level data type.
@@ -661,28 +2315,357 @@ pub struct ServiceLevel {
     /// These names vary depending on the provider.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
-    /// Token of the Rate's servicelevel, e.g. "usps_priority" or "fedex_ground".
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub token: String,
+    /// Token of the Rate's servicelevel, e.g. `ServiceLevelToken::UspsPriority`.
+    #[serde(default)]
+    pub token: ServiceLevelToken,
     /// Further clarification of the service.
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub terms: String,
+    pub terms: String,
+}
+
+/// The estimated duties and taxes for an international rate.
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct DutiesAndTaxes {
+    /// Estimated import duties.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub duties: String,
+    /// Estimated taxes, for example VAT.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub taxes: String,
+    /// Estimated fees charged by the carrier for clearing customs.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub fees: String,
+    /// Currency of the estimates. The official ISO 4217 currency codes are
+    /// used, e.g. "USD" or "EUR".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+}
+
+impl DutiesAndTaxes {
+    /// Return the estimated duties, with their currency.
+    pub fn duties(&self) -> Option<Money> {
+        Money::parse(&self.duties, &self.currency)
+    }
+
+    /// Return the estimated taxes, with their currency.
+    pub fn taxes(&self) -> Option<Money> {
+        Money::parse(&self.taxes, &self.currency)
+    }
+
+    /// Return the estimated fees, with their currency.
+    pub fn fees(&self) -> Option<Money> {
+        Money::parse(&self.fees, &self.currency)
+    }
+
+    /// Return the sum of the duties, taxes, and fees that are set, or `None`
+    /// if none of them are.
+    pub fn total(&self) -> Option<Money> {
+        let mut amounts = vec![self.duties(), self.taxes(), self.fees()].into_iter().flatten();
+        let first = amounts.next()?;
+        amounts.try_fold(first, |total, m| total.checked_add(&m))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewShipment {
+    /// Address object that should be used as sender Address.
+    #[serde(default)]
+    pub address_from: Address,
+    /// Address object that should be used as recipient Address.
+    #[serde(default)]
+    pub address_to: Address,
+    /// Address object where the shipment will be sent back to if it is not
+    /// delivered (Only available for UPS, USPS, and Fedex shipments).
+    /// If this field is not set, your shipments will be returned to the address_from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_return: Option<Address>,
+    /// Parcel objects to be shipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parcels: Vec<Parcel>,
+    /// Customs Declarations object for an international shipment. It is created
+    /// along with the shipment, so it does not need to be created first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customs_declaration: Option<CustomsDeclaration>,
+    /// ID of the Order the shipment is for, so the label purchased for the
+    /// shipment is linked back to the order.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub order: String,
+    /// Additional services and options for the shipment.
+    #[serde(default, skip_serializing_if = "ShipmentExtra::is_empty")]
+    pub extra: ShipmentExtra,
+    /// A unique key for this request, for example built from the ID of the job
+    /// that sends it. If the request is sent again with the same key, Shippo
+    /// returns the shipment it already created instead of creating another one.
+    #[serde(skip)]
+    pub idempotency_key: String,
+}
+
+/// Additional services and options for a shipment.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShipmentExtra {
+    /// Makes the label a prepaid return label. Keep `address_from` as our address
+    /// and `address_to` as the customer's, the carrier swaps them on the label.
+    /// Return labels are only charged for when they are used.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_return: bool,
+    /// Insure the shipment, through Shippo or the carrier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insurance: Option<Insurance>,
+    /// Request a signature on delivery.
+    /// "STANDARD" | "ADULT" | "CERTIFIED" | "INDIRECT" | "CARRIER_CONFIRMATION"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub signature_confirmation: String,
+    /// Deliver the shipment on Saturday, for carriers and service levels that support it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub saturday_delivery: bool,
+    /// Buy the label even if the carrier fails to validate the address.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bypass_address_validation: bool,
+    /// Printed on the label, for example an order number. Not all carriers support it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reference_1: String,
+    /// Printed on the label under `reference_1`. Not all carriers support it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reference_2: String,
+    /// Bill the shipment to another account, for example the customer's UPS or
+    /// FedEx account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billing: Option<Billing>,
+    /// Collect payment from the recipient on delivery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cod: Option<Cod>,
+    /// Declare dry ice in the shipment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_ice: Option<DryIce>,
+    /// Declare alcohol in the shipment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alcohol: Option<Alcohol>,
+}
+
+/// Who pays for a shipment, if it is not the account buying the label.
+/// Only supported by UPS and FedEx.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Billing {
+    /// "SENDER" | "RECIPIENT" | "THIRD_PARTY" | "THIRD_PARTY_CONSIGNEE" | "COLLECT"
+    #[serde(rename = "type")]
+    pub billing_type: String,
+    /// The carrier account number to bill.
+    pub account: String,
+    /// The zip code the account is registered to.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub zip: String,
+    /// The country the account is registered to, e.g. "US".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub country: String,
+    /// The participation code, for UPS accounts that require one.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub participation_code: String,
+}
+
+/// Collect on delivery.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cod {
+    /// The amount to collect, e.g. "50.00".
+    pub amount: String,
+    /// The currency of the amount, e.g. "USD".
+    pub currency: String,
+    /// "SECURED_FUNDS" | "CASH" | "ANY"
+    pub payment_method: String,
+}
+
+/// Dry ice in a shipment.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DryIce {
+    #[serde(default)]
+    pub contains_dry_ice: bool,
+    /// The weight of the dry ice in kilograms.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub weight: String,
+}
+
+/// Alcohol in a shipment.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Alcohol {
+    #[serde(default)]
+    pub contains_alcohol: bool,
+    /// "licensee" | "consumer"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub recipient_type: String,
+}
+
+/// Insurance for a shipment.
+/// FROM: https://goshippo.com/docs/reference#shipment-extras
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Insurance {
+    /// The amount to insure the shipment for, e.g. "200".
+    pub amount: String,
+    /// The currency of the amount, e.g. "USD".
+    pub currency: String,
+    /// A description of the contents of the shipment.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content: String,
+    /// Buy the insurance from the carrier instead of Shippo.
+    /// "FEDEX" | "UPS" | "ONTRAC"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub provider: String,
+}
+
+impl ShipmentExtra {
+    /// Returns if no extras are set, so we can leave them out of the request.
+    pub fn is_empty(&self) -> bool {
+        *self == Default::default()
+    }
+}
+
+impl NewShipment {
+    /// Start building a shipment.
+    pub fn builder() -> NewShipmentBuilder {
+        NewShipmentBuilder { shipment: Default::default() }
+    }
+}
+
+/// Builder for a new shipment, returned by `NewShipment::builder`.
+#[derive(Clone, Debug)]
+pub struct NewShipmentBuilder {
+    shipment: NewShipment,
+}
+
+impl NewShipmentBuilder {
+    pub fn address_from(mut self, address: Address) -> Self {
+        self.shipment.address_from = address;
+        self
+    }
+
+    pub fn address_to(mut self, address: Address) -> Self {
+        self.shipment.address_to = address;
+        self
+    }
+
+    /// Set where the shipment is sent back to if it can not be delivered,
+    /// instead of `address_from`.
+    pub fn address_return(mut self, address: Address) -> Self {
+        self.shipment.address_return = Some(address);
+        self
+    }
+
+    /// Add a parcel to the shipment.
+    pub fn parcel(mut self, parcel: Parcel) -> Self {
+        self.shipment.parcels.push(parcel);
+        self
+    }
+
+    pub fn customs_declaration(mut self, customs_declaration: CustomsDeclaration) -> Self {
+        self.shipment.customs_declaration = Some(customs_declaration);
+        self
+    }
+
+    pub fn order(mut self, order: &str) -> Self {
+        self.shipment.order = order.to_string();
+        self
+    }
+
+    /// Make the label a prepaid return label, see `ShipmentExtra::is_return`.
+    pub fn is_return(mut self, is_return: bool) -> Self {
+        self.shipment.extra.is_return = is_return;
+        self
+    }
+
+    /// Set the additional services and options for the shipment.
+    pub fn extra(mut self, extra: ShipmentExtra) -> Self {
+        self.shipment.extra = extra;
+        self
+    }
+
+    /// Set the idempotency key, see `NewShipment::idempotency_key`.
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.shipment.idempotency_key = key.to_string();
+        self
+    }
+
+    /// Build the shipment, making sure it has both addresses, at least one parcel,
+    /// and a customs declaration if it is international.
+    pub fn build(self) -> Result<NewShipment, BuildError> {
+        let s = &self.shipment;
+        if s.address_from.country.is_empty() {
+            return Err(BuildError::new("address_from", "is required"));
+        }
+        if s.address_to.country.is_empty() {
+            return Err(BuildError::new("address_to", "is required"));
+        }
+        if s.parcels.is_empty() {
+            return Err(BuildError::new("parcels", "at least one parcel is required"));
+        }
+        if s.address_from.country != s.address_to.country && s.customs_declaration.is_none() {
+            return Err(BuildError::new("customs_declaration", "is required for international shipments"));
+        }
+
+        Ok(self.shipment)
+    }
+}
+
+/// The data type for a webhook.
+/// FROM: https://goshippo.com/docs/reference#webhooks
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Webhook {
+    /// Unique identifier of the given Webhook object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// The URL Shippo sends the events to.
+    pub url: String,
+    /// The type of event to send to the URL.
+    /// "track_updated" | "transaction_created" | "transaction_updated" | "batch_created" | "batch_purchased" | "all"
+    pub event: String,
+    /// Whether the webhook is sent events for objects created in test mode.
+    #[serde(default)]
+    pub is_test: bool,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
 }
 
+/// The data type for a carrier account.
+/// FROM: https://goshippo.com/docs/reference#carrier-accounts
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct NewShipment {
-    /// Address object that should be used as sender Address.
+pub struct CarrierAccount {
+    /// Unique identifier of the given CarrierAccount object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Username of the user who created the CarrierAccount object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
+    /// Name of the carrier. Only one of your CarrierAccounts per carrier can
+    /// be active at the same time.
+    /// "usps" | "fedex" | "ups" | "dhl_express" | ...
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub carrier: String,
+    /// Unique identifier of the account. Please check the carrier accounts
+    /// tutorial page for the `account_id` per carrier.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub account_id: String,
+    /// An array of additional parameters for the account, such as the API
+    /// credentials or the account's address. These differ per carrier.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub parameters: HashMap<String, serde_json::Value>,
+    /// Determines whether the account is enabled.
     #[serde(default)]
-    pub address_from: Address,
-    /// Address object that should be used as recipient Address.
+    pub active: bool,
+    /// Indicates whether the account is used in test mode.
     #[serde(default)]
-    pub address_to: Address,
-    /// Parcel objects to be shipped.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub parcels: Vec<Parcel>,
-    /// Customs Declarations object for an international shipment.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub customs_declaration: Option<CustomsDeclaration>,
+    pub test: bool,
+    /// A string of up to 100 characters that can be filled with any additional
+    /// information you want to attach to the object.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
 }
 
 /// The data type for a pickup.
@@ -723,8 +2706,8 @@ pub struct Pickup {
     pub confirmed_end_time: Option<DateTime<Utc>>,
     /// The latest time to cancel a pickup.
     /// Expressed in the timezone specified in the response.
-    /// To cancel a pickup, you will need to contact the carrier directly.
-    /// The ability to cancel a pickup through Shippo may be released in future iterations.
+    /// Pickups can be cancelled with `Shippo::cancel_pickup` for carriers that
+    /// allow it, otherwise you will need to contact the carrier directly.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cancel_by_time: Option<DateTime<Utc>>,
     /// Indicates the status of the pickup.
@@ -732,14 +2715,15 @@ pub struct Pickup {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub status: String,
     /// Pickup's confirmation code returned by the carrier.
-    /// To edit or cancel a pickup, you will need to contact USPS or DHL Express directly
-    /// and provide your confirmation_code.
+    /// To edit a pickup, or cancel one the carrier does not let us cancel through
+    /// Shippo, you will need to contact USPS or DHL Express directly and provide
+    /// your confirmation_code.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub confirmation_code: String,
     /// The pickup time windows will be in the time zone specified here, not UTC.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub timezone: String,
-    /// An array containing strings of any messages generated during validation.
+    /// Any messages generated during validation, each with a source, code, and text.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub messages: Vec<Message>,
     /// A string of up to 100 characters that can be filled with any additional
@@ -804,14 +2788,12 @@ pub struct Transaction {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub object_owner: String,
     /// Indicates the status of the Transaction.
-    /// "WAITING" | "QUEUED" | "SUCCESS" | "ERROR" | "REFUNDED" | "REFUNDPENDING" | "REFUNDREJECTED"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub status: String,
+    #[serde(default)]
+    pub status: TransactionStatus,
     /// Indicates the validity of the Transaction object based on the given data,
     /// regardless of what the corresponding carrier returns.
-    /// "VALID" | "INVALID"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub object_state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_state: Option<ObjectState>,
     /// ID of the Rate object for which a Label has to be obtained.
     /// Please note that only rates that are not older than 7 days can be purchased
     /// in order to ensure up-to-date pricing.
@@ -822,17 +2804,15 @@ pub struct Transaction {
     pub metadata: String,
     /// Specify the label file format for this label.
     /// If you don't specify this value, the API will default to your default file format that you can set on the settings page.
-    /// "PNG" | "PNG_2.3x7.5" | "PDF" | "PDF_2.3x7.5" | "PDF_4x6" | "PDF_4x8" | "PDF_A4" | "PDF_A6"
-    /// "ZPLII"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub label_file_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_file_type: Option<LabelFileType>,
     /// The carrier-specific tracking number that can be used to track the Shipment.
     /// A value will only be returned if the Rate is for a trackable Shipment and if the Transactions has been processed successfully.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub tracking_number: String,
-    /// Indicates the high level status of the shipment: 'UNKNOWN', 'DELIVERED', 'TRANSIT', 'FAILURE', 'RETURNED'.
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub tracking_status: String,
+    /// Indicates the high level status of the shipment.
+    #[serde(default)]
+    pub tracking_status: TrackingStatusCode,
     /// A link to track this item on the carrier-provided tracking website.
     /// A value will only be returned if tracking is available and the carrier provides such a service.
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -848,9 +2828,7 @@ pub struct Transaction {
     /// A value will only be returned if the Transactions has been processed successfully and if the shipment is international.
     #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
     pub commercial_invoice_url: String,
-    /// An array containing elements of the following schema:
-    /// - "code" (string): an identifier for the corresponding message (not always available")
-    /// - "message" (string): a publishable message containing further information.
+    /// Any messages from Shippo or the carrier, each with a source, code, and text.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub messages: Vec<Message>,
     /// A URL pointing directly to the QR code in PNG format.
@@ -860,38 +2838,523 @@ pub struct Transaction {
     /// Indicates whether the object has been created in test mode.
     #[serde(default)]
     pub test: bool,
+    /// Fields returned by Shippo that this crate does not know about yet.
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct NewTransaction {
     pub rate: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub metadata: String,
+    pub metadata: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_file_type: Option<LabelFileType>,
+    #[serde(default)]
+    pub r#async: bool,
+    /// Request a QR code, returned in the transaction's `qr_code_url`, that can be
+    /// shown at drop off instead of a printed label. Only USPS supports this.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub qr_code_requested: bool,
+    /// Buy the label even if the rate costs more than `Shippo::with_max_label_cost`.
+    #[serde(skip)]
+    pub allow_over_max_cost: bool,
+    /// A unique key for this request, for example built from the ID of the job
+    /// that sends it. If the request is sent again with the same key, Shippo
+    /// returns the label it already bought instead of buying another one.
+    #[serde(skip)]
+    pub idempotency_key: String,
+}
+
+/// The data type for buying a label in one call, without picking a rate from
+/// a shipment first.
+/// FROM: https://goshippo.com/docs/reference#transactions-create-instant
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewInstantTransaction {
+    /// The shipment to buy the label for.
+    pub shipment: NewShipment,
+    /// The object ID of the carrier account to buy the label with.
+    pub carrier_account: String,
+    /// Token of the service level to buy, e.g. `ServiceLevelToken::UspsPriority`.
+    pub servicelevel_token: ServiceLevelToken,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_file_type: Option<LabelFileType>,
+    #[serde(default)]
+    pub r#async: bool,
+    /// Buy the label even if the rate costs more than `Shippo::with_max_label_cost`.
+    #[serde(skip)]
+    pub allow_over_max_cost: bool,
+    /// A unique key for this request, for example built from the ID of the job
+    /// that sends it. If the request is sent again with the same key, Shippo
+    /// returns the label it already bought instead of buying another one.
+    #[serde(skip)]
+    pub idempotency_key: String,
+}
+
+/// A message returned by Shippo or the carrier.
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct Message {
+    /// Where the message came from, for example the name of the carrier or
+    /// "Shippo Address Validator".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// An identifier for the message, not always set.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub code: String,
+    /// The human readable message.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+impl fmt::Display for Message {
+    /// Format the message as "source: code: text", skipping any parts that
+    /// are not set, so it can be shown to people.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<&str> = vec![self.source.as_str(), self.code.as_str(), self.text.as_str()].into_iter().filter(|p| !p.is_empty()).collect();
+        write!(f, "{}", parts.join(": "))
+    }
+}
+
+/// The results of validating an address.
+/// FROM: https://goshippo.com/docs/reference#addresses
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct ValidationResults {
+    /// Whether the address is valid and deliverable.
+    #[serde(default)]
+    pub is_valid: bool,
+    /// Any messages from Shippo or the carrier, each with a source, code, and text.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    /// The fixed copy of the address Shippo suggests when the one given can be
+    /// corrected, for example by adding a missing apartment or fixing the ZIP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_address: Option<Box<Address>>,
+}
+
+impl ValidationResults {
+    /// Returns the corrected address suggested by validation, if there is one.
+    pub fn corrected_address(&self) -> Option<&Address> {
+        self.corrected_address.as_deref()
+    }
+}
+
+/// The data type for a refund.
+/// Refunds are reimbursements for successfully created but unused shipping labels.
+/// FROM: https://goshippo.com/docs/reference#refunds
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Refund {
+    /// Unique identifier of the given Refund object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Date and time of object creation.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    /// Date and time of last object update.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
+    /// Username of the user who created the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
+    /// Indicates the status of the Refund.
+    #[serde(default)]
+    pub status: RefundStatus,
+    /// Object ID of the Transaction to be refunded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transaction: String,
+    /// Indicates whether the object has been created in test mode.
+    #[serde(default)]
+    pub test: bool,
+}
+
+/// The data type for a manifest.
+/// Manifests are close-outs of shipping labels of a certain day. Some carriers
+/// require manifests to properly process the shipments.
+/// FROM: https://goshippo.com/docs/reference#manifests
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Unique identifier of the given Manifest object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Date and time of object creation.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    /// Date and time of last object update.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
+    /// Username of the user who created the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
+    /// Indicates the status of the Manifest.
+    #[serde(default)]
+    pub status: ManifestStatus,
+    /// ID of carrier account the manifest is for.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub carrier_account: String,
+    /// All shipping labels on this date for the carrier account are included
+    /// in the manifest.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub shipment_date: Option<DateTime<Utc>>,
+    /// ID of the Address object that is the pickup address for the manifest.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub address_from: String,
+    /// IDs of the transactions included in the manifest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<String>,
+    /// URLs of the manifest documents to print and hand to the carrier.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<String>,
+    /// Indicates whether the object has been created in test mode.
+    #[serde(default)]
+    pub test: bool,
+}
+
+/// The data type for creating a manifest.
+/// FROM: https://goshippo.com/docs/reference#manifests-create
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewManifest {
+    /// ID of carrier account to create the manifest for.
+    pub carrier_account: String,
+    /// All shipping labels on this date for the carrier account are included
+    /// in the manifest.
+    pub shipment_date: DateTime<Utc>,
+    /// ID of the Address object that is the pickup address for the manifest.
+    pub address_from: String,
+    /// IDs of the transactions to include in the manifest. If this is empty
+    /// all the transactions for the shipment date are included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<String>,
+    /// Create the manifest asynchronously. If this is false, the response waits
+    /// until the manifest is created.
+    #[serde(default)]
+    pub r#async: bool,
+}
+
+/// The data type for a batch.
+/// Batches are used to purchase the labels for many shipments at once.
+/// FROM: https://goshippo.com/docs/reference#batches
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Batch {
+    /// Unique identifier of the given Batch object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Username of the user who created the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
+    /// Date and time of object creation.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    /// Date and time of last object update.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
+    /// Status of the batch.
+    #[serde(default)]
+    pub status: BatchStatus,
+    /// ID of the carrier account used for shipments that do not set their own.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default_carrier_account: String,
+    /// Token of the service level used for shipments that do not set their own.
+    #[serde(default)]
+    pub default_servicelevel_token: ServiceLevelToken,
+    /// Print format of the labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_filetype: Option<LabelFileType>,
+    /// A string of up to 100 characters that can be filled with any additional
+    /// information you want to attach to the object.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+    /// A page of the shipments in the batch.
+    #[serde(default)]
+    pub batch_shipments: BatchShipments,
+    /// URLs of the merged label files, once the batch is purchased.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub label_url: Vec<String>,
+    /// The counts of the shipments in the batch by their result.
+    #[serde(default)]
+    pub object_results: BatchResults,
+    /// Indicates whether the object has been created in test mode.
+    #[serde(default)]
+    pub test: bool,
+}
+
+/// A page of the shipments in a batch.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BatchShipments {
+    #[serde(default)]
+    pub count: i64,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub next: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub previous: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<BatchShipment>,
+}
+
+/// The counts of the shipments in a batch by their result.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BatchResults {
+    #[serde(default)]
+    pub creation_succeeded: i64,
+    #[serde(default)]
+    pub creation_failed: i64,
+    #[serde(default)]
+    pub purchase_succeeded: i64,
+    #[serde(default)]
+    pub purchase_failed: i64,
+}
+
+/// The data type for a shipment in a batch.
+/// FROM: https://goshippo.com/docs/reference#batches
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BatchShipment {
+    /// Unique identifier of the given BatchShipment object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Status of the shipment in the batch.
+    #[serde(default)]
+    pub status: BatchShipmentStatus,
+    /// ID of the carrier account used for the shipment.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub carrier_account: String,
+    /// Token of the service level used for the shipment, if not the batch default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servicelevel_token: Option<ServiceLevelToken>,
+    /// ID of the Shipment object.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub shipment: String,
+    /// ID of the Transaction object, once the label is purchased.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub transaction: String,
+    /// Any messages generated while validating or purchasing the shipment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    /// A string of up to 100 characters that can be filled with any additional
+    /// information you want to attach to the object.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+}
+
+/// The data type for creating a batch.
+/// FROM: https://goshippo.com/docs/reference#batches-create
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewBatch {
+    /// ID of the carrier account to use for shipments that do not set their own.
+    pub default_carrier_account: String,
+    /// Token of the service level to use for shipments that do not set their own.
+    pub default_servicelevel_token: ServiceLevelToken,
+    /// Print format of the labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_filetype: Option<LabelFileType>,
+    /// A string of up to 100 characters that can be filled with any additional
+    /// information you want to attach to the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+    /// The shipments to add to the batch.
+    #[serde(default)]
+    pub batch_shipments: Vec<NewBatchShipment>,
+}
+
+/// The data type for adding a shipment to a batch.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewBatchShipment {
+    /// The shipment to add.
+    pub shipment: NewShipment,
+    /// ID of the carrier account to use, if not the batch default.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub carrier_account: String,
+    /// Token of the service level to use, if not the batch default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servicelevel_token: Option<ServiceLevelToken>,
+    /// A string of up to 100 characters that can be filled with any additional
+    /// information you want to attach to the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+}
+
+/// The data type for an order.
+/// Orders are e-commerce style orders, labels can be purchased for an order by
+/// creating a shipment with the `order` set.
+/// FROM: https://goshippo.com/docs/reference#orders
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Order {
+    /// Unique identifier of the given Order object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Username of the user who created the object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_owner: String,
+    /// An alphanumeric identifier for the order used by the seller/shop.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub order_number: String,
+    /// Current state of the order.
+    #[serde(default)]
+    pub order_status: OrderStatus,
+    /// Date and time when the order was placed.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub placed_at: Option<DateTime<Utc>>,
+    /// The address the order is shipped to.
+    #[serde(default)]
+    pub to_address: Address,
+    /// The address the order is shipped from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_address: Option<Address>,
+    /// The items in the order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_items: Vec<LineItem>,
+    /// Amount paid by the customer for shipping.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub shipping_cost: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub shipping_cost_currency: String,
+    /// The shipping method chosen by the customer.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub shipping_method: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub subtotal_price: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub total_price: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub total_tax: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    /// Total weight of the order.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub weight: String,
+    /// The unit used for weight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_unit: Option<MassUnit>,
+    /// Custom buyer or seller provided notes about the order.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// The shipping labels purchased for the order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<serde_json::Value>,
+    /// Indicates whether the object has been created in test mode.
+    #[serde(default)]
+    pub test: bool,
+}
+
+/// The data type for an item in an order.
+/// FROM: https://goshippo.com/docs/reference#orders
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LineItem {
+    /// Unique identifier of the given LineItem object.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub object_id: String,
+    /// Title of the product.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    /// Title of the variant of the product, for example the size.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub variant_title: String,
+    /// Stock keeping unit of the product.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub sku: String,
+    /// Number of units of the item in the order.
+    #[serde(default)]
+    pub quantity: i64,
+    /// Total price paid by the customer for the units of the item.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub total_price: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    /// Total weight of the units of the item.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub weight: String,
+    /// The unit used for weight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_unit: Option<MassUnit>,
+    /// Country of origin of the item, used for customs.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub manufacture_country: String,
+}
+
+/// The data type for creating an order.
+/// FROM: https://goshippo.com/docs/reference#orders-create
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewOrder {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub order_number: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_status: Option<OrderStatus>,
+    pub placed_at: DateTime<Utc>,
+    pub to_address: Address,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_items: Vec<LineItem>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub shipping_cost: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub label_file_type: String,
-    #[serde(default)]
-    pub r#async: bool,
-}
-
-#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
-pub struct Message {
+    pub shipping_cost_currency: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub source: String,
+    pub shipping_method: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub code: String,
+    pub subtotal_price: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub text: String,
+    pub total_price: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub total_tax: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    pub weight: String,
+    pub weight_unit: MassUnit,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
 }
 
-#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
-pub struct ValidationResults {
+/// The data type for requesting live rates.
+/// FROM: https://goshippo.com/docs/reference#live-rates
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NewLiveRates {
+    /// The sender address. If it is not set, the default sender address of the
+    /// account is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_from: Option<Address>,
+    /// The recipient address.
     #[serde(default)]
-    pub is_valid: bool,
-    /// An array containing elements of the following schema:
-    /// - "code" (string): an identifier for the corresponding message (not always available")
-    /// - "message" (string): a publishable message containing further information.
+    pub address_to: Address,
+    /// The items to ship, the weight of each item is used to calculate the rates.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub messages: Vec<Message>,
+    pub line_items: Vec<LineItem>,
+    /// The parcel the items are shipped in. If it is not set, the default
+    /// parcel template of the account is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parcel: Option<Parcel>,
+}
+
+/// The data type for a live rate.
+/// FROM: https://goshippo.com/docs/reference#live-rates
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LiveRate {
+    /// The name of the rate, this is the name of the service level, or the
+    /// name set for the rate in the Shippo dashboard.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// The price of the rate, in the currency used in the sender's country.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub amount: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub currency: String,
+    /// The price of the rate, in the currency used in the recipient's country.
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub amount_local: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub currency_local: String,
+    /// The estimated number of days in transit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_days: Option<i64>,
+}
+
+impl LiveRate {
+    /// Return the price of the rate, with its currency, in the sender's currency.
+    pub fn price(&self) -> Option<Money> {
+        Money::parse(&self.amount, &self.currency)
+    }
 }
 
 /// The data type for a tracking status.
@@ -936,14 +3399,29 @@ pub struct TrackingStatus {
     /// want to attach to the object.
     #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
     pub metadata: String,
+    /// Fields returned by Shippo that this crate does not know about yet.
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
+/// The data type for a tracking event, this is used for both the latest
+/// status and the tracking history.
+/// FROM: https://goshippo.com/docs/reference#tracks
 #[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize)]
 pub struct Status {
-    /// Indicates the high level status of the shipment.
-    /// 'UNKNOWN' | 'PRE_TRANSIT' | 'TRANSIT' | 'DELIVERED' | 'RETURNED' | 'FAILURE'
+    /// Unique identifier of the tracking event.
     #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
-    pub status: String,
+    pub object_id: String,
+    /// Date and time the tracking event was created in Shippo.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    /// Date and time the tracking event was last updated in Shippo.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
+    /// Indicates the high level status of the shipment.
+    #[serde(default)]
+    pub status: TrackingStatusCode,
     /// The human-readable description of the status.
     #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
     pub status_details: String,
@@ -956,6 +3434,7 @@ pub struct Status {
     pub location: Option<TrackingLocation>,
 }
 
+/// The location of a tracking event.
 #[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 pub struct TrackingLocation {
     #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
@@ -969,6 +3448,7 @@ pub struct TrackingLocation {
 }
 
 impl TrackingLocation {
+    /// Return the location formatted as `city, state zip country`.
     pub fn formatted(&self) -> String {
         let mut zip = self.zip.to_string();
         if self.country == "US" && zip.len() > 5 {
@@ -978,6 +3458,104 @@ impl TrackingLocation {
     }
 }
 
+/// The payload Shippo POSTs to a registered tracking webhook.
+/// FROM: https://goshippo.com/docs/webhooks
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct TrackingWebhookEvent {
+    /// The type of event, for tracking updates this is "track_updated".
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub event: String,
+    /// Whether the event was sent for a test object or from the webhook test button.
+    #[serde(default)]
+    pub test: bool,
+    /// The tracking status of the shipment.
+    #[serde(default)]
+    pub data: TrackingWebhookData,
+}
+
+impl TrackingWebhookEvent {
+    /// Parse the body of a webhook request.
+    pub fn parse(body: &[u8]) -> Result<Self, ShippoError> {
+        Ok(serde_json::from_slice(body)?)
+    }
+
+    /// Returns if the event is a tracking update.
+    pub fn is_track_updated(&self) -> bool {
+        self.event == "track_updated"
+    }
+
+    /// Return the tracking status the event is for.
+    pub fn tracking_status(&self) -> TrackingStatus {
+        self.data.clone().into()
+    }
+}
+
+/// The tracking status sent in a webhook event. This is the same as a
+/// `TrackingStatus`, but the events sent before the carrier has any information,
+/// and test events, leave most of the fields null.
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct TrackingWebhookData {
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub carrier: String,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub tracking_number: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_from: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_to: Option<Address>,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub transaction: String,
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub eta: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub original_eta: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servicelevel: Option<ServiceLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracking_status: Option<Status>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tracking_history: Vec<Status>,
+    #[serde(default, deserialize_with = "deserialize_null_string::deserialize", skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+}
+
+impl TrackingStatus {
+    /// Return where the package was last scanned, from the current status or,
+    /// if the carrier did not give a location for it, the latest tracking event
+    /// that has one.
+    pub fn last_location(&self) -> Option<&TrackingLocation> {
+        if let Some(location) = &self.tracking_status.location {
+            return Some(location);
+        }
+
+        self.tracking_history
+            .iter()
+            .filter(|s| s.location.is_some())
+            .max_by_key(|s| s.status_date)
+            .and_then(|s| s.location.as_ref())
+    }
+}
+
+impl From<TrackingWebhookData> for TrackingStatus {
+    fn from(data: TrackingWebhookData) -> Self {
+        TrackingStatus {
+            carrier: data.carrier,
+            tracking_number: data.tracking_number,
+            address_from: data.address_from.unwrap_or_default(),
+            address_to: data.address_to.unwrap_or_default(),
+            transaction: data.transaction,
+            eta: data.eta,
+            original_eta: data.original_eta,
+            servicelevel: data.servicelevel.unwrap_or_default(),
+            tracking_status: data.tracking_status.unwrap_or_default(),
+            tracking_history: data.tracking_history,
+            metadata: data.metadata,
+            #[cfg(feature = "unknown-fields")]
+            extra: Default::default(),
+        }
+    }
+}
+
 /// A customs declaration object.
 /// Customs declarations are relevant information, including one or multiple
 /// customs items, you need to provide for customs clearance for your international shipments.
@@ -991,9 +3569,8 @@ pub struct CustomsDeclaration {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub object_owner: String,
     /// Indicates the validity of the Customs Item.
-    /// "VALID" | "INVALID"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub object_state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_state: Option<ObjectState>,
     /// Exporter reference of an export shipment.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub exporter_reference: String,
@@ -1075,9 +3652,14 @@ pub struct CustomsItem {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub object_owner: String,
     /// Indicates the validity of the Customs Item.
-    /// "VALID" | "INVALID"
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub object_state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_state: Option<ObjectState>,
+    /// Date and time of object creation.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_created: Option<DateTime<Utc>>,
+    /// Date and time of last object update.
+    #[serde(default, deserialize_with = "null_date_format::deserialize", skip_serializing_if = "Option::is_none")]
+    pub object_updated: Option<DateTime<Utc>>,
     /// Text description of your item.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
@@ -1088,8 +3670,8 @@ pub struct CustomsItem {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub net_weight: String,
     /// The unit used for net_weight.
-    #[serde(default, skip_serializing_if = "String::is_empty")]
-    pub mass_unit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass_unit: Option<MassUnit>,
     /// Total value of this item, i.e. quantity * value per item.
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub value_amount: String,
@@ -1100,22 +3682,375 @@ pub struct CustomsItem {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub origin_country: String,
     /// The tariff number of the item.
-    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
     pub tariff_number: String,
     /// SKU code of the item, which is required by some carriers.
     #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
     pub sku_code: String,
+    /// The Harmonized System code of the item, required by some carriers for
+    /// commercial shipments, e.g. "8471.50".
+    #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
+    pub hs_code: String,
     /// Export Control Classification Number, required on some exports from the United States.
-    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
     pub eccn_ear99: String,
     /// A string of up to 100 characters that can be filled with any additional information you want to attach to the object.
-    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty", deserialize_with = "deserialize_null_string::deserialize")]
     pub metadata: String,
     /// Indicates whether the object has been created in test mode.
     #[serde(default)]
     pub test: bool,
 }
 
+impl CustomsItem {
+    /// Return the total value of the item, with its currency.
+    pub fn value(&self) -> Option<Money> {
+        Money::parse(&self.value_amount, &self.value_currency)
+    }
+}
+
+/// Define an enum for a set of string values returned by the API. Values we
+/// do not know about yet are kept in the `Other` variant, so new values from
+/// Shippo do not break deserialization.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:expr,)*
+        }
+        default = $default:expr;
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A value we do not know about yet.
+            Other(String),
+        }
+
+        impl $name {
+            /// Return the value as it is sent by the API.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(s) => s,
+                }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $default
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                // The API is not consistent about case, so match without it.
+                $(if s.trim().eq_ignore_ascii_case($value) {
+                    return $name::$variant;
+                })*
+                $name::Other(s.to_string())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                // The API sometimes returns null instead of leaving the value out.
+                let s: Option<String> = Option::deserialize(deserializer)?;
+                Ok(match s {
+                    Some(s) if !s.is_empty() => $name::from(s.as_str()),
+                    _ => Default::default(),
+                })
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                String::json_schema(gen)
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// The status of a shipment.
+    pub enum ShipmentStatus {
+        /// The shipment was submitted but has not been processed yet.
+        Waiting => "WAITING",
+        /// The shipment is being processed.
+        Queued => "QUEUED",
+        /// The shipment was processed and rate generation has concluded.
+        Success => "SUCCESS",
+        Error => "ERROR",
+    }
+    default = ShipmentStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of a transaction, which is the purchase of a shipping label.
+    pub enum TransactionStatus {
+        Waiting => "WAITING",
+        Queued => "QUEUED",
+        Success => "SUCCESS",
+        Error => "ERROR",
+        Refunded => "REFUNDED",
+        RefundPending => "REFUNDPENDING",
+        RefundRejected => "REFUNDREJECTED",
+    }
+    default = TransactionStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of a refund of a shipping label.
+    pub enum RefundStatus {
+        Queued => "QUEUED",
+        Pending => "PENDING",
+        Success => "SUCCESS",
+        Error => "ERROR",
+    }
+    default = RefundStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of a manifest.
+    pub enum ManifestStatus {
+        Queued => "QUEUED",
+        Success => "SUCCESS",
+        Error => "ERROR",
+    }
+    default = ManifestStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of a batch.
+    pub enum BatchStatus {
+        /// The shipments in the batch are being validated.
+        Validating => "VALIDATING",
+        Valid => "VALID",
+        Invalid => "INVALID",
+        /// The labels for the batch are being purchased.
+        Purchasing => "PURCHASING",
+        Purchased => "PURCHASED",
+    }
+    default = BatchStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of a shipment in a batch.
+    pub enum BatchShipmentStatus {
+        Invalid => "INVALID",
+        Valid => "VALID",
+        Incomplete => "INCOMPLETE",
+        TransactionFailed => "TRANSACTION_FAILED",
+    }
+    default = BatchShipmentStatus::Other(String::new());
+}
+
+string_enum! {
+    /// The status of an order.
+    pub enum OrderStatus {
+        Unknown => "UNKNOWN",
+        AwaitingPayment => "AWAITING_PAYMENT",
+        Paid => "PAID",
+        Refunded => "REFUNDED",
+        Cancelled => "CANCELLED",
+        PartiallyFulfilled => "PARTIALLY_FULFILLED",
+        Shipped => "SHIPPED",
+    }
+    default = OrderStatus::Unknown;
+}
+
+string_enum! {
+    /// The high level status of a tracked shipment.
+    pub enum TrackingStatusCode {
+        Unknown => "UNKNOWN",
+        PreTransit => "PRE_TRANSIT",
+        Transit => "TRANSIT",
+        Delivered => "DELIVERED",
+        Returned => "RETURNED",
+        Failure => "FAILURE",
+    }
+    default = TrackingStatusCode::Unknown;
+}
+
+string_enum! {
+    /// Whether an object is valid, based on the data that was sent.
+    pub enum ObjectState {
+        Valid => "VALID",
+        Invalid => "INVALID",
+    }
+    default = ObjectState::Other(String::new());
+}
+
+string_enum! {
+    /// The file format of a shipping label.
+    pub enum LabelFileType {
+        Png => "PNG",
+        Png2_3x7_5 => "PNG_2.3x7.5",
+        Pdf => "PDF",
+        Pdf2_3x7_5 => "PDF_2.3x7.5",
+        Pdf4x6 => "PDF_4x6",
+        Pdf4x8 => "PDF_4x8",
+        PdfA4 => "PDF_A4",
+        PdfA6 => "PDF_A6",
+        Zplii => "ZPLII",
+    }
+    default = LabelFileType::Other(String::new());
+}
+
+string_enum! {
+    /// The unit for the length, width, and height of a parcel.
+    pub enum DistanceUnit {
+        Cm => "cm",
+        In => "in",
+        Ft => "ft",
+        Mm => "mm",
+        M => "m",
+        Yd => "yd",
+    }
+    default = DistanceUnit::Other(String::new());
+}
+
+string_enum! {
+    /// The unit for the weight of a parcel or customs item.
+    pub enum MassUnit {
+        G => "g",
+        Oz => "oz",
+        Lb => "lb",
+        Kg => "kg",
+    }
+    default = MassUnit::Other(String::new());
+}
+
+string_enum! {
+    /// A carrier's standard packaging, to use instead of the dimensions of a parcel.
+    /// FROM: https://goshippo.com/docs/reference#parcel-templates
+    pub enum ParcelTemplate {
+        UspsFlatRateEnvelope => "USPS_FlatRateEnvelope",
+        UspsFlatRateLegalEnvelope => "USPS_FlatRateLegalEnvelope",
+        UspsFlatRatePaddedEnvelope => "USPS_FlatRatePaddedEnvelope",
+        UspsSmallFlatRateBox => "USPS_SmallFlatRateBox",
+        UspsMediumFlatRateBox1 => "USPS_MediumFlatRateBox1",
+        UspsMediumFlatRateBox2 => "USPS_MediumFlatRateBox2",
+        UspsLargeFlatRateBox => "USPS_LargeFlatRateBox",
+        FedExEnvelope => "FedEx_Envelope",
+        FedExPaddedPak => "FedEx_Padded_Pak",
+        FedExPak => "FedEx_Pak_2",
+        FedExSmallBox => "FedEx_Box_Small_1",
+        FedExMediumBox => "FedEx_Box_Medium_1",
+        FedExLargeBox => "FedEx_Box_Large_1",
+        FedExTube => "FedEx_Tube",
+    }
+    default = ParcelTemplate::Other(String::new());
+}
+
+string_enum! {
+    /// The service levels of the carriers we ship with.
+    /// FROM: https://goshippo.com/docs/reference#servicelevels
+    pub enum ServiceLevelToken {
+        UspsPriority => "usps_priority",
+        UspsPriorityExpress => "usps_priority_express",
+        UspsFirst => "usps_first",
+        UspsParcelSelect => "usps_parcel_select",
+        UspsMediaMail => "usps_media_mail",
+        UspsPriorityMailInternational => "usps_priority_mail_international",
+        UspsPriorityMailExpressInternational => "usps_priority_mail_express_international",
+        UspsFirstClassPackageInternationalService => "usps_first_class_package_international_service",
+        UpsGround => "ups_ground",
+        UpsStandard => "ups_standard",
+        UpsSaver => "ups_saver",
+        Ups3DaySelect => "ups_3_day_select",
+        UpsSecondDayAir => "ups_second_day_air",
+        UpsSecondDayAirAm => "ups_second_day_air_am",
+        UpsNextDayAir => "ups_next_day_air",
+        UpsNextDayAirSaver => "ups_next_day_air_saver",
+        UpsNextDayAirEarlyAm => "ups_next_day_air_early_am",
+        UpsExpress => "ups_express",
+        UpsExpedited => "ups_expedited",
+        FedexGround => "fedex_ground",
+        FedexHomeDelivery => "fedex_home_delivery",
+        FedexExpressSaver => "fedex_express_saver",
+        Fedex2Day => "fedex_2_day",
+        Fedex2DayAm => "fedex_2_day_am",
+        FedexStandardOvernight => "fedex_standard_overnight",
+        FedexPriorityOvernight => "fedex_priority_overnight",
+        FedexFirstOvernight => "fedex_first_overnight",
+        FedexInternationalEconomy => "fedex_international_economy",
+        FedexInternationalPriority => "fedex_international_priority",
+        DhlExpressWorldwide => "dhl_express_worldwide",
+    }
+    default = ServiceLevelToken::Other(String::new());
+}
+
+impl ServiceLevelToken {
+    /// Return the carrier the service level is for, e.g. `Carrier::Usps`.
+    pub fn carrier(&self) -> Carrier {
+        Carrier::from_friendly_name(self.as_str().split('_').next().unwrap_or_default())
+    }
+}
+
+string_enum! {
+    /// The tokens Shippo uses for carriers, for example in the tracking endpoints.
+    /// FROM: https://goshippo.com/docs/reference#carriers
+    pub enum Carrier {
+        Usps => "usps",
+        Ups => "ups",
+        Fedex => "fedex",
+        DhlExpress => "dhl_express",
+        DhlEcommerce => "dhl_ecommerce",
+        DhlGermany => "dhl_germany",
+        DeutschePost => "deutsche_post",
+        CanadaPost => "canada_post",
+        AustraliaPost => "australia_post",
+        RoyalMail => "royal_mail",
+        Purolator => "purolator",
+        Ontrac => "ontrac",
+        Lasership => "lasership",
+        Shippo => "shippo",
+    }
+    default = Carrier::Other(String::new());
+}
+
+impl Carrier {
+    /// Parse a carrier from the name people use for it, for example "DHL",
+    /// "FedEx", or "DHL eCommerce", as well as from its token. Plain "DHL" is
+    /// taken to mean DHL Express.
+    pub fn from_friendly_name(name: &str) -> Self {
+        let name = name.trim().to_lowercase().replace(|c: char| c == ' ' || c == '-', "_");
+        match name.as_str() {
+            "dhl" => Carrier::DhlExpress,
+            "dhl_ecommerce_solutions" => Carrier::DhlEcommerce,
+            "fed_ex" | "federal_express" => Carrier::Fedex,
+            "united_parcel_service" => Carrier::Ups,
+            "united_states_postal_service" | "us_postal_service" => Carrier::Usps,
+            n => Carrier::from(n),
+        }
+    }
+}
+
 pub mod deserialize_null_string {
     use serde::{self, Deserialize, Deserializer};
 
@@ -1156,6 +4091,417 @@ pub mod null_date_format {
             return Ok(None);
         }
 
-        Ok(Some(Utc.datetime_from_str(&s, "%+").unwrap()))
+        Ok(Some(Utc.datetime_from_str(&s, "%+").map_err(serde::de::Error::custom)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::offset::Utc;
+    use testutils::fixture;
+
+    use crate::{
+        Address, Batch, BatchShipmentStatus, BatchStatus, Billing, Carrier, CustomsDeclaration, CustomsItem, DistanceUnit, Insurance, LabelFileType, Manifest, ManifestStatus, MassUnit, Money,
+        NewBatch, NewShipment, NewTransaction, Order, OrderStatus, Parcel, ParcelTemplate, Rate, RateSelection, Refund, RefundStatus, ServiceLevelToken, ShipmentExtra, ShipmentListParams,
+        ShippoError, TrackingStatusCode, TrackingWebhookEvent, Transaction, MAX_SHIPMENT_LIST_DAYS,
+    };
+
+    #[test]
+    fn test_status_enums() {
+        let refund: Refund = serde_json::from_str(r#"{"object_id":"r1","status":"pending"}"#).unwrap();
+        assert_eq!(refund.status, RefundStatus::Pending);
+
+        let batch: Batch = serde_json::from_str(r#"{"object_id":"b1","status":"PURCHASED","batch_shipments":{"results":[{"status":"TRANSACTION_FAILED"}]}}"#).unwrap();
+        assert_eq!(batch.status, BatchStatus::Purchased);
+        assert_eq!(batch.batch_shipments.results[0].status, BatchShipmentStatus::TransactionFailed);
+
+        let manifest: Manifest = serde_json::from_str(r#"{"object_id":"m1","status":null}"#).unwrap();
+        assert_eq!(manifest.status, ManifestStatus::Other(String::new()));
+
+        let order: Order = serde_json::from_str(r#"{"object_id":"o1","order_status":"ON_HOLD","weight_unit":"lb"}"#).unwrap();
+        assert_eq!(order.order_status, OrderStatus::Other("ON_HOLD".to_string()));
+        assert_eq!(order.weight_unit, Some(MassUnit::Lb));
+        assert_eq!(serde_json::to_value(&order.order_status).unwrap(), "ON_HOLD");
+    }
+
+    #[test]
+    fn test_created_in_last_days_is_clamped() {
+        let days_ago = |params: ShipmentListParams| (Utc::now() - params.object_created_gte.unwrap()).num_days();
+
+        assert_eq!(days_ago(ShipmentListParams::created_in_last_days(30)), 30);
+        assert_eq!(days_ago(ShipmentListParams::created_in_last_days(365)), MAX_SHIPMENT_LIST_DAYS);
+        assert_eq!(days_ago(ShipmentListParams::created_in_last_days(-5)), 0);
+    }
+
+    #[test]
+    fn test_corrected_address() {
+        let mut body = fixture("shippo", "address");
+        let address: Address = serde_json::from_value(body.clone()).unwrap();
+        assert!(address.validation_results.corrected_address().is_none());
+
+        body["street1"] = "215 CLAYTON".into();
+        body["zip"] = "94117".into();
+        body["validation_results"]["corrected_address"] = serde_json::json!({
+            "street1": "215 CLAYTON ST",
+            "city": "SAN FRANCISCO",
+            "state": "CA",
+            "zip": "94117-1913",
+            "country": "US",
+        });
+        let address: Address = serde_json::from_value(body).unwrap();
+        let corrected = address.validation_results.corrected_address().unwrap();
+        assert_eq!(corrected.street1, "215 CLAYTON ST");
+        assert_eq!(corrected.zip, "94117-1913");
+    }
+
+    #[test]
+    fn test_shipment_builders() {
+        let from = Address::builder()
+            .company("Oxide Computer Company")
+            .street1("1251 Park Avenue")
+            .city("Emeryville")
+            .state("CA")
+            .zip("94608")
+            .country("us")
+            .build()
+            .unwrap();
+        assert_eq!(from.country, "US");
+        let to = Address::builder().name("Jane Doe").street1("1 Rue de Rivoli").city("Paris").zip("75001").country("FR").build().unwrap();
+
+        let err = Address::builder().name("Jane Doe").street1("1 Main St").city("Oakland").country("USA").build().unwrap_err();
+        assert_eq!(err.field, "country");
+
+        let parcel = Parcel::builder().dimensions(18.75, 14.5, 3.0, DistanceUnit::In).weight(1.0, MassUnit::Lb).build().unwrap();
+        assert_eq!(parcel.length, "18.75");
+        let json = serde_json::to_value(&parcel).unwrap();
+        assert_eq!(json["distance_unit"], "in");
+        assert_eq!(json["mass_unit"], "lb");
+        let err = Parcel::builder()
+            .dimensions(18.75, 14.5, 3.0, DistanceUnit::In)
+            .weight(1.0, MassUnit::from("pounds"))
+            .build()
+            .unwrap_err();
+        assert_eq!(err.field, "mass_unit");
+        let err = Parcel::builder().weight(1.0, MassUnit::Lb).build().unwrap_err();
+        assert_eq!(err.field, "length");
+
+        // Carrier templates are used instead of the dimensions.
+        let envelope = Parcel::builder().template(ParcelTemplate::UspsFlatRateEnvelope).weight(4.0, MassUnit::Oz).build().unwrap();
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["template"], "USPS_FlatRateEnvelope");
+        assert!(json.get("length").is_none());
+        let err = Parcel::builder().template(ParcelTemplate::from("USPS_Crate")).weight(4.0, MassUnit::Oz).build().unwrap_err();
+        assert_eq!(err.field, "template");
+
+        // International shipments need a customs declaration.
+        let err = NewShipment::builder().address_from(from.clone()).address_to(to.clone()).parcel(parcel.clone()).build().unwrap_err();
+        assert_eq!(err.field, "customs_declaration");
+
+        // The customs declaration and return address are sent along with the shipment.
+        let shipment = NewShipment::builder()
+            .address_from(from.clone())
+            .address_to(to)
+            .address_return(from.clone())
+            .parcel(parcel.clone())
+            .customs_declaration(CustomsDeclaration {
+                contents_type: "MERCHANDISE".to_string(),
+                non_delivery_option: "RETURN".to_string(),
+                certify: true,
+                certify_signer: "Jane Doe".to_string(),
+                items: vec!["a1b2c3".to_string()],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(&shipment).unwrap();
+        assert_eq!(json["address_return"]["city"], "Emeryville");
+        assert_eq!(json["customs_declaration"]["contents_type"], "MERCHANDISE");
+        assert_eq!(json["customs_declaration"]["items"], serde_json::json!(["a1b2c3"]));
+
+        let err = NewShipment::builder().address_from(from.clone()).address_to(from.clone()).build().unwrap_err();
+        assert_eq!(err.field, "parcels");
+
+        let shipment = NewShipment::builder().address_from(from.clone()).address_to(from.clone()).parcel(parcel.clone()).build().unwrap();
+        assert_eq!(shipment.parcels.len(), 1);
+        // Extras are left out of the request unless they are set.
+        assert!(serde_json::to_value(&shipment).unwrap().get("extra").is_none());
+
+        let shipment = NewShipment::builder()
+            .address_from(from.clone())
+            .address_to(from.clone())
+            .parcel(parcel.clone())
+            .is_return(true)
+            .build()
+            .unwrap();
+        assert_eq!(serde_json::to_value(&shipment).unwrap()["extra"], serde_json::json!({ "is_return": true }));
+
+        let shipment = NewShipment::builder()
+            .address_from(from.clone())
+            .address_to(from)
+            .parcel(parcel)
+            .extra(ShipmentExtra {
+                insurance: Some(Insurance {
+                    amount: "200".to_string(),
+                    currency: "USD".to_string(),
+                    content: "Hardware".to_string(),
+                    ..Default::default()
+                }),
+                signature_confirmation: "STANDARD".to_string(),
+                reference_1: "order-1234".to_string(),
+                billing: Some(Billing {
+                    billing_type: "THIRD_PARTY".to_string(),
+                    account: "1Z999".to_string(),
+                    zip: "94608".to_string(),
+                    country: "US".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&shipment).unwrap()["extra"],
+            serde_json::json!({
+                "insurance": { "amount": "200", "currency": "USD", "content": "Hardware" },
+                "signature_confirmation": "STANDARD",
+                "reference_1": "order-1234",
+                "billing": { "type": "THIRD_PARTY", "account": "1Z999", "zip": "94608", "country": "US" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_tracking_webhook() {
+        let body = fixture("shippo", "tracking_webhook").to_string();
+        let event = TrackingWebhookEvent::parse(body.as_bytes()).unwrap();
+        assert!(event.is_track_updated());
+        assert!(!event.test);
+
+        let status = event.tracking_status();
+        assert_eq!(status.tracking_number, "9205590164917312751089");
+        assert_eq!(status.tracking_status.status, TrackingStatusCode::Transit);
+        assert_eq!(status.tracking_history.len(), 2);
+        assert_eq!(status.address_to.city, "San Francisco");
+        assert_eq!(status.last_location().unwrap().formatted(), "Oakland, CA 94615 US");
+
+        // Test events leave most of the fields null.
+        let body = r#"{"event": "track_updated", "test": true, "data": {"tracking_number": "SHIPPO_TRANSIT", "address_from": null, "eta": null, "servicelevel": null, "tracking_status": null}}"#;
+        let event = TrackingWebhookEvent::parse(body.as_bytes()).unwrap();
+        assert!(event.test);
+        let status = event.tracking_status();
+        assert_eq!(status.tracking_number, "SHIPPO_TRANSIT");
+        assert_eq!(status.tracking_status.status, TrackingStatusCode::Unknown);
+        assert!(status.last_location().is_none());
+
+        assert!(matches!(TrackingWebhookEvent::parse(b"not json"), Err(ShippoError::Serde(_))));
+    }
+
+    #[cfg(feature = "unknown-fields")]
+    #[test]
+    fn test_unknown_fields() {
+        let mut transaction = fixture("shippo", "transaction");
+        transaction["carbon_offset"] = serde_json::json!({ "kg": 1.5 });
+
+        let label: Transaction = serde_json::from_value(transaction).unwrap();
+        assert_eq!(label.extra["carbon_offset"]["kg"], 1.5);
+        // Fields we know about are not duplicated in the map.
+        assert!(!label.extra.contains_key("tracking_number"));
+        assert_eq!(serde_json::to_value(&label).unwrap()["carbon_offset"]["kg"], 1.5);
+    }
+
+    #[test]
+    fn test_transaction_messages() {
+        let mut transaction = fixture("shippo", "transaction");
+        transaction["status"] = "ERROR".into();
+        transaction["messages"] = serde_json::json!([
+            {"source": "USPS", "code": "", "text": "The address is not deliverable."},
+            {"source": "Shippo", "code": "carrier_timeout", "text": "The carrier did not respond."},
+        ]);
+
+        let label: Transaction = serde_json::from_value(transaction).unwrap();
+        assert_eq!(label.label_file_type, Some(LabelFileType::Pdf4x6));
+        let messages: Vec<String> = label.messages.iter().map(|m| m.to_string()).collect();
+        assert_eq!(messages, vec!["USPS: The address is not deliverable.", "Shippo: carrier_timeout: The carrier did not respond."]);
+    }
+
+    #[test]
+    fn test_duties_and_taxes() {
+        let rate: Rate = serde_json::from_value(serde_json::json!({
+            "object_id": "545ab0a1a6ea4c9f9adb2512a57d6d8b",
+            "object_created": "2021-03-30T18:14:12Z",
+            "provider": "DHL Express",
+            "amount": "42.10",
+            "currency": "EUR",
+            "duties_and_taxes": {
+                "duties": "4.20",
+                "taxes": "9.81",
+                "currency": "EUR",
+            },
+        }))
+        .unwrap();
+
+        let charges = rate.estimated_customs_charges().unwrap();
+        assert_eq!(charges.to_string(), "14.01 EUR");
+        assert!(rate.duties_and_taxes.as_ref().unwrap().fees().is_none());
+        assert_eq!(rate.landed_cost().unwrap().to_string(), "56.11 EUR");
+
+        // Domestic rates have no estimate.
+        let rate: Rate = serde_json::from_value(serde_json::json!({
+            "object_created": "2021-03-30T18:14:12Z",
+            "amount": "5.50",
+            "currency": "USD",
+        }))
+        .unwrap();
+        assert!(rate.estimated_customs_charges().is_none());
+        assert!(rate.landed_cost().is_none());
+    }
+
+    #[test]
+    fn test_carrier() {
+        assert_eq!(Carrier::from_friendly_name("DHL"), Carrier::DhlExpress);
+        assert_eq!(Carrier::from_friendly_name("DHL eCommerce"), Carrier::DhlEcommerce);
+        assert_eq!(Carrier::from_friendly_name(" FedEx "), Carrier::Fedex);
+        assert_eq!(Carrier::from_friendly_name("Canada Post"), Carrier::CanadaPost);
+        assert_eq!(Carrier::from_friendly_name("usps"), Carrier::Usps);
+        assert_eq!(Carrier::from_friendly_name("Pony Express"), Carrier::Other("pony_express".to_string()));
+        assert_eq!(Carrier::DhlExpress.as_str(), "dhl_express");
+    }
+
+    #[test]
+    fn test_rate_selection() {
+        let rate = |provider: &str, amount: &str, days: Option<i64>, attributes: &[&str]| -> Rate {
+            serde_json::from_value(serde_json::json!({
+                "object_id": format!("{}-{}", provider, amount),
+                "object_created": "2021-03-30T18:14:12Z",
+                "provider": provider,
+                "amount": amount,
+                "currency": "USD",
+                "estimated_days": days,
+                "attributes": attributes,
+            }))
+            .unwrap()
+        };
+        let mut rates = [
+            rate("USPS", "7.85", Some(2), &["BESTVALUE"]),
+            rate("USPS", "5.50", Some(5), &["CHEAPEST"]),
+            rate("UPS", "30.10", Some(1), &["FASTEST"]),
+            rate("UPS", "12.00", None, &[]),
+        ];
+
+        assert_eq!(rates.cheapest().unwrap().amount, "5.50");
+        assert_eq!(rates.fastest().unwrap().amount, "30.10");
+        assert_eq!(rates.best_value().unwrap().amount, "7.85");
+
+        // The selection only looks at the filtered rates.
+        let ups = rates.filter_by_provider(&["ups"]);
+        assert_eq!(ups.len(), 2);
+        assert_eq!(ups.cheapest().unwrap().amount, "12.00");
+        assert_eq!(ups.best_value().unwrap().amount, "12.00");
+        assert!(rates.filter_by_provider(&["FedEx"]).fastest().is_none());
+
+        rates[0].servicelevel.token = ServiceLevelToken::UspsPriority;
+        rates[1].servicelevel.token = ServiceLevelToken::from("usps_first");
+        let priority = rates.filter_by_service_level(&[ServiceLevelToken::UspsPriority, ServiceLevelToken::UspsPriorityExpress]);
+        assert_eq!(priority.len(), 1);
+        assert_eq!(priority[0].servicelevel.token.carrier(), Carrier::Usps);
+        assert_eq!(ServiceLevelToken::DhlExpressWorldwide.carrier(), Carrier::DhlExpress);
+        assert_eq!(rates[1].servicelevel.token, ServiceLevelToken::UspsFirst);
+    }
+
+    #[test]
+    fn test_customs_item() {
+        let item: CustomsItem = serde_json::from_value(serde_json::json!({
+            "object_created": "2021-03-30T18:14:12.000Z",
+            "object_updated": "2021-03-30T18:14:12.000Z",
+            "object_id": "55358464c7b740aca199b395536981bd",
+            "object_owner": "shippotle@goshippo.com",
+            "object_state": "VALID",
+            "description": "Oxide rack switch",
+            "quantity": 2,
+            "net_weight": "40",
+            "mass_unit": "lb",
+            "value_amount": "4000",
+            "value_currency": "USD",
+            "origin_country": "US",
+            "tariff_number": "8517.62",
+            "sku_code": null,
+            "hs_code": "851762",
+            "eccn_ear99": "5A002",
+            "metadata": null,
+            "test": true,
+        }))
+        .unwrap();
+
+        assert!(item.object_created.is_some());
+        assert_eq!(item.tariff_number, "8517.62");
+        assert_eq!(item.hs_code, "851762");
+        assert_eq!(item.mass_unit, Some(MassUnit::Lb));
+        assert_eq!(item.value(), Money::parse("4000", "USD"));
+        // Empty fields are left out when creating the item.
+        assert!(serde_json::to_value(&item).unwrap().get("sku_code").is_none());
+    }
+
+    #[test]
+    fn test_money() {
+        let rate: Rate = serde_json::from_value(serde_json::json!({
+            "object_id": "545ab0a1a6ea4c9f9adb2512a57d6d8b",
+            "object_created": "2021-03-30T18:14:12Z",
+            "amount": "5.50",
+            "currency": "USD",
+            "amount_local": "4.61",
+            "currency_local": "eur",
+        }))
+        .unwrap();
+
+        let price = rate.price().unwrap();
+        assert_eq!(price.to_string(), "5.50 USD");
+        assert_eq!(rate.amount_as_decimal(), Some("5.5".parse().unwrap()));
+        let local = rate.price_local().unwrap();
+        assert_eq!(local.currency, "EUR");
+
+        // Amounts in different currencies can not be compared or added.
+        assert!(price.partial_cmp(&local).is_none());
+        assert!(price.checked_add(&local).is_none());
+
+        let cheaper = Money::parse("4.99", "USD").unwrap();
+        assert!(cheaper < price);
+        assert_eq!(price.checked_add(&cheaper), Money::parse("10.49", "USD"));
+        assert!(Money::parse("", "USD").is_none());
+    }
+
+    #[test]
+    fn test_qr_code_requested() {
+        let nt = NewTransaction {
+            rate: "545ab0a1a6ea4c9f9adb2512a57d6d8b".to_string(),
+            ..Default::default()
+        };
+        assert!(serde_json::to_value(&nt).unwrap().get("qr_code_requested").is_none());
+
+        let nt = NewTransaction { qr_code_requested: true, ..nt };
+        assert_eq!(serde_json::to_value(&nt).unwrap()["qr_code_requested"], true);
+    }
+
+    #[test]
+    fn test_batch_types() {
+        let batch: Batch = serde_json::from_value(serde_json::json!({
+            "object_id": "5ef63c54f5bf45d3b1f8fb37dcb1c5f4",
+            "default_servicelevel_token": "usps_priority",
+            "label_filetype": "PDF_4x6",
+            "batch_shipments": {"count": 2, "results": [
+                {"object_id": "bs1", "servicelevel_token": null},
+                {"object_id": "bs2", "servicelevel_token": "ups_ground"},
+            ]},
+        }))
+        .unwrap();
+        assert_eq!(batch.default_servicelevel_token, ServiceLevelToken::UspsPriority);
+        assert_eq!(batch.label_filetype, Some(LabelFileType::Pdf4x6));
+        assert_eq!(batch.batch_shipments.results[0].servicelevel_token, None);
+        assert_eq!(batch.batch_shipments.results[1].servicelevel_token, Some(ServiceLevelToken::UpsGround));
+
+        let new = NewBatch {
+            label_filetype: Some(LabelFileType::Pdf),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&new).unwrap()["label_filetype"], "PDF");
     }
 }
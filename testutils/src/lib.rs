@@ -0,0 +1,307 @@
+/*!
+ * Fake API servers, pre-loaded with recorded responses, for testing our API
+ * clients and the jobs built on top of them without hitting production APIs.
+ *
+ * Example:
+ *
+ * ```
+ * use testutils::FakeApis;
+ *
+ * async fn test_with_fakes() {
+ *     // Start the fake servers and point our clients at them.
+ *     let apis = FakeApis::start().await;
+ *     let _env = apis.env();
+ *
+ *     // Any clients created with `new_from_env` now talk to the fakes.
+ *     let shippo = shippo::Shippo::new_from_env().unwrap();
+ *     let shipments = shippo.list_shipments(&Default::default()).await.unwrap();
+ *     assert!(!shipments.is_empty());
+ * }
+ * ```
+ */
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+
+use lazy_static::lazy_static;
+use serde_json::Value;
+use wiremock::matchers::{method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+lazy_static! {
+    /// Environment variables are global to the process, so tests that change them
+    /// need to take turns.
+    static ref ENV_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// The recorded responses, keyed by `service/name`.
+static FIXTURES: &[(&str, &str)] = &[
+    ("airtable/records", include_str!("../fixtures/airtable/records.json")),
+    ("auth0/logs", include_str!("../fixtures/auth0/logs.json")),
+    ("auth0/token", include_str!("../fixtures/auth0/token.json")),
+    ("auth0/users", include_str!("../fixtures/auth0/users.json")),
+    ("sheets/values", include_str!("../fixtures/sheets/values.json")),
+    ("shippo/address", include_str!("../fixtures/shippo/address.json")),
+    ("shippo/shipments", include_str!("../fixtures/shippo/shipments.json")),
+    ("shippo/tracking_status", include_str!("../fixtures/shippo/tracking_status.json")),
+    ("shippo/tracking_webhook", include_str!("../fixtures/shippo/tracking_webhook.json")),
+    ("shippo/transaction", include_str!("../fixtures/shippo/transaction.json")),
+];
+
+/// Return a recorded response for a service, for example `fixture("shippo", "shipments")`.
+pub fn fixture(service: &str, name: &str) -> Value {
+    let key = format!("{}/{}", service, name);
+    match FIXTURES.iter().find(|(k, _)| *k == key) {
+        Some((_, contents)) => serde_json::from_str(contents).unwrap_or_else(|e| panic!("parsing fixture `{}` failed: {}", key, e)),
+        None => panic!("no fixture named `{}`", key),
+    }
+}
+
+/// Fake servers for each of the APIs we talk to.
+pub struct FakeApis {
+    pub airtable: MockServer,
+    pub auth0: MockServer,
+    pub sheets: MockServer,
+    pub shippo: MockServer,
+}
+
+impl FakeApis {
+    /// Start all the fake servers, loaded with our recorded responses.
+    pub async fn start() -> Self {
+        let apis = FakeApis {
+            airtable: MockServer::start().await,
+            auth0: MockServer::start().await,
+            sheets: MockServer::start().await,
+            shippo: MockServer::start().await,
+        };
+
+        mount_airtable(&apis.airtable).await;
+        mount_auth0(&apis.auth0).await;
+        mount_sheets(&apis.sheets).await;
+        mount_shippo(&apis.shippo).await;
+
+        apis
+    }
+
+    /// Return the environment variables that point our clients at the fake servers,
+    /// along with fake credentials.
+    pub fn env_vars(&self) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::new();
+        vars.insert("AIRTABLE_API_KEY", "fake-airtable-key".to_string());
+        vars.insert("AIRTABLE_BASE_ID", "appFakeBase".to_string());
+        vars.insert("AIRTABLE_ENDPOINT", format!("{}/v0/", self.airtable.uri()));
+        vars.insert("AUTH0_ENDPOINT", self.auth0.uri());
+        vars.insert("CIO_AUTH0_CLIENT_ID", "fake-auth0-client-id".to_string());
+        vars.insert("CIO_AUTH0_CLIENT_SECRET", "fake-auth0-client-secret".to_string());
+        vars.insert("SHEETS_DRIVE_ENDPOINT", format!("{}/drive/v3/", self.sheets.uri()));
+        vars.insert("SHEETS_ENDPOINT", format!("{}/v4/", self.sheets.uri()));
+        vars.insert("SHIPPO_API_TOKEN", "fake-shippo-token".to_string());
+        vars.insert("SHIPPO_ENDPOINT", format!("{}/", self.shippo.uri()));
+        vars
+    }
+
+    /// Set the environment variables that point our clients at the fake servers.
+    /// The previous values are restored when the returned guard is dropped.
+    pub fn env(&self) -> TestEnv {
+        TestEnv::set(self.env_vars())
+    }
+}
+
+/// A guard that holds the environment lock and restores the environment
+/// variables it changed when it is dropped.
+pub struct TestEnv {
+    previous: HashMap<&'static str, Option<String>>,
+
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    /// Set the environment variables, holding the lock until the guard is dropped.
+    pub fn set(vars: HashMap<&'static str, String>) -> Self {
+        // If another test panicked while holding the lock, the environment will
+        // still be restored by its guard, so we can keep going.
+        let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut previous = HashMap::new();
+        for (key, value) in vars {
+            previous.insert(key, env::var(key).ok());
+            env::set_var(key, value);
+        }
+
+        TestEnv { previous, _lock: lock }
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        for (key, value) in &self.previous {
+            match value {
+                Some(v) => env::set_var(key, v),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+
+/// Responds to Airtable create and update calls with the records that were sent,
+/// like the real API does.
+struct EchoRecords;
+
+impl Respond for EchoRecords {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+        let records: Vec<Value> = body["records"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut record)| {
+                if record["id"].as_str().unwrap_or_default().is_empty() {
+                    record["id"] = Value::String(format!("recFake{:010}", i));
+                }
+                record["createdTime"] = Value::String("2021-03-30T18:14:12.000Z".to_string());
+                record
+            })
+            .collect();
+
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({ "records": records }))
+    }
+}
+
+async fn mount_airtable(server: &MockServer) {
+    let records = fixture("airtable", "records");
+    let record = records["records"][0].clone();
+
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/v0/[^/]+/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(records))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/v0/[^/]+/[^/]+/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(record))
+        .mount(server)
+        .await;
+    Mock::given(method("POST")).and(path_regex(r"^/v0/[^/]+/[^/]+$")).respond_with(EchoRecords).mount(server).await;
+    Mock::given(method("PATCH")).and(path_regex(r"^/v0/[^/]+/[^/]+$")).respond_with(EchoRecords).mount(server).await;
+    Mock::given(method("DELETE"))
+        .and(path_regex(r"^/v0/[^/]+/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "records": [] })))
+        .mount(server)
+        .await;
+}
+
+async fn mount_auth0(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/oauth/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("auth0", "token")))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v2/users/[^/]+/logs$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("auth0", "logs")))
+        .mount(server)
+        .await;
+    // We only have one page of users, after that the list is empty.
+    Mock::given(method("GET"))
+        .and(path("/api/v2/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("auth0", "users")))
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v2/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .mount(server)
+        .await;
+}
+
+async fn mount_sheets(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/v4/spreadsheets/[^/]+/values/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("sheets", "values")))
+        .mount(server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path_regex(r"^/v4/spreadsheets/[^/]+/values/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "fake-sheet-id",
+            "updatedRange": "'Form Responses 1'!A1",
+            "updatedRows": 1,
+            "updatedColumns": 1,
+            "updatedCells": 1,
+        })))
+        .mount(server)
+        .await;
+    // The revisions of spreadsheets come from the Drive API.
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/drive/v3/files/[^/]+$"))
+        .and(query_param("fields", "version"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"version": "42"})))
+        .mount(server)
+        .await;
+}
+
+async fn mount_shippo(server: &MockServer) {
+    let shipments = fixture("shippo", "shipments");
+    let shipment = shipments["results"][0].clone();
+    let transaction = fixture("shippo", "transaction");
+
+    let address = fixture("shippo", "address");
+
+    Mock::given(method("POST"))
+        .and(path("/addresses"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(address.clone()))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/addresses/[^/]+(/validate)?$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(address))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(shipments))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(shipment.clone()))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/shipments/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(shipment))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/transactions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "previous": null,
+            "results": [transaction.clone()],
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(transaction.clone()))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/transactions/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(transaction))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/tracks"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "tracking_status")))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/tracks/[^/]+/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "tracking_status")))
+        .mount(server)
+        .await;
+}
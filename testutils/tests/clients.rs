@@ -0,0 +1,1560 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use airtable_api::{Airtable, Record};
+use chrono::{DateTime, Utc};
+use futures_util::{pin_mut, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sheets::{CellValue, GetValuesOptions, Sheets, SheetsError, ValueRange, ValuesCache};
+use shippo::{
+    redact_headers, Address, Client, DistanceUnit, Hook, LabelFileType, LineItem, MassUnit, Money, NewInstantTransaction, NewLiveRates, NewShipment, Parcel, RetryPolicy, ServiceLevelToken,
+    ShipmentListParams, Shippo, ShippoError, TrackingStatusCode, Transaction, TransactionListParams, TransactionStatus, Transport, TESTED_API_VERSION,
+};
+use testutils::{fixture, FakeApis, TestEnv};
+use wiremock::matchers::{body_partial_json, header, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Person {
+    name: String,
+    email: String,
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_against_fakes() {
+    let apis = FakeApis::start().await;
+    let _env = apis.env();
+
+    let shippo = Shippo::new_from_env().unwrap();
+
+    let shipments = shippo.list_shipments(&Default::default()).await.unwrap();
+    assert_eq!(shipments.len(), 1);
+    assert_eq!(shipments[0].address_to.name, "Jane Doe");
+
+    let label = shippo.get_shipping_label("70ae8117ee1749e393f249d5b77c45e0").await.unwrap();
+    assert_eq!(label.tracking_number, "9205590164917312751089");
+
+    let labels: Vec<_> = shippo.stream_transactions().try_collect().await.unwrap();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].tracking_number, label.tracking_number);
+
+    let status = shippo.get_tracking_status("usps", &label.tracking_number).await.unwrap();
+    assert_eq!(status.tracking_status.status, TrackingStatusCode::Transit);
+    assert_eq!(status.tracking_history.len(), 2);
+
+    let address = shippo.create_address(shipments[0].address_to.clone(), true).await.unwrap();
+    assert!(address.is_valid());
+    let address = shippo.validate_address(&address.object_id).await.unwrap();
+    assert_eq!(address.validation_results.messages[0].source, "USPS");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_follows_next_cursor() {
+    let server = MockServer::start().await;
+    let shipment = fixture("shippo", "shipments")["results"][0].clone();
+
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "previous": format!("{}/shipments?page=1&results=1", server.uri()),
+            "results": [shipment.clone()],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": format!("{}/shipments?page=2&results=1", server.uri()),
+            "previous": null,
+            "results": [shipment],
+        })))
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let shipments = shippo.list_shipments(&Default::default()).await.unwrap();
+    assert_eq!(shipments.len(), 2);
+
+    let shipments = shippo
+        .list_shipments_paginated(
+            &ShipmentListParams {
+                results: Some(1),
+                ..Default::default()
+            },
+            Some(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(shipments.len(), 1);
+
+    // Streaming follows the cursor lazily, one page at a time.
+    let stream = shippo.stream_shipments(&Default::default());
+    pin_mut!(stream);
+    assert!(stream.next().await.unwrap().is_ok());
+    assert_eq!(server.received_requests().await.unwrap().len(), 4);
+    assert!(stream.next().await.unwrap().is_ok());
+    assert!(stream.next().await.is_none());
+    assert_eq!(server.received_requests().await.unwrap().len(), 5);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_find_shipment_by_metadata() {
+    let server = MockServer::start().await;
+    let mut first = fixture("shippo", "shipments")["results"][0].clone();
+    first["metadata"] = "order-41".into();
+    let mut second = first.clone();
+    second["object_id"] = "6f2e7c3b1d6a4f0c9e8b7a5d4c3b2a10".into();
+    second["metadata"] = "order-42".into();
+
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "previous": format!("{}/shipments?page=1&results=1", server.uri()),
+            "results": [second],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": format!("{}/shipments?page=2&results=1", server.uri()),
+            "previous": null,
+            "results": [first],
+        })))
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    // The first page has a match, so the second is never fetched.
+    let shipment = shippo.find_shipment_by_metadata("order-41").await.unwrap().unwrap();
+    assert_eq!(shipment.metadata, "order-41");
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+    let shipment = shippo.find_shipment_by_metadata("order-42").await.unwrap().unwrap();
+    assert_eq!(shipment.object_id, "6f2e7c3b1d6a4f0c9e8b7a5d4c3b2a10");
+
+    assert!(shippo.find_shipment_by_metadata("order-43").await.unwrap().is_none());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_paginates_shipping_labels() {
+    let server = MockServer::start().await;
+    let transaction = fixture("shippo", "transaction");
+
+    Mock::given(method("GET"))
+        .and(path("/transactions"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "results": [transaction.clone()],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/transactions"))
+        .and(query_param("results", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": format!("{}/transactions?page=2&results=1", server.uri()),
+            "results": [transaction],
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let labels = shippo.list_shipping_labels_paginated(1, None).await.unwrap();
+    assert_eq!(labels.len(), 2);
+
+    let labels = shippo.list_shipping_labels_paginated(1, Some(1)).await.unwrap();
+    assert_eq!(labels.len(), 1);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_list_params() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .and(query_param("object_created_gt", "2021-01-01T00:00:00Z"))
+        .and(query_param("results", "25"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let params = ShipmentListParams {
+        object_created_gt: Some("2021-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()),
+        results: Some(25),
+        ..Default::default()
+    };
+    let shipments = shippo.list_shipments(&params).await.unwrap();
+    assert_eq!(shipments.len(), 1);
+
+    Mock::given(method("GET"))
+        .and(path("/transactions"))
+        .and(query_param("object_status", "SUCCESS"))
+        .and(query_param("object_created_gte", "2021-03-01T00:00:00Z"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "results": [fixture("shippo", "transaction")],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let params = TransactionListParams {
+        object_created_gte: Some("2021-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()),
+        ..Default::default()
+    }
+    .with_status(TransactionStatus::Success);
+    let labels = shippo.list_shipping_labels(&params).await.unwrap();
+    assert_eq!(labels.len(), 1);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_errors() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/transactions/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/tracks/usps/broken"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+            "address_to": { "zip": ["Invalid zip code for country US."] },
+            "parcels": [{ "weight": ["This field is required."] }],
+            "__all__": ["The shipment could not be created."],
+        })))
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    match shippo.get_shipment("missing").await {
+        Err(ShippoError::Api { status, body }) => {
+            assert_eq!(status.as_u16(), 404);
+            assert_eq!(body, "not found");
+        }
+        r => panic!("expected an api error, got {:?}", r),
+    }
+    assert!(matches!(shippo.get_shipping_label("broken").await, Err(ShippoError::Serde(_))));
+    assert!(matches!(shippo.get_tracking_status("usps", "broken").await, Err(ShippoError::Serde(_))));
+
+    // Validation errors tell us which fields are wrong.
+    let err = shippo.create_shipment(Default::default()).await.unwrap_err();
+    let details = err.details().unwrap();
+    assert_eq!(details.fields[1].field, "address_to.zip");
+    assert_eq!(
+        details.messages(),
+        vec![
+            "The shipment could not be created.",
+            "address_to.zip: Invalid zip code for country US.",
+            "parcels.0.weight: This field is required."
+        ]
+    );
+    assert!(shippo.get_shipment("missing").await.unwrap_err().details().is_none());
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint("not a url");
+    assert!(matches!(shippo.get_shipment("missing").await, Err(ShippoError::InvalidUrl(_))));
+}
+
+#[derive(Default)]
+struct RecordingHook {
+    requests: Mutex<Vec<String>>,
+    responses: Mutex<Vec<u16>>,
+}
+
+impl Hook for RecordingHook {
+    fn on_request(&self, request: &reqwest::Request) {
+        let headers = redact_headers(request.headers());
+        self.requests.lock().unwrap().push(format!("{} {:?}", request.url().path(), headers["authorization"]));
+    }
+
+    fn on_response(&self, _method: &reqwest::Method, _url: &reqwest::Url, response: Result<&reqwest::Response, &reqwest::Error>, _elapsed: Duration) {
+        self.responses.lock().unwrap().push(response.unwrap().status().as_u16());
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_hooks() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments/5e40ead7cffe4cc1ad45108696162e42"))
+        .respond_with(ResponseTemplate::new(429))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments/5e40ead7cffe4cc1ad45108696162e42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")["results"][0].clone()))
+        .mount(&server)
+        .await;
+
+    let hook = Arc::new(RecordingHook::default());
+    let shippo = Shippo::new("fake-shippo-token")
+        .unwrap()
+        .with_endpoint(format!("{}/", server.uri()))
+        .with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        })
+        .with_hook(hook.clone());
+
+    shippo.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.unwrap();
+
+    // Retries are seen by the hook too, and the token is never logged.
+    let requests = hook.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0], "/shipments/5e40ead7cffe4cc1ad45108696162e42 \"[redacted]\"");
+    assert_eq!(*hook.responses.lock().unwrap(), vec![429, 200]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_download_label() {
+    let api = MockServer::start().await;
+    let labels = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/70ae8117ee1749e393f249d5b77c45e0.zpl"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"^XA^FO50,50^FDJane Doe^FS^XZ".to_vec()))
+        .expect(1)
+        .mount(&labels)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", api.uri()));
+
+    let mut transaction: Transaction = serde_json::from_value(fixture("shippo", "transaction")).unwrap();
+    transaction.label_file_type = Some(LabelFileType::Zplii);
+    transaction.label_url = format!("{}/70ae8117ee1749e393f249d5b77c45e0.zpl", labels.uri());
+
+    let label = shippo.download_label(&transaction, LabelFileType::Zplii).await.unwrap();
+    assert_eq!(&label[..], b"^XA^FO50,50^FDJane Doe^FS^XZ");
+
+    // The label is not served by the API, so it never sees our token.
+    let requests = labels.received_requests().await.unwrap();
+    assert!(!requests[0].headers.contains_key(&"authorization".into()));
+
+    // Labels only come in the format they were bought in.
+    assert!(matches!(
+        shippo.download_label(&transaction, LabelFileType::Pdf4x6).await,
+        Err(ShippoError::LabelUnavailable { format: LabelFileType::Pdf4x6, .. })
+    ));
+
+    transaction.label_url = String::new();
+    assert!(matches!(shippo.download_label(&transaction, LabelFileType::Zplii).await, Err(ShippoError::LabelUnavailable { .. })));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_shared_client() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments/5e40ead7cffe4cc1ad45108696162e42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")["results"][0].clone()))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = Client::new("cio").unwrap();
+    let live = Shippo::builder("fake-shippo-token").client(client.clone()).endpoint(format!("{}/", server.uri())).build().unwrap();
+    let test = Shippo::builder("shippo_test_token").client(client.clone()).endpoint(format!("{}/", server.uri())).build().unwrap();
+
+    live.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.unwrap();
+    test.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.unwrap();
+
+    // Both clients count against the same pool.
+    let metrics = client.metrics();
+    assert_eq!(metrics.requests(), 2);
+    assert_eq!(live.metrics().requests(), 2);
+    assert_eq!(metrics.in_flight(), 0);
+    assert_eq!(metrics.errors(), 0);
+
+    // Nothing listens on port 1, so the request fails without a response.
+    let unreachable = Shippo::builder("fake-shippo-token")
+        .client(client)
+        .endpoint("http://127.0.0.1:1/")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    assert!(unreachable.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.is_err());
+    assert_eq!(metrics.requests(), 3);
+    assert_eq!(metrics.errors(), 1);
+}
+
+/// Returns the same response for every request, without a server.
+struct CannedTransport {
+    status: u16,
+    body: serde_json::Value,
+    urls: Mutex<Vec<String>>,
+}
+
+impl Transport for CannedTransport {
+    fn execute(&self, request: reqwest::Request) -> Pin<Box<dyn Future<Output = Result<reqwest::Response, reqwest::Error>> + Send + '_>> {
+        self.urls.lock().unwrap().push(request.url().to_string());
+        let resp = http::Response::builder().status(self.status).body(self.body.to_string()).unwrap();
+        Box::pin(async move { Ok(reqwest::Response::from(resp)) })
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_transport() {
+    let transport = Arc::new(CannedTransport {
+        status: 200,
+        body: fixture("shippo", "shipments")["results"][0].clone(),
+        urls: Default::default(),
+    });
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint("https://api.goshippo.com/").with_transport(transport.clone());
+
+    let shipment = shippo.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.unwrap();
+    assert_eq!(shipment.object_id, "5e40ead7cffe4cc1ad45108696162e42");
+    assert_eq!(*transport.urls.lock().unwrap(), vec!["https://api.goshippo.com/shipments/5e40ead7cffe4cc1ad45108696162e42"]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_deadline() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/shipments/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let start = Instant::now();
+    let stuck = shippo.with_token("fake-shippo-token").with_deadline(start + Duration::from_millis(200));
+    assert!(matches!(stuck.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await, Err(ShippoError::DeadlineExceeded)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // Once the deadline has passed nothing else is sent.
+    assert!(matches!(stuck.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await, Err(ShippoError::DeadlineExceeded)));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_tokens() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/shipments/[^/]+$"))
+        .and(header("Authorization", "ShippoToken shippo_test_1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")["results"][0].clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    {
+        let mut vars = HashMap::new();
+        vars.insert("SHIPPO_API_TOKEN", String::new());
+        vars.insert("SHIPPO_TEST_API_TOKEN", "shippo_test_1234".to_string());
+        let _env = TestEnv::set(vars);
+
+        assert!(matches!(Shippo::new_from_env(), Err(ShippoError::MissingEnv("SHIPPO_API_TOKEN"))));
+        assert!(Shippo::new_test_from_env().unwrap().is_test());
+    }
+
+    // The same client can make calls in live and test mode.
+    let live = Shippo::new("shippo_live_1234").unwrap().with_endpoint(format!("{}/", server.uri()));
+    let test = live.with_token("shippo_test_1234");
+    assert!(!live.is_test());
+    assert!(test.is_test());
+    test.get_shipment("5e40ead7cffe4cc1ad45108696162e42").await.unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_retries_rate_limited_requests() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri())).with_retry_policy(RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(10),
+        ..Default::default()
+    });
+
+    let shipments = shippo.list_shipments(&Default::default()).await.unwrap();
+    assert_eq!(shipments.len(), 1);
+
+    // Once we are out of retries, the rate limit is returned as an error.
+    let shippo = shippo.with_retry_policy(RetryPolicy::none());
+    Mock::given(method("GET")).and(path("/shipments/limited")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+    assert!(matches!(shippo.get_shipment("limited").await, Err(ShippoError::Api { .. })));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_builder() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments"))
+        .and(header("user-agent", "cio-tests"))
+        .and(header("shippo-api-version", TESTED_API_VERSION))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "shipments")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments/slow"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::builder("fake-shippo-token")
+        .endpoint(format!("{}/", server.uri()))
+        .user_agent("cio-tests")
+        .api_version(TESTED_API_VERSION)
+        .timeout(Duration::from_millis(200))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let shipments = shippo.list_shipments(&Default::default()).await.unwrap();
+    assert_eq!(shipments.len(), 1);
+
+    assert!(matches!(shippo.get_shipment("slow").await, Err(ShippoError::Http(e)) if e.is_timeout()));
+
+    // By default we say who we are, but leave the version to the account.
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+    shippo.list_shipments(&Default::default()).await.unwrap_err();
+    let requests = server.received_requests().await.unwrap();
+    let last = requests.last().unwrap();
+    assert!(last.headers[&"user-agent".into()].as_str().starts_with("shippo-rust/"));
+    assert!(!last.headers.contains_key(&"shippo-api-version".into()));
+
+    assert!(matches!(Shippo::builder("fake-shippo-token").endpoint("not a url").build(), Err(ShippoError::InvalidUrl(_))));
+}
+
+#[test]
+fn test_shippo_blocking() {
+    // The blocking client has to be used outside of a runtime, so we only use
+    // one to start the fake servers.
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let apis = runtime.block_on(FakeApis::start());
+    let _env = apis.env();
+
+    let shippo = shippo::blocking::Shippo::new_from_env().unwrap();
+
+    let shipments = shippo.list_shipments(&Default::default()).unwrap();
+    assert_eq!(shipments.len(), 1);
+
+    let label = shippo.get_shipping_label("70ae8117ee1749e393f249d5b77c45e0").unwrap();
+    assert_eq!(label.tracking_number, "9205590164917312751089");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_rates_for_shipment() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/shipments/5e40ead7cffe4cc1ad45108696162e42/rates/EUR"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "previous": null,
+            "results": [{
+                "object_id": "545ab0a1a6ea4c9f9adb2512a57d6d8b",
+                "object_created": "2021-03-30T18:14:12Z",
+                "shipment": "5e40ead7cffe4cc1ad45108696162e42",
+                "amount": "5.50",
+                "currency": "USD",
+                "amount_local": "4.61",
+                "currency_local": "EUR",
+                "provider": "USPS",
+                "servicelevel": { "name": "Priority Mail", "token": "usps_priority" },
+            }],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let rates = shippo.get_rates_for_shipment("5e40ead7cffe4cc1ad45108696162e42", "EUR").await.unwrap();
+    assert_eq!(rates.len(), 1);
+    assert_eq!(rates[0].price_local(), Money::parse("4.61", "EUR"));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_live_rates() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/live-rates"))
+        .and(body_partial_json(serde_json::json!({
+            "address_to": { "zip": "94117" },
+            "line_items": [{ "title": "Oxide t-shirt", "quantity": 2 }],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "next": null,
+            "previous": null,
+            "results": [{
+                "title": "USPS Priority Mail",
+                "description": null,
+                "amount": "7.85",
+                "currency": "USD",
+                "amount_local": "7.85",
+                "currency_local": "USD",
+                "estimated_days": 2,
+            }],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let rates = shippo
+        .get_live_rates(NewLiveRates {
+            address_to: Address {
+                name: "Jane Doe".to_string(),
+                zip: "94117".to_string(),
+                country: "US".to_string(),
+                ..Default::default()
+            },
+            line_items: vec![LineItem {
+                title: "Oxide t-shirt".to_string(),
+                quantity: 2,
+                weight: "0.5".to_string(),
+                weight_unit: Some(MassUnit::Lb),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(rates.len(), 1);
+    assert_eq!(rates[0].estimated_days, Some(2));
+    assert_eq!(rates[0].price(), Money::parse("7.85", "USD"));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_pickups() {
+    let server = MockServer::start().await;
+    let pickup = serde_json::json!({
+        "object_id": "7c7b3b9f0e2b4e1da8e4f4c5a1b2c3d4",
+        "object_created": "2021-03-30T18:14:12Z",
+        "carrier_account": "b741b99f95e841639b54272834bc478c",
+        "location": {
+            "building_location_type": "Front Door",
+            "address": { "name": "The Oxide Shipping Bot", "country": "US" },
+        },
+        "transactions": ["70ae8117ee1749e393f249d5b77c45e0"],
+        "requested_start_time": "2021-04-01T16:00:00Z",
+        "requested_end_time": "2021-04-01T20:00:00Z",
+        "status": "CONFIRMED",
+        "confirmation_code": "WTC12345",
+        "timezone": "US/Pacific",
+        "is_test": true,
+    });
+    let mut cancelled = pickup.clone();
+    cancelled["status"] = serde_json::json!("CANCELLED");
+
+    Mock::given(method("GET"))
+        .and(path("/pickups"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "next": null, "results": [pickup.clone()] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/pickups/[^/]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(pickup))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path_regex(r"^/pickups/[^/]+/cancel$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(cancelled))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let pickups = shippo.list_pickups().await.unwrap();
+    assert_eq!(pickups.len(), 1);
+    let pickup = shippo.get_pickup(&pickups[0].object_id).await.unwrap();
+    assert_eq!(pickup.status, "CONFIRMED");
+    let pickup = shippo.cancel_pickup(&pickup.object_id).await.unwrap();
+    assert_eq!(pickup.status, "CANCELLED");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_instant_label() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .and(body_partial_json(serde_json::json!({
+            "shipment": { "address_to": { "name": "Jane Doe" }, "parcels": [{ "weight": "1" }] },
+            "carrier_account": "b741b99f95e841639b54272834bc478c",
+            "servicelevel_token": "usps_priority",
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "transaction")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let shipment: shippo::Shipment = serde_json::from_value(fixture("shippo", "shipments")["results"][0].clone()).unwrap();
+    let label = shippo
+        .create_shipping_label_from_shipment(NewInstantTransaction {
+            shipment: NewShipment {
+                address_from: shipment.address_from,
+                address_to: shipment.address_to,
+                parcels: vec![Parcel::builder().dimensions(18.75, 14.5, 3.0, DistanceUnit::In).weight(1.0, MassUnit::Lb).build().unwrap()],
+                ..Default::default()
+            },
+            carrier_account: "b741b99f95e841639b54272834bc478c".to_string(),
+            servicelevel_token: ServiceLevelToken::UspsPriority,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(label.tracking_number, "9205590164917312751089");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_tracking_statuses() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/tracks/usps/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/tracks/usps/[0-9]+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture("shippo", "tracking_status")))
+        .expect(20)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let tracking_numbers: Vec<String> = (0..20).map(|i| format!("92055901649173127510{:02}", i)).collect();
+    let mut shipments: Vec<(&str, &str)> = tracking_numbers.iter().map(|t| ("usps", t.as_str())).collect();
+    shipments.insert(3, ("usps", "missing"));
+
+    let statuses = shippo.get_tracking_statuses(&shipments).await;
+    assert_eq!(statuses.len(), 21);
+    assert!(matches!(statuses[3], Err(ShippoError::Api { .. })));
+    assert_eq!(statuses.iter().filter(|s| s.is_ok()).count(), 20);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_max_label_cost() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/rates/545ab0a1a6ea4c9f9adb2512a57d6d8b"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "object_id": "545ab0a1a6ea4c9f9adb2512a57d6d8b",
+            "object_created": "2021-03-30T18:14:12Z",
+            "amount": "30.10",
+            "currency": "USD",
+            "provider": "UPS",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "transaction")))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let live = Shippo::new("shippo_live_1234")
+        .unwrap()
+        .with_endpoint(format!("{}/", server.uri()))
+        .with_max_label_cost(Money::parse("25", "USD").unwrap());
+    let nt = shippo::NewTransaction {
+        rate: "545ab0a1a6ea4c9f9adb2512a57d6d8b".to_string(),
+        ..Default::default()
+    };
+
+    match live.create_shipping_label_from_rate(nt.clone()).await {
+        Err(ShippoError::OverMaxCost { price, .. }) => assert_eq!(price, Money::parse("30.10", "USD")),
+        r => panic!("expected the label to be refused, got {:?}", r),
+    }
+
+    // It can be bought if we say so, and test labels are never checked.
+    live.create_shipping_label_from_rate(shippo::NewTransaction {
+        allow_over_max_cost: true,
+        ..nt.clone()
+    })
+    .await
+    .unwrap();
+    live.with_token("shippo_test_1234").create_shipping_label_from_rate(nt).await.unwrap();
+}
+
+/// A shipment from the fixture with a single USPS Priority rate at `amount`.
+fn shipment_with_rate(amount: &str) -> serde_json::Value {
+    let mut shipment = fixture("shippo", "shipments")["results"][0].clone();
+    shipment["rates"] = serde_json::json!([{
+        "object_id": "545ab0a1a6ea4c9f9adb2512a57d6d8b",
+        "object_created": "2021-03-30T18:14:12Z",
+        "amount": amount,
+        "currency": "USD",
+        "provider": "USPS",
+        "carrier_account": "b741b99f95e841639b54272834bc478c",
+        "servicelevel": {"name": "Priority Mail", "token": "usps_priority"},
+    }]);
+    shipment
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_max_label_cost_instant() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/shipments"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(shipment_with_rate("30.10")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "transaction")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let live = Shippo::new("shippo_live_1234")
+        .unwrap()
+        .with_endpoint(format!("{}/", server.uri()))
+        .with_max_label_cost(Money::parse("25", "USD").unwrap());
+    let shipment: shippo::Shipment = serde_json::from_value(fixture("shippo", "shipments")["results"][0].clone()).unwrap();
+    let nit = NewInstantTransaction {
+        shipment: NewShipment {
+            address_from: shipment.address_from,
+            address_to: shipment.address_to,
+            ..Default::default()
+        },
+        carrier_account: "b741b99f95e841639b54272834bc478c".to_string(),
+        servicelevel_token: ServiceLevelToken::UspsPriority,
+        ..Default::default()
+    };
+
+    match live.create_shipping_label_from_shipment(nit.clone()).await {
+        Err(ShippoError::OverMaxCost { rate, price, .. }) => {
+            assert_eq!(rate, "545ab0a1a6ea4c9f9adb2512a57d6d8b");
+            assert_eq!(price, Money::parse("30.10", "USD"));
+        }
+        r => panic!("expected the label to be refused, got {:?}", r),
+    }
+
+    // Saying so skips the check, so the shipment is not rated again.
+    live.create_shipping_label_from_shipment(NewInstantTransaction {
+        allow_over_max_cost: true,
+        ..nit.clone()
+    })
+    .await
+    .unwrap();
+
+    // A label under the maximum is bought from the rate of the shipment that
+    // was rated, with keys so a retry does not create another shipment.
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/shipments"))
+        .and(header("Idempotency-Key", "job-1-shipment"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(shipment_with_rate("10.00")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .and(header("Idempotency-Key", "job-1"))
+        .and(body_partial_json(serde_json::json!({"rate": "545ab0a1a6ea4c9f9adb2512a57d6d8b"})))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "transaction")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let live = live.with_endpoint(format!("{}/", server.uri()));
+    live.create_shipping_label_from_shipment(NewInstantTransaction {
+        idempotency_key: "job-1".to_string(),
+        ..nit
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_max_label_cost_batch() {
+    let server = MockServer::start().await;
+
+    let batch = |next: String, results: serde_json::Value| {
+        serde_json::json!({
+            "object_id": "5ef63c54f5bf45d3b1f8fb37dcb1c5f4",
+            "status": "VALID",
+            "default_carrier_account": "b741b99f95e841639b54272834bc478c",
+            "default_servicelevel_token": "usps_priority",
+            "batch_shipments": {"count": 3, "next": next, "previous": "", "results": results},
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/batches/5ef63c54f5bf45d3b1f8fb37dcb1c5f4"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(batch(String::new(), serde_json::json!([{"object_id": "bs3", "status": "VALID", "shipment": "expensive"}]))))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/batches/5ef63c54f5bf45d3b1f8fb37dcb1c5f4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(batch(
+            format!("{}/batches/5ef63c54f5bf45d3b1f8fb37dcb1c5f4?page=2", server.uri()),
+            serde_json::json!([
+                {"object_id": "bs1", "status": "VALID", "shipment": "cheap"},
+                // Shipments that already have a label are not checked again.
+                {"object_id": "bs2", "status": "VALID", "shipment": "bought", "transaction": "a9b5b3e4c0c14f7e8c0d0d3b3f1c1e5a"},
+            ]),
+        )))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments/cheap"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(shipment_with_rate("10.00")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/shipments/expensive"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(shipment_with_rate("30.10")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/batches/5ef63c54f5bf45d3b1f8fb37dcb1c5f4/purchase"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(batch(String::new(), serde_json::json!([]))))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let live = Shippo::new("shippo_live_1234")
+        .unwrap()
+        .with_endpoint(format!("{}/", server.uri()))
+        .with_max_label_cost(Money::parse("25", "USD").unwrap());
+
+    // One shipment on the second page is over, so nothing is bought.
+    match live.purchase_batch("5ef63c54f5bf45d3b1f8fb37dcb1c5f4").await {
+        Err(ShippoError::OverMaxCost { price, .. }) => assert_eq!(price, Money::parse("30.10", "USD")),
+        r => panic!("expected the batch to be refused, got {:?}", r),
+    }
+
+    // Test batches are never checked.
+    live.with_token("shippo_test_1234").purchase_batch("5ef63c54f5bf45d3b1f8fb37dcb1c5f4").await.unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_idempotency_key() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/transactions"))
+        .and(header("Idempotency-Key", "shipment-1234-label"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "transaction")))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    // A retried job sends the same key, so the label is only bought once.
+    let nt = shippo::NewTransaction {
+        rate: "545ab0a1a6ea4c9f9adb2512a57d6d8b".to_string(),
+        idempotency_key: "shipment-1234-label".to_string(),
+        ..Default::default()
+    };
+    assert!(serde_json::to_value(&nt).unwrap().get("idempotency_key").is_none());
+    let first = shippo.create_shipping_label_from_rate(nt.clone()).await.unwrap();
+    let second = shippo.create_shipping_label_from_rate(nt).await.unwrap();
+    assert_eq!(first.object_id, second.object_id);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_register_tracking_webhook_once() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/tracks"))
+        .and(header("Idempotency-Key", "track-usps-9205590164917312751089"))
+        .and(body_partial_json(serde_json::json!({"carrier": "usps", "tracking_number": "9205590164917312751089"})))
+        .respond_with(ResponseTemplate::new(201).set_body_json(fixture("shippo", "tracking_status")))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let first = shippo.register_tracking_webhook_once("usps", "9205590164917312751089").await.unwrap();
+    let second = shippo.register_tracking_webhook_once("usps", "9205590164917312751089").await.unwrap();
+    assert_eq!(first.tracking_number, second.tracking_number);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_shippo_webhooks() {
+    let server = MockServer::start().await;
+    let webhook = serde_json::json!({
+        "object_id": "e8c4a9e7e1d74f0f8e1c2a7b3d5f6a90",
+        "url": "https://webhooks.corp.oxide.computer/shippo/tracking/update",
+        "event": "track_updated",
+        "is_test": false,
+        "active": true,
+        "object_created": "2021-03-30T18:14:12Z",
+        "object_updated": null,
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/webhooks"))
+        .and(body_partial_json(serde_json::json!({ "event": "track_updated" })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(webhook.clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/webhooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "next": null, "results": [webhook] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path_regex(r"^/webhooks/[^/]+$"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let shippo = Shippo::new("fake-shippo-token").unwrap().with_endpoint(format!("{}/", server.uri()));
+
+    let webhook = shippo
+        .create_webhook(shippo::Webhook {
+            url: "https://webhooks.corp.oxide.computer/shippo/tracking/update".to_string(),
+            event: "track_updated".to_string(),
+            active: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(!webhook.object_id.is_empty());
+
+    let webhooks = shippo.list_webhooks().await.unwrap();
+    assert_eq!(webhooks.len(), 1);
+
+    shippo.delete_webhook(&webhook.object_id).await.unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_airtable_against_fakes() {
+    let apis = FakeApis::start().await;
+    let _env = apis.env();
+
+    let airtable = Airtable::new_from_env();
+
+    let records: Vec<Record<Person>> = airtable.list_records("People", "Grid view", vec![]).await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].fields.email, "jane@example.com");
+
+    let created = airtable
+        .create_records(
+            "People",
+            vec![Record {
+                id: "".to_string(),
+                created_time: None,
+                fields: Person {
+                    name: "John Doe".to_string(),
+                    email: "john@example.com".to_string(),
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(created.len(), 1);
+    assert!(!created[0].id.is_empty());
+    assert_eq!(created[0].fields.name, "John Doe");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_airtable_list_records_matching() {
+    let server = MockServer::start().await;
+    let records = fixture("airtable", "records");
+
+    // The formula is sent with every page.
+    Mock::given(method("GET"))
+        .and(path("/v0/appFakeBase/People"))
+        .and(query_param("filterByFormula", "{Email} = 'jane@example.com'"))
+        .and(query_param("offset", "page2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(records.clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut first = records.clone();
+    first["offset"] = serde_json::json!("page2");
+    Mock::given(method("GET"))
+        .and(path("/v0/appFakeBase/People"))
+        .and(query_param("filterByFormula", "{Email} = 'jane@example.com'"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(first))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let airtable = Airtable::new("fake-airtable-key", "appFakeBase", "").with_endpoint(format!("{}/v0/", server.uri()));
+    let matching: Vec<Record<Person>> = airtable.list_records_matching("People", "Grid view", vec![], "{Email} = 'jane@example.com'").await.unwrap();
+    assert_eq!(matching.len(), 2);
+    assert_eq!(matching[0].fields.email, "jane@example.com");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_token_source() {
+    let server = MockServer::start().await;
+
+    for token in &["Bearer token-1", "Bearer token-2"] {
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/v4/spreadsheets/[^/]+/values/.+$"))
+            .and(header("Authorization", *token))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fixture("sheets", "values")))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    // The source is asked for a token before every request, so a client that
+    // outlives its first token keeps working.
+    let calls = Arc::new(Mutex::new(0));
+    let counter = calls.clone();
+    let sheets = Sheets::from_token_source(move || {
+        let mut calls = counter.lock().unwrap();
+        *calls += 1;
+        let token = format!("token-{}", calls);
+        async move { Ok(token) }
+    })
+    .unwrap()
+    .with_endpoint(format!("{}/v4/", server.uri()));
+
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(*calls.lock().unwrap(), 2);
+
+    let failing = Sheets::from_token_source(|| async { Err(SheetsError::TokenExpired) })
+        .unwrap()
+        .with_endpoint(format!("{}/v4/", server.uri()));
+    assert!(matches!(failing.get_values("1234", "Sheet1!A1:B2".to_string()).await, Err(SheetsError::TokenExpired)));
+}
+
+/// A client for the fake Sheets API, sharing `cache`.
+fn cached_sheets(apis: &FakeApis, cache: Arc<ValuesCache>) -> Sheets {
+    Sheets::from_token_source(|| async { Ok("fake-sheets-token".to_string()) })
+        .unwrap()
+        .with_endpoint(format!("{}/v4/", apis.sheets.uri()))
+        .with_drive_endpoint(format!("{}/drive/v3/", apis.sheets.uri()))
+        .with_cache(cache)
+}
+
+/// How many times the fake Sheets API was asked for values, leaving out the
+/// revision checks sent to the Drive API.
+async fn values_reads(apis: &FakeApis) -> usize {
+    apis.sheets.received_requests().await.unwrap().iter().filter(|r| r.url.path().starts_with("/v4/")).count()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_values_cache() {
+    let apis = FakeApis::start().await;
+    let cache = Arc::new(ValuesCache::new(Duration::from_secs(60)));
+    let sheets = cached_sheets(&apis, cache.clone());
+
+    // Reading the same range twice only hits the API once, even from another
+    // client sharing the cache.
+    let first = sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    let second = cached_sheets(&apis, cache.clone()).get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(first.values, second.values);
+    assert_eq!(values_reads(&apis).await, 1);
+
+    // Other ranges and other options are cached on their own.
+    sheets.get_values("1234", "Sheet1!A1:C3".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 2);
+
+    // Writing to the spreadsheet clears it from the cache.
+    sheets.update_values("1234", "Sheet1!A1", "new".to_string()).await.unwrap();
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 4);
+
+    // As does invalidating it by hand.
+    cache.invalidate("1234");
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 5);
+
+    // Someone else changed the spreadsheet, so the values are read again.
+    Mock::given(method("GET"))
+        .and(path("/drive/v3/files/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"version": "43"})))
+        .with_priority(1)
+        .mount(&apis.sheets)
+        .await;
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 6);
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 6);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_values_cache_ttl() {
+    let apis = FakeApis::start().await;
+    let sheets = cached_sheets(&apis, Arc::new(ValuesCache::new(Duration::from_millis(50))));
+
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 1);
+
+    // Once the values expire they are read again.
+    tokio::time::delay_for(Duration::from_millis(100)).await;
+    sheets.get_values("1234", "Sheet1!A1:B2".to_string()).await.unwrap();
+    assert_eq!(values_reads(&apis).await, 2);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_check_revision() {
+    let apis = FakeApis::start().await;
+    let _env = apis.env();
+
+    // The fake Drive API has the spreadsheet at revision 42.
+    let sheets = Sheets::from_token_source(|| async { Ok("fake-sheets-token".to_string()) }).unwrap();
+    assert_eq!(sheets.get_revision("1234").await.unwrap(), 42);
+    sheets.check_revision("1234", 42).await.unwrap();
+
+    // Someone else changed it since we read it at 41.
+    match sheets.check_revision("1234", 41).await {
+        Err(SheetsError::Conflict { expected, actual }) => assert_eq!((expected, actual), (41, 42)),
+        r => panic!("expected a conflict, got {:?}", r),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_invalid_revision() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/drive/v3/files/1234"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"version": "not-a-number"})))
+        .mount(&server)
+        .await;
+
+    let sheets = Sheets::from_token_source(|| async { Ok("fake-sheets-token".to_string()) })
+        .unwrap()
+        .with_drive_endpoint(format!("{}/drive/v3/", server.uri()));
+    match sheets.get_revision("1234").await {
+        Err(SheetsError::InvalidVersion(v)) => assert_eq!(v, "not-a-number"),
+        r => panic!("expected an invalid version, got {:?}", r),
+    }
+}
+
+/// A client for a fake Sheets API at `server`.
+fn sheets_client(server: &MockServer) -> Sheets {
+    Sheets::from_token_source(|| async { Ok("fake-sheets-token".to_string()) })
+        .unwrap()
+        .with_endpoint(format!("{}/v4/", server.uri()))
+}
+
+/// Respond with the rows as the values of a range.
+fn rows_response(rows: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({"range": "Sheet1", "majorDimension": "ROWS", "values": rows}))
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_stream_rows() {
+    let server = MockServer::start().await;
+
+    // Two full chunks and a short one, which is the end of the sheet.
+    for (range, rows) in &[
+        ("Sheet1!1:2", serde_json::json!([["a"], ["b"]])),
+        ("Sheet1!3:4", serde_json::json!([["c"], ["d"]])),
+        ("Sheet1!5:6", serde_json::json!([["e"]])),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/v4/spreadsheets/1234/values/{}", range)))
+            .respond_with(rows_response(rows.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let sheets = sheets_client(&server);
+    let rows: Vec<Vec<CellValue>> = sheets.stream_rows("1234", "Sheet1", 2).try_collect().await.unwrap();
+    let rows: Vec<String> = rows.iter().map(|r| r[0].to_string()).collect();
+    assert_eq!(rows, vec!["a", "b", "c", "d", "e"]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_stream_rows_stops_at_empty_row() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!1:3"))
+        .respond_with(rows_response(serde_json::json!([["a"], [], ["notes"]])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!4:6"))
+        .respond_with(rows_response(serde_json::json!([["never read"]])))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    // Everything after the first empty row is left alone, even in the same chunk.
+    let sheets = sheets_client(&server);
+    let rows: Vec<Vec<CellValue>> = sheets.stream_rows("1234", "Sheet1", 3).try_collect().await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0].to_string(), "a");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_batch_values() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values:batchGet"))
+        .and(query_param("ranges", "Sheet1!A1:B2"))
+        .and(query_param("ranges", "Sheet2!A1"))
+        .and(header("Authorization", "Bearer fake-sheets-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "valueRanges": [
+                {"range": "Sheet1!A1:B2", "values": [["a", "b"]]},
+                {"range": "Sheet2!A1", "values": [["c"]]},
+            ],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234/values:batchUpdate"))
+        .and(body_partial_json(serde_json::json!({
+            "valueInputOption": "USER_ENTERED",
+            "data": [{"range": "Sheet1!A1", "values": [["new"]]}],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "totalUpdatedRows": 1,
+            "totalUpdatedColumns": 1,
+            "totalUpdatedCells": 1,
+            "totalUpdatedSheets": 1,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!A2:B:clear"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"spreadsheetId": "1234", "clearedRange": "Sheet1!A2:B1000"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!A1:B2"))
+        .and(query_param("valueInputOption", "RAW"))
+        .and(body_partial_json(serde_json::json!({"values": [["1", "2"], ["3", "4"]]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "updatedRange": "Sheet1!A1:B2",
+            "updatedRows": 2,
+            "updatedColumns": 2,
+            "updatedCells": 4,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sheets = sheets_client(&server);
+
+    let got = sheets.batch_get_values("1234", &["Sheet1!A1:B2", "Sheet2!A1"], &GetValuesOptions::default()).await.unwrap();
+    assert_eq!(got.value_ranges.len(), 2);
+    assert_eq!(got.value_ranges[1].string_rows(), vec![vec!["c"]]);
+
+    let updated = sheets
+        .batch_update_values(
+            "1234",
+            vec![ValueRange {
+                range: Some("Sheet1!A1".to_string()),
+                values: Some(vec![vec!["new".into()]]),
+                ..Default::default()
+            }],
+            sheets::ValueInputOption::UserEntered,
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.total_updated_cells, Some(1));
+
+    let cleared = sheets.clear_values("1234", "Sheet1!A2:B").await.unwrap();
+    assert_eq!(cleared.cleared_range.as_deref(), Some("Sheet1!A2:B1000"));
+
+    let values = ValueRange {
+        values: Some(vec![vec!["1".into(), "2".into()], vec!["3".into(), "4".into()]]),
+        ..Default::default()
+    };
+    let updated = sheets.update_values_range("1234", "Sheet1!A1:B2", values, sheets::ValueInputOption::Raw).await.unwrap();
+    assert_eq!(updated.updated_cells, Some(4));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_spreadsheets() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets"))
+        .and(body_partial_json(serde_json::json!({"properties": {"title": "Swag"}})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "properties": {"title": "Swag"},
+            "sheets": [{"properties": {"sheetId": 0, "title": "Sheet1"}}],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234"))
+        .and(query_param("includeGridData", "false"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "sheets": [{"properties": {"sheetId": 0, "title": "Sheet1"}}, {"properties": {"sheetId": 7, "title": "Archive"}}],
+            "namedRanges": [{"namedRangeId": "n1", "name": "Totals", "range": {"sheetId": 0}}],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234:batchUpdate"))
+        .and(body_partial_json(serde_json::json!({"requests": [{"addSheet": {"properties": {"title": "Archive"}}}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "replies": [{"addSheet": {"properties": {"sheetId": 7, "title": "Archive"}}}],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234:batchUpdate"))
+        .and(body_partial_json(
+            serde_json::json!({"requests": [{"duplicateSheet": {"sourceSheetId": 7, "newSheetName": "Archive 2"}}]}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "spreadsheetId": "1234",
+            "replies": [{"duplicateSheet": {"properties": {"sheetId": 8, "title": "Archive 2"}}}],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234:batchUpdate"))
+        .and(body_partial_json(serde_json::json!({"requests": [{"deleteSheet": {"sheetId": 8}}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"spreadsheetId": "1234", "replies": [{}]})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v4/spreadsheets/1234/sheets/7:copyTo"))
+        .and(body_partial_json(serde_json::json!({"destinationSpreadsheetId": "5678"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"sheetId": 3, "title": "Copy of Archive"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sheets = sheets_client(&server);
+
+    let created = sheets.create_spreadsheet("Swag").await.unwrap();
+    assert_eq!(created.spreadsheet_id.as_deref(), Some("1234"));
+
+    let spreadsheet = sheets.get_spreadsheet("1234").await.unwrap();
+    assert_eq!(spreadsheet.sheet_titles(), vec!["Sheet1", "Archive"]);
+    assert_eq!(spreadsheet.sheet_by_title("Archive").and_then(|s| s.sheet_id), Some(7));
+    assert_eq!(spreadsheet.named_ranges.len(), 1);
+
+    let added = sheets.add_sheet("1234", "Archive").await.unwrap();
+    assert_eq!(added.sheet_id, Some(7));
+    let duplicate = sheets.duplicate_sheet("1234", 7, "Archive 2").await.unwrap();
+    assert_eq!(duplicate.sheet_id, Some(8));
+    sheets.delete_sheet("1234", 8).await.unwrap();
+
+    let copy = sheets.copy_sheet_to("1234", 7, "5678").await.unwrap();
+    assert_eq!(copy.title.as_deref(), Some("Copy of Archive"));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_sheets_retries_and_hooks() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!A1"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!A1"))
+        .respond_with(rows_response(serde_json::json!([["a"]])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let hook = Arc::new(RecordingHook::default());
+    let sheets = sheets_client(&server)
+        .with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        })
+        .with_hook(hook.clone());
+
+    // The quota error is retried, and the hook and metrics see both requests.
+    let values = sheets.get_values("1234", "Sheet1!A1".to_string()).await.unwrap();
+    assert_eq!(values.string_rows(), vec![vec!["a"]]);
+    assert_eq!(*hook.responses.lock().unwrap(), vec![429, 200]);
+    assert_eq!(hook.requests.lock().unwrap()[0], "/v4/spreadsheets/1234/values/Sheet1!A1 \"[redacted]\"");
+    assert_eq!(sheets.metrics().requests(), 2);
+
+    // Without retries the quota error is returned.
+    Mock::given(method("GET"))
+        .and(path("/v4/spreadsheets/1234/values/Sheet1!B1"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+    let sheets = sheets.with_retry_policy(RetryPolicy::none());
+    match sheets.get_values("1234", "Sheet1!B1".to_string()).await {
+        Err(SheetsError::Api { status, .. }) => assert_eq!(status, reqwest::StatusCode::TOO_MANY_REQUESTS),
+        r => panic!("expected the quota error, got {:?}", r),
+    }
+}
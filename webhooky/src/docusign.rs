@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::instrument;
+
+/// The event DocuSign Connect sends when every recipient has signed an envelope.
+pub const ENVELOPE_COMPLETED: &str = "envelope-completed";
+
+/// A DocuSign Connect event, in the JSON (SIM) format.
+/// FROM: https://developers.docusign.com/platform/webhooks/connect/json-sim-event-model/
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DocuSignEvent {
+    /// The event type, for example `envelope-completed`.
+    #[serde(default)]
+    pub event: String,
+    #[serde(default, rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(default)]
+    pub data: DocuSignEventData,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DocuSignEventData {
+    #[serde(default, rename = "accountId")]
+    pub account_id: String,
+    #[serde(default, rename = "envelopeId")]
+    pub envelope_id: String,
+    #[serde(default, rename = "envelopeSummary")]
+    pub envelope_summary: EnvelopeSummary,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EnvelopeSummary {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, rename = "emailSubject")]
+    pub email_subject: String,
+    #[serde(rename = "completedDateTime")]
+    pub completed_date_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub recipients: Recipients,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Recipients {
+    #[serde(default)]
+    pub signers: Vec<Signer>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Signer {
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub status: String,
+}
+
+/// The kind of document in an envelope, based on the subject of the envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentKind {
+    OfferLetter,
+    Nda,
+    Other,
+}
+
+impl DocuSignEvent {
+    /// Parse the raw body of the request.
+    #[instrument(skip(body))]
+    #[inline]
+    pub fn parse(body: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(body)
+    }
+
+    /// Returns the kind of document that was signed.
+    #[instrument]
+    #[inline]
+    pub fn document_kind(&self) -> DocumentKind {
+        // Match whole words, so subjects like "Agenda" are not taken for an NDA.
+        let subject = self.data.envelope_summary.email_subject.to_lowercase();
+        let words: Vec<&str> = subject.split(|c: char| !c.is_alphanumeric()).collect();
+        if words.contains(&"offer") {
+            DocumentKind::OfferLetter
+        } else if words.contains(&"nda") || words.windows(2).any(|w| w == ["non", "disclosure"]) {
+            DocumentKind::Nda
+        } else {
+            DocumentKind::Other
+        }
+    }
+}
+
+/// Verify a request came from DocuSign Connect, with the raw body of the
+/// request and the values of the `X-DocuSign-Signature-N` headers. There is one
+/// header per HMAC key configured in Connect, so any of them matching is enough.
+/// FROM: https://developers.docusign.com/platform/webhooks/connect/hmac/
+#[instrument(skip(key, body))]
+#[inline]
+pub fn verify_signature(key: &str, body: &[u8], signatures: &[String]) -> bool {
+    // If the key is not set, nobody gets in.
+    if key.is_empty() {
+        return false;
+    }
+
+    signatures.iter().any(|signature| {
+        let expected = match base64::decode(signature.trim()) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+
+        let mut mac = Hmac::<Sha256>::new_varkey(key.as_bytes()).unwrap();
+        mac.update(body);
+        mac.verify(&expected).is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac, NewMac};
+    use sha2::Sha256;
+
+    use crate::docusign::{verify_signature, DocuSignEvent, DocumentKind, ENVELOPE_COMPLETED};
+
+    fn sign(key: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(key.as_bytes()).unwrap();
+        mac.update(body);
+        base64::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_docusign_event_signature() {
+        let body = br#"{"event":"envelope-completed","apiVersion":"v2.1","data":{"accountId":"1","envelopeId":"2","envelopeSummary":{"status":"completed","emailSubject":"Please DocuSign: Offer Letter","completedDateTime":"2021-01-22T18:41:37.84Z","recipients":{"signers":[{"email":"jess@example.com","name":"Jess","status":"completed"}]}}}}"#;
+        let signature = sign("key", body);
+
+        assert!(verify_signature("key", body, &[signature.to_string()]));
+        assert!(verify_signature("key", body, &["bm9wZQ==".to_string(), signature.to_string()]));
+        assert!(!verify_signature("other", body, &[signature.to_string()]));
+        assert!(!verify_signature("", body, &[signature.to_string()]));
+        assert!(!verify_signature("key", b"{}", &[signature.to_string()]));
+        assert!(!verify_signature("key", body, &[]));
+
+        let event = DocuSignEvent::parse(body).unwrap();
+        assert_eq!(event.event, ENVELOPE_COMPLETED);
+        assert_eq!(event.data.envelope_id, "2");
+        assert_eq!(event.document_kind(), DocumentKind::OfferLetter);
+        assert_eq!(event.data.envelope_summary.recipients.signers[0].email, "jess@example.com");
+    }
+
+    fn event_with_subject(subject: &str) -> DocuSignEvent {
+        let body = serde_json::json!({"event": ENVELOPE_COMPLETED, "data": {"envelopeId": "2", "envelopeSummary": {"emailSubject": subject}}});
+        DocuSignEvent::parse(&serde_json::to_vec(&body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_docusign_document_kind() {
+        assert_eq!(event_with_subject("Please DocuSign: Offer Letter").document_kind(), DocumentKind::OfferLetter);
+        assert_eq!(event_with_subject("Please DocuSign: Oxide NDA").document_kind(), DocumentKind::Nda);
+        assert_eq!(event_with_subject("Please DocuSign: NDA.pdf").document_kind(), DocumentKind::Nda);
+        assert_eq!(event_with_subject("Mutual Non-Disclosure Agreement").document_kind(), DocumentKind::Nda);
+
+        // Words that only contain "nda" or "offer" are not matched.
+        assert_eq!(event_with_subject("Please DocuSign: Board Meeting Agenda").document_kind(), DocumentKind::Other);
+        assert_eq!(event_with_subject("Calendar for Q3").document_kind(), DocumentKind::Other);
+        assert_eq!(event_with_subject("Standard Vendor Agreement").document_kind(), DocumentKind::Other);
+        assert_eq!(event_with_subject("Counteroffers").document_kind(), DocumentKind::Other);
+    }
+}
@@ -0,0 +1,385 @@
+use std::sync::Arc;
+
+use dropshot::{HttpError, HttpResponseAccepted};
+use futures_util::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{event, instrument, Level};
+
+use cio_api::models::GitHubUser;
+
+use crate::event_types::EventType;
+use crate::repos::Repo;
+use crate::{Context, GitHubCheckRun, GitHubComment, GitHubCommit, GitHubIssue, GitHubPullRequest, GitHubRepo, GitHubWebhook};
+
+/// A `push` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#push
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct PushEvent {
+    /// The full `git ref` that was pushed. Example: `refs/heads/main`.
+    #[serde(default, skip_serializing_if = "String::is_empty", rename = "ref")]
+    pub refv: String,
+    /// The SHA of the most recent commit on `ref` before the push.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub before: String,
+    /// The SHA of the most recent commit on `ref` after the push.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub after: String,
+    /// An array of commit objects describing the pushed commits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<GitHubCommit>,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+impl PushEvent {
+    /// Return the last distinct commit of the push with the files changed by
+    /// all of the distinct commits, so a file is in `removed` only if the
+    /// last commit that touched it removed it.
+    /// If none of the commits are distinct, for example when a branch is
+    /// fast-forwarded to commits that were already pushed elsewhere, all of
+    /// the commits are combined instead.
+    #[instrument]
+    #[inline]
+    pub fn combined_commit(&self) -> Option<GitHubCommit> {
+        let any_distinct = self.commits.iter().any(|c| c.distinct);
+
+        let mut combined: Option<GitHubCommit> = None;
+        for commit in self.commits.iter().filter(|c| c.distinct || !any_distinct) {
+            let mut c = match combined.take() {
+                Some(c) => c,
+                None => {
+                    combined = Some(commit.clone());
+                    continue;
+                }
+            };
+
+            for file in commit.added.iter().chain(commit.modified.iter()) {
+                c.removed.retain(|f| f != file);
+                if !c.added.contains(file) && !c.modified.contains(file) {
+                    if commit.added.contains(file) {
+                        c.added.push(file.to_string());
+                    } else {
+                        c.modified.push(file.to_string());
+                    }
+                }
+            }
+            for file in &commit.removed {
+                c.added.retain(|f| f != file);
+                c.modified.retain(|f| f != file);
+                if !c.removed.contains(file) {
+                    c.removed.push(file.to_string());
+                }
+            }
+
+            combined = Some(GitHubCommit {
+                added: c.added,
+                modified: c.modified,
+                removed: c.removed,
+                ..commit.clone()
+            });
+        }
+
+        combined
+    }
+}
+
+/// A `pull_request` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#pull_request
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct PullRequestEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    /// The pull request number.
+    #[serde(default)]
+    pub number: i64,
+    #[serde(default)]
+    pub pull_request: GitHubPullRequest,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+/// An `issues` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#issues
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct IssuesEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    #[serde(default)]
+    pub issue: GitHubIssue,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+/// An `issue_comment` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#issue_comment
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct IssueCommentEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    /// The issue or pull request the comment belongs to.
+    #[serde(default)]
+    pub issue: GitHubIssue,
+    #[serde(default)]
+    pub comment: GitHubComment,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+/// A `check_run` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#check_run
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct CheckRunEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    #[serde(default)]
+    pub check_run: GitHubCheckRun,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+/// A `repository` event.
+/// FROM: https://docs.github.com/en/free-pro-team@latest/developers/webhooks-and-events/webhook-events-and-payloads#repository
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct RepositoryEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    #[serde(default)]
+    pub repository: GitHubRepo,
+    #[serde(default)]
+    pub sender: GitHubUser,
+}
+
+/// A GitHub webhook event with the payload for its event type.
+#[derive(Debug, Clone)]
+pub enum GitHubEvent {
+    Push(PushEvent),
+    PullRequest(PullRequestEvent),
+    Issues(IssuesEvent),
+    IssueComment(IssueCommentEvent),
+    CheckRun(CheckRunEvent),
+    Repository(RepositoryEvent),
+    /// An event type we do not have a payload for yet.
+    Other(EventType),
+}
+
+impl GitHubEvent {
+    /// Split the fields of a webhook into the payload for its event type.
+    #[instrument]
+    #[inline]
+    pub fn new(event_type: EventType, webhook: GitHubWebhook) -> Self {
+        match event_type {
+            EventType::Push => GitHubEvent::Push(PushEvent {
+                refv: webhook.refv,
+                before: webhook.before,
+                after: webhook.after,
+                commits: webhook.commits,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            EventType::PullRequest => GitHubEvent::PullRequest(PullRequestEvent {
+                action: webhook.action,
+                number: webhook.number,
+                pull_request: webhook.pull_request,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            EventType::Issues => GitHubEvent::Issues(IssuesEvent {
+                action: webhook.action,
+                issue: webhook.issue,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            EventType::IssueComment => GitHubEvent::IssueComment(IssueCommentEvent {
+                action: webhook.action,
+                issue: webhook.issue,
+                comment: webhook.comment,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            EventType::CheckRun => GitHubEvent::CheckRun(CheckRunEvent {
+                action: webhook.action,
+                check_run: webhook.check_run,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            EventType::Repository => GitHubEvent::Repository(RepositoryEvent {
+                action: webhook.action,
+                repository: webhook.repository,
+                sender: webhook.sender,
+            }),
+            _ => GitHubEvent::Other(event_type),
+        }
+    }
+}
+
+/// A function that handles one type of event.
+/// Async functions can be registered with a closure like
+/// `|ctx, event| handle_push(ctx, event).boxed()`.
+pub type Handler<T> = fn(Arc<Context>, T) -> BoxFuture<'static, Result<HttpResponseAccepted<String>, HttpError>>;
+
+/// The handlers for one type of event, with the repo each one is for.
+pub struct Handlers<T> {
+    handlers: Vec<(Repo, Handler<T>)>,
+}
+
+impl<T> Default for Handlers<T> {
+    fn default() -> Self {
+        Handlers { handlers: Default::default() }
+    }
+}
+
+impl<T: Clone> Handlers<T> {
+    /// Register a handler for events to a repo, `Repo::Wildcard` is any repo.
+    pub fn on(&mut self, repo: Repo, handler: Handler<T>) -> &mut Self {
+        self.handlers.push((repo, handler));
+        self
+    }
+
+    /// Run every handler for the repo in the order they were registered.
+    /// All of them run even if one fails, the first error is returned.
+    async fn run(&self, api_context: Arc<Context>, repo: Repo, event: T) -> Result<usize, HttpError> {
+        let mut ran = 0;
+        let mut error = None;
+        for (r, handler) in &self.handlers {
+            if *r != Repo::Wildcard && *r != repo {
+                continue;
+            }
+
+            ran += 1;
+            if let Err(e) = handler(api_context.clone(), event.clone()).await {
+                event!(Level::WARN, "handler for repo `{}` failed: {:?}", r, e);
+                error.get_or_insert(e);
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(ran),
+        }
+    }
+}
+
+/// The dispatch table for GitHub events, other automations hook in by
+/// registering a handler for the events they care about.
+#[derive(Default)]
+pub struct GitHubEventHandlers {
+    pub push: Handlers<PushEvent>,
+    pub pull_request: Handlers<PullRequestEvent>,
+    pub issues: Handlers<IssuesEvent>,
+    pub issue_comment: Handlers<IssueCommentEvent>,
+    pub check_run: Handlers<CheckRunEvent>,
+    pub repository: Handlers<RepositoryEvent>,
+}
+
+impl GitHubEventHandlers {
+    /// Run the handlers registered for the event.
+    pub async fn dispatch(&self, api_context: Arc<Context>, event: GitHubEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
+        let (event_type, repo_name) = match &event {
+            GitHubEvent::Push(e) => (EventType::Push, &e.repository.name),
+            GitHubEvent::PullRequest(e) => (EventType::PullRequest, &e.repository.name),
+            GitHubEvent::Issues(e) => (EventType::Issues, &e.repository.name),
+            GitHubEvent::IssueComment(e) => (EventType::IssueComment, &e.repository.name),
+            GitHubEvent::CheckRun(e) => (EventType::CheckRun, &e.repository.name),
+            GitHubEvent::Repository(e) => (EventType::Repository, &e.repository.name),
+            GitHubEvent::Other(event_type) => {
+                event!(Level::DEBUG, "no handlers for `{}` events", event_type);
+                return Ok(HttpResponseAccepted("ok".to_string()));
+            }
+        };
+        let repo = repo_name.parse::<Repo>().unwrap();
+
+        let ran = match event {
+            GitHubEvent::Push(e) => self.push.run(api_context, repo, e).await?,
+            GitHubEvent::PullRequest(e) => self.pull_request.run(api_context, repo, e).await?,
+            GitHubEvent::Issues(e) => self.issues.run(api_context, repo, e).await?,
+            GitHubEvent::IssueComment(e) => self.issue_comment.run(api_context, repo, e).await?,
+            GitHubEvent::CheckRun(e) => self.check_run.run(api_context, repo, e).await?,
+            GitHubEvent::Repository(e) => self.repository.run(api_context, repo, e).await?,
+            GitHubEvent::Other(_) => 0,
+        };
+
+        if ran == 0 {
+            // We can throw this out, log it and return early.
+            event!(Level::INFO, "`{}` event was to the {} repo, no automations are set up for this repo yet", event_type, repo);
+        }
+
+        Ok(HttpResponseAccepted("ok".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event_types::EventType;
+    use crate::github_events::{GitHubEvent, PushEvent};
+    use crate::GitHubWebhook;
+
+    #[test]
+    fn test_github_event_payloads() {
+        let webhook: GitHubWebhook = serde_json::from_value(json!({
+            "action": "created",
+            "issue": {"number": 42, "labels_url": "", "events_url": ""},
+            "comment": {"id": 7, "body": "lgtm"},
+            "repository": {"name": "rfd"},
+        }))
+        .unwrap();
+
+        match GitHubEvent::new(EventType::IssueComment, webhook.clone()) {
+            GitHubEvent::IssueComment(e) => {
+                assert_eq!(e.action, "created");
+                assert_eq!(e.issue.number, 42);
+                assert_eq!(e.comment.body, "lgtm");
+                assert_eq!(e.repository.name, "rfd");
+            }
+            e => panic!("expected an issue_comment event, got {:?}", e),
+        }
+
+        assert!(matches!(GitHubEvent::new(EventType::Fork, webhook), GitHubEvent::Other(EventType::Fork)));
+    }
+
+    #[test]
+    fn test_push_event_combined_commit() {
+        let push: PushEvent = serde_json::from_value(json!({
+            "ref": "refs/heads/0042",
+            "commits": [
+                {"id": "a", "distinct": true, "added": ["rfd/0042/README.adoc", "rfd/0042/diagram.svg"]},
+                {"id": "b", "distinct": false, "modified": ["rfd/0001/README.adoc"]},
+                {"id": "c", "distinct": true, "modified": ["rfd/0042/README.adoc"], "removed": ["rfd/0042/diagram.svg", "rfd/0007/README.md"]},
+            ],
+        }))
+        .unwrap();
+
+        let commit = push.combined_commit().unwrap();
+        assert_eq!(commit.id, "c");
+        assert_eq!(commit.added, vec!["rfd/0042/README.adoc"]);
+        assert!(commit.modified.is_empty());
+        assert_eq!(commit.removed, vec!["rfd/0042/diagram.svg", "rfd/0007/README.md"]);
+
+        assert!(PushEvent::default().combined_commit().is_none());
+
+        // A fast-forward push of commits that were already pushed to another branch.
+        let push: PushEvent = serde_json::from_value(json!({
+            "ref": "refs/heads/master",
+            "commits": [
+                {"id": "a", "distinct": false, "added": ["rfd/0042/README.adoc"]},
+                {"id": "b", "distinct": false, "modified": ["rfd/0001/README.adoc"]},
+            ],
+        }))
+        .unwrap();
+
+        let commit = push.combined_commit().unwrap();
+        assert_eq!(commit.id, "b");
+        assert_eq!(commit.added, vec!["rfd/0042/README.adoc"]);
+        assert_eq!(commit.modified, vec!["rfd/0001/README.adoc"]);
+    }
+}
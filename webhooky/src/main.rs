@@ -1,9 +1,15 @@
 #![allow(clippy::field_reassign_with_default)]
+pub mod docusign;
+use crate::docusign::{DocuSignEvent, DocumentKind, ENVELOPE_COMPLETED};
 pub mod event_types;
 use crate::event_types::EventType;
+pub mod github_events;
+use crate::github_events::{GitHubEvent, GitHubEventHandlers, PullRequestEvent, PushEvent, RepositoryEvent};
 pub mod repos;
 use crate::repos::Repo;
 pub mod influx;
+pub mod slack_commands;
+use crate::slack_commands::{verify_signature, SlackCommand};
 #[macro_use]
 extern crate serde_json;
 
@@ -20,29 +26,33 @@ use chrono::{DateTime, TimeZone};
 use chrono_humanize::HumanTime;
 use diesel::prelude::*;
 use dropshot::{endpoint, ApiDescription, ConfigDropshot, ConfigLogging, ConfigLoggingLevel, HttpError, HttpResponseAccepted, HttpResponseOk, HttpServer, Path, Query, RequestContext, TypedBody};
+use futures_util::future::FutureExt;
 use google_drive::GoogleDrive;
 use hubcaps::issues::{IssueListOptions, State};
 use hubcaps::Github;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sheets::Sheets;
+use shippo::TrackingWebhookEvent;
 use tracing::{event, instrument, span, Level};
 use tracing_subscriber::prelude::*;
 
 use cio_api::analytics::NewPageView;
 use cio_api::applicants::get_role_from_sheet_id;
-use cio_api::applicants::{Applicant, NewApplicant};
+use cio_api::applicants::{onboard_applicants_with_signed_offer, Applicant, NewApplicant};
 use cio_api::configs::{get_configs_from_repo, sync_buildings, sync_certificates, sync_conference_rooms, sync_github_outside_collaborators, sync_groups, sync_links, sync_users};
 use cio_api::db::Database;
+use cio_api::expenses::{handle_inbound_receipt, InboundReceipt, NewCardTransaction, ReceiptAttachment};
 use cio_api::mailing_list::{MailchimpWebhook, MailingListSubscriber};
 use cio_api::models::{GitHubUser, NewRFD, NewRepo, RFD};
 use cio_api::rfds::is_image;
 use cio_api::schema::applicants;
-use cio_api::shipments::{get_shipments_spreadsheets, InboundShipment, NewInboundShipment, Shipment};
+use cio_api::shipments::{get_shipments_spreadsheets, update_shipment_from_tracking_status, InboundShipment, NewInboundShipment, Shipment};
 use cio_api::shorturls::{generate_shorturls_for_configs_links, generate_shorturls_for_repos, generate_shorturls_for_rfds};
 use cio_api::slack::{get_hiring_channel_post_url, get_public_relations_channel_post_url, post_to_channel};
+use cio_api::swag_store::{swag_stock_as_slack_msg, SwagItem, SwagOrder, SwagOrderError};
 use cio_api::templates::generate_terraform_files_for_okta;
-use cio_api::utils::{authenticate_github_jwt, create_or_update_file_in_github_repo, get_file_content_from_repo, get_gsuite_token, github_org};
+use cio_api::utils::{authenticate_github_jwt, check_bearer_token, check_secret, create_or_update_file_in_github_repo, get_file_content_from_repo, get_gsuite_token, github_org};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
@@ -104,11 +114,19 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     api.register(listen_airtable_shipments_outbound_create_webhooks).unwrap();
     api.register(listen_airtable_shipments_outbound_edit_webhooks).unwrap();
     api.register(listen_analytics_page_view_webhooks).unwrap();
+    api.register(listen_docusign_webhooks).unwrap();
+    api.register(listen_finance_transactions_webhooks).unwrap();
     api.register(listen_google_sheets_edit_webhooks).unwrap();
     api.register(listen_google_sheets_row_create_webhooks).unwrap();
+    api.register(listen_google_sheets_swag_webhooks).unwrap();
     api.register(listen_github_webhooks).unwrap();
     api.register(listen_mailchimp_webhooks).unwrap();
+    api.register(listen_sendgrid_inbound_receipts_webhooks).unwrap();
+    api.register(listen_shippo_tracking_webhooks).unwrap();
+    api.register(listen_slack_commands).unwrap();
     api.register(listen_shippo_tracking_update_webhooks).unwrap();
+    api.register(list_store_items).unwrap();
+    api.register(create_store_order).unwrap();
     api.register(ping_mailchimp_webhooks).unwrap();
     api.register(trigger_rfd_update_by_number).unwrap();
 
@@ -133,10 +151,11 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
 /**
  * Application-specific context (state shared by handler functions)
  */
-struct Context {
+pub struct Context {
     drive_rfd_shared_id: String,
     github: Github,
     github_org: String,
+    github_handlers: GitHubEventHandlers,
     influx: influx::Client,
     db: Database,
 }
@@ -162,6 +181,7 @@ impl Context {
             drive_rfd_shared_id,
             github: authenticate_github_jwt(),
             github_org: github_org(),
+            github_handlers: github_event_handlers(),
             influx: influx::Client::new_from_env(),
             db: Database::new(),
         })
@@ -177,6 +197,22 @@ impl Context {
     }
 }
 
+/**
+ * Return the handlers for GitHub events. New automations for GitHub events
+ * should be registered here.
+ */
+fn github_event_handlers() -> GitHubEventHandlers {
+    let mut handlers = GitHubEventHandlers::default();
+    handlers
+        .push
+        .on(Repo::RFD, |ctx, event| handle_rfd_push(ctx, event).boxed())
+        .on(Repo::Configs, |ctx, event| handle_configs_push(ctx, event).boxed());
+    handlers.pull_request.on(Repo::RFD, |ctx, event| handle_rfd_pull_request(ctx, event).boxed());
+    handlers.repository.on(Repo::Wildcard, |ctx, event| handle_repository_event(ctx, event).boxed());
+
+    handlers
+}
+
 /*
  * HTTP API interface
  */
@@ -285,40 +321,16 @@ async fn listen_github_webhooks(rqctx: Arc<RequestContext>, body_param: TypedBod
             event!(Level::DEBUG, "`{}` {:?}", event_type.name(), event);
             let influx_event = event.as_influx_repository();
             api_context.influx.query(influx_event, event_type.name()).await;
-
-            // Now let's handle the event.
-            return handle_repository_event(api_context, event).await;
         }
         _ => (),
     }
 
-    // Run the correct handler function based on the event type and repo.
-    if !event.repository.name.is_empty() {
-        let repo = &event.repository;
-        let repo_name = Repo::from_str(&repo.name).unwrap();
-        match repo_name {
-            Repo::RFD => match event_type {
-                EventType::Push => {
-                    return handle_rfd_push(api_context, event).await;
-                }
-                EventType::PullRequest => {
-                    return handle_rfd_pull_request(api_context, event).await;
-                }
-                _ => (),
-            },
-            Repo::Configs => {
-                if let EventType::Push = event_type {
-                    return handle_configs_push(api_context, event).await;
-                }
-            }
-            _ => {
-                // We can throw this out, log it and return early.
-                event!(Level::INFO, "`{}` event was to the {} repo, no automations are set up for this repo yet", event_type, repo_name);
-            }
-        }
+    // Run the handler functions registered for the event type and repo.
+    if event.repository.name.is_empty() {
+        return Ok(HttpResponseAccepted("ok".to_string()));
     }
-
-    Ok(HttpResponseAccepted("ok".to_string()))
+    let github_event = GitHubEvent::new(event_type, event);
+    api_context.github_handlers.dispatch(api_context.clone(), github_event).await
 }
 
 #[derive(Deserialize, Debug, JsonSchema)]
@@ -407,7 +419,7 @@ async fn listen_google_sheets_edit_webhooks(rqctx: Arc<RequestContext>, body_par
     // We re-get the token here since otherwise it will expire.
     let token = get_gsuite_token("").await;
     // Initialize the GSuite sheets client.
-    let sheets = Sheets::new(token.clone());
+    let sheets = Sheets::new(token.clone()).map_err(|e| HttpError::for_internal_error(format!("creating the sheets client failed: {}", e)))?;
 
     let api_context = Context::from_rqctx(&rqctx);
     let db = &api_context.db;
@@ -593,7 +605,7 @@ async fn listen_google_sheets_row_create_webhooks(rqctx: Arc<RequestContext>, bo
     // We re-get the token here since otherwise it will expire.
     let token = get_gsuite_token("").await;
     // Initialize the GSuite sheets client.
-    let sheets = Sheets::new(token.clone());
+    let sheets = Sheets::new(token.clone()).map_err(|e| HttpError::for_internal_error(format!("creating the sheets client failed: {}", e)))?;
     // Initialize the Google Drive client.
     let drive = GoogleDrive::new(token);
 
@@ -604,22 +616,11 @@ async fn listen_google_sheets_row_create_webhooks(rqctx: Arc<RequestContext>, bo
     event!(Level::DEBUG, "{:?}", event);
 
     // Ensure this was an applicant and not some other google form!!
+    // Swag spreadsheets send their rows to `/google/sheets/swag`.
     let role = get_role_from_sheet_id(&event.spreadsheet.id);
     if role.is_empty() {
-        // Check if the event is for a swag spreadsheet.
-        let swag_spreadsheets = get_shipments_spreadsheets();
-        if !swag_spreadsheets.contains(&event.spreadsheet.id) {
-            // Return early if not
-            event!(Level::INFO, "event is not for an application spreadsheet or a swag spreadsheet: {:?}", event);
-            return Ok(HttpResponseAccepted("ok".to_string()));
-        }
-
-        // Parse the shipment out of the row information.
-        let mut shipment = Shipment::parse_from_row(&event.event.named_values);
-        // Create or update the shipment in airtable.
-        shipment.create_or_update_in_airtable().await;
-
-        // Handle if the event is for a swag spreadsheet.
+        // Return early if not
+        event!(Level::INFO, "event is not for an application spreadsheet: {:?}", event);
         return Ok(HttpResponseAccepted("ok".to_string()));
     }
 
@@ -637,6 +638,7 @@ async fn listen_google_sheets_row_create_webhooks(rqctx: Arc<RequestContext>, bo
     let sent_email_follow_up_index = event.event.range.column_end + 6;
     applicant
         .expand(
+            db,
             &drive,
             &sheets,
             sent_email_received_column_index.try_into().unwrap(),
@@ -673,6 +675,73 @@ pub struct GoogleSpreadsheetRowCreateEvent {
     pub spreadsheet: GoogleSpreadsheet,
 }
 
+/**
+ * Listen for swag requests submitted to our Google Forms.
+ * These are sent by an Apps Script on the form's spreadsheet when a row is added,
+ * with the `GOOGLE_SHEETS_SWAG_TOKEN` as a bearer token.
+ */
+#[endpoint {
+    method = POST,
+    path = "/google/sheets/swag",
+}]
+#[instrument]
+#[inline]
+async fn listen_google_sheets_swag_webhooks(rqctx: Arc<RequestContext>, body_param: TypedBody<GoogleSpreadsheetRowCreateEvent>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    authorize_bearer_token(&rqctx, "GOOGLE_SHEETS_SWAG_TOKEN").await?;
+    let api_context = Context::from_rqctx(&rqctx);
+
+    let event = body_param.into_inner();
+    event!(Level::DEBUG, "{:?}", event);
+
+    // Ensure this was a swag spreadsheet and not some other google form!!
+    if !get_shipments_spreadsheets().contains(&event.spreadsheet.id) {
+        // Return early if not
+        event!(Level::INFO, "event is not for a swag spreadsheet: {:?}", event);
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
+
+    handle_swag_row(&api_context.db, event).await
+}
+
+/// Create the shipment for a row added to a swag spreadsheet and its label.
+/// The same row can be sent more than once, for example when someone re-runs
+/// the Apps Script, so this does nothing for a row we already have a label for.
+#[instrument(skip(db))]
+#[inline]
+async fn handle_swag_row(db: &Database, event: GoogleSpreadsheetRowCreateEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
+    // Parse the shipment out of the row information.
+    let mut shipment = Shipment::parse_from_row(&event.event.named_values);
+    if shipment.email.is_empty() || shipment.street_1.is_empty() {
+        event!(Level::WARN, "shipment has an empty email or address: {:?}", shipment);
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
+
+    // Check if we already have the shipment for this form submission.
+    let existing = shipment.get_submission_from_airtable().await.map_err(|e| {
+        event!(Level::ERROR, "getting the shipment for {} from Airtable failed: {}", shipment.email, e);
+        HttpError::for_internal_error(e.to_string())
+    })?;
+    if let Some(existing) = existing {
+        if !existing.fields.shippo_id.is_empty() {
+            event!(Level::INFO, "shipment {} already has a label, ignoring the duplicate row", shipment.email);
+            return Ok(HttpResponseAccepted("ok".to_string()));
+        }
+
+        // Keep the changes we made in Airtable, and create the label we are missing.
+        shipment = existing.fields;
+    }
+
+    // Create the label before the shipment is in Airtable, this saves it to Airtable
+    // with the shippo id set, so the Airtable create webhook does not create a
+    // second label.
+    shipment.create_or_get_shippo_shipment(db).await;
+    // Create or update the shipment in airtable.
+    shipment.create_or_update_in_airtable().await;
+
+    event!(Level::INFO, "shipment {} created successfully", shipment.email);
+    Ok(HttpResponseAccepted("ok".to_string()))
+}
+
 /**
  * Listen for rows edited in our Airtable workspace.
  * These are set up with an Airtable script on the workspaces themselves.
@@ -715,7 +784,10 @@ async fn listen_airtable_applicants_edit_webhooks(rqctx: Arc<RequestContext>, bo
 }]
 #[instrument]
 #[inline]
-async fn listen_airtable_shipments_outbound_create_webhooks(_rqctx: Arc<RequestContext>, body_param: TypedBody<AirtableRowEvent>) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn listen_airtable_shipments_outbound_create_webhooks(rqctx: Arc<RequestContext>, body_param: TypedBody<AirtableRowEvent>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
     let event = body_param.into_inner();
     event!(Level::DEBUG, "{:?}", event);
 
@@ -728,7 +800,7 @@ async fn listen_airtable_shipments_outbound_create_webhooks(_rqctx: Arc<RequestC
     let mut shipment = Shipment::get_from_airtable(&event.record_id).await;
 
     // Create the shipment in shippo.
-    shipment.create_or_get_shippo_shipment().await;
+    shipment.create_or_get_shippo_shipment(db).await;
     // Update airtable again.
     shipment.create_or_update_in_airtable().await;
 
@@ -753,7 +825,10 @@ pub struct AirtableRowEvent {
 }]
 #[instrument]
 #[inline]
-async fn listen_airtable_shipments_outbound_edit_webhooks(_rqctx: Arc<RequestContext>, body_param: TypedBody<AirtableRowEvent>) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn listen_airtable_shipments_outbound_edit_webhooks(rqctx: Arc<RequestContext>, body_param: TypedBody<AirtableRowEvent>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
     let event = body_param.into_inner();
     event!(Level::DEBUG, "{:?}", event);
 
@@ -784,7 +859,7 @@ async fn listen_airtable_shipments_outbound_edit_webhooks(_rqctx: Arc<RequestCon
 
     if shipment.resend_email_to_recipient {
         // Resend the email to the recipient.
-        shipment.send_email_to_recipient().await;
+        shipment.send_email_to_recipient(db).await;
         event!(Level::INFO, "resent the shipment email to the recipient {}", shipment.email);
 
         // Update the field.
@@ -848,7 +923,10 @@ async fn listen_airtable_shipments_inbound_create_webhooks(rqctx: Arc<RequestCon
         tracking_link: record.tracking_link,
     };
 
-    new_shipment.expand().await;
+    if let Err(e) = new_shipment.expand().await {
+        // The shipment is saved without a tracking status, the next refresh tries again.
+        event!(Level::WARN, "expanding inbound shipment {} failed: {}", new_shipment.tracking_number, e);
+    }
     let mut shipment = new_shipment.upsert_in_db(&db);
     if shipment.airtable_record_id.is_empty() {
         shipment.airtable_record_id = event.record_id;
@@ -860,40 +938,259 @@ async fn listen_airtable_shipments_inbound_create_webhooks(rqctx: Arc<RequestCon
 }
 
 /**
- * Listen for shimpment tracking updated from Shippo.
+ * Listen for shipment tracking updates from Shippo.
+ * Shippo does not sign its webhooks, so the URL we give it has the secret
+ * `SHIPPO_WEBHOOK_TOKEN` in its `token` query parameter.
+ */
+#[endpoint {
+    method = POST,
+    path = "/shippo/tracking",
+}]
+#[instrument(skip(query_args))]
+#[inline]
+async fn listen_shippo_tracking_webhooks(
+    rqctx: Arc<RequestContext>,
+    query_args: Query<ShippoWebhookQuery>,
+    body_param: TypedBody<TrackingWebhookEvent>,
+) -> Result<HttpResponseAccepted<String>, HttpError> {
+    authorize_shippo_webhook(&query_args.into_inner())?;
+    let api_context = Context::from_rqctx(&rqctx);
+
+    handle_shippo_tracking_update(&api_context.db, body_param.into_inner()).await
+}
+
+/**
+ * Listen for shipment tracking updates from Shippo.
+ * This is the path the webhooks were registered with before `/shippo/tracking`.
+ * The db updates cron job moves them to `/shippo/tracking` with the token, see
+ * `cio_api::shipments::update_shippo_tracking_webhook`. Until it has run,
+ * updates sent here without the token are refused.
  */
 #[endpoint {
     method = POST,
     path = "/shippo/tracking/update",
 }]
-#[instrument]
+#[instrument(skip(query_args))]
 #[inline]
-async fn listen_shippo_tracking_update_webhooks(_rqctx: Arc<RequestContext>, body_param: TypedBody<serde_json::Value>) -> Result<HttpResponseAccepted<String>, HttpError> {
-    let event = body_param.into_inner();
-    let body: ShippoTrackingUpdateEvent = serde_json::from_str(&event.to_string()).unwrap_or_else(|e| {
-        println!("decoding event body `{}` failed: {}", event.to_string(), e);
-        Default::default()
-    });
-    event!(Level::INFO, "shipment parsed: {:?}", body);
-
-    if body.data.address_from.street1.is_empty() {
-        // We can reaturn early.
-        // It's too early to get anything good from this event.
-        event!(Level::WARN, "too early to get any information about the shipment");
+async fn listen_shippo_tracking_update_webhooks(
+    rqctx: Arc<RequestContext>,
+    query_args: Query<ShippoWebhookQuery>,
+    body_param: TypedBody<TrackingWebhookEvent>,
+) -> Result<HttpResponseAccepted<String>, HttpError> {
+    authorize_shippo_webhook(&query_args.into_inner())?;
+    let api_context = Context::from_rqctx(&rqctx);
+
+    handle_shippo_tracking_update(&api_context.db, body_param.into_inner()).await
+}
+
+/// The query parameters of the Shippo webhook URL.
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct ShippoWebhookQuery {
+    /// The secret the webhook URL was set up with.
+    #[serde(default)]
+    pub token: String,
+}
+
+/// Check a Shippo webhook was sent to the URL with our secret in it. If
+/// `SHIPPO_WEBHOOK_TOKEN` is not set, every request is rejected.
+#[instrument(skip(query))]
+#[inline]
+fn authorize_shippo_webhook(query: &ShippoWebhookQuery) -> Result<(), HttpError> {
+    if !check_secret(&query.token, &env::var("SHIPPO_WEBHOOK_TOKEN").unwrap_or_default()) {
+        event!(Level::WARN, "unauthorized Shippo webhook, the token does not match `SHIPPO_WEBHOOK_TOKEN`");
+        return Err(HttpError::for_client_error(None, http::StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Update the shipment a Shippo `track_updated` event is for.
+#[instrument(skip(db))]
+#[inline]
+async fn handle_shippo_tracking_update(db: &Database, event: TrackingWebhookEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
+    event!(Level::DEBUG, "shipment parsed: {:?}", event);
+
+    if !event.is_track_updated() {
+        // We can throw this out, log it and return early.
+        event!(Level::INFO, "`{}` events from Shippo are not handled", event.event);
         return Ok(HttpResponseAccepted("ok".to_string()));
     }
 
-    println!("shippo-tracking-update parsed: {:?}", body);
+    if event.test {
+        // Test events, including the ones from the test button in Shippo, are
+        // not for any of our shipments.
+        event!(Level::INFO, "ignoring test tracking event for `{}`", event.data.tracking_number);
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
+
+    let ts = event.tracking_status();
+    if ts.tracking_number.is_empty() || ts.carrier.is_empty() {
+        // We can return early.
+        event!(Level::WARN, "tracking_number and carrier are empty, ignoring");
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
+
+    let tracking_number = ts.tracking_number.to_string();
+    let status = ts.tracking_status.status.clone();
+    // If this fails, Shippo sends the event again later.
+    let found = update_shipment_from_tracking_status(db, ts)
+        .await
+        .map_err(|e| HttpError::for_internal_error(format!("updating the shipment with tracking number `{}` failed: {}", tracking_number, e)))?;
+    if !found {
+        event!(Level::WARN, "no shipment found with tracking number `{}`", tracking_number);
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
 
-    //event!(Level::INFO, "shipment {} tracking status updated successfully", a.email);
+    event!(Level::INFO, "shipment {} tracking status updated to `{}` successfully", tracking_number, status.as_str());
     Ok(HttpResponseAccepted("ok".to_string()))
 }
 
-/// A Shippo tracking update event.
-#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
-pub struct ShippoTrackingUpdateEvent {
-    #[serde(default)]
-    pub data: shippo::TrackingStatus,
+/**
+ * Listen for Slack slash commands.
+ * The request is signed with `SLACK_SIGNING_SECRET`, so we need to read the raw
+ * body ourselves to check the signature before parsing it.
+ */
+#[endpoint {
+    method = POST,
+    path = "/slack/commands",
+}]
+#[instrument]
+#[inline]
+async fn listen_slack_commands(rqctx: Arc<RequestContext>) -> Result<HttpResponseOk<serde_json::Value>, HttpError> {
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    // TODO: make this nicer when supported as a first class method in dropshot.
+    let mut req = rqctx.request.lock().await;
+    let header = |name: &str| -> String {
+        req.headers()
+            .get(name)
+            .unwrap_or(&http::header::HeaderValue::from_str("").unwrap())
+            .to_str()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let timestamp = header("X-Slack-Request-Timestamp");
+    let signature = header("X-Slack-Signature");
+    let body = std::mem::replace(req.body_mut(), hyper::Body::empty());
+    drop(req);
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| HttpError::for_bad_request(None, format!("reading the request body failed: {}", e)))?;
+
+    if !verify_signature(&env::var("SLACK_SIGNING_SECRET").unwrap_or_default(), &timestamp, &body, &signature) {
+        event!(Level::WARN, "slack command has an invalid signature");
+        return Err(HttpError::for_client_error(None, http::StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    let command = SlackCommand::parse(&body).map_err(|e| HttpError::for_bad_request(None, format!("parsing the slack command failed: {}", e)))?;
+    event!(Level::DEBUG, "{:?}", command);
+
+    let text = command.text.trim();
+    let response = match command.command.as_str() {
+        "/shipment" => {
+            if text.is_empty() {
+                json!({ "text": "Usage: `/shipment <tracking number>`" })
+            } else if let Some(shipment) = InboundShipment::get_from_db_by_tracking_number(db, text) {
+                shipment.as_slack_msg()
+            } else {
+                json!({ "text": format!("No shipment found with tracking number `{}`.", text) })
+            }
+        }
+        "/swag-stock" => {
+            let items = SwagItem::search_in_db(db, text);
+            if items.is_empty() {
+                json!({ "text": format!("No swag found matching `{}`.", text) })
+            } else {
+                swag_stock_as_slack_msg(&items)
+            }
+        }
+        c => {
+            event!(Level::WARN, "slack command `{}` is not supported", c);
+            json!({ "text": format!("The command `{}` is not supported.", c) })
+        }
+    };
+
+    event!(Level::INFO, "slack command `{}` from {} handled", command.command, command.user_name);
+    Ok(HttpResponseOk(response))
+}
+
+/**
+ * Listen for DocuSign Connect events.
+ * The request is signed with `DOCUSIGN_CONNECT_KEY`, so we need to read the raw
+ * body ourselves to check the signature before parsing it.
+ */
+#[endpoint {
+    method = POST,
+    path = "/docusign",
+}]
+#[instrument]
+#[inline]
+async fn listen_docusign_webhooks(rqctx: Arc<RequestContext>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    // TODO: make this nicer when supported as a first class method in dropshot.
+    let mut req = rqctx.request.lock().await;
+    let signatures: Vec<String> = req
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-docusign-signature-"))
+        .map(|(_, value)| value.to_str().unwrap_or_default().to_string())
+        .collect();
+    let body = std::mem::replace(req.body_mut(), hyper::Body::empty());
+    drop(req);
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| HttpError::for_bad_request(None, format!("reading the request body failed: {}", e)))?;
+
+    if !docusign::verify_signature(&env::var("DOCUSIGN_CONNECT_KEY").unwrap_or_default(), &body, &signatures) {
+        event!(Level::WARN, "docusign event has an invalid signature");
+        return Err(HttpError::for_client_error(None, http::StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    let event = DocuSignEvent::parse(&body).map_err(|e| HttpError::for_bad_request(None, format!("parsing the docusign event failed: {}", e)))?;
+    event!(Level::DEBUG, "{:?}", event);
+
+    if event.event != ENVELOPE_COMPLETED {
+        // We only care about envelopes that everyone has signed.
+        event!(Level::INFO, "docusign event `{}` is not `{}`, skipping", event.event, ENVELOPE_COMPLETED);
+        return Ok(HttpResponseAccepted("ok".to_string()));
+    }
+
+    let summary = &event.data.envelope_summary;
+    match event.document_kind() {
+        DocumentKind::OfferLetter => {
+            for signer in &summary.recipients.signers {
+                // Fail the request so DocuSign sends the event again.
+                let applicants = onboard_applicants_with_signed_offer(db, &api_context.github, &signer.email)
+                    .await
+                    .map_err(|e| HttpError::for_internal_error(format!("onboarding signer {} of envelope `{}` failed: {}", signer.email, event.data.envelope_id, e)))?;
+                if applicants.is_empty() {
+                    event!(Level::WARN, "no applicant giving an offer found for signer {} of envelope `{}`", signer.email, event.data.envelope_id);
+                    continue;
+                }
+
+                event!(Level::INFO, "applicant {} signed their offer letter, moved to onboarding", signer.email);
+            }
+        }
+        DocumentKind::Nda => {
+            for signer in &summary.recipients.signers {
+                event!(Level::INFO, "{} <{}> signed the NDA in envelope `{}`", signer.name, signer.email, event.data.envelope_id);
+            }
+        }
+        DocumentKind::Other => {
+            event!(
+                Level::INFO,
+                "envelope `{}` with subject `{}` is not an offer letter or NDA, skipping",
+                event.data.envelope_id,
+                summary.email_subject
+            );
+        }
+    }
+
+    event!(Level::INFO, "docusign envelope `{}` completed event handled", event.data.envelope_id);
+    Ok(HttpResponseAccepted("ok".to_string()))
 }
 
 /** Ping endpoint for MailChimp webhooks. */
@@ -931,6 +1228,202 @@ async fn listen_analytics_page_view_webhooks(rqctx: Arc<RequestContext>, body_pa
     Ok(HttpResponseAccepted("ok".to_string()))
 }
 
+/**
+ * Listen for card transactions from our finance provider.
+ * The request needs a bearer token matching `FINANCE_TRANSACTIONS_API_TOKEN`,
+ * and the amount is sent in cents as `amount_cents`.
+ */
+#[endpoint {
+    method = POST,
+    path = "/finance/transactions",
+}]
+#[instrument]
+#[inline]
+async fn listen_finance_transactions_webhooks(rqctx: Arc<RequestContext>, body_param: TypedBody<NewCardTransaction>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    authorize_bearer_token(&rqctx, "FINANCE_TRANSACTIONS_API_TOKEN").await?;
+
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    let event = body_param.into_inner();
+    event!(Level::DEBUG, "{:?}", event);
+
+    // Add the transaction to the database and, if it needs a receipt, Airtable.
+    let transaction = event.ingest(db).await;
+
+    event!(
+        Level::INFO,
+        "card transaction `{}` from {} ingested successfully",
+        transaction.transaction_id,
+        transaction.merchant_name
+    );
+    Ok(HttpResponseAccepted("ok".to_string()))
+}
+
+/**
+ * Listen for receipts emailed to us, parsed by the SendGrid inbound parse webhook.
+ * SendGrid does not sign the parsed emails, so the URL we give it has the secret
+ * `SENDGRID_PARSE_TOKEN` in its `token` query parameter.
+ */
+#[endpoint {
+    method = POST,
+    path = "/sendgrid/inbound/receipts",
+}]
+#[instrument(skip(query_args))]
+#[inline]
+async fn listen_sendgrid_inbound_receipts_webhooks(rqctx: Arc<RequestContext>, query_args: Query<SendGridParseQuery>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    if !check_secret(&query_args.into_inner().token, &env::var("SENDGRID_PARSE_TOKEN").unwrap_or_default()) {
+        event!(Level::WARN, "unauthorized SendGrid inbound parse webhook, the token does not match `SENDGRID_PARSE_TOKEN`");
+        return Err(HttpError::for_client_error(None, http::StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    // SendGrid posts the email as `multipart/form-data` so we need to parse the body ourselves.
+    // TODO: make this nicer when supported as a first class method in dropshot.
+    let mut req = rqctx.request.lock().await;
+    let content_type = req
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .unwrap_or(&http::header::HeaderValue::from_str("").unwrap())
+        .to_str()
+        .unwrap_or_default()
+        .to_string();
+    let boundary = match multer::parse_boundary(&content_type) {
+        Ok(b) => b,
+        Err(e) => {
+            event!(Level::WARN, "parsing multipart boundary from content type `{}` failed: {}", content_type, e);
+            return Ok(HttpResponseAccepted("ok".to_string()));
+        }
+    };
+    let body = std::mem::replace(req.body_mut(), hyper::Body::empty());
+    drop(req);
+
+    // SendGrid will not post emails over 30MB, so neither will we read them.
+    let constraints = multer::Constraints::new().size_limit(multer::SizeLimit::new().whole_stream(MAX_INBOUND_EMAIL_SIZE).per_field(MAX_INBOUND_EMAIL_FIELD_SIZE));
+    let mut receipt: InboundReceipt = Default::default();
+    let mut multipart = multer::Multipart::with_constraints(body, boundary, constraints);
+    let bad_request = |e: multer::Error| match e {
+        multer::Error::StreamSizeExceeded { .. } | multer::Error::FieldSizeExceeded { .. } => {
+            event!(Level::WARN, "rejecting inbound email: {}", e);
+            HttpError::for_client_error(None, http::StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+        }
+        _ => HttpError::for_bad_request(None, format!("parsing the multipart body failed: {}", e)),
+    };
+    while let Some(field) = multipart.next_field().await.map_err(bad_request)? {
+        let name = field.name().unwrap_or_default().to_string();
+        let filename = field.file_name().unwrap_or_default().to_string();
+        let field_content_type = field.content_type().map(|m| m.to_string()).unwrap_or_default();
+
+        if !filename.is_empty() {
+            // This is an attachment.
+            receipt.attachments.push(ReceiptAttachment {
+                filename,
+                content_type: field_content_type,
+                contents: field.bytes().await.map_err(bad_request)?.to_vec(),
+            });
+            continue;
+        }
+
+        let value = field.text().await.map_err(bad_request)?;
+        match name.as_str() {
+            "from" => receipt.from = value,
+            "to" => receipt.to = value,
+            "subject" => receipt.subject = value,
+            "text" => receipt.text = value,
+            _ => (),
+        }
+    }
+    event!(Level::DEBUG, "{:?}", receipt);
+
+    match handle_inbound_receipt(db, &receipt).await {
+        Some(transaction) => event!(Level::INFO, "receipt `{}` matched to card transaction `{}`", receipt.subject, transaction.transaction_id),
+        None => event!(Level::INFO, "receipt `{}` did not match any card transactions", receipt.subject),
+    }
+
+    Ok(HttpResponseAccepted("ok".to_string()))
+}
+
+/// The largest email, in bytes, we accept from the SendGrid inbound parse webhook.
+const MAX_INBOUND_EMAIL_SIZE: u64 = 30 * 1024 * 1024;
+/// The largest single field or attachment, in bytes, of an inbound email.
+const MAX_INBOUND_EMAIL_FIELD_SIZE: u64 = 20 * 1024 * 1024;
+
+/// The query parameters of the SendGrid inbound parse webhook URL.
+#[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
+pub struct SendGridParseQuery {
+    /// The secret the inbound parse URL was set up with.
+    #[serde(default)]
+    pub token: String,
+}
+
+/**
+ * Check that a request has a bearer token matching the environment variable
+ * `token_env`. If the variable is not set, every request is rejected.
+ */
+#[instrument]
+#[inline]
+async fn authorize_bearer_token(rqctx: &Arc<RequestContext>, token_env: &str) -> Result<(), HttpError> {
+    let req = rqctx.request.lock().await;
+    let authorization = req.headers().get(http::header::AUTHORIZATION).and_then(|h| h.to_str().ok()).unwrap_or_default();
+
+    if !check_bearer_token(authorization, &env::var(token_env).unwrap_or_default()) {
+        event!(Level::WARN, "unauthorized request, it needs a bearer token matching `{}`", token_env);
+        return Err(HttpError::for_client_error(None, http::StatusCode::UNAUTHORIZED, "unauthorized".to_string()));
+    }
+
+    Ok(())
+}
+
+/** List the swag we have in stock. */
+#[endpoint {
+    method = GET,
+    path = "/store/items",
+}]
+#[instrument]
+#[inline]
+async fn list_store_items(rqctx: Arc<RequestContext>) -> Result<HttpResponseOk<Vec<SwagItem>>, HttpError> {
+    authorize_bearer_token(&rqctx, "SWAG_STORE_API_TOKEN").await?;
+
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    Ok(HttpResponseOk(SwagItem::get_in_stock_from_db(db)))
+}
+
+/** Place an order for swag, it will be shipped like the orders from the Google Form. */
+#[endpoint {
+    method = POST,
+    path = "/store/orders",
+}]
+#[instrument]
+#[inline]
+async fn create_store_order(rqctx: Arc<RequestContext>, body_param: TypedBody<SwagOrder>) -> Result<HttpResponseAccepted<String>, HttpError> {
+    authorize_bearer_token(&rqctx, "SWAG_STORE_API_TOKEN").await?;
+
+    let api_context = Context::from_rqctx(&rqctx);
+    let db = &api_context.db;
+
+    let mut order = body_param.into_inner();
+    event!(Level::DEBUG, "{:?}", order);
+
+    match order.submit(db).await {
+        Ok(shipment) => {
+            event!(Level::INFO, "swag order for {} queued for shipment: {}", shipment.email, shipment.contents);
+            Ok(HttpResponseAccepted("ok".to_string()))
+        }
+        Err(e) => {
+            event!(Level::INFO, "swag order from {} was rejected: {}", order.email, e);
+            match e {
+                SwagOrderError::Invalid(_) => Err(HttpError::for_bad_request(None, e.to_string())),
+                SwagOrderError::OutOfStock(_) => Err(HttpError::for_client_error(None, http::StatusCode::CONFLICT, e.to_string())),
+                SwagOrderError::Failed(_) => Err(HttpError::for_internal_error(e.to_string())),
+            }
+        }
+    }
+}
+
 /** Listen for MailChimp webhooks. */
 #[endpoint {
     method = POST,
@@ -1541,7 +2034,7 @@ fn filter(files: &[String], dir: &str) -> Vec<String> {
 /// Handle a `pull_request` event for the rfd repo.
 #[instrument(skip(api_context))]
 #[inline]
-async fn handle_rfd_pull_request(api_context: Arc<Context>, event: GitHubWebhook) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn handle_rfd_pull_request(api_context: Arc<Context>, event: PullRequestEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
     let db = &api_context.db;
 
     // Get the repo.
@@ -1673,7 +2166,7 @@ async fn handle_rfd_pull_request(api_context: Arc<Context>, event: GitHubWebhook
 /// Handle a `push` event for the rfd repo.
 #[instrument(skip(api_context))]
 #[inline]
-async fn handle_rfd_push(api_context: Arc<Context>, event: GitHubWebhook) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn handle_rfd_push(api_context: Arc<Context>, event: PushEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
     // Get gsuite token.
     // We re-get the token here because otherwise it will expire.
     let token = get_gsuite_token("").await;
@@ -1685,8 +2178,15 @@ async fn handle_rfd_push(api_context: Arc<Context>, event: GitHubWebhook) -> Res
     // Get the repo.
     let github_repo = api_context.github.repo(api_context.github_org.to_string(), event.repository.name.to_string());
 
-    // Get the commit.
-    let mut commit = event.commits.get(0).unwrap().clone();
+    // Get the files changed by all the commits in the push, a push can have
+    // more than one commit touching RFDs.
+    let mut commit = match event.combined_commit() {
+        Some(commit) => commit,
+        None => {
+            event!(Level::INFO, "`push` event has no distinct commits: {:?}", event);
+            return Ok(HttpResponseAccepted("ok".to_string()));
+        }
+    };
 
     // Ignore any changes that are not to the `rfd/` directory.
     let dir = "rfd/";
@@ -1952,7 +2452,7 @@ async fn handle_rfd_push(api_context: Arc<Context>, event: GitHubWebhook) -> Res
 /// Handle a `push` event for the configs repo.
 #[instrument(skip(api_context))]
 #[inline]
-async fn handle_configs_push(api_context: Arc<Context>, event: GitHubWebhook) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn handle_configs_push(api_context: Arc<Context>, event: PushEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
     // Get the repo.
     let github_repo = api_context.github.repo(api_context.github_org.to_string(), event.repository.name.to_string());
 
@@ -2045,7 +2545,7 @@ async fn handle_configs_push(api_context: Arc<Context>, event: GitHubWebhook) ->
 /// Handle the `repository` event for all repos.
 #[instrument(skip(api_context))]
 #[inline]
-async fn handle_repository_event(api_context: Arc<Context>, event: GitHubWebhook) -> Result<HttpResponseAccepted<String>, HttpError> {
+async fn handle_repository_event(api_context: Arc<Context>, event: RepositoryEvent) -> Result<HttpResponseAccepted<String>, HttpError> {
     let repo = &api_context.github.repo(event.repository.owner.login, event.repository.name).get().await.unwrap();
     let nr = NewRepo::new(repo.clone());
     nr.upsert(&api_context.db).await;
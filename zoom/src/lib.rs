@@ -36,8 +36,6 @@ use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use reqwest::{get, header, Client, Method, Request, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 
-use cio_api::configs::{BuildingConfig, ResourceConfig};
-
 /// Endpoint for the Zoom API.
 const ENDPOINT: &str = "https://api.zoom.us/v2/";
 
@@ -490,6 +488,44 @@ impl Zoom {
 
         Ok(())
     }
+
+    /// Delete the recordings for a single instance of a meeting, by the
+    /// meeting instance UUID. Passing the meeting ID to `delete_meeting_recordings`
+    /// deletes the recordings of every instance of a recurring meeting.
+    pub async fn delete_meeting_instance_recordings(&self, meeting_uuid: &str) -> Result<(), APIError> {
+        // UUIDs that start with `/` or contain `//` need to be double encoded.
+        // It does not hurt to always do it.
+        // From: https://marketplace.zoom.us/docs/api-reference/zoom-api/cloud-recording/recordingdelete
+        let uuid = encode_path_segment(&encode_path_segment(meeting_uuid));
+
+        // Build the request.
+        let request = self.request(Method::DELETE, format!("meetings/{}/recordings", uuid), (), None);
+
+        let resp = self.client.execute(request).await.unwrap();
+        match resp.status() {
+            StatusCode::NO_CONTENT => (),
+            s => {
+                return Err(APIError {
+                    status_code: s,
+                    body: resp.text().await.unwrap(),
+                })
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Percent encode everything but unreserved characters in a path segment.
+fn encode_path_segment(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
 }
 
 /// Error type returned by our library.
@@ -704,9 +740,9 @@ pub struct Room {
 }
 
 impl Room {
-    /// Update a room from a configuration.
-    pub fn update(mut self, resource: ResourceConfig, passcode: String, location_id: String) -> Room {
-        self.name = resource.name;
+    /// Update a room with the name from its configuration.
+    pub fn update(mut self, name: String, passcode: String, location_id: String) -> Room {
+        self.name = name;
         self.room_passcode = Some(passcode);
         self.required_code_to_ext = Some(true);
         self.typev = Some("ZoomRoom".to_string());
@@ -772,11 +808,12 @@ struct UpdateBuildingRequest {
 }
 
 impl Building {
-    /// Update a building from a configuration.
-    pub fn update(mut self, building: BuildingConfig, passcode: String) -> Building {
-        self.name = building.name;
-        self.description = Some(building.description);
-        self.address = Some(building.address_formatted);
+    /// Update a building with the name, description and formatted address from
+    /// its configuration.
+    pub fn update(mut self, name: String, description: String, address: String, passcode: String) -> Building {
+        self.name = name;
+        self.description = Some(description);
+        self.address = Some(address);
         self.room_passcode = Some(passcode);
         self.required_code_to_ext = Some(true);
         self.typev = Some("building".to_string());